@@ -0,0 +1,123 @@
+//! Autosave: after any command that changes files or the current directory,
+//! the page saves a snapshot to localStorage (debounced, see index.html), and
+//! on the next visit offers to restore it. Sites can turn it off with the
+//! setting autosave=off.
+
+use std::cell::RefCell;
+use serde_json::{json, Value};
+use crate::commands::{export_session, import_session, OnConflict};
+use crate::filesystem::CURRENT_DIR;
+use crate::filesystem::events::{subscribe, FsEvent};
+use crate::filesystem::helpers::format_timestamp;
+use crate::js_interop::{now, schedule_autosave};
+use crate::settings::get_setting;
+
+const SNAPSHOT_VERSION: u64 = 1;
+
+thread_local! {
+    // Something changed since the last autosave was scheduled
+    static DIRTY: RefCell<bool> = const { RefCell::new(false) };
+}
+
+pub fn enabled() -> bool {
+    get_setting("autosave").as_deref() != Some("off")
+}
+
+/// Note changes worth saving as they happen
+pub fn register() {
+    subscribe(|event| {
+        if !matches!(event, FsEvent::FileRead(_)) {
+            DIRTY.with_borrow_mut(|dirty| *dirty = true);
+        }
+    });
+}
+
+/// Whether anything changed that hasn't been scheduled for saving yet
+pub fn dirty() -> bool {
+    DIRTY.with_borrow(|dirty| *dirty)
+}
+
+pub fn set_dirty(changed: bool) {
+    DIRTY.with_borrow_mut(|dirty| *dirty = changed);
+}
+
+/// Called after each command: if it changed anything, have the page save soon
+pub fn after_command() {
+    let dirty = DIRTY.with_borrow_mut(std::mem::take);
+    if dirty && enabled() {
+        schedule_autosave();
+    }
+}
+
+/// The current session and directory, as stored in localStorage
+pub fn snapshot() -> String {
+    let session: Value = serde_json::from_str(&export_session()).unwrap_or_default();
+    json!({
+        "version": SNAPSHOT_VERSION,
+        "saved": now(),
+        "cwd": CURRENT_DIR.with_borrow(|cd| cd.to_string()),
+        "session": session,
+    }).to_string()
+}
+
+/// If `saved` holds anything worth restoring, ask whether to restore it
+pub fn offer_restore(saved: &str) {
+    let Ok(snapshot) = serde_json::from_str::<Value>(saved) else { return };
+    if !enabled() || snapshot.get("version").and_then(|v| v.as_u64()) != Some(SNAPSHOT_VERSION) {
+        return;
+    }
+    let files = snapshot["session"]["files"].as_object().map_or(0, |files| files.len());
+    let abyss_edits = crate::filesystem::abyss_delta::has_edits(&snapshot["session"]["abyss"]);
+    let cwd = snapshot["cwd"].as_str().unwrap_or("/");
+    if files == 0 && !abyss_edits && cwd == "/" {
+        return;
+    }
+
+    let saved_at = snapshot["saved"].as_f64().map(format_timestamp).unwrap_or_else(|| "earlier".to_string());
+    let saved = saved.to_string();
+    crate::prompt::confirm(
+        &format!("Found an autosaved session from {} ({} file(s), in {}). Restore it?", saved_at, files, cwd),
+        move || async move { restore(&saved).await },
+    );
+}
+
+/// Bring back an autosaved session's files, directory and history
+pub async fn restore(saved: &str) -> String {
+    let Ok(snapshot) = serde_json::from_str::<Value>(saved) else {
+        return "autosave: The saved session is corrupt".to_string();
+    };
+    let summary = import_session(snapshot["session"].to_string(), true, &OnConflict::Overwrite).await;
+    if summary.starts_with("Error:") {
+        return summary;
+    }
+    // The session carries the directory and history too
+    format!("Restored autosaved session.\n{}", summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{enable, run};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_autosave_restore() {
+        enable(1);
+        run("mkdir work");
+        run("cd work");
+        crate::write_file("todo.txt", "milk".to_string()).unwrap();
+        let saved = crate::autosave::snapshot();
+
+        // The next visit
+        enable(1);
+        crate::autosave::offer_restore(&saved);
+        assert_eq!(take_output(), ["Found an autosaved session from 2023-11-14 22:13:20 (1 file(s), in /work). Restore it? (y/n)"]);
+        assert!(run("y")[0].starts_with("Restored autosaved session.\nimport summary:\n  succeeded     1"));
+        assert_eq!(run("pwd"), ["/work"]);
+        assert_eq!(run("cat todo.txt"), ["milk"]);
+
+        // Nothing to offer for an untouched session
+        enable(1);
+        crate::autosave::offer_restore(&crate::autosave::snapshot());
+        assert!(take_output().is_empty());
+    }
+}
@@ -1,14 +1,183 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen::closure::Closure;
 use web_sys::{BroadcastChannel, MessageEvent};
 use std::cell::RefCell;
-use crate::filesystem::{DirPath, FilePath, VIRTUAL_FS};
-use crate::filesystem::helpers::{get_file_content, path_in_abyss, write_file_abyss};
-use crate::js_interop::add_output;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::filesystem::{DirPath, FilePath};
+use crate::filesystem::front_matter::{self, FrontMatter, is_markdown_name};
+use crate::filesystem::events::{subscribe, FsEvent};
+use crate::filesystem::helpers::{get_file_bytes, get_file_content, has_in_memory_content, is_read_only, remove_file, rename_file, write_file};
+use crate::highlight::{self, Language};
+use crate::js_interop::{add_output, now, scroll_to_bottom};
+
+type Handler = Closure<dyn FnMut(MessageEvent)>;
 
 thread_local! {
     pub static EDITOR_CHANNEL: RefCell<Option<BroadcastChannel>> = RefCell::new(None);
     pub static PRETTY_CHANNEL: RefCell<Option<BroadcastChannel>> = RefCell::new(None);
+    // The channels' onmessage handlers, kept alive until the channels close
+    pub static CHANNEL_HANDLERS: RefCell<Vec<Handler>> = const { RefCell::new(Vec::new()) };
+    // Editor and pretty tabs that have said hello, by their IDs
+    static TABS: RefCell<HashMap<String, Tab>> = RefCell::new(HashMap::new());
+    // Site files opened with edit -f, which the editor may save over
+    static FORCED_EDITS: RefCell<HashSet<FilePath>> = RefCell::new(HashSet::new());
+}
+
+// Tabs introduce themselves with a `hello` (also their answer to the
+// terminal's `terminal_hello`, sent when it starts), send a `heartbeat`
+// every 20 seconds and say `goodbye` when closed. Browsers slow the timers
+// of background tabs to as little as once a minute, so a tab only counts as
+// gone once it's been silent for a good while longer than that.
+const TAB_TIMEOUT_MS: f64 = 150_000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TabKind {
+    Editor,
+    Pretty,
+}
+
+impl TabKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TabKind::Editor => "editor",
+            TabKind::Pretty => "pretty",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Tab {
+    pub id: String,
+    pub kind: TabKind,
+    // The file as the tab names it; updates have to be sent under this name
+    pub filename: String,
+    pub filepath: FilePath,
+    pub last_seen: f64,
+}
+
+/// A tab said hello or sent a heartbeat
+pub fn note_tab(id: &str, kind: TabKind, filename: &str) {
+    let tab = Tab {
+        id: id.to_string(),
+        kind,
+        filename: filename.to_string(),
+        filepath: FilePath::parse(filename, &DirPath::root()),
+        last_seen: now(),
+    };
+    TABS.with_borrow_mut(|tabs| tabs.insert(id.to_string(), tab));
+}
+
+/// A tab said goodbye
+pub fn note_tab_closed(id: &str) {
+    TABS.with_borrow_mut(|tabs| tabs.remove(id));
+}
+
+/// The tabs still open, by kind and then file. Tabs not heard from in a
+/// while are forgotten, so nothing more is sent to them.
+pub fn open_tabs() -> Vec<Tab> {
+    let now = now();
+    TABS.with_borrow_mut(|tabs| {
+        tabs.retain(|_, tab| now - tab.last_seen < TAB_TIMEOUT_MS);
+        let mut open: Vec<Tab> = tabs.values().cloned().collect();
+        open.sort_by(|a, b| (a.kind.name(), &a.filename, &a.id).cmp(&(b.kind.name(), &b.filename, &b.id)));
+        open
+    })
+}
+
+// An editor tab renamed its file, so it now has the new one open
+fn note_renamed_in_editor(from: &FilePath, to: &FilePath) {
+    TABS.with_borrow_mut(|tabs| {
+        for tab in tabs.values_mut().filter(|tab| tab.kind == TabKind::Editor && tab.filepath == *from) {
+            tab.filename = to.to_string();
+            tab.filepath = to.clone();
+        }
+    });
+}
+
+/// Let the editor save over a read-only file (it was opened with edit -f)
+pub fn allow_forced_edit(filepath: &FilePath) {
+    FORCED_EDITS.with_borrow_mut(|forced| forced.insert(filepath.clone()));
+}
+
+/// Whether the editor may save to a file: site content only if opened with edit -f
+pub fn may_save(filepath: &FilePath) -> Result<(), String> {
+    if is_read_only(filepath) && !FORCED_EDITS.with_borrow(|forced| forced.contains(filepath)) {
+        return Err("Read-only file".to_string());
+    }
+    Ok(())
+}
+
+/// Whether an editor tab has the file open (so saving there would bring it back)
+pub fn is_open_in_editor(filepath: &FilePath) -> bool {
+    open_tabs().iter().any(|tab| tab.kind == TabKind::Editor && tab.filepath == *filepath)
+}
+
+/// Whether an editor tab has a file somewhere in the directory open
+pub fn has_open_in_editor_within(dirpath: &DirPath) -> bool {
+    open_tabs().iter().any(|tab| tab.kind == TabKind::Editor && tab.filepath.dir.is_within(dirpath))
+}
+
+/// Close both channels and drop their handlers, before another build of the
+/// terminal opens its own (see reload.rs)
+pub fn close_channels() {
+    for channel in [&EDITOR_CHANNEL, &PRETTY_CHANNEL] {
+        if let Some(channel) = channel.with_borrow_mut(Option::take) {
+            channel.set_onmessage(None);
+            channel.close();
+        }
+    }
+    CHANNEL_HANDLERS.with_borrow_mut(Vec::clear);
+}
+
+/// Ask tabs opened before the terminal (e.g. before a reload) to say hello
+pub fn greet_tabs() {
+    let message = js_sys::Object::new();
+    js_sys::Reflect::set(&message, &JsValue::from_str("action"), &JsValue::from_str("terminal_hello")).ok();
+    for channel in [&EDITOR_CHANNEL, &PRETTY_CHANNEL] {
+        if let Some(channel) = channel.with(|ch| ch.borrow().clone()) {
+            channel.post_message(&message).ok();
+        }
+    }
+}
+
+/// Keep other tabs up to date with files written in the terminal. Editor
+/// tabs with the file open are told it changed (by load, load-session and
+/// so on) so they can offer to reload it; saves from the editor itself come
+/// through here too, and the tab recognises its own content by the hash.
+/// Pretty tabs showing the file are sent the new content, so they refresh.
+pub fn register() {
+    subscribe(|event| {
+        let FsEvent::FileWritten(filepath) = event else {
+            return;
+        };
+        if is_open_in_editor(filepath) {
+            let filepath = filepath.clone();
+            wasm_bindgen_futures::spawn_local(async move { send_file_changed(&filepath).await });
+        }
+        let viewed_as: BTreeSet<String> = open_tabs().into_iter()
+            .filter(|tab| tab.kind == TabKind::Pretty && tab.filepath == *filepath)
+            .map(|tab| tab.filename)
+            .collect();
+        for filename in viewed_as {
+            send_file_content(&filename, false);
+        }
+    });
+}
+
+async fn send_file_changed(filepath: &FilePath) {
+    let Some(channel) = EDITOR_CHANNEL.with(|ch| ch.borrow().clone()) else {
+        return;
+    };
+    let Ok(bytes) = get_file_bytes(filepath).await else {
+        return;
+    };
+    let hash = crate::digest::hex_digest("sha256", &bytes).unwrap_or_default();
+    let message = js_sys::Object::new();
+    js_sys::Reflect::set(&message, &JsValue::from_str("action"), &JsValue::from_str("file_changed_externally")).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("filename"), &JsValue::from_str(&filepath.to_string())).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("hash"), &JsValue::from_str(&hash)).ok();
+    channel.post_message(&message).ok();
 }
 
 // Handle messages from editor
@@ -20,6 +189,10 @@ pub fn handle_editor_message(event: MessageEvent) {
         let action = js_sys::Reflect::get(&obj, &JsValue::from_str("action")).ok();
         let filename = js_sys::Reflect::get(&obj, &JsValue::from_str("filename")).ok();
         let content = js_sys::Reflect::get(&obj, &JsValue::from_str("content")).ok();
+        // Set when the editor started a new file rather than editing the terminal's copy
+        let created = js_sys::Reflect::get(&obj, &JsValue::from_str("created")).ok()
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
 
         if let (Some(action), Some(filename)) = (action, filename) {
             let action_str = action.as_string().unwrap_or_default();
@@ -34,17 +207,32 @@ pub fn handle_editor_message(event: MessageEvent) {
 
                             // Spawn async task to handle both abyss and regular files
                             wasm_bindgen_futures::spawn_local(async move {
-                                if path_in_abyss(&filepath.dir) {
-                                    // Handle abyss files
-                                    write_file_abyss(&filepath, content_str).await;
-                                } else {
-                                    // Handle regular virtual filesystem
-                                    VIRTUAL_FS.with(|vfs| {
-                                        vfs.borrow_mut().write_file(&filepath, content_str);
-                                    });
+                                if let Err(e) = may_save(&filepath) {
+                                    add_output(&format!("Error saving {}: {}", filename_str, e));
+                                    add_output("\u{00A0}");
+                                    send_action_result("file_saved", &filename_str, None, &Err(e));
+                                    return;
+                                }
+                                // Saving a "new" file over edits made since would lose them - ask first
+                                if created && has_in_memory_content(&filepath).await
+                                    && get_file_content(&filepath).await.ok().as_ref() != Some(&content_str)
+                                {
+                                    crate::prompt::confirm(
+                                        &format!("edit: {}: File already has in-memory content. Overwrite it with the editor's version?", filename_str),
+                                        move || async move {
+                                            match write_file(&filepath, content_str).await {
+                                                Ok(_) => format!("File saved: {}", filename_str),
+                                                Err(e) => format!("Error saving {}: {}", filename_str, e),
+                                            }
+                                        },
+                                    );
+                                    scroll_to_bottom();
+                                    return;
+                                }
+                                match write_file(&filepath, content_str).await {
+                                    Ok(_) => add_output(&format!("File saved: {}", filename_str)),
+                                    Err(e) => add_output(&format!("Error saving {}: {}", filename_str, e)),
                                 }
-
-                                add_output(&format!("File saved: {}", filename_str));
                                 add_output("\u{00A0}");
                             });
                         }
@@ -53,12 +241,88 @@ pub fn handle_editor_message(event: MessageEvent) {
                 "request_file" => {
                     send_file_content(&filename_str, true);
                 }
+                "rename_file" => {
+                    let new_filename = js_sys::Reflect::get(&obj, &JsValue::from_str("new_filename")).ok()
+                        .and_then(|v| v.as_string())
+                        .unwrap_or_default();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let from = FilePath::parse(&filename_str, &DirPath::root());
+                        // A bare name stays in the file's directory
+                        let to = FilePath::parse(&new_filename, &from.dir);
+                        let result = if new_filename.trim().is_empty() {
+                            Err("No new name given".to_string())
+                        } else if is_read_only(&from) {
+                            Err("Read-only file".to_string())
+                        } else {
+                            rename_file(&from, &to).await
+                        };
+                        match &result {
+                            Ok(_) => {
+                                note_renamed_in_editor(&from, &to);
+                                add_output(&format!("File renamed: {} -> {}", filename_str, to.to_string()));
+                            }
+                            Err(e) => add_output(&format!("Error renaming {}: {}", filename_str, e)),
+                        }
+                        add_output("\u{00A0}");
+                        send_action_result("rename_file", &filename_str, Some(&to.to_string()), &result);
+                    });
+                }
+                "delete_file" => {
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let filepath = FilePath::parse(&filename_str, &DirPath::root());
+                        let result = if is_read_only(&filepath) {
+                            Err("Read-only file".to_string())
+                        } else {
+                            remove_file(&filepath).await
+                        };
+                        match &result {
+                            Ok(_) => add_output(&format!("File deleted: {}", filename_str)),
+                            Err(e) => add_output(&format!("Error deleting {}: {}", filename_str, e)),
+                        }
+                        add_output("\u{00A0}");
+                        send_action_result("delete_file", &filename_str, None, &result);
+                    });
+                }
+                "hello" | "heartbeat" => note_tab(&tab_id(&obj), TabKind::Editor, &filename_str),
+                "goodbye" => note_tab_closed(&tab_id(&obj)),
+                "highlight_request" => {
+                    // The editor numbers its requests so it can ignore stale results
+                    let id = js_sys::Reflect::get(&obj, &JsValue::from_str("id")).unwrap_or(JsValue::NULL);
+                    let text = content.and_then(|c| c.as_string()).unwrap_or_default();
+                    send_highlight_result(&filename_str, &text, &id);
+                }
                 _ => {}
             }
         }
     }
 }
 
+// Tell the editor how a rename or delete it asked for went
+fn send_action_result(request: &str, filename: &str, new_filename: Option<&str>, result: &Result<(), String>) {
+    let Some(channel) = EDITOR_CHANNEL.with(|ch| ch.borrow().clone()) else {
+        return;
+    };
+    let message = js_sys::Object::new();
+    js_sys::Reflect::set(&message, &JsValue::from_str("action"), &JsValue::from_str("file_action_result")).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("request"), &JsValue::from_str(request)).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("filename"), &JsValue::from_str(filename)).ok();
+    if let Some(new_filename) = new_filename {
+        js_sys::Reflect::set(&message, &JsValue::from_str("new_filename"), &JsValue::from_str(new_filename)).ok();
+    }
+    js_sys::Reflect::set(&message, &JsValue::from_str("ok"), &JsValue::from_bool(result.is_ok())).ok();
+    if let Err(e) = result {
+        js_sys::Reflect::set(&message, &JsValue::from_str("error"), &JsValue::from_str(e)).ok();
+    }
+    channel.post_message(&message).ok();
+}
+
+// The ID a tab gives in its hello, heartbeat and goodbye messages
+fn tab_id(message: &js_sys::Object) -> String {
+    js_sys::Reflect::get(message, &JsValue::from_str("tab")).ok()
+        .and_then(|v| v.as_string())
+        .unwrap_or_default()
+}
+
 // Handle messages from pretty viewer
 pub fn handle_pretty_message(event: MessageEvent) {
     let data = event.data();
@@ -71,22 +335,39 @@ pub fn handle_pretty_message(event: MessageEvent) {
             let action_str = action.as_string().unwrap_or_default();
             let filename_str = filename.as_string().unwrap_or_default();
 
-            if action_str == "request_file" {
-                send_file_content(&filename_str, false);
+            match action_str.as_str() {
+                "request_file" => send_file_content(&filename_str, false),
+                "hello" | "heartbeat" => note_tab(&tab_id(&obj), TabKind::Pretty, &filename_str),
+                "goodbye" => note_tab_closed(&tab_id(&obj)),
+                _ => {}
             }
         }
     }
 }
 
 // Build a file_content message for BroadcastChannel
-fn build_file_content_message(filename: &str, content: &str) -> js_sys::Object {
+fn build_file_content_message(filename: &str, content: &str, exists: bool) -> js_sys::Object {
     let message = js_sys::Object::new();
     js_sys::Reflect::set(&message, &JsValue::from_str("action"), &JsValue::from_str("file_content")).ok();
     js_sys::Reflect::set(&message, &JsValue::from_str("filename"), &JsValue::from_str(filename)).ok();
     js_sys::Reflect::set(&message, &JsValue::from_str("content"), &JsValue::from_str(content)).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("exists"), &JsValue::from_bool(exists)).ok();
     message
 }
 
+// Add a post's title, date and tags to a file_content message
+fn add_front_matter(message: &js_sys::Object, front_matter: &FrontMatter) {
+    let set = |key: &str, value: Option<&String>| {
+        if let Some(value) = value {
+            js_sys::Reflect::set(message, &JsValue::from_str(key), &JsValue::from_str(value)).ok();
+        }
+    };
+    set("title", front_matter.title.as_ref());
+    set("date", front_matter.date.as_ref());
+    let tags: js_sys::Array = front_matter.tags.iter().map(|tag| JsValue::from_str(tag)).collect();
+    js_sys::Reflect::set(message, &JsValue::from_str("tags"), &tags).ok();
+}
+
 // Send file content via BroadcastChannel
 fn send_file_content(filename: &str, to_editor: bool) {
     let filepath = FilePath::parse(filename, &DirPath::root());
@@ -102,16 +383,46 @@ fn send_file_content(filename: &str, to_editor: bool) {
         // Spawn async task to get file content (works for both abyss and regular files)
         wasm_bindgen_futures::spawn_local(async move {
             match get_file_content(&filepath).await {
+                // The pretty viewer shows a post's front matter as a header above the rest
+                Ok(content) if !to_editor && is_markdown_name(&filepath.file) => {
+                    let (front_matter, body) = front_matter::split(&content);
+                    let message = build_file_content_message(&filename, body, true);
+                    if let Some(front_matter) = front_matter {
+                        add_front_matter(&message, &front_matter);
+                    }
+                    channel.post_message(&message).ok();
+                }
                 Ok(content) => {
-                    let message = build_file_content_message(&filename, &content);
+                    let message = build_file_content_message(&filename, &content, true);
                     channel.post_message(&message).ok();
                 }
                 Err(_) => {
                     // File not found, send empty content
-                    let message = build_file_content_message(&filename, "");
+                    let message = build_file_content_message(&filename, "", false);
                     channel.post_message(&message).ok();
                 }
             }
         });
     }
 }
+
+// Send the editor its text split into highlighted runs
+fn send_highlight_result(filename: &str, text: &str, id: &JsValue) {
+    let Some(channel) = EDITOR_CHANNEL.with(|ch| ch.borrow().clone()) else {
+        return;
+    };
+    let language = Language::from_name(filename);
+    let runs: js_sys::Array = highlight::highlight(language, text)
+        .into_iter()
+        .map(|(kind, text)| js_sys::Array::of2(&JsValue::from_str(kind), &JsValue::from_str(text)))
+        .collect();
+
+    let message = js_sys::Object::new();
+    js_sys::Reflect::set(&message, &JsValue::from_str("action"), &JsValue::from_str("highlight_result")).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("filename"), &JsValue::from_str(filename)).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("id"), id).ok();
+    let language = language.map_or(JsValue::NULL, |language| JsValue::from_str(language.name()));
+    js_sys::Reflect::set(&message, &JsValue::from_str("language"), &language).ok();
+    js_sys::Reflect::set(&message, &JsValue::from_str("runs"), &runs).ok();
+    channel.post_message(&message).ok();
+}
@@ -0,0 +1,101 @@
+use crate::js_interop::add_output;
+
+/// Tracks a multi-item operation (import, recursive copy/remove, prefetch...).
+/// Each item's result is reported as it happens, failures are collected rather
+/// than aborting the batch, and a summary table is produced at the end.
+pub struct Batch {
+    operation: String,
+    total: usize,
+    done: usize,
+    succeeded: usize,
+    skipped: Vec<(String, String)>,
+    failed: Vec<(String, String)>,
+    quiet: bool,
+}
+
+impl Batch {
+    pub fn new(operation: &str, total: usize) -> Self {
+        Self {
+            operation: operation.to_string(),
+            total,
+            done: 0,
+            succeeded: 0,
+            skipped: Vec::new(),
+            failed: Vec::new(),
+            quiet: false,
+        }
+    }
+
+    /// Don't print per-item progress (only the summary)
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    fn progress(&mut self, item: &str, status: &str) {
+        self.done += 1;
+        if !self.quiet {
+            let width = self.total.to_string().len();
+            add_output(&format!("[{:>width$}/{}] {} ... {}", self.done, self.total, item, status));
+        }
+    }
+
+    /// Record the result of one item
+    pub fn record(&mut self, item: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                self.succeeded += 1;
+                self.progress(item, "ok");
+            }
+            Err(e) => {
+                self.progress(item, &format!("failed: {}", e));
+                self.failed.push((item.to_string(), e));
+            }
+        }
+    }
+
+    /// Record an item that was deliberately left alone
+    pub fn skip(&mut self, item: &str, reason: &str) {
+        self.progress(item, &format!("skipped: {}", reason));
+        self.skipped.push((item.to_string(), reason.to_string()));
+    }
+
+    /// Summary table of the batch, listing every failure
+    pub fn summary(&self) -> String {
+        let mut rows = vec![("succeeded", self.succeeded)];
+        if !self.skipped.is_empty() {
+            rows.push(("skipped", self.skipped.len()));
+        }
+        rows.push(("failed", self.failed.len()));
+        rows.push(("total", self.done));
+
+        let width = self.done.to_string().len().max(5);
+        let mut out = format!("{} summary:", self.operation);
+        for (label, count) in rows {
+            out.push_str(&format!("\n  {:<10}{:>width$}", label, count));
+        }
+
+        if !self.failed.is_empty() {
+            let name_width = self.failed.iter().map(|(item, _)| item.len()).max().unwrap_or(0);
+            out.push_str("\nFailures:");
+            for (item, error) in &self.failed {
+                out.push_str(&format!("\n  {:<name_width$}  {}", item, error));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut batch = Batch::new("import", 3).quiet();
+        batch.record("/a.txt", Ok(()));
+        batch.record("/bb.txt", Err("bad".to_string()));
+        batch.skip("/c.txt", "exists");
+        assert_eq!(batch.summary(), "import summary:\n  succeeded     1\n  skipped       1\n  failed        1\n  total         3\nFailures:\n  /bb.txt  bad");
+    }
+}
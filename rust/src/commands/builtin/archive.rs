@@ -0,0 +1,228 @@
+use std::io::{Cursor, Read, Write};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use zip::write::SimpleFileOptions;
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::filesystem::{CURRENT_DIR, DirPath, FilePath, NextDir};
+use crate::filesystem::events::{emit, FsEvent};
+use crate::filesystem::helpers::{dir_exists, get_contents, get_directories, get_file_bytes, is_read_only, path_in_abyss, write_file};
+use super::filesystem::create_dir_all;
+
+/// Everything under `dir` as (path relative to `dir`, file), directories
+/// first with a trailing '/' and no file. Like `pick`, abyss directories
+/// only contribute their own files, since every level is a fetch.
+async fn walk(dir: &DirPath) -> Vec<(String, Option<FilePath>)> {
+    let mut entries = Vec::new();
+    let mut pending = vec![(dir.clone(), String::new())];
+
+    while let Some((current, prefix)) = pending.pop() {
+        let mut filenames: Vec<String> = get_contents(&current).await.0.into_keys().collect();
+        filenames.sort();
+        for filename in filenames {
+            entries.push((format!("{}{}", prefix, filename), Some(FilePath::new(current.clone(), filename))));
+        }
+        if path_in_abyss(&current) {
+            continue;
+        }
+        let mut subdirs: Vec<String> = get_directories(&current).await.0.into_iter().collect();
+        subdirs.sort();
+        for subdir in subdirs.into_iter().rev() {
+            let mut path = current.clone();
+            path.cd(&NextDir::In(subdir.clone()), true);
+            let name = format!("{}{}/", prefix, subdir);
+            entries.push((name.clone(), None));
+            pending.push((path, name));
+        }
+    }
+    entries
+}
+
+/// Zip up `dirpath` and everything under it, returning the archive and its entry names.
+/// Entries go under the directory's own name, as `zip -r archive dir` does.
+/// Errors are "ENTRY: message".
+pub async fn zip_directory(dirpath: &DirPath) -> Result<(Vec<u8>, Vec<String>), String> {
+    let root = dirpath.final_component().map(|name| format!("{}/", name)).unwrap_or_default();
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut names = Vec::new();
+
+    for (name, file) in walk(dirpath).await {
+        let name = format!("{}{}", root, name);
+        let added = match file {
+            None => writer.add_directory(name.as_str(), options).map_err(|e| e.to_string()),
+            Some(filepath) => match get_file_bytes(&filepath).await {
+                Ok(bytes) => {
+                    emit(FsEvent::FileRead(filepath));
+                    writer.start_file(name.as_str(), options)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| writer.write_all(&bytes).map_err(|e| e.to_string()))
+                }
+                Err(e) => Err(e),
+            },
+        };
+        added.map_err(|e| format!("{}: {}", name, e))?;
+        names.push(name);
+    }
+
+    let cursor = writer.finish().map_err(|e| format!("{}: {}", dirpath.to_string(), e))?;
+    Ok((cursor.into_inner(), names))
+}
+
+pub struct Zip;
+impl CommandData for Zip {
+    fn name(&self) -> &str { "zip" }
+    fn description(&self) -> &str { "Bundle a directory into a zip archive" }
+    fn usage(&self) -> &str { "zip <archive> <directory>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
+}
+impl Command for Zip {
+    async fn execute(&self, args: &[&str]) -> String {
+        let [archive_arg, dir_arg] = args else {
+            return self.usage_error();
+        };
+
+        let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
+        if !dir_exists(&dirpath).await {
+            return format!("zip: {}: No such directory", dir_arg);
+        }
+
+        let (bytes, names) = match zip_directory(&dirpath).await {
+            Ok(zipped) => zipped,
+            Err(e) => return format!("zip: {}", e),
+        };
+        let archive = CURRENT_DIR.with(|cd| FilePath::parse(archive_arg, &cd.borrow()));
+        if is_read_only(&archive) {
+            return format!("zip: {}: Read-only file", archive_arg);
+        }
+        if let Err(e) = write_file(&archive, bytes).await {
+            return format!("zip: {}: {}", archive_arg, e);
+        }
+        names.iter().map(|name| format!("  adding: {}", name)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+// Entry names that would land outside the destination
+fn escapes_destination(name: &str) -> bool {
+    name.starts_with('/') || name.split(['/', '\\']).any(|part| part == "..")
+}
+
+pub struct Unzip;
+impl CommandData for Unzip {
+    fn name(&self) -> &str { "unzip" }
+    fn description(&self) -> &str { "Extract a zip archive (into destination, or here)" }
+    fn usage(&self) -> &str { "unzip <archive> [destination]" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
+}
+impl Command for Unzip {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (archive_arg, dest) = match args {
+            [archive_arg] => (*archive_arg, CURRENT_DIR.with(|cd| cd.borrow().clone())),
+            [archive_arg, dest_arg] => (*archive_arg, CURRENT_DIR.with(|cd| DirPath::parse(dest_arg, &cd.borrow()))),
+            _ => return self.usage_error(),
+        };
+
+        let archive_path = CURRENT_DIR.with(|cd| FilePath::parse(archive_arg, &cd.borrow()));
+        let Ok(bytes) = get_file_bytes(&archive_path).await else {
+            return format!("unzip: {}: No such file", archive_arg);
+        };
+        emit(FsEvent::FileRead(archive_path));
+        let Ok(mut archive) = ZipArchive::new(Cursor::new(bytes)) else {
+            return format!("unzip: {}: Not a zip archive", archive_arg);
+        };
+
+        if let Err(e) = create_dir_all(&dest).await {
+            return format!("unzip: {}: {}", dest.to_string(), e);
+        }
+
+        let mut lines = Vec::new();
+        for index in 0..archive.len() {
+            let mut entry = match archive.by_index(index) {
+                Ok(entry) => entry,
+                Err(zip::result::ZipError::UnsupportedArchive(_)) => {
+                    return format!("unzip: {}: Encrypted or unsupported archive", archive_arg);
+                }
+                Err(e) => return format!("unzip: {}: {}", archive_arg, e),
+            };
+            let name = entry.name().to_string();
+            if escapes_destination(&name) {
+                lines.push(format!("unzip: {}: Outside the destination (skipped)", name));
+                continue;
+            }
+
+            if entry.is_dir() {
+                let dirpath = DirPath::parse(&name, &dest);
+                if let Err(e) = create_dir_all(&dirpath).await {
+                    return format!("unzip: {}: {}", name, e);
+                }
+                lines.push(format!("   creating: {}", name));
+                continue;
+            }
+
+            let filepath = FilePath::parse(&name, &dest);
+            if is_read_only(&filepath) {
+                lines.push(format!("unzip: {}: Read-only file (skipped)", name));
+                continue;
+            }
+            let mut content = Vec::new();
+            if let Err(e) = entry.read_to_end(&mut content) {
+                return format!("unzip: {}: {}", name, e);
+            }
+            if let Err(e) = create_dir_all(&filepath.dir).await {
+                return format!("unzip: {}: {}", name, e);
+            }
+            if let Err(e) = write_file(&filepath, content).await {
+                return format!("unzip: {}: {}", name, e);
+            }
+            lines.push(format!("  inflating: {}", name));
+        }
+        lines.join("\n")
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Zip);
+    registry.add(Unzip);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_escapes_destination() {
+        assert!(!escapes_destination("docs/notes.txt"));
+        assert!(!escapes_destination("docs/..hidden"));
+        assert!(escapes_destination("../notes.txt"));
+        assert!(escapes_destination("docs/../../notes.txt"));
+        assert!(escapes_destination("/etc/passwd"));
+    }
+
+    #[test]
+    fn test_zip_and_unzip() {
+        enable(1);
+        run("mkdir -p docs/drafts");
+        crate::write_file("docs/notes.txt", "milk".to_string()).unwrap();
+        crate::write_file("docs/drafts/post.md", "# Hello".to_string()).unwrap();
+
+        assert_eq!(run("zip docs.zip docs"), [
+            "  adding: docs/notes.txt",
+            "  adding: docs/drafts/",
+            "  adding: docs/drafts/post.md",
+        ]);
+        assert_eq!(run("file docs.zip"), ["docs.zip: Zip archive"]);
+        assert_eq!(run("save -r docs"), ["Downloading: docs.zip (2 file(s))"]);
+        assert_eq!(run("save -r nowhere"), ["save: nowhere: No such directory"]);
+
+        run("rm -r docs");
+        assert_eq!(run("unzip docs.zip backup"), [
+            "  inflating: docs/notes.txt",
+            "   creating: docs/drafts/",
+            "  inflating: docs/drafts/post.md",
+        ]);
+        assert_eq!(run("cat backup/docs/drafts/post.md"), ["# Hello"]);
+        assert_eq!(run("cat backup/docs/notes.txt"), ["milk"]);
+
+        assert_eq!(run("unzip about.txt"), ["unzip: about.txt: Not a zip archive"]);
+        assert_eq!(run("zip out.zip nowhere"), ["zip: nowhere: No such directory"]);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::digest::{ALGORITHMS, hex_digest};
+use crate::filesystem::{CURRENT_DIR, FilePath};
+use crate::filesystem::events::{emit, FsEvent};
+use crate::filesystem::helpers::get_file_bytes;
+
+// Printable ASCII as itself, anything else as '.'
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }
+}
+
+// Parse "[FLAG LENGTH] FILE" for hexdump -n / xxd -l
+fn parse_limit<'a>(args: &[&'a str], command: &dyn CommandData, flag: &str) -> Result<(Option<usize>, &'a str), String> {
+    match args {
+        [path_arg] if !path_arg.starts_with('-') => Ok((None, path_arg)),
+        [f, n, path_arg] if *f == flag => n.parse()
+            .map(|n| (Some(n), *path_arg))
+            .map_err(|_| format!("{}: {}: Invalid length", command.name(), n)),
+        _ => Err(command.usage_error()),
+    }
+}
+
+// Bytes of a file, up to `limit`
+async fn read_bytes(command: &str, path_arg: &str, limit: Option<usize>) -> Result<Vec<u8>, String> {
+    let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+    let mut bytes = get_file_bytes(&filepath).await
+        .map_err(|_| format!("{}: {}: No such file", command, path_arg))?;
+    emit(FsEvent::FileRead(filepath));
+    if let Some(limit) = limit {
+        bytes.truncate(limit);
+    }
+    Ok(bytes)
+}
+
+/// Canonical hex+ASCII display (like `hexdump -C`), ending with the total length
+fn hexdump_lines(bytes: &[u8]) -> Vec<String> {
+    let mut lines: Vec<String> = bytes.chunks(16).enumerate()
+        .map(|(row, chunk)| {
+            let mut hex = String::new();
+            for i in 0..16 {
+                match chunk.get(i) {
+                    Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                    None => hex.push_str("   "),
+                }
+                if i == 7 {
+                    hex.push(' ');
+                }
+            }
+            let ascii: String = chunk.iter().map(|byte| printable(*byte)).collect();
+            format!("{:08x}  {} |{}|", row * 16, hex, ascii)
+        })
+        .collect();
+    if !bytes.is_empty() {
+        lines.push(format!("{:08x}", bytes.len()));
+    }
+    lines
+}
+
+/// xxd's default display: offset, bytes in pairs, then ASCII
+fn xxd_lines(bytes: &[u8]) -> Vec<String> {
+    bytes.chunks(16).enumerate()
+        .map(|(row, chunk)| {
+            let hex: String = chunk.chunks(2)
+                .map(|pair| pair.iter().map(|byte| format!("{:02x}", byte)).collect::<String>() + " ")
+                .collect();
+            let ascii: String = chunk.iter().map(|byte| printable(*byte)).collect();
+            format!("{:08x}: {:<40} {}", row * 16, hex, ascii)
+        })
+        .collect()
+}
+
+pub struct Hexdump;
+impl CommandData for Hexdump {
+    fn name(&self) -> &str { "hexdump" }
+    fn description(&self) -> &str { "Show a file's bytes in hex and ASCII (for binary files)" }
+    fn usage(&self) -> &str { "hexdump [-n LENGTH] <file>" }
+    fn category(&self) -> Option<Category> { Some(Category::Text) }
+}
+impl Command for Hexdump {
+    async fn execute(&self, args: &[&str]) -> String {
+        let result = async {
+            let (limit, path_arg) = parse_limit(args, self, "-n")?;
+            read_bytes("hexdump", path_arg, limit).await
+        }.await;
+
+        match result {
+            Ok(bytes) => hexdump_lines(&bytes).join("\n"),
+            Err(e) => e,
+        }
+    }
+}
+
+pub struct Xxd;
+impl CommandData for Xxd {
+    fn name(&self) -> &str { "xxd" }
+    fn description(&self) -> &str { "Show a file's bytes in hex, xxd style" }
+    fn usage(&self) -> &str { "xxd [-l LENGTH] <file>" }
+    fn category(&self) -> Option<Category> { Some(Category::Text) }
+}
+impl Command for Xxd {
+    async fn execute(&self, args: &[&str]) -> String {
+        let result = async {
+            let (limit, path_arg) = parse_limit(args, self, "-l")?;
+            read_bytes("xxd", path_arg, limit).await
+        }.await;
+
+        match result {
+            Ok(bytes) => xxd_lines(&bytes).join("\n"),
+            Err(e) => e,
+        }
+    }
+}
+
+pub struct Hash;
+impl CommandData for Hash {
+    fn name(&self) -> &str { "hash" }
+    fn description(&self) -> &str { "Print a file's sha256, md5 or crc32 digest" }
+    fn usage(&self) -> &str { "hash <sha256|md5|crc32> <file>..." }
+    fn category(&self) -> Option<Category> { Some(Category::Text) }
+}
+impl Command for Hash {
+    async fn execute(&self, args: &[&str]) -> String {
+        let [algorithm, paths @ ..] = args else {
+            return self.usage_error();
+        };
+        if paths.is_empty() {
+            return self.usage_error();
+        }
+        if !ALGORITHMS.contains(algorithm) {
+            return format!("hash: {}: Unknown algorithm (use {})", algorithm, ALGORITHMS.join(", "));
+        }
+
+        let mut lines = Vec::new();
+        for path_arg in paths {
+            // Like sha256sum and friends: "DIGEST  FILE"
+            match read_bytes("hash", path_arg, None).await {
+                Ok(bytes) => lines.push(format!("{}  {}", hex_digest(algorithm, &bytes).unwrap_or_default(), path_arg)),
+                Err(e) => lines.push(e),
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Hexdump);
+    registry.add(Xxd);
+    registry.add(Hash);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+    use crate::commands::OnConflict;
+    use crate::filesystem::{DirPath, FilePath};
+
+    const SAMPLE: &[u8] = b"Hello, world!\n\xff\x00abcdefghijklmnop";
+
+    #[test]
+    fn test_hexdump_lines() {
+        assert_eq!(hexdump_lines(SAMPLE), [
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a ff 00  |Hello, world!...|",
+            "00000010  61 62 63 64 65 66 67 68  69 6a 6b 6c 6d 6e 6f 70  |abcdefghijklmnop|",
+            "00000020",
+        ]);
+        assert_eq!(hexdump_lines(b"hi\n"), [
+            "00000000  68 69 0a                                          |hi.|",
+            "00000003",
+        ]);
+        assert!(hexdump_lines(b"").is_empty());
+    }
+
+    #[test]
+    fn test_xxd_lines() {
+        assert_eq!(xxd_lines(SAMPLE), [
+            "00000000: 4865 6c6c 6f2c 2077 6f72 6c64 210a ff00  Hello, world!...",
+            "00000010: 6162 6364 6566 6768 696a 6b6c 6d6e 6f70  abcdefghijklmnop",
+        ]);
+        assert_eq!(xxd_lines(b"hi\n"), ["00000000: 6869 0a                                  hi."]);
+    }
+
+    #[test]
+    fn test_binary_files() {
+        enable(1);
+        let picture = FilePath::parse("/picture.bin", &DirPath::root());
+        crate::VIRTUAL_FS.with_borrow_mut(|vfs| vfs.write_file(&picture, vec![0x89, b'P', b'N', b'G', 0xff, 0x00]));
+
+        assert_eq!(run("cat picture.bin"), ["cat: picture.bin: Binary file (use hexdump or xxd to view it)"]);
+        assert_eq!(run("edit picture.bin"), ["edit: picture.bin: Binary file (use hexdump or xxd to view it)"]);
+        assert_eq!(run("xxd picture.bin"), ["00000000: 8950 4e47 ff00                           .PNG.."]);
+        assert_eq!(run("hexdump -n 4 picture.bin"), [
+            "00000000  89 50 4e 47                                       |.PNG|",
+            "00000004",
+        ]);
+        assert_eq!(run("hexdump -n x picture.bin"), ["hexdump: x: Invalid length"]);
+        assert_eq!(run("xxd missing.bin"), ["xxd: missing.bin: No such file"]);
+
+        // Static text files can be inspected too
+        assert_eq!(run("xxd -l 4 about.txt").len(), 1);
+
+        assert_eq!(run("hash sha256 picture.bin missing.bin"), [
+            "854e4b5f04c08dd7a8df75e0c0c9ce444f353bf1385a8a7ddbc9c73c4181ce0b  picture.bin",
+            "hash: missing.bin: No such file",
+        ]);
+        assert_eq!(run("hash crc32 picture.bin"), ["2d546241  picture.bin"]);
+        assert_eq!(run("hash sha1 picture.bin"), ["hash: sha1: Unknown algorithm (use sha256, md5, crc32)"]);
+
+        // Binary files survive a session round trip
+        let session = crate::commands::export_session();
+        crate::VIRTUAL_FS.with_borrow_mut(|vfs| vfs.remove_file(&picture));
+        block_on(crate::commands::import_session(session, true, &OnConflict::Overwrite));
+        assert_eq!(run("xxd picture.bin"), ["00000000: 8950 4e47 ff00                           .PNG.."]);
+    }
+}
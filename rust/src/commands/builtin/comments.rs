@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::filesystem::{CURRENT_DIR, FilePath};
+use crate::filesystem::helpers::{fetch_text, post_json};
+use crate::settings::get_setting;
+
+/// Settings key holding the comment backend, which accepts `GET ?post=<path>`
+/// and `POST {post, author, body}`. While it's unset, comments are read-only
+/// and come from static files under ./comments/
+pub const COMMENTS_ENDPOINT_KEY: &str = "comments.endpoint";
+
+fn backend() -> Option<String> {
+    get_setting(COMMENTS_ENDPOINT_KEY)
+}
+
+// `text` percent-encoded for a URL path or query value (keeping '/' readable)
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// Where a post's comment thread is fetched from
+fn thread_url(post: &FilePath) -> String {
+    match backend() {
+        Some(backend) => format!("{}?post={}", backend, percent_encode(&post.to_string())),
+        None => format!("./comments{}.json", percent_encode(&post.to_string())),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Comment {
+    #[serde(default = "anonymous")]
+    pub author: String,
+    #[serde(default)]
+    pub date: String,
+    pub body: String,
+}
+
+fn anonymous() -> String {
+    "anonymous".to_string()
+}
+
+#[derive(Serialize)]
+struct NewComment<'a> {
+    post: &'a str,
+    author: &'a str,
+    body: &'a str,
+}
+
+// Fetch the comment thread for a post from the backend, or the static fallback
+async fn fetch_comments(post: &FilePath) -> Result<Vec<Comment>, String> {
+    let text = fetch_text(&thread_url(post)).await?;
+    serde_json::from_str(&text).map_err(|e| format!("Malformed comment thread: {}", e))
+}
+
+fn format_thread(post: &FilePath, comments: &[Comment]) -> String {
+    if comments.is_empty() {
+        return format!("No comments on {} yet.", post.to_string());
+    }
+
+    let mut out = format!("Comments on {} ({}):", post.to_string(), comments.len());
+    for comment in comments {
+        out.push_str("\n\n");
+        if comment.date.is_empty() {
+            out.push_str(&comment.author);
+        } else {
+            out.push_str(&format!("{} - {}", comment.author, comment.date));
+        }
+        for line in comment.body.lines() {
+            out.push_str(&format!("\n  {}", line));
+        }
+    }
+    out
+}
+
+pub struct Comments;
+impl CommandData for Comments {
+    fn name(&self) -> &str { "comments" }
+    fn description(&self) -> &str { "Show the comment thread for a post ('comments add' to post)" }
+    fn usage(&self) -> &str { "comments <post>\ncomments add <post> [--as NAME] <text...>" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+impl Command for Comments {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] | ["add"] | ["add", _] => self.usage_error(),
+            ["add", post_arg, rest @ ..] => {
+                let Some(backend) = backend() else {
+                    return "comments: Comments are read-only on this site".to_string();
+                };
+
+                let post = CURRENT_DIR.with(|cd| FilePath::parse(post_arg, &cd.borrow()));
+                if !post.exists().await {
+                    return format!("comments: {}: No such file", post_arg);
+                }
+
+                let (author, words) = match rest {
+                    ["--as", name, words @ ..] => (name.to_string(), words),
+                    words => (anonymous(), words),
+                };
+                if words.is_empty() {
+                    return self.usage_error();
+                }
+
+                let body = serde_json::to_string(&NewComment {
+                    post: &post.to_string(),
+                    author: &author,
+                    body: &words.join(" "),
+                }).unwrap_or_default();
+
+                match post_json(&backend, &body).await {
+                    Ok(_) => format!("Comment posted on {}", post.to_string()),
+                    Err(e) => format!("comments: {}", e),
+                }
+            }
+            [post_arg] => {
+                let post = CURRENT_DIR.with(|cd| FilePath::parse(post_arg, &cd.borrow()));
+                if !post.exists().await {
+                    return format!("comments: {}: No such file", post_arg);
+                }
+
+                match fetch_comments(&post).await {
+                    Ok(comments) => format_thread(&post, &comments),
+                    // No static thread file just means nobody has commented
+                    Err(_) if backend().is_none() => format_thread(&post, &[]),
+                    Err(e) => format!("comments: {}", e),
+                }
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Comments);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::DirPath;
+    use crate::fixtures::{enable, run};
+    use crate::settings::set_setting;
+
+    #[test]
+    fn test_thread_url() {
+        let post = FilePath::parse("/blog/q&a #1.md", &DirPath::root());
+        assert_eq!(thread_url(&post), "./comments/blog/q%26a%20%231.md.json");
+        set_setting(COMMENTS_ENDPOINT_KEY, "https://comments.example.com/thread");
+        assert_eq!(thread_url(&post), "https://comments.example.com/thread?post=/blog/q%26a%20%231.md");
+    }
+
+    #[test]
+    fn test_one_post_at_a_time() {
+        enable(1);
+        assert_eq!(run("comments about.txt"), ["No comments on /about.txt yet."]);
+        assert_eq!(run("comments about.txt fortunes.txt"), [
+            "Usage: comments <post>",
+            "       comments add <post> [--as NAME] <text...>",
+        ]);
+    }
+}
@@ -1,9 +1,12 @@
-use crate::{commands::{Command, CommandData, command_data}, filesystem::{DirPath, FilePath, file_paths::{HELP_FILE_PATH, HELP_VERBOSE_FILE_PATH, SIMPLE_MANUAL_DIR_PATH, VERBOSE_MANUAL_DIR_PATH}, helpers::get_file_content}};
+use crate::{commands::{COMMANDS, Category, Command, CommandData, Registry, UserDefined}, filesystem::{CURRENT_DIR, DirPath, FilePath, NextDir, file_paths::{HELP_NOTES_FILE_PATH, SIMPLE_MANUAL_DIR_PATH, VERBOSE_MANUAL_DIR_PATH}, regions::in_region, front_matter::is_markdown_name, helpers::{dir_exists, path_in_abyss, file_front_matter, get_contents, get_directories, get_file_content, today_iso}}, fortune, hit::fetch_popular, script, tips, wizard::{self, Answers, Question, Wizard}};
 
 pub struct Help;
 
 impl CommandData for Help {
     fn name(&self) -> &str { "help" }
+    fn description(&self) -> &str { "Show a help message (-v for verbose)" }
+    fn usage(&self) -> &str { "help [-v] [command]" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
 }
 
 impl Command for Help {
@@ -45,17 +48,17 @@ impl Command for Help {
                 true
             }
         }).collect::<Vec<_>>();
-        let mut filepath = match args.get(0) {
-            Some(&&command) => {
-                command_data(command).manual(verbose)
-            },
-            None => {
-                if verbose {
-                    HELP_VERBOSE_FILE_PATH.clone()
-                } else {
-                    HELP_FILE_PATH.clone()
-                }
-            }
+        let Some(&&command) = args.first() else {
+            let content = overview(verbose).await;
+            return match tips::occasional_tip().await {
+                Some(tip) => format!("{}\n\nTip: {}", content, tip),
+                None => content,
+            };
+        };
+        let builtin = COMMANDS.get(command);
+        let mut filepath = match builtin {
+            Some(builtin) => builtin.manual(verbose),
+            None => UserDefined(command.to_string()).manual(verbose),
         };
         match get_file_content(&filepath).await {
             Ok(content) => content,
@@ -63,21 +66,131 @@ impl Command for Help {
                 // If could not find verbose, try simple instead.
                 if filepath.dir == *VERBOSE_MANUAL_DIR_PATH {
                     filepath.dir = SIMPLE_MANUAL_DIR_PATH.clone();
-                    match get_file_content(&filepath).await {
-                        Ok(content) => return content,
-                        Err(_) => {}
+                    if let Ok(content) = get_file_content(&filepath).await {
+                        return content;
                     }
                 }
-                "Could not find relevant help page".to_string()
+                // Manuals are optional: a command without one still has its usage
+                match builtin {
+                    Some(builtin) if builtin.description().is_empty() => builtin.usage_error(),
+                    Some(builtin) => format!("{} - {}\n\n{}", builtin.name(), builtin.description(), builtin.usage_error()),
+                    None => match script::find_command(command).await {
+                        Some(script) => format!("{} runs the script {}", command, script.to_string()),
+                        None => "Could not find relevant help page".to_string(),
+                    },
+                }
             }
         }
     }
 }
 
+const HELP_FOOTER: &str = "For help on a specific command:
+  help COMMAND        Show brief help for a command
+  help -v COMMAND     Show detailed help with examples
+
+Type 'help -v' for detailed usage information.";
+
+/// The registered commands by category: just their names, or with -v each
+/// one's usage and description, followed by the notes in help-notes.txt
+async fn overview(verbose: bool) -> String {
+    let mut out = String::from("Available commands:\n");
+    for category in Category::ALL {
+        // Commands behind a feature that's off aren't available, so aren't listed
+        let commands: Vec<_> = COMMANDS.iter()
+            .filter(|command| command.category() == Some(category))
+            .filter(|command| crate::features::disabled_flag_for(command.name()).is_none())
+            .collect();
+        if commands.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n{}:\n", category.title()));
+        if !verbose {
+            let names: Vec<&str> = commands.iter().map(|command| command.name()).collect();
+            out.push_str(&format!("  {}\n", names.join(", ")));
+            continue;
+        }
+        for command in commands {
+            let mut forms = command.usage().lines();
+            if let Some(first) = forms.next() {
+                out.push_str(&format!("  {:<14} - {}\n", first, command.description()));
+            }
+            for form in forms {
+                out.push_str(&format!("  {}\n", form));
+            }
+        }
+    }
+    let footer = match verbose {
+        true => get_file_content(&HELP_NOTES_FILE_PATH).await.unwrap_or_default(),
+        false => HELP_FOOTER.to_string(),
+    };
+    format!("{}\n{}", out, footer).trim_end().to_string()
+}
+
+pub struct Tip;
+
+impl CommandData for Tip {
+    fn name(&self) -> &str { "tip" }
+    fn description(&self) -> &str { "Show the next tip of the day (or list them all)" }
+    fn usage(&self) -> &str { "tip [all]" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+
+impl Command for Tip {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => match tips::next_tip().await {
+                Some(tip) => format!("Tip: {}", tip),
+                None => "No tips available.".to_string(),
+            },
+            ["all"] => {
+                let tips = tips::tips().await;
+                let width = tips.len().to_string().len();
+                tips.iter().enumerate()
+                    .map(|(i, tip)| format!("{:>width$}. {}", i + 1, tip))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub struct Fortune;
+
+impl CommandData for Fortune {
+    fn name(&self) -> &str { "fortune" }
+    fn description(&self) -> &str { "Show a random saying (-c for one from a category, -l to list them)" }
+    fn usage(&self) -> &str { "fortune [-c <category>]\nfortune -l" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+
+impl Command for Fortune {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => fortune::pick(None).await.unwrap_or_else(|| "fortune: No fortunes available".to_string()),
+            ["-c", category] => fortune::pick(Some(category)).await
+                .unwrap_or_else(|| format!("fortune: {}: No such category", category)),
+            ["-l"] => {
+                let categories = fortune::categories().await;
+                if categories.is_empty() {
+                    return "fortune: No categories".to_string();
+                }
+                categories.iter()
+                    .map(|(name, count)| format!("{:<14} {}", name, count))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
 pub struct About;
 
 impl CommandData for About {
     fn name(&self) -> &str { "about" }
+    fn description(&self) -> &str { "About this site" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
 }
 
 impl Command for About {
@@ -94,6 +207,8 @@ pub struct Contact;
 
 impl CommandData for Contact {
     fn name(&self) -> &str { "contact" }
+    fn description(&self) -> &str { "Contact information" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
 }
 
 impl Command for Contact {
@@ -105,3 +220,191 @@ impl Command for Contact {
         }
     }
 }
+
+pub struct Popular;
+
+impl CommandData for Popular {
+    fn name(&self) -> &str { "popular" }
+    fn description(&self) -> &str { "List the most viewed posts (if view counting is enabled)" }
+    fn usage(&self) -> &str { "popular [count]" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+
+impl Command for Popular {
+    async fn execute(&self, args: &[&str]) -> String {
+        let count = match args.first() {
+            Some(n) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => return self.usage_error(),
+            },
+            None => 10,
+        };
+
+        match fetch_popular().await {
+            Ok(views) if views.is_empty() => "No views recorded yet.".to_string(),
+            Ok(views) => {
+                let views = &views[..count.min(views.len())];
+                let width = views[0].views.to_string().len();
+                let mut out = "Most viewed:".to_string();
+                for (rank, page) in views.iter().enumerate() {
+                    out.push_str(&format!("\n{:>2}. {:>width$}  {}", rank + 1, page.views, page.path));
+                }
+                out
+            }
+            Err(e) => format!("popular: {}", e),
+        }
+    }
+}
+
+pub struct Blog;
+
+impl CommandData for Blog {
+    fn name(&self) -> &str { "blog" }
+    fn description(&self) -> &str { "List posts with their dates and titles, newest first" }
+    fn usage(&self) -> &str { "blog [directory]" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+
+impl Command for Blog {
+    async fn execute(&self, args: &[&str]) -> String {
+        let dir_arg = match args {
+            [] => "/blog",
+            [dir_arg] => dir_arg,
+            _ => return self.usage_error(),
+        };
+        let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
+        if !dir_exists(&dirpath).await {
+            return format!("blog: {}: No such directory", dir_arg);
+        }
+
+        // Every markdown post below the directory, with its front matter if it has some
+        // (not wandering into the abyss from outside it, nor the endless cave of dice)
+        let in_abyss = path_in_abyss(&dirpath);
+        let mut posts = Vec::new();
+        let mut dirs = vec![dirpath];
+        while let Some(dir) = dirs.pop() {
+            for (name, content) in get_contents(&dir).await.0 {
+                if is_markdown_name(&name) {
+                    let filepath = FilePath::new(dir.clone(), name);
+                    let front_matter = file_front_matter(&filepath, &content).unwrap_or_default();
+                    posts.push((filepath, front_matter));
+                }
+            }
+            for subdir in get_directories(&dir).await.0 {
+                let mut subdir_path = dir.clone();
+                subdir_path.cd(&NextDir::In(subdir), true);
+                if (in_abyss || !path_in_abyss(&subdir_path)) && !in_region(&subdir_path) {
+                    dirs.push(subdir_path);
+                }
+            }
+        }
+        if posts.is_empty() {
+            return format!("blog: {}: No posts", dir_arg);
+        }
+
+        // Newest first; undated posts last, by path
+        posts.sort_by(|(a_path, a), (b_path, b)| {
+            b.date.cmp(&a.date).then_with(|| a_path.to_string().cmp(&b_path.to_string()))
+        });
+        let width = posts.iter().map(|(_, front_matter)| front_matter.date.as_deref().unwrap_or("-").chars().count()).max().unwrap_or(1);
+        posts.iter()
+            .map(|(filepath, front_matter)| {
+                let date = front_matter.date.as_deref().unwrap_or("-");
+                let title = front_matter.title.clone().unwrap_or_else(|| filepath.file.clone());
+                format!("{:<width$}  {}  ({})", date, title, filepath.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// new-post's templates: a name, and what goes under the front matter
+const POST_TEMPLATES: &[(&str, &str)] = &[
+    ("post", "Write your post here.\n"),
+    ("note", "- \n"),
+    ("math", "Let $x \\in \\RR$.\n\n$$\nx^2 \\geq 0\n$$\n"),
+];
+
+pub struct NewPost;
+
+impl CommandData for NewPost {
+    fn name(&self) -> &str { "new-post" }
+    fn description(&self) -> &str { "Start a new post by answering a few questions" }
+    fn usage(&self) -> &str { "new-post [directory]" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+
+impl Command for NewPost {
+    async fn execute(&self, args: &[&str]) -> String {
+        let dir_arg = match args {
+            [] => "/blog",
+            [dir_arg] if !dir_arg.starts_with('-') => dir_arg,
+            _ => return self.usage_error(),
+        };
+        let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
+        wizard::start(PostWizard { dirpath })
+    }
+}
+
+// Asks for a post's title, tags and template, and writes it (dated today) as <title>.md
+struct PostWizard {
+    dirpath: DirPath,
+}
+
+impl Wizard for PostWizard {
+    fn name(&self) -> &str { "new-post" }
+
+    fn questions(&self) -> Vec<Question> {
+        let templates: Vec<&str> = POST_TEMPLATES.iter().map(|(name, _)| *name).collect();
+        vec![
+            Question::new("title", "Title:").with_check(wizard::required),
+            Question::new("tags", "Tags (comma separated, or none):"),
+            Question::new("template", &format!("Template ({}):", templates.join(", ")))
+                .with_default("post")
+                .with_check(|answer| {
+                    let answer = answer.trim().to_lowercase();
+                    match POST_TEMPLATES.iter().any(|(name, _)| *name == answer) {
+                        true => Ok(answer),
+                        false => Err(format!("No template called '{}'.", answer)),
+                    }
+                }),
+        ]
+    }
+
+    fn assemble(&self, answers: &Answers) -> Result<(FilePath, String), String> {
+        let title = &answers["title"];
+        let tags: Vec<&str> = answers["tags"].split(',').map(str::trim).filter(|tag| !tag.is_empty()).collect();
+        let body = POST_TEMPLATES.iter()
+            .find(|(name, _)| *name == answers["template"])
+            .map_or("", |(_, body)| body);
+
+        let mut post = format!("---\ntitle: \"{}\"\ndate: {}\n", title.replace('"', "'"), today_iso());
+        if !tags.is_empty() {
+            post.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+        }
+        post.push_str("---\n\n");
+        post.push_str(body);
+        Ok((FilePath::new(self.dirpath.clone(), format!("{}.md", slug(title))), post))
+    }
+}
+
+// A title as a file name: lowercase words joined by dashes
+fn slug(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() { "post".to_string() } else { words.join("-") }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Help);
+    registry.add(About);
+    registry.add(Contact);
+    registry.add(Blog);
+    registry.add(NewPost);
+    registry.add(Popular);
+    registry.add(Tip);
+    registry.add(Fortune);
+}
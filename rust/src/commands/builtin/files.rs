@@ -1,22 +1,41 @@
-use crate::commands::{Command, CommandData, export_session, import_session};
-use crate::filesystem::{CURRENT_DIR, FilePath, VIRTUAL_FS};
-use crate::filesystem::helpers::{get_file_content, path_in_abyss, write_file_abyss};
-use crate::js_interop::{prompt_file_picker, trigger_download};
+use std::collections::BTreeSet;
+use crate::commands::{Category, Command, CommandData, Registry, OnConflict, SessionChanges, compress_session, diff_session, export_session, export_subtree, import_session, json_output, remap_session, session_conflicts, session_from_bytes, take_json_flag};
+use crate::filesystem::{CURRENT_DIR, Content, DirPath, FilePath, VIRTUAL_FS, fetch_cache, quota};
+use crate::persist;
+use crate::filesystem::helpers::{dir_exists, fetch_text, file_exists, get_file_bytes, has_in_memory_content, is_read_only, path_in_abyss, write_file};
+use crate::settings::get_setting;
+use crate::channels::{allow_forced_edit, open_tabs};
+use crate::js_interop::{now, prompt_file_picker, trigger_download};
+use super::archive::zip_directory;
 use wasm_bindgen_futures::JsFuture;
 use js_sys::{Uint8Array, Date};
 
 pub struct Edit;
 impl CommandData for Edit {
     fn name(&self) -> &str { "edit" }
+    fn description(&self) -> &str { "Edit or create a file (opens in new tab; -f for site content)" }
+    fn usage(&self) -> &str { "edit [-f] <filename>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
 }
 impl Command for Edit {
     async fn execute(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: edit <filename>".to_string();
-        }
+        let (force, path_arg) = match args {
+            ["-f", path_arg] => (true, *path_arg),
+            [path_arg] if !path_arg.starts_with('-') => (false, *path_arg),
+            _ => return self.usage_error(),
+        };
 
-        let path_arg = args[0];
         let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+        if is_read_only(&filepath) {
+            if !force {
+                return format!("edit: {}: Read-only file (use edit -f to edit it anyway)", path_arg);
+            }
+            allow_forced_edit(&filepath);
+        }
+        // The editor works on text, so saving would mangle a binary file
+        if get_file_bytes(&filepath).await.is_ok_and(|bytes| std::str::from_utf8(&bytes).is_err()) {
+            return format!("edit: {}: Binary file (use hexdump or xxd to view it)", path_arg);
+        }
 
         // Open editor for this file (create new or edit existing)
         let url = format!("./editor.html?file={}", filepath.to_string());
@@ -32,69 +51,109 @@ impl Command for Edit {
     }
 }
 
+pub struct Tabs;
+impl CommandData for Tabs {
+    fn name(&self) -> &str { "tabs" }
+    fn description(&self) -> &str { "List the editor and pretty tabs that are open" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
+}
+impl Command for Tabs {
+    async fn execute(&self, args: &[&str]) -> String {
+        if !args.is_empty() {
+            return self.usage_error();
+        }
+        let tabs = open_tabs();
+        if tabs.is_empty() {
+            return "No editor or pretty tabs open".to_string();
+        }
+        let now = now();
+        let mut lines = vec![format!("{} tab(s) open:", tabs.len())];
+        for tab in tabs {
+            let seconds = ((now - tab.last_seen) / 1000.0).max(0.0) as u64;
+            lines.push(format!("  {:<7} {}  (tab {}, last heard from {}s ago)", tab.kind.name(), tab.filename, tab.id, seconds));
+        }
+        lines.join("\n")
+    }
+}
+
 pub struct Load;
 impl CommandData for Load {
     fn name(&self) -> &str { "load" }
+    fn description(&self) -> &str { "Upload a file from your device (asks before overwriting your changes)" }
+    fn usage(&self) -> &str { "load [-f] <filename>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
 }
 impl Command for Load {
     async fn execute(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: load <filename>\n\nOpens a file picker to load a file from your device into the virtual filesystem.".to_string();
-        }
+        let (force, target_filename) = match args {
+            ["-f", target_filename] => (true, *target_filename),
+            [target_filename] if !target_filename.starts_with('-') => (false, *target_filename),
+            _ => return format!("{}\n\nOpens a file picker to load a file from your device into the virtual filesystem.", self.usage_error()),
+        };
 
-        let target_filename = args[0].to_string();
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(target_filename, &cd.borrow()));
+        if !force && is_read_only(&filepath) {
+            return format!("load: {}: Read-only file (use load -f to overwrite it anyway)", target_filename);
+        }
 
-        // Prompt for file picker (returns binary data)
-        let file_data = JsFuture::from(prompt_file_picker(".kh,.txt,.md")).await;
+        // Don't silently replace the user's own edits - ask first
+        if has_in_memory_content(&filepath).await {
+            let target_filename = target_filename.to_string();
+            crate::prompt::confirm(
+                &format!("load: {}: File has in-memory changes that will be replaced. Overwrite?", target_filename),
+                move || async move { load_from_device(&filepath, &target_filename).await },
+            );
+            return String::new();
+        }
 
-        match file_data {
-            Ok(data) if !data.is_null() && !data.is_undefined() => {
-                // Convert JsValue to Vec<u8>
-                let uint8_array = Uint8Array::new(&data);
-                let bytes = uint8_array.to_vec();
+        load_from_device(&filepath, target_filename).await
+    }
+}
 
-                // Interpret as UTF-8 string
-                match String::from_utf8(bytes) {
-                    Ok(content) => {
-                        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(&target_filename, &cd.borrow()));
+/// Pick a file from the user's device and write it to `filepath`
+async fn load_from_device(filepath: &FilePath, target_filename: &str) -> String {
+    // Prompt for file picker (returns binary data; any kind of file can be loaded)
+    let file_data = JsFuture::from(prompt_file_picker("")).await;
 
-                        if path_in_abyss(&filepath.dir) {
-                            // Handle abyss files
-                            write_file_abyss(&filepath, content).await;
-                        } else {
-                            // Handle regular virtual filesystem
-                            VIRTUAL_FS.with(|vfs| {
-                                vfs.borrow_mut().write_file(&filepath, content);
-                            });
-                        }
+    match file_data {
+        Ok(data) if !data.is_null() && !data.is_undefined() => {
+            // Convert JsValue to Vec<u8>
+            let uint8_array = Uint8Array::new(&data);
+            let bytes = uint8_array.to_vec();
 
-                        format!("Loaded file into: {}", target_filename)
-                    }
-                    Err(_) => "Error: File is not valid UTF-8 text".to_string(),
-                }
+            match write_file(filepath, bytes).await {
+                Ok(_) => format!("Loaded file into: {}", target_filename),
+                Err(e) => format!("load: {}: {}", target_filename, e),
             }
-            _ => "No file selected.".to_string(),
         }
+        _ => "No file selected.".to_string(),
     }
 }
 
 pub struct Save;
 impl CommandData for Save {
     fn name(&self) -> &str { "save" }
+    fn description(&self) -> &str { "Download a file (or with -r a directory, as a zip) to your device" }
+    fn usage(&self) -> &str { "save <filename>\nsave -r <directory>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
 }
 impl Command for Save {
     async fn execute(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: save <filename>\n\nDownloads a file from the virtual filesystem to your device.".to_string();
+        if let ["-r", dir_arg] = args {
+            return save_directory(dir_arg).await;
+        }
+        if args.is_empty() || args[0].starts_with('-') {
+            return format!("{}\n\nDownloads a file (or a whole directory, as a zip) from the virtual filesystem to your device.", self.usage_error());
         }
 
         let path_arg = args[0];
         let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
 
-        match get_file_content(&filepath).await {
-            Ok(content) => {
+        match get_file_bytes(&filepath).await {
+            Ok(bytes) => {
                 let download_name = filepath.file.clone();
-                trigger_download(content.as_bytes(), "text/plain", &download_name);
+                let mime_type = if std::str::from_utf8(&bytes).is_ok() { "text/plain" } else { "application/octet-stream" };
+                trigger_download(&bytes, mime_type, &download_name);
                 format!("Downloading: {}", path_arg)
             }
             Err(_) => format!("save: {}: No such file", path_arg),
@@ -102,14 +161,204 @@ impl Command for Save {
     }
 }
 
+/// save -r: download a directory and everything in it as one zip
+async fn save_directory(dir_arg: &str) -> String {
+    let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
+    if !dir_exists(&dirpath).await {
+        return format!("save: {}: No such directory", dir_arg);
+    }
+
+    match zip_directory(&dirpath).await {
+        Ok((bytes, names)) => {
+            let download_name = format!("{}.zip", dirpath.final_component().unwrap_or("site"));
+            trigger_download(&bytes, "application/zip", &download_name);
+            let files = names.iter().filter(|name| !name.ends_with('/')).count();
+            format!("Downloading: {} ({} file(s))", download_name, files)
+        }
+        Err(e) => format!("save: {}", e),
+    }
+}
+
+/// Hosts `fetch` may download from unless the site sets fetch.hosts (comma separated).
+/// Both serve raw files and allow other pages to read them.
+pub const DEFAULT_FETCH_HOSTS: [&str; 2] = ["raw.githubusercontent.com", "gist.githubusercontent.com"];
+/// The largest download `fetch` keeps unless the site sets fetch.bytes
+pub const DEFAULT_FETCH_LIMIT: usize = 1024 * 1024;
+
+fn fetch_hosts() -> Vec<String> {
+    match get_setting("fetch.hosts") {
+        Some(hosts) => hosts.split(',').map(|host| host.trim().to_ascii_lowercase()).filter(|host| !host.is_empty()).collect(),
+        None => DEFAULT_FETCH_HOSTS.iter().map(|host| host.to_string()).collect(),
+    }
+}
+
+fn fetch_limit() -> usize {
+    get_setting("fetch.bytes")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_FETCH_LIMIT)
+}
+
+// The host and path of an https URL
+fn split_https_url(url: &str) -> Option<(String, &str)> {
+    let rest = url.strip_prefix("https://")?;
+    let (authority, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    if authority.is_empty() || authority.contains('@') {
+        return None;
+    }
+    let host = authority.split(':').next().unwrap_or_default();
+    Some((host.to_ascii_lowercase(), path))
+}
+
+pub struct Fetch;
+impl CommandData for Fetch {
+    fn name(&self) -> &str { "fetch" }
+    fn description(&self) -> &str { "Download a text file from another site (allowed hosts only)" }
+    fn usage(&self) -> &str { "fetch <url> [destination]" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
+}
+impl Command for Fetch {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (url, dest_arg) = match args {
+            [url] if !url.starts_with('-') => (*url, None),
+            [url, dest] if !url.starts_with('-') => (*url, Some(*dest)),
+            _ => return format!("{}\n\nDownloads a text file from another site into the virtual filesystem.", self.usage_error()),
+        };
+
+        let Some((host, path)) = split_https_url(url) else {
+            return format!("fetch: {}: Only https:// URLs can be fetched", url);
+        };
+        let hosts = fetch_hosts();
+        if !hosts.contains(&host) {
+            return format!("fetch: {}: Host '{}' isn't one this site allows (allowed: {})", url, host, hosts.join(", "));
+        }
+
+        // Without a destination, the file keeps its name from the URL
+        let name = path.split(['?', '#']).next().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+        let dest_arg = match dest_arg {
+            Some(dest) => dest.to_string(),
+            None if !name.is_empty() => name.to_string(),
+            None => return format!("fetch: {}: The URL doesn't name a file; give a destination", url),
+        };
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(&dest_arg, &cd.borrow()));
+        if is_read_only(&filepath) {
+            return format!("fetch: {}: Read-only file", dest_arg);
+        }
+
+        let text = match fetch_text(url).await {
+            Ok(text) => text,
+            // An opaque failure is what a blocked cross-origin request looks like
+            Err(e) if e == format!("Failed to fetch {}", url) => return format!(
+                "fetch: {}: The request was blocked or the host couldn't be reached. \
+                 Browsers only let pages read sites that allow it (CORS), so the URL must be one that does, \
+                 such as a raw file on raw.githubusercontent.com",
+                url
+            ),
+            Err(e) => return format!("fetch: {}: {}", url, e.strip_prefix(&format!("Failed to fetch {}: ", url)).unwrap_or(&e)),
+        };
+        let limit = fetch_limit();
+        if text.len() > limit {
+            return format!("fetch: {}: Too big ({}; the limit is {})", url, quota::format_size(text.len()), quota::format_size(limit));
+        }
+
+        // Don't silently replace a file that's already there
+        if file_exists(&filepath).await {
+            crate::prompt::confirm(
+                &format!("fetch: {}: File exists. Overwrite?", dest_arg),
+                move || async move {
+                    match write_file(&filepath, text).await {
+                        Ok(_) => format!("File saved: {}", dest_arg),
+                        Err(e) => format!("Error saving {}: {}", dest_arg, e),
+                    }
+                },
+            );
+            return String::new();
+        }
+
+        let size = quota::format_size(text.len());
+        match write_file(&filepath, text).await {
+            Ok(_) => format!("Fetched {} ({}) into: {}", url, size, dest_arg),
+            Err(e) => format!("fetch: {}: {}", dest_arg, e),
+        }
+    }
+}
+
+pub struct Persist;
+impl CommandData for Persist {
+    fn name(&self) -> &str { "persist" }
+    fn description(&self) -> &str { "Keep your files in this browser across reloads" }
+    fn usage(&self) -> &str { "persist [on|off|status]" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
+}
+impl Command for Persist {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] | ["status"] => {
+                if !persist::enabled() {
+                    return "Persistence is off. Type 'persist on' to keep your files across reloads.".to_string();
+                }
+                let (files, bytes) = in_memory_totals();
+                format!("Persistence is on: {} file(s) ({} bytes) are kept in this browser.", files, bytes)
+            }
+            ["on"] => {
+                if persist::enabled() {
+                    return "Persistence is already on.".to_string();
+                }
+                let stored = persist::enable();
+                format!("Persistence is on: your files will be kept in this browser ({} file(s) stored now).", stored)
+            }
+            ["off"] => {
+                if !persist::enabled() {
+                    return "Persistence is already off.".to_string();
+                }
+                persist::disable();
+                "Persistence is off: stored files were removed from this browser (your open files are unchanged).".to_string()
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+// Number and total size of the visitor's in-memory files
+pub(super) fn in_memory_totals() -> (usize, usize) {
+    VIRTUAL_FS.with_borrow(|vfs| {
+        vfs.content.values()
+            .flat_map(|files| files.values())
+            .filter_map(|content| match content {
+                Content::InMemory(data) => Some(data.bytes.len()),
+                Content::ToFetch | Content::Fetched(_) => None,
+            })
+            .fold((0, 0), |(files, bytes), size| (files + 1, bytes + size))
+    })
+}
+
 pub struct SaveSession;
 impl CommandData for SaveSession {
     fn name(&self) -> &str { "save-session" }
+    fn description(&self) -> &str { "Export your files as a gzipped session (downloads with timestamp)" }
+    fn usage(&self) -> &str { "save-session [--plain] [directory]" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
 }
 impl Command for SaveSession {
-    async fn execute(&self, _args: &[&str]) -> String {
-        // Get session JSON from WASM
-        let session_json = export_session();
+    async fn execute(&self, args: &[&str]) -> String {
+        // Sessions are gzipped (v2) unless asked for plain JSON (v1)
+        let (plain, dir_arg) = match args {
+            [] => (false, None),
+            ["--plain"] => (true, None),
+            [dir_arg] | ["--plain", dir_arg] | [dir_arg, "--plain"] if !dir_arg.starts_with('-') => (args.contains(&"--plain"), Some(*dir_arg)),
+            _ => return self.usage_error(),
+        };
+
+        // Get session JSON from WASM (just the files under the directory, if given)
+        let session_json = match dir_arg {
+            Some(dir_arg) => {
+                let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
+                if !dir_exists(&dirpath).await {
+                    return format!("save-session: {}: No such directory", dir_arg);
+                }
+                export_subtree(&dirpath)
+            }
+            None => export_session(),
+        };
         let session: serde_json::Value = serde_json::from_str(&session_json)
             .unwrap_or(serde_json::json!({"files": {}}));
 
@@ -118,8 +367,13 @@ impl Command for SaveSession {
             .map(|obj| obj.len())
             .unwrap_or(0);
 
-        if file_count == 0 {
-            "No in-memory files to export.".to_string()
+        let abyss_edits = crate::filesystem::abyss_delta::has_edits(&session["abyss"]);
+
+        if file_count == 0 && !abyss_edits {
+            match dir_arg {
+                Some(dir_arg) => format!("No in-memory files under {} to export.", dir_arg),
+                None => "No in-memory files to export.".to_string(),
+            }
         } else {
             // Create filename with timestamp
             let timestamp = Date::new_0().to_iso_string().as_string().unwrap()
@@ -128,39 +382,437 @@ impl Command for SaveSession {
                 .chars()
                 .take(19)
                 .collect::<String>();
-            let filename = format!("session-{}.json", timestamp);
-
-            trigger_download(session_json.as_bytes(), "application/json", &filename);
-            format!("Exported {} file(s) to: {}", file_count, filename)
+            // Named after the directory, when it's a part
+            let name = dir_arg
+                .map(|dir_arg| CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow())))
+                .and_then(|dirpath| dirpath.final_component().map(|name| format!("{}-", name)))
+                .unwrap_or_default();
+            let filename = if plain {
+                let filename = format!("session-{}{}.json", name, timestamp);
+                trigger_download(session_json.as_bytes(), "application/json", &filename);
+                filename
+            } else {
+                let filename = format!("session-{}{}.json.gz", name, timestamp);
+                trigger_download(&compress_session(&session_json), "application/gzip", &filename);
+                filename
+            };
+            let abyss_note = if abyss_edits { " and abyss edits" } else { "" };
+            format!("Exported {} file(s){} to: {}", file_count, abyss_note, filename)
         }
     }
 }
 
+/// Import a session, first asking about each conflicting file if `on_conflict` is None
+pub async fn import_with(session_json: String, on_conflict: Option<OnConflict>) -> String {
+    if let Some(on_conflict) = on_conflict {
+        return import_session(session_json, false, &on_conflict).await;
+    }
+    let conflicts = session_conflicts(&session_json);
+    if conflicts.is_empty() {
+        return import_session(session_json, false, &OnConflict::Overwrite).await;
+    }
+    crate::ask_import_conflict(session_json, conflicts, BTreeSet::new());
+    String::new()
+}
+
 pub struct LoadSession;
 impl CommandData for LoadSession {
     fn name(&self) -> &str { "load-session" }
+    fn description(&self) -> &str { "Import a previously saved session file (asks before replacing files)" }
+    fn usage(&self) -> &str { "load-session [--overwrite | --skip-existing] [--into <directory>]" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
 }
 impl Command for LoadSession {
+    async fn execute(&self, args: &[&str]) -> String {
+        // Without a flag, each file that would be replaced is asked about
+        let mut on_conflict = None;
+        let mut into = None;
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "--overwrite" if on_conflict.is_none() => on_conflict = Some(OnConflict::Overwrite),
+                "--skip-existing" if on_conflict.is_none() => on_conflict = Some(OnConflict::SkipExisting),
+                "--into" if into.is_none() => match rest.next() {
+                    Some(dir_arg) => into = Some(CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()))),
+                    None => return self.usage_error(),
+                },
+                _ => return self.usage_error(),
+            }
+        }
+        if into.as_ref().is_some_and(path_in_abyss) {
+            return "load-session: --into: Sessions can't be loaded into the abyss".to_string();
+        }
+
+        match pick_session_file(into.as_ref()).await {
+            Ok(session_json) => import_with(session_json, on_conflict).await,
+            Err(e) => e,
+        }
+    }
+}
+
+/// Ask for a session file and read it, moved under `into` if given.
+/// Errors are the message to show (including when nothing was picked).
+async fn pick_session_file(into: Option<&DirPath>) -> Result<String, String> {
+    // Prompt for file picker (returns binary data)
+    let file_data = JsFuture::from(prompt_file_picker(".json,.gz")).await;
+
+    match file_data {
+        Ok(data) if !data.is_null() && !data.is_undefined() => {
+            // Convert JsValue to Vec<u8>
+            let uint8_array = Uint8Array::new(&data);
+            let bytes = uint8_array.to_vec();
+
+            // Gzipped or plain JSON, told apart by the content
+            let session_json = session_from_bytes(bytes)?;
+            match into {
+                Some(into) => remap_session(&session_json, into),
+                None => Ok(session_json),
+            }
+        }
+        _ => Err("No file selected.".to_string()),
+    }
+}
+
+/// session-diff's report: each kind of file with its paths, then the counts
+pub fn format_session_diff(diff: &SessionChanges, abyss_edits: bool) -> String {
+    let mut lines = Vec::new();
+    for (label, paths) in [("Added", &diff.added), ("Changed", &diff.changed), ("Identical", &diff.identical)] {
+        if !paths.is_empty() {
+            lines.push(format!("{} ({}):", label, paths.len()));
+            lines.extend(paths.iter().map(|path| format!("  {}", path)));
+        }
+    }
+    lines.push(format!(
+        "{} to add, {} to change, {} identical",
+        diff.added.len(), diff.changed.len(), diff.identical.len()
+    ));
+    if abyss_edits {
+        lines.push("The session also has abyss edits, which would be replayed.".to_string());
+    }
+    lines.join("\n")
+}
+
+pub struct SessionDiff;
+impl CommandData for SessionDiff {
+    fn name(&self) -> &str { "session-diff" }
+    fn description(&self) -> &str { "Show what load-session would add, change or leave alone" }
+    fn usage(&self) -> &str { "session-diff [--into <directory>] [--json]" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
+}
+impl Command for SessionDiff {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (json, args) = take_json_flag(args);
+        let into = match args.as_slice() {
+            [] => None,
+            ["--into", dir_arg] => Some(CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()))),
+            _ => return self.usage_error(),
+        };
+
+        let session_json = match pick_session_file(into.as_ref()).await {
+            Ok(session_json) => session_json,
+            Err(e) => return e,
+        };
+        let diff = match diff_session(&session_json) {
+            Ok(diff) => diff,
+            Err(e) => return e,
+        };
+        let abyss_edits = serde_json::from_str::<serde_json::Value>(&session_json)
+            .is_ok_and(|session| crate::filesystem::abyss_delta::has_edits(&session["abyss"]));
+
+        if json {
+            return json_output(serde_json::json!({
+                "added": diff.added,
+                "changed": diff.changed,
+                "identical": diff.identical,
+                "abyss_edits": abyss_edits,
+            }));
+        }
+        format_session_diff(&diff, abyss_edits)
+    }
+}
+
+pub struct Quota;
+impl CommandData for Quota {
+    fn name(&self) -> &str { "quota" }
+    fn description(&self) -> &str { "Show how much of the write quota is used" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
+}
+impl Command for Quota {
     async fn execute(&self, _args: &[&str]) -> String {
-        // Prompt for file picker (returns binary data)
-        let file_data = JsFuture::from(prompt_file_picker(".json")).await;
-
-        match file_data {
-            Ok(data) if !data.is_null() && !data.is_undefined() => {
-                // Convert JsValue to Vec<u8>
-                let uint8_array = Uint8Array::new(&data);
-                let bytes = uint8_array.to_vec();
-
-                // Interpret as UTF-8 string (JSON)
-                match String::from_utf8(bytes) {
-                    Ok(session_json) => {
-                        let result = import_session(session_json);
-                        result
-                    }
-                    Err(_) => "Error: File is not valid UTF-8 text".to_string(),
-                }
+        let (used, limit) = (quota::used(), quota::limit());
+        let percent = if limit == 0 { 100.0 } else { used as f64 * 100.0 / limit as f64 };
+        format!(
+            "Write quota: {} of {} used ({:.1}%)\n{} free",
+            quota::format_size(used),
+            quota::format_size(limit),
+            percent,
+            quota::format_size(limit.saturating_sub(used)),
+        )
+    }
+}
+
+pub struct Cache;
+impl CommandData for Cache {
+    fn name(&self) -> &str { "cache" }
+    fn description(&self) -> &str { "Show or clear the cache of fetched site files" }
+    fn usage(&self) -> &str { "cache [status|clear]" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
+}
+impl Command for Cache {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] | ["status"] => {
+                let (files, used) = fetch_cache::usage();
+                format!(
+                    "Cache: {} file(s), {} of {} used",
+                    files, quota::format_size(used), quota::format_size(fetch_cache::limit())
+                )
+            }
+            ["clear"] => {
+                let (files, used) = fetch_cache::clear();
+                format!("Cleared {} cached file(s) ({}).", files, quota::format_size(used))
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Edit);
+    registry.add(Tabs);
+    registry.add(Save);
+    registry.add(Load);
+    registry.add(Fetch);
+    registry.add(Quota);
+    registry.add(Cache);
+    registry.add(SaveSession);
+    registry.add(LoadSession);
+    registry.add(SessionDiff);
+    registry.add(Persist);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{block_on, enable, overwrite, run};
+    use crate::commands::OnConflict;
+    use crate::filesystem::DirPath;
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_gzipped_sessions() {
+        enable(1);
+        crate::write_file("notes.txt", "x".repeat(1000)).unwrap();
+        let session = crate::commands::export_session();
+        let compressed = crate::commands::compress_session(&session);
+        assert!(compressed.len() < session.len() / 2);
+
+        // Both formats are told apart on import
+        for bytes in [compressed, session.clone().into_bytes()] {
+            enable(1);
+            let session_json = crate::commands::session_from_bytes(bytes).unwrap();
+            assert_eq!(session_json, session);
+            block_on(crate::commands::import_session(session_json, true, &OnConflict::Overwrite));
+            assert_eq!(run("wc -c notes.txt"), ["1000 notes.txt"]);
+        }
+        assert_eq!(
+            crate::commands::session_from_bytes(vec![0x1f, 0x8b, 0x00]),
+            Err("Error: Failed to decompress session file: unexpected end of file".to_string())
+        );
+    }
+
+    #[test]
+    fn test_import_conflicts() {
+        enable(1);
+        for (name, text) in [("a.txt", "saved a"), ("b.txt", "saved b"), ("c.txt", "saved c")] {
+            crate::write_file(name, text.to_string()).unwrap();
+        }
+        let session = crate::commands::export_session();
+
+        let start = || {
+            enable(1);
+            for (name, text) in [("a.txt", "mine a"), ("b.txt", "mine b"), ("c.txt", "saved c")] {
+                crate::write_file(name, text.to_string()).unwrap();
             }
-            _ => "No file selected.".to_string(),
+        };
+        let contents = || ["a.txt", "b.txt"].map(|name| run(&format!("cat {}", name))[0].clone());
+
+        start();
+        let summary = block_on(crate::commands::import_session(session.clone(), true, &OnConflict::SkipExisting));
+        assert!(summary.contains("skipped       3"), "{}", summary);
+        assert_eq!(contents(), ["mine a", "mine b"]);
+
+        // Asked file by file (c.txt is the same, so isn't asked about)
+        start();
+        assert_eq!(block_on(crate::commands::builtin::files::import_with(session.clone(), None)), "");
+        assert_eq!(take_output(), ["/a.txt already exists. Replace it? (y/n, a = replace all, s = skip all) [n]"]);
+        assert_eq!(run("maybe"), ["Please answer y, n, a or s.", "/a.txt already exists. Replace it? (y/n, a = replace all, s = skip all) [n]"]);
+        assert_eq!(run("n"), ["/b.txt already exists. Replace it? (y/n, a = replace all, s = skip all) [n]"]);
+        let output = run("y");
+        assert_eq!(output[..3], ["[1/3] /a.txt ... skipped: exists", "[2/3] /b.txt ... ok", "[3/3] /c.txt ... skipped: unchanged"]);
+        assert_eq!(contents(), ["mine a", "saved b"]);
+
+        start();
+        block_on(crate::commands::builtin::files::import_with(session, None));
+        take_output();
+        run("a");
+        assert_eq!(contents(), ["saved a", "saved b"]);
+    }
+
+    #[test]
+    fn test_export_and_import_a_subtree() {
+        enable(1);
+        run("mkdir -p notes/ideas");
+        crate::write_file("notes/ideas/app.txt", "todo list".to_string()).unwrap();
+        crate::write_file("diary.txt", "private".to_string()).unwrap();
+        let session = crate::commands::export_subtree(&DirPath::parse("/notes", &DirPath::root()));
+        let value: serde_json::Value = serde_json::from_str(&session).unwrap();
+        assert_eq!(value["files"].as_object().unwrap().keys().collect::<Vec<_>>(), ["/notes/ideas/app.txt"]);
+        assert!(value.get("shell").is_none());
+
+        // Shared with someone else, who keeps it apart from their own notes
+        enable(1);
+        let remapped = crate::commands::remap_session(&session, &DirPath::parse("/shared", &DirPath::root())).unwrap();
+        block_on(crate::commands::import_session(remapped, true, &OnConflict::Overwrite));
+        assert_eq!(run("cat /shared/notes/ideas/app.txt"), ["todo list"]);
+        assert_eq!(run("ls /shared"), ["notes/"]);
+        assert_eq!(run("cat diary.txt"), ["cat: diary.txt: No such file"]);
+
+        assert_eq!(run("save-session nowhere"), ["save-session: nowhere: No such directory"]);
+        assert_eq!(run("save-session abyss"), ["No in-memory files under abyss to export."]);
+        assert_eq!(run("load-session --into"), ["Usage: load-session [--overwrite | --skip-existing] [--into <directory>]"]);
+        assert_eq!(run("load-session --into /abyss/x"), ["load-session: --into: Sessions can't be loaded into the abyss"]);
+    }
+
+    #[test]
+    fn test_session_diff() {
+        enable(1);
+        for (name, text) in [("a.txt", "saved a"), ("b.txt", "saved b")] {
+            crate::write_file(name, text.to_string()).unwrap();
         }
+        let session = crate::commands::export_session();
+
+        enable(1);
+        crate::write_file("b.txt", "saved b".to_string()).unwrap();
+        overwrite("about.txt", "mine");
+        let mut session: serde_json::Value = serde_json::from_str(&session).unwrap();
+        session["files"]["/about.txt"] = serde_json::json!("theirs");
+        let diff = crate::commands::diff_session(&session.to_string()).unwrap();
+        assert_eq!(crate::commands::builtin::files::format_session_diff(&diff, false).lines().collect::<Vec<_>>(), [
+            "Added (1):",
+            "  /a.txt",
+            "Changed (1):",
+            "  /about.txt",
+            "Identical (1):",
+            "  /b.txt",
+            "1 to add, 1 to change, 1 identical",
+        ]);
+        // Nothing was touched
+        assert_eq!(run("cat about.txt"), ["mine"]);
+        assert_eq!(run("cat a.txt"), ["cat: a.txt: No such file"]);
+
+        assert_eq!(crate::commands::diff_session(r#"{"version": "9"}"#), Err("Error: Unsupported session version: 9".to_string()));
+        assert_eq!(run("session-diff --into"), ["Usage: session-diff [--into <directory>] [--json]"]);
+    }
+
+    #[test]
+    fn test_fetch_from_allowed_hosts() {
+        enable(1);
+        let url = "https://raw.githubusercontent.com/HaineSensei/examples/main/hello.txt";
+        assert_eq!(run(&format!("fetch {}", url)), [format!("Fetched {} (21 B) into: hello.txt", url)]);
+        assert_eq!(run("cat hello.txt"), ["Hello from elsewhere"]);
+
+        // Replacing a file asks first
+        assert_eq!(run(&format!("fetch {} hello.txt", url)), ["fetch: hello.txt: File exists. Overwrite? (y/n)"]);
+        assert_eq!(run("y"), ["File saved: hello.txt"]);
+
+        assert_eq!(run(&format!("fetch {} about.txt", url)), ["fetch: about.txt: Read-only file"]);
+        assert_eq!(run("fetch http://raw.githubusercontent.com/a.txt"), [
+            "fetch: http://raw.githubusercontent.com/a.txt: Only https:// URLs can be fetched",
+        ]);
+        assert_eq!(run("fetch https://example.com/a.txt"), [
+            "fetch: https://example.com/a.txt: Host 'example.com' isn't one this site allows (allowed: raw.githubusercontent.com, gist.githubusercontent.com)",
+        ]);
+        assert_eq!(run("fetch https://raw.githubusercontent.com/missing.txt"), [
+            "fetch: https://raw.githubusercontent.com/missing.txt: HTTP 404",
+        ]);
+
+        crate::settings::set_setting("fetch.bytes", "10");
+        assert_eq!(run(&format!("fetch {} small.txt", url)), [
+            format!("fetch: {}: Too big (21 B; the limit is 10 B)", url),
+        ]);
+        crate::settings::set_setting("fetch.bytes", "");
+    }
+
+    #[test]
+    fn test_session_resumes_shell_state() {
+        enable(1);
+        run("mkdir -p notes/daily");
+        run("cd notes/daily");
+        crate::write_file("today.txt", "rain".to_string()).unwrap();
+        let session = crate::commands::export_session();
+
+        // A fresh visit picks up in the same directory, with the earlier history first
+        enable(1);
+        crate::input_history::INPUT_HISTORY.with_borrow_mut(|history| *history = crate::input_history::InputHistory::new());
+        run("pwd");
+        block_on(crate::commands::import_session(session, true, &OnConflict::Overwrite));
+        assert_eq!(run("pwd"), ["/notes/daily"]);
+        assert_eq!(crate::input_history::INPUT_HISTORY.with_borrow(|history| history.inputs().to_vec()), [
+            "mkdir -p notes/daily", "cd notes/daily", "pwd", "pwd",
+        ]);
+
+        // A directory that no longer exists leaves the shell where it is
+        let session = r#"{"version": "1.4", "files": {}, "shell": {"cwd": "/abyss/nowhere/deeper", "history": []}}"#;
+        enable(1);
+        block_on(crate::commands::import_session(session.to_string(), true, &OnConflict::Overwrite));
+        assert_eq!(run("pwd"), ["/"]);
+    }
+
+    #[test]
+    fn test_rm_files_open_in_the_editor() {
+        use crate::channels::{note_tab, note_tab_closed, TabKind};
+        enable(1);
+        note_tab("a1", TabKind::Editor, "/abyss/warning.txt");
+        // Showing a file in a pretty tab doesn't hold it open
+        note_tab("b2", TabKind::Pretty, "/abyss/level1/camp.txt");
+        assert_eq!(run("tabs"), [
+            "2 tab(s) open:",
+            "  editor  /abyss/warning.txt  (tab a1, last heard from 0s ago)",
+            "  pretty  /abyss/level1/camp.txt  (tab b2, last heard from 0s ago)",
+        ]);
+        run("cd /abyss");
+        assert_eq!(run("rm warning.txt"), ["rm: warning.txt: Open in the editor (close its tab first, or use rm -f)"]);
+        assert_eq!(run("rm -r /abyss"), ["rm: /abyss: Contains files open in the editor (close their tabs first, or use rm -rf)"]);
+        assert_eq!(run("rmdir /abyss"), ["rmdir: /abyss: Contains files open in the editor (close their tabs first, or use rmdir -f)"]);
+        assert_eq!(run("rm -f warning.txt"), ["rm: warning.txt: Still open in the editor; saving it there will create it again"]);
+
+        // Closing the tab lets rm go ahead
+        crate::write_file("/notes.txt", "my notes".to_string()).unwrap();
+        note_tab("c3", TabKind::Editor, "/notes.txt");
+        note_tab_closed("c3");
+        assert_eq!(run("rm /notes.txt"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_editor_saves_site_files_only_after_edit_f() {
+        use crate::channels::{allow_forced_edit, may_save};
+        use crate::filesystem::FilePath;
+        enable(1);
+        let about = FilePath::parse("/about.txt", &DirPath::root());
+        assert_eq!(may_save(&about), Err("Read-only file".to_string()));
+        assert_eq!(may_save(&FilePath::parse("/notes.txt", &DirPath::root())), Ok(()));
+        allow_forced_edit(&about);
+        assert_eq!(may_save(&about), Ok(()));
+        crate::write_file("/about.txt", "mine".to_string()).unwrap();
+        assert_eq!(run("cat /about.txt"), ["mine"]);
+    }
+
+    #[test]
+    fn test_load_asks_before_overwriting() {
+        enable(1);
+        crate::write_file("notes.txt", "my notes".to_string()).unwrap();
+        assert_eq!(run("load notes.txt"), ["load: notes.txt: File has in-memory changes that will be replaced. Overwrite? (y/n)"]);
+        assert_eq!(run("n"), ["Cancelled."]);
+        assert_eq!(run("cat notes.txt"), ["my notes"]);
     }
 }
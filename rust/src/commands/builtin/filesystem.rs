@@ -1,10 +1,18 @@
-use crate::commands::{Command, CommandData};
-use crate::filesystem::{DirPath, FilePath, CURRENT_DIR, VIRTUAL_FS};
-use crate::filesystem::helpers::{get_current_dir_string, get_file_content, dir_exists, list_directory, path_in_abyss, remove_file_abyss, remove_dir_abyss, create_dir_abyss};
+use crate::commands::{Category, Command, CommandData, Registry, json_output, take_json_flag};
+use serde_json::{json, Value};
+use crate::filesystem::{cave_of_dice, Content, DirPath, EntryKind, FilePath, CURRENT_DIR};
+use crate::js_interop::add_output_styled;
+use crate::filesystem::events::{emit, FsEvent};
+use crate::filesystem::sniff::{sniff, FileType};
+use crate::filesystem::front_matter::FrontMatter;
+use crate::channels::{has_open_in_editor_within, is_open_in_editor};
+use crate::filesystem::helpers::{file_front_matter, get_current_dir_string, get_file_bytes, get_file_content, get_contents, get_directories, dir_exists, file_exists, is_read_only, has_read_only_within, list_directory, is_hidden, path_in_abyss, change_dir, remove_file, create_dir, remove_dir, remove_dir_all, file_metadata, format_timestamp, origin_url};
 
 pub struct Pwd;
 impl CommandData for Pwd {
     fn name(&self) -> &str { "pwd" }
+    fn description(&self) -> &str { "Print working directory" }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
 }
 impl Command for Pwd {
     async fn execute(&self, _args: &[&str]) -> String {
@@ -15,10 +23,35 @@ impl Command for Pwd {
 pub struct Ls;
 impl CommandData for Ls {
     fn name(&self) -> &str { "ls" }
+    fn description(&self) -> &str { "List files and directories (-a hidden, -l table, -t/-S sort)" }
+    fn usage(&self) -> &str { "ls [-altS] [--json] [directory]" }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
+    fn aliases(&self) -> &[&str] { &["dir"] }
 }
 impl Command for Ls {
     async fn execute(&self, args: &[&str]) -> String {
-        let target_dir = if let Some(&target) = args.get(0) {
+        let (json, args) = take_json_flag(args);
+        let mut long = false;
+        let mut all = false;
+        let mut sort = SortBy::Name;
+        let mut target_arg = None;
+        for arg in args {
+            if let Some(flags) = arg.strip_prefix('-') && !flags.is_empty() {
+                for flag in flags.chars() {
+                    match flag {
+                        'l' => long = true,
+                        'a' => all = true,
+                        't' => sort = SortBy::Modified,
+                        'S' => sort = SortBy::Size,
+                        _ => return format!("ls: invalid option -- '{}'\nUsage: ls [-altS] [--json] [directory]", flag),
+                    }
+                }
+            } else {
+                target_arg = Some(arg);
+            }
+        }
+
+        let target_dir = if let Some(target) = target_arg {
             // ls with directory argument
             let new_path = CURRENT_DIR.with(|cd| DirPath::parse(target, &cd.borrow()));
 
@@ -33,27 +66,189 @@ impl Command for Ls {
             CURRENT_DIR.with(|cd| cd.borrow().clone())
         };
 
-        let entries = list_directory(&target_dir).await;
+        let names = if json || long || sort != SortBy::Name {
+            let mut entries = ls_entries(&target_dir).await;
+            if !all {
+                entries.retain(|entry| !is_hidden(&entry.name));
+            }
+            sort_entries(&mut entries, sort);
+            if json {
+                return json_output(entries_json(&entries));
+            }
+            if long {
+                if entries.is_empty() {
+                    return "(empty directory)".to_string();
+                }
+                print_lines(&long_listing(&entries));
+                return String::new();
+            }
+            entries.into_iter().map(|entry| entry.name).collect()
+        } else {
+            list_directory(&target_dir).await
+        };
 
-        if entries.is_empty() {
+        let lines: Vec<LsLine> = names.into_iter()
+            .filter(|name| all || !is_hidden(name))
+            .map(|name| {
+                let kind = EntryKind::of(&name);
+                (name, Some(kind), String::new())
+            })
+            .collect();
+
+        if lines.is_empty() {
             "(empty directory)".to_string()
         } else {
-            entries.join("\n")
+            print_lines(&lines);
+            String::new()  // Already output with styling
         }
     }
 }
 
+// A line of ls output: the entry name (coloured by its kind, if any) and the rest of the line
+type LsLine = (String, Option<EntryKind>, String);
+
+fn print_lines(lines: &[LsLine]) {
+    for (name, kind, rest) in lines {
+        let class = kind.map(|kind| kind.css_class()).unwrap_or("");
+        add_output_styled(&[(name, class), (rest, "")]);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortBy {
+    Name,
+    Modified,
+    Size,
+}
+
+struct LsEntry {
+    name: String,
+    kind: EntryKind,
+    size: Option<usize>,
+    modified: Option<f64>,
+    front_matter: Option<FrontMatter>,
+}
+
+// Directory entries with whatever metadata is known.
+// Static files have no size or time until they have been fetched.
+async fn ls_entries(path: &DirPath) -> Vec<LsEntry> {
+    let mut entries = Vec::new();
+
+    for dir in get_directories(path).await.0 {
+        entries.push(LsEntry { name: format!("{}/", dir), kind: EntryKind::Dir, size: None, modified: None, front_matter: None });
+    }
+
+    for (filename, content) in get_contents(path).await.0 {
+        let filepath = FilePath::new(path.clone(), filename.clone());
+        let meta = file_metadata(&filepath, &content);
+        entries.push(LsEntry {
+            kind: EntryKind::of(&filename),
+            front_matter: file_front_matter(&filepath, &content),
+            name: filename,
+            size: meta.as_ref().map(|meta| meta.size),
+            modified: meta.map(|meta| meta.modified),
+        });
+    }
+
+    entries
+}
+
+// ls --json: one object per entry. Directory names lose their trailing '/',
+// as the kind says what they are. Posts with front matter also have its
+// title, date and tags.
+fn entries_json(entries: &[LsEntry]) -> Value {
+    entries.iter()
+        .map(|entry| {
+            let mut object = json!({
+                "name": entry.name.trim_end_matches('/'),
+                "kind": entry.kind.label(),
+                "size": entry.size,
+                "modified": entry.modified,
+            });
+            if let Some(front_matter) = &entry.front_matter {
+                object["title"] = json!(front_matter.title);
+                object["date"] = json!(front_matter.date);
+                object["tags"] = json!(front_matter.tags);
+            }
+            object
+        })
+        .collect()
+}
+
+// -t and -S put the newest/largest first; entries without the metadata go last.
+// Ties (and the default order) are by name.
+fn sort_entries(entries: &mut [LsEntry], sort: SortBy) {
+    entries.sort_by(|a, b| {
+        let by_key = match sort {
+            SortBy::Name => std::cmp::Ordering::Equal,
+            SortBy::Modified => b.modified.partial_cmp(&a.modified).unwrap_or(std::cmp::Ordering::Equal),
+            SortBy::Size => b.size.cmp(&a.size),
+        };
+        by_key.then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+// ls -l table: name, kind, size and modification time in aligned columns,
+// then the date and title of posts if there are any
+fn long_listing(entries: &[LsEntry]) -> Vec<LsLine> {
+    let rows: Vec<[String; 4]> = entries.iter()
+        .map(|entry| [
+            entry.name.clone(),
+            entry.kind.label().to_string(),
+            match (entry.kind, entry.size) {
+                (EntryKind::Dir, _) => "-".to_string(),
+                (_, Some(size)) => size.to_string(),
+                (_, None) => "?".to_string(),
+            },
+            entry.modified.map(format_timestamp).unwrap_or_else(|| "-".to_string()),
+        ])
+        .collect();
+
+    let header = ["NAME", "KIND", "SIZE", "MODIFIED"].map(|title| title.to_string());
+    let width = |col: usize| rows.iter().chain([&header]).map(|row| row[col].len()).max().unwrap_or(0);
+    let (name_width, kind_width, size_width) = (width(0), width(1), width(2));
+
+    // DATE and TITLE, when some entry has front matter
+    let posts: Option<Vec<[String; 2]>> = entries.iter().any(|entry| entry.front_matter.is_some()).then(|| {
+        entries.iter()
+            .map(|entry| {
+                let front_matter = entry.front_matter.clone().unwrap_or_default();
+                [front_matter.date.unwrap_or_else(|| "-".to_string()), front_matter.title.unwrap_or_else(|| "-".to_string())]
+            })
+            .collect()
+    });
+    let post_header = ["DATE", "TITLE"].map(|title| title.to_string());
+    let modified_width = width(3);
+    let date_width = posts.iter().flatten().chain([&post_header]).map(|[date, _]| date.chars().count()).max().unwrap_or(0);
+
+    let line = |[name, kind, size, modified]: &[String; 4], post: Option<&[String; 2]>, entry_kind: Option<EntryKind>| {
+        let padding = " ".repeat(name_width - name.len());
+        let mut rest = format!("{}  {:<kind_width$}  {:>size_width$}  {}", padding, kind, size, modified);
+        if let Some([date, title]) = post {
+            rest.push_str(&format!("{}  {:<date_width$}  {}", " ".repeat(modified_width - modified.len()), date, title));
+        }
+        (name.clone(), entry_kind, rest)
+    };
+
+    std::iter::once(line(&header, posts.as_ref().map(|_| &post_header), None))
+        .chain(rows.iter().zip(entries).enumerate().map(|(i, (row, entry))| {
+            line(row, posts.as_ref().map(|posts| &posts[i]), Some(entry.kind))
+        }))
+        .collect()
+}
+
 pub struct Cd;
 impl CommandData for Cd {
     fn name(&self) -> &str { "cd" }
+    fn description(&self) -> &str { "Change directory (supports . .. / and relative/absolute paths)" }
+    fn usage(&self) -> &str { "cd <directory>" }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
 }
 impl Command for Cd {
     async fn execute(&self, args: &[&str]) -> String {
         if args.is_empty() {
             // cd with no arguments goes to root
-            CURRENT_DIR.with(|cd| {
-                *cd.borrow_mut() = DirPath::root();
-            });
+            change_dir(DirPath::root());
             return String::new();
         }
 
@@ -62,9 +257,7 @@ impl Command for Cd {
 
         // Check if directory exists
         if dir_exists(&new_path).await {
-            CURRENT_DIR.with(|cd| {
-                *cd.borrow_mut() = new_path;
-            });
+            change_dir(new_path);
             String::new()
         } else {
             format!("cd: {}: No such directory", target)
@@ -72,61 +265,227 @@ impl Command for Cd {
     }
 }
 
+pub struct Map;
+impl CommandData for Map {
+    fn name(&self) -> &str { "map" }
+    fn description(&self) -> &str { "Map the rooms of the cave of dice you've been to" }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
+}
+impl Command for Map {
+    async fn execute(&self, _args: &[&str]) -> String {
+        let here = CURRENT_DIR.with_borrow(|cd| cd.clone());
+        cave_of_dice::map(&here).unwrap_or_else(|| "map: Only works in the cave of dice (/abyss/level1/level2/cave_of_dice)".to_string())
+    }
+}
+
 pub struct Cat;
 impl CommandData for Cat {
     fn name(&self) -> &str { "cat" }
+    fn description(&self) -> &str { "Display file contents, one file after another (-n numbers lines)" }
+    fn usage(&self) -> &str { "cat [-n] <file>..." }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
 }
 impl Command for Cat {
     async fn execute(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: cat <filename>".to_string();
+        let usage = self.usage_error();
+        let mut number = false;
+        let mut paths = Vec::new();
+        for &arg in args {
+            if arg.starts_with('-') && arg.len() > 1 {
+                for flag in arg[1..].chars() {
+                    match flag {
+                        'n' => number = true,
+                        _ => return format!("cat: invalid option -- '{}'\n{}", flag, usage),
+                    }
+                }
+            } else {
+                paths.push(arg);
+            }
+        }
+        if paths.is_empty() {
+            return usage;
         }
 
-        let path_arg = args[0];
-        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
-
-        match get_file_content(&filepath).await {
-            Ok(content) => content,
-            Err(_) => format!("cat: {}: No such file", path_arg),
+        // Each file's text (or error) in turn, each starting on a line of its own.
+        // With -n, lines are numbered right through as if the files were one; errors aren't numbered.
+        let mut output = String::new();
+        let mut line_number = 0;
+        for path_arg in paths {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+            let content = match get_file_bytes(&filepath).await {
+                Ok(bytes) => match String::from_utf8(bytes) {
+                    Ok(content) => {
+                        emit(FsEvent::FileRead(filepath));
+                        content
+                    }
+                    Err(_) => {
+                        output.push_str(&format!("cat: {}: Binary file (use hexdump or xxd to view it)", path_arg));
+                        continue;
+                    }
+                },
+                Err(_) => {
+                    output.push_str(&format!("cat: {}: No such file", path_arg));
+                    continue;
+                }
+            };
+            if number {
+                let numbered: Vec<String> = content.lines()
+                    .map(|line| {
+                        line_number += 1;
+                        format!("{:>6}  {}", line_number, line)
+                    })
+                    .collect();
+                output.push_str(&numbered.join("\n"));
+            } else {
+                output.push_str(&content);
+            }
         }
+        output
     }
 }
 
 pub struct Rm;
 impl CommandData for Rm {
     fn name(&self) -> &str { "rm" }
+    fn description(&self) -> &str { "Remove a file (-r a directory too, -i to ask first, -f for site content)" }
+    fn usage(&self) -> &str { "rm [-rif] <path>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
 }
 impl Command for Rm {
     async fn execute(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: rm <filename>".to_string();
+        let usage = self.usage_error();
+        let mut recursive = false;
+        let mut interactive = false;
+        let mut force = false;
+        let mut paths = Vec::new();
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'r' | 'R' => recursive = true,
+                            'i' => interactive = true,
+                            'f' => force = true,
+                            _ => return usage,
+                        }
+                    }
+                }
+                _ => paths.push(*arg),
+            }
         }
+        let [path_arg] = paths[..] else {
+            return usage;
+        };
 
-        let path_arg = args[0];
-        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
-
-        if path_in_abyss(&filepath.dir) {
-            // Handle abyss files
-            match remove_file_abyss(&filepath).await {
-                Ok(_) => String::new(),
-                Err(_) => format!("rm: {}: No such file", path_arg)
+        let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(path_arg, &cd.borrow()));
+        if dir_exists(&dirpath).await {
+            if !recursive {
+                return format!("rm: {}: Is a directory (use rm -r to remove it and its contents)", path_arg);
             }
-        } else {
-            // Handle regular virtual filesystem
-            VIRTUAL_FS.with(|vfs| {
-                if vfs.borrow_mut().remove_file(&filepath) {
-                    String::new()
-                } else {
-                    format!("rm: {}: No such file", path_arg)
+            if interactive && !force {
+                if has_read_only_within(&dirpath) {
+                    return read_only_tree_error("rm", path_arg);
                 }
-            })
+                if has_open_in_editor_within(&dirpath) {
+                    return open_tree_error("rm", path_arg);
+                }
+            }
+            if interactive {
+                let path_arg = path_arg.to_string();
+                crate::prompt::confirm(
+                    &format!("rm: remove directory '{}' and everything in it?", path_arg),
+                    move || async move { remove_tree(&dirpath, &path_arg, "rm", force).await },
+                );
+                return String::new();
+            }
+            return remove_tree(&dirpath, path_arg, "rm", force).await;
+        }
+
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+        if !force && is_read_only(&filepath) {
+            return format!("rm: {}: Read-only file (use rm -f to remove it anyway)", path_arg);
+        }
+        // Saving in the editor would quietly bring the file back
+        let open_in_editor = is_open_in_editor(&filepath);
+        if !force && open_in_editor {
+            return format!("rm: {}: Open in the editor (close its tab first, or use rm -f)", path_arg);
+        }
+        if interactive {
+            if !file_exists(&filepath).await {
+                return format!("rm: {}: No such file", path_arg);
+            }
+            let path_arg = path_arg.to_string();
+            crate::prompt::confirm(
+                &format!("rm: remove '{}'?", path_arg),
+                move || async move { remove_file_or_report(&filepath, &path_arg).await },
+            );
+            return String::new();
+        }
+        let result = remove_file_or_report(&filepath, path_arg).await;
+        if open_in_editor && result.is_empty() {
+            return format!("rm: {}: Still open in the editor; saving it there will create it again", path_arg);
         }
+        result
     }
 }
 
+/// Remove a file, reporting a missing file the way rm does
+async fn remove_file_or_report(filepath: &FilePath, path_arg: &str) -> String {
+    match remove_file(filepath).await {
+        Ok(_) => String::new(),
+        Err(_) => format!("rm: {}: No such file", path_arg)
+    }
+}
+
+/// Remove a directory and everything in it (rm -r, rmdir --force).
+/// Read-only files are only removed with `force`. Errors are reported as coming from `command`.
+async fn remove_tree(dirpath: &DirPath, path_arg: &str, command: &str, force: bool) -> String {
+    let Some(parent) = dirpath.super_dir() else {
+        return format!("{}: /: Refusing to remove the root directory", command);
+    };
+    if !force && has_read_only_within(dirpath) {
+        return read_only_tree_error(command, path_arg);
+    }
+    let open_in_editor = has_open_in_editor_within(dirpath);
+    if !force && open_in_editor {
+        return open_tree_error(command, path_arg);
+    }
+
+    if let Err(e) = remove_dir_all(dirpath).await {
+        return format!("{}: {}: {}", command, path_arg, e);
+    }
+
+    // Don't leave the current directory pointing into the removed tree
+    if CURRENT_DIR.with_borrow(|cd| cd.is_within(dirpath)) {
+        change_dir(parent);
+    }
+    if open_in_editor {
+        return format!("{}: {}: Had files open in the editor; saving them there will create them again", command, path_arg);
+    }
+    String::new()
+}
+
+fn forced_tree_command(command: &str) -> &'static str {
+    if command == "rm" { "rm -rf" } else { "rmdir -f" }
+}
+
+fn read_only_tree_error(command: &str, path_arg: &str) -> String {
+    format!("{}: {}: Contains read-only files (use {} to remove them too)", command, path_arg, forced_tree_command(command))
+}
+
+fn open_tree_error(command: &str, path_arg: &str) -> String {
+    format!("{}: {}: Contains files open in the editor (close their tabs first, or use {})", command, path_arg, forced_tree_command(command))
+}
+
 pub struct Mkdir;
 impl CommandData for Mkdir {
     fn name(&self) -> &str { "mkdir" }
+    fn description(&self) -> &str { "Create a directory (-p creates missing parents)" }
+    fn usage(&self) -> &str { "mkdir [-p] <directory>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
 }
 impl Command for Mkdir {
     async fn execute(&self, args: &[&str]) -> String {
@@ -134,78 +493,491 @@ impl Command for Mkdir {
         // the entire abyss and are making a new abyss. 
         // (low priority)
 
-        if args.is_empty() {
-            return "Usage: mkdir <directory>".to_string();
-        }
+        let (parents, dir_arg) = match args {
+            ["-p", dir_arg] => (true, *dir_arg),
+            [dir_arg] if !dir_arg.starts_with('-') => (false, *dir_arg),
+            _ => return self.usage_error(),
+        };
 
-        let dir_arg = args[0];
         let new_path = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
 
+        if parents {
+            return match create_dir_all(&new_path).await {
+                Ok(_) => String::new(),
+                Err(e) => format!("mkdir: {}: {}", dir_arg, e),
+            };
+        }
+
         // Check if directory already exists
         if dir_exists(&new_path).await {
             return format!("mkdir: {}: Directory already exists", dir_arg);
         }
 
-        if path_in_abyss(&new_path) {
-            // Handle abyss directories
-            // Check parent exists
-            if let Some(parent) = new_path.super_dir() {
-                if !dir_exists(&parent).await {
-                    return format!("mkdir: {}: Parent directory does not exist", dir_arg);
-                }
-            }
+        // The parent has to exist (mkdir -p creates it)
+        if let Some(parent) = new_path.super_dir() && !dir_exists(&parent).await {
+            return format!("mkdir: {}: Parent directory does not exist (use mkdir -p to create it)", dir_arg);
+        }
 
-            match create_dir_abyss(&new_path).await {
-                Ok(_) => String::new(),
-                Err(e) => format!("mkdir: {}: {}", dir_arg, e)
-            }
-        } else{
-            // Handle regular virtual filesystem
-            VIRTUAL_FS.with(|vfs| {
-                let mut vfs_mut = vfs.borrow_mut();
-                if vfs_mut.dir_exists(&new_path) {
-                    format!("mkdir: {}: Directory already exists", dir_arg)
-                } else {
-                    vfs_mut.create_dir(new_path);
-                    String::new()
-                }
-            })
+        match create_dir(&new_path).await {
+            Ok(_) => String::new(),
+            Err(e) => format!("mkdir: {}: {}", dir_arg, e)
         }
     }
 }
 
+// mkdir -p: create each missing level from the top down, so every new
+// directory is added to its (possibly also new) parent
+pub async fn create_dir_all(path: &DirPath) -> Result<(), String> {
+    for depth in 1..=path.0.len() {
+        let level = DirPath(path.0[..depth].to_vec());
+        if !dir_exists(&level).await {
+            create_dir(&level).await?;
+        }
+    }
+    Ok(())
+}
+
 pub struct Rmdir;
 impl CommandData for Rmdir {
     fn name(&self) -> &str { "rmdir" }
+    fn description(&self) -> &str { "Remove an empty directory (asks before removing contents, --force to skip)" }
+    fn usage(&self) -> &str { "rmdir [-f|--force] <directory>" }
+    fn category(&self) -> Option<Category> { Some(Category::Files) }
 }
 impl Command for Rmdir {
     async fn execute(&self, args: &[&str]) -> String {
-        if args.is_empty() {
-            return "Usage: rmdir <directory>".to_string();
-        }
+        let (force, dir_arg) = match args {
+            ["-f" | "--force", dir_arg] => (true, *dir_arg),
+            [dir_arg] if !dir_arg.starts_with('-') => (false, *dir_arg),
+            _ => return self.usage_error(),
+        };
 
-        let dir_arg = args[0];
         let target_path = CURRENT_DIR.with(|cd| DirPath::parse(dir_arg, &cd.borrow()));
 
-        if path_in_abyss(&target_path) {
-            // Handle abyss directories
-            // Check if directory exists first
-            if !dir_exists(&target_path).await {
-                return format!("rmdir: {}: No such directory", dir_arg);
-            }
+        if !dir_exists(&target_path).await {
+            return format!("rmdir: {}: No such directory", dir_arg);
+        }
 
-            match remove_dir_abyss(&target_path).await {
-                Ok(_) => String::new(),
-                Err(e) => format!("rmdir: {}: {}", dir_arg, e)
+        let is_empty = get_contents(&target_path).await.0.is_empty()
+            && get_directories(&target_path).await.0.is_empty();
+        if !is_empty {
+            if force {
+                return remove_tree(&target_path, dir_arg, "rmdir", true).await;
+            }
+            if has_read_only_within(&target_path) {
+                return read_only_tree_error("rmdir", dir_arg);
             }
+            if has_open_in_editor_within(&target_path) {
+                return open_tree_error("rmdir", dir_arg);
+            }
+
+            // Offer to remove everything instead - handled on the next input
+            let dir_arg = dir_arg.to_string();
+            crate::prompt::confirm(
+                &format!("rmdir: {}: Directory not empty. Remove it and everything in it?", dir_arg),
+                move || async move { remove_tree(&target_path, &dir_arg, "rmdir", false).await },
+            );
+            return String::new();  // Prompt already displayed
+        }
+
+        match remove_dir(&target_path).await {
+            Ok(_) => String::new(),
+            Err(e) => format!("rmdir: {}: {}", dir_arg, e),
+        }
+    }
+}
+
+pub struct Stat;
+impl CommandData for Stat {
+    fn name(&self) -> &str { "stat" }
+    fn description(&self) -> &str { "Show type, size, origin and modification time of a file" }
+    fn usage(&self) -> &str { "stat [--json] <path>" }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
+}
+impl Command for Stat {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (json, args) = take_json_flag(args);
+        let [path_arg] = args[..] else {
+            return self.usage_error();
+        };
+
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+        let in_abyss = path_in_abyss(&filepath.dir);
+
+        let content = if dir_exists(&filepath.dir).await {
+            get_contents(&filepath.dir).await.get(&filepath.file).cloned()
         } else {
-            // Handle regular virtual filesystem
-            VIRTUAL_FS.with(|vfs| {
-                match vfs.borrow_mut().remove_dir(&target_path) {
-                    Ok(_) => String::new(),
-                    Err(e) => format!("rmdir: {}: {}", dir_arg, e),
+            None
+        };
+
+        match content {
+            Some(content) => {
+                let (kind, origin) = match &content {
+                    Content::InMemory(_) => ("in-memory file", "created in this browser".to_string()),
+                    Content::ToFetch | Content::Fetched(_) => (
+                        "fetched file",
+                        origin_url(&filepath).unwrap_or_else(|| "generated".to_string()),
+                    ),
+                };
+
+                // Static files only have metadata once they have been fetched
+                let mut fetched_size = None;
+                if file_metadata(&filepath, &content).is_none() {
+                    fetched_size = get_file_content(&filepath).await.ok().map(|text| text.len());
                 }
-            })
+
+                if json {
+                    let meta = file_metadata(&filepath, &content);
+                    return json_output(json!({
+                        "path": filepath.to_string(),
+                        "type": "file",
+                        "in_memory": matches!(content, Content::InMemory(_)),
+                        "abyss": in_abyss,
+                        "size": meta.as_ref().map(|meta| meta.size).or(fetched_size),
+                        "origin": origin,
+                        "read_only": is_read_only(&filepath),
+                        "created": meta.as_ref().map(|meta| meta.created),
+                        "modified": meta.as_ref().map(|meta| meta.modified),
+                    }));
+                }
+
+                let (size, created, modified) = match file_metadata(&filepath, &content) {
+                    Some(meta) => (
+                        meta.size.to_string(),
+                        format_timestamp(meta.created),
+                        format_timestamp(meta.modified),
+                    ),
+                    None => (
+                        fetched_size.map_or("unknown".to_string(), |size| size.to_string()),
+                        "-".to_string(),
+                        "-".to_string(),
+                    ),
+                };
+
+                format!(
+                    "  File: {}\n  Type: {}{}\n  Size: {} bytes\nOrigin: {}\nAccess: {}\nCreate: {}\nModify: {}",
+                    filepath.to_string(),
+                    kind,
+                    if in_abyss { " (abyss)" } else { "" },
+                    size,
+                    origin,
+                    if is_read_only(&filepath) { "read-only (site content)" } else { "read-write" },
+                    created,
+                    modified,
+                )
+            }
+            None => {
+                let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(path_arg, &cd.borrow()));
+                if !dir_exists(&dirpath).await {
+                    return format!("stat: {}: No such file or directory", path_arg);
+                }
+
+                let files = get_contents(&dirpath).await.0.len();
+                let dirs = get_directories(&dirpath).await.0.len();
+                if json {
+                    return json_output(json!({
+                        "path": dirpath.to_string(),
+                        "type": "directory",
+                        "abyss": path_in_abyss(&dirpath),
+                        "files": files,
+                        "directories": dirs,
+                    }));
+                }
+                format!(
+                    "  File: {}\n  Type: directory{}\n Items: {} file(s), {} directory(s)",
+                    dirpath.to_string(),
+                    if path_in_abyss(&dirpath) { " (abyss)" } else { "" },
+                    files,
+                    dirs,
+                )
+            }
         }
     }
 }
+
+
+pub struct File;
+impl CommandData for File {
+    fn name(&self) -> &str { "file" }
+    fn description(&self) -> &str { "Say what kind of content a file holds (text, JSON, zip...)" }
+    fn usage(&self) -> &str { "file <path>..." }
+    fn category(&self) -> Option<Category> { Some(Category::Text) }
+}
+impl Command for File {
+    async fn execute(&self, args: &[&str]) -> String {
+        if args.is_empty() {
+            return self.usage_error();
+        }
+
+        let mut lines = Vec::new();
+        for path_arg in args {
+            let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+            let line = match get_file_bytes(&filepath).await {
+                Ok(bytes) => {
+                    emit(FsEvent::FileRead(filepath));
+                    let description = match sniff(path_arg, &bytes) {
+                        FileType::Text if bytes.is_ascii() => "ASCII text",
+                        FileType::Text => "UTF-8 text",
+                        file_type => file_type.description(),
+                    };
+                    format!("{}: {}", path_arg, description)
+                }
+                Err(_) => {
+                    let dirpath = CURRENT_DIR.with(|cd| DirPath::parse(path_arg, &cd.borrow()));
+                    if dir_exists(&dirpath).await {
+                        format!("{}: directory", path_arg)
+                    } else {
+                        format!("file: {}: No such file or directory", path_arg)
+                    }
+                }
+            };
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Ls);
+    registry.add(Cd);
+    registry.add(Pwd);
+    registry.add(Map);
+    registry.add(Cat);
+    registry.add(Stat);
+    registry.add(File);
+    registry.add(Rm);
+    registry.add(Mkdir);
+    registry.add(Rmdir);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+    use crate::filesystem::{DirPath, FilePath};
+
+    fn entry(name: &str, size: Option<usize>, modified: Option<f64>) -> LsEntry {
+        LsEntry { name: name.to_string(), kind: EntryKind::of(name), size, modified, front_matter: None }
+    }
+
+    fn names(entries: &[LsEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_entries() {
+        let mut entries = vec![
+            entry("b.txt", Some(10), Some(2.0)),
+            entry("blog/", None, None),
+            entry("a.txt", Some(300), Some(1.0)),
+            entry("c.md", None, None),
+        ];
+
+        sort_entries(&mut entries, SortBy::Name);
+        assert_eq!(names(&entries), ["a.txt", "b.txt", "blog/", "c.md"]);
+
+        sort_entries(&mut entries, SortBy::Modified);
+        assert_eq!(names(&entries), ["b.txt", "a.txt", "blog/", "c.md"]);
+
+        sort_entries(&mut entries, SortBy::Size);
+        assert_eq!(names(&entries), ["a.txt", "b.txt", "blog/", "c.md"]);
+    }
+
+    #[test]
+    fn test_long_listing_alignment() {
+        let entries = vec![
+            entry("blog/", None, None),
+            entry("notes.txt", Some(1234), None),
+            entry("post.md", None, None),
+        ];
+        let lines: Vec<String> = long_listing(&entries).into_iter()
+            .map(|(name, _, rest)| name + &rest)
+            .collect();
+        assert_eq!(lines, [
+            "NAME       KIND      SIZE  MODIFIED",
+            "blog/      dir          -  -",
+            "notes.txt  text      1234  -",
+            "post.md    markdown     ?  -",
+        ]);
+    }
+
+    #[test]
+    fn test_ls_and_cd_through_the_abyss() {
+        enable(1);
+        assert_eq!(run("ls"), ["about.txt", "abyss/", "config/", "fortunes.txt", "rpg/"]);
+        assert_eq!(run("cd abyss"), Vec::<String>::new());
+        assert_eq!(run("ls"), ["level1/", "mirror_halls/", "warning.txt"]);
+        assert_eq!(run("cd level1/level2/level3"), Vec::<String>::new());
+        assert_eq!(run("pwd"), ["/abyss/level1/level2/level3"]);
+        assert_eq!(run("cd nowhere"), ["cd: nowhere: No such directory"]);
+    }
+
+    #[test]
+    fn test_rm_and_mkdir_in_the_abyss() {
+        enable(1);
+        run("cd /abyss");
+        assert_eq!(run("rm warning.txt"), Vec::<String>::new());
+        assert_eq!(run("ls"), ["level1/", "mirror_halls/"]);
+        assert_eq!(run("cat warning.txt"), ["cat: warning.txt: No such file"]);
+
+        assert_eq!(run("mkdir camp"), Vec::<String>::new());
+        run("cd camp");
+        assert_eq!(run("pwd"), ["/abyss/camp"]);
+        assert_eq!(run("ls"), ["(empty directory)"]);
+    }
+
+    #[test]
+    fn test_cat_several_files_with_line_numbers() {
+        enable(1);
+        crate::write_file("a.txt", "one\ntwo\n".to_string()).unwrap();
+        crate::write_file("b.txt", "three".to_string()).unwrap();
+        assert_eq!(run("cat a.txt b.txt a.txt"), ["one", "two", "three", "one", "two"]);
+        assert_eq!(run("cat -n a.txt missing.txt b.txt"), [
+            "     1  one", "     2  two", "cat: missing.txt: No such file", "     3  three",
+        ]);
+        assert_eq!(run("cat -x a.txt"), ["cat: invalid option -- 'x'", "Usage: cat [-n] <file>..."]);
+        assert_eq!(run("cat"), ["Usage: cat [-n] <file>..."]);
+    }
+
+    #[test]
+    fn test_big_files_are_shown_in_full() {
+        enable(1);
+        let lines: Vec<String> = (1..=1000).map(|n| format!("line {}", n)).collect();
+        crate::write_file("big.txt", lines.join("\n")).unwrap();
+        assert_eq!(run("cat big.txt"), lines);
+    }
+
+    #[test]
+    fn test_mkdir_p() {
+        enable(1);
+        assert_eq!(run("mkdir notes/2024"), ["mkdir: notes/2024: Parent directory does not exist (use mkdir -p to create it)"]);
+        assert_eq!(run("mkdir -p notes/2024/june"), Vec::<String>::new());
+        assert_eq!(run("ls notes/2024"), ["june/"]);
+        assert_eq!(run("mkdir -p notes/2024"), Vec::<String>::new());
+
+        assert_eq!(run("mkdir -p /abyss/level1/camp/tent"), Vec::<String>::new());
+        assert_eq!(run("ls /abyss/level1"), ["camp/", "level2/", "note.txt"]);
+        assert_eq!(run("ls /abyss/level1/camp"), ["tent/"]);
+    }
+
+    #[test]
+    fn test_rm_r_in_the_abyss() {
+        enable(1);
+        run("cd /abyss/level1/level2/level3");
+        assert_eq!(run("rm /abyss/level1"), ["rm: /abyss/level1: Is a directory (use rm -r to remove it and its contents)"]);
+        assert_eq!(run("rm -r /abyss/level1"), Vec::<String>::new());
+        assert_eq!(run("pwd"), ["/abyss"]);
+        assert_eq!(run("ls"), ["mirror_halls/", "warning.txt"]);
+        assert_eq!(run("cd level1"), ["cd: level1: No such directory"]);
+        assert_eq!(run("rm -r /"), ["rm: /: Refusing to remove the root directory"]);
+    }
+
+    #[test]
+    fn test_rmdir_non_empty() {
+        enable(1);
+        run("cd /abyss");
+        assert_eq!(run("rmdir level1"), ["rmdir: level1: Directory not empty. Remove it and everything in it? (y/n)"]);
+        assert_eq!(run("n"), ["Cancelled."]);
+        assert_eq!(run("ls"), ["level1/", "mirror_halls/", "warning.txt"]);
+
+        run("rmdir level1");
+        assert_eq!(run("y"), Vec::<String>::new());
+        assert_eq!(run("ls"), ["mirror_halls/", "warning.txt"]);
+
+        assert_eq!(run("mkdir -p camp/tent"), Vec::<String>::new());
+        assert_eq!(run("rmdir --force camp"), Vec::<String>::new());
+        assert_eq!(run("ls"), ["mirror_halls/", "warning.txt"]);
+    }
+
+    #[test]
+    fn test_rm_i() {
+        enable(1);
+        run("cd /abyss");
+        assert_eq!(run("rm -i warning.txt"), ["rm: remove 'warning.txt'? (y/n)"]);
+        assert_eq!(run("no"), ["Cancelled."]);
+        assert_eq!(run("rm -i warning.txt"), ["rm: remove 'warning.txt'? (y/n)"]);
+        assert_eq!(run("y"), Vec::<String>::new());
+        assert_eq!(run("rm -i level1"), ["rm: level1: Is a directory (use rm -r to remove it and its contents)"]);
+        assert_eq!(run("rm -ri level1"), ["rm: remove directory 'level1' and everything in it? (y/n)"]);
+        assert_eq!(run("YES"), Vec::<String>::new());
+        assert_eq!(run("ls"), ["mirror_halls/"]);
+        assert_eq!(run("rm -i missing.txt"), ["rm: missing.txt: No such file"]);
+        assert_eq!(run("rm -x missing.txt"), ["Usage: rm [-rif] <path>"]);
+    }
+
+    #[test]
+    fn test_static_content_is_read_only() {
+        enable(1);
+        assert_eq!(run("rm about.txt"), ["rm: about.txt: Read-only file (use rm -f to remove it anyway)"]);
+        assert_eq!(run("edit about.txt"), ["edit: about.txt: Read-only file (use edit -f to edit it anyway)"]);
+        assert_eq!(run("load about.txt"), ["load: about.txt: Read-only file (use load -f to overwrite it anyway)"]);
+        assert_eq!(run("rm -r /"), ["rm: /: Refusing to remove the root directory"]);
+
+        let guide = FilePath::parse("/docs/guide.txt", &DirPath::root());
+        crate::VIRTUAL_FS.with_borrow_mut(|vfs| {
+            vfs.write_file(&guide, "site docs".to_string());
+            vfs.read_only.insert(guide);
+        });
+        assert_eq!(run("rm -r docs"), ["rm: docs: Contains read-only files (use rm -rf to remove them too)"]);
+        assert_eq!(run("rmdir docs"), ["rmdir: docs: Contains read-only files (use rmdir -f to remove them too)"]);
+        assert_eq!(run("rm -rf docs"), Vec::<String>::new());
+
+        assert_eq!(run("rm -f about.txt"), Vec::<String>::new());
+        assert_eq!(run("ls"), ["abyss/", "config/", "fortunes.txt", "rpg/"]);
+        // A file created in its place is the user's own
+        crate::write_file("about.txt", "mine".to_string()).unwrap();
+        assert_eq!(run("rm about.txt"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_json_output() {
+        enable(1);
+        crate::write_file("notes.txt", "milk".to_string()).unwrap();
+
+        let ls: serde_json::Value = serde_json::from_str(&run("ls --json").join("\n")).unwrap();
+        assert_eq!(ls, serde_json::json!([
+            { "name": "about.txt", "kind": "text", "size": null, "modified": null },
+            { "name": "abyss", "kind": "dir", "size": null, "modified": null },
+            { "name": "config", "kind": "dir", "size": null, "modified": null },
+            { "name": "fortunes.txt", "kind": "text", "size": null, "modified": null },
+            { "name": "notes.txt", "kind": "text", "size": 4, "modified": crate::js_interop::FIXED_NOW },
+            { "name": "rpg", "kind": "dir", "size": null, "modified": null },
+        ]));
+
+        let stat: serde_json::Value = serde_json::from_str(&run("stat --json notes.txt").join("\n")).unwrap();
+        assert_eq!(stat["type"], "file");
+        assert_eq!(stat["in_memory"], true);
+        assert_eq!(stat["read_only"], false);
+        assert_eq!(stat["size"], 4);
+
+        let stat: serde_json::Value = serde_json::from_str(&run("stat /abyss --json").join("\n")).unwrap();
+        assert_eq!(stat, serde_json::json!({ "path": "/abyss", "type": "directory", "abyss": true, "files": 1, "directories": 2 }));
+
+        assert_eq!(run("ls --json nowhere"), ["ls: nowhere: No such directory"]);
+    }
+
+    #[test]
+    fn test_file_types() {
+        enable(1);
+        crate::VIRTUAL_FS.with_borrow_mut(|vfs| {
+            vfs.write_file(&FilePath::parse("/notes.bin", &DirPath::root()), b"PK\x05\x06".to_vec());
+            vfs.write_file(&FilePath::parse("/data.txt", &DirPath::root()), r#"{"a": 1}"#);
+        });
+
+        assert_eq!(run("file about.txt abyss notes.bin data.txt missing"), [
+            "about.txt: ASCII text",
+            "abyss: directory",
+            "notes.bin: Zip archive",
+            "data.txt: JSON data",
+            "file: missing: No such file or directory",
+        ]);
+        assert_eq!(run("file"), ["Usage: file <path>..."]);
+
+        // pretty only asks about files that don't look renderable; JSON is laid out in place
+        assert_eq!(run("pretty notes.bin"), ["Warning: 'notes.bin' is not a markdown or html file (Zip archive). Render anyway? (y/n)"]);
+        assert_eq!(run("n"), ["Cancelled."]);
+        assert_eq!(run("pretty data.txt"), ["{", "  \"a\": 1", "}"]);
+        assert_eq!(run("pp data.txt"), ["{", "  \"a\": 1", "}"]);
+        assert_eq!(run("pp about.txt"), ["pp: about.txt: Not JSON or TOML (text)"]);
+    }
+}
@@ -0,0 +1,100 @@
+use std::rc::Rc;
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::filesystem::file_paths::TWENTY48_FILE_PATH;
+use crate::filesystem::helpers::get_file_content;
+use crate::game;
+use crate::js_interop::{add_output, now};
+use crate::prompt::{self, Prompt};
+use crate::twenty48::Board;
+use crate::typetest::{self, MAX_SCORES};
+
+pub struct Typetest;
+impl CommandData for Typetest {
+    fn name(&self) -> &str { "typetest" }
+    fn description(&self) -> &str { "Test your typing speed and accuracy (scores to see the best)" }
+    fn usage(&self) -> &str { "typetest\ntypetest scores" }
+    fn category(&self) -> Option<Category> { Some(Category::Games) }
+}
+impl Command for Typetest {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => {
+                let Some(passage) = typetest::pick().await else {
+                    return "typetest: No passages to type".to_string();
+                };
+                add_output("Type each line as it appears and press Enter (Escape to give up).");
+                add_output("The clock starts now.");
+                ask_line(Rc::new(passage), Vec::new(), now());
+                String::new()  // Prompt already displayed
+            }
+            ["scores"] => {
+                let scores = typetest::scores().await;
+                if scores.is_empty() {
+                    return "No scores yet: run typetest to set one".to_string();
+                }
+                scores.iter()
+                    .take(MAX_SCORES)
+                    .enumerate()
+                    .map(|(i, score)| format!("{}. {:>3} WPM  {:>3}%  {}", i + 1, score.wpm, score.accuracy, score.date).trim_end().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+// Ask for the next line of the passage, scoring the test after the last
+fn ask_line(passage: Rc<Vec<String>>, mut typed: Vec<String>, started: f64) {
+    let line = passage[typed.len()].clone();
+    prompt::ask(Prompt::new(&line, move |answer| async move {
+        typed.push(answer);
+        if typed.len() < passage.len() {
+            ask_line(passage, typed, started);
+            return String::new();
+        }
+        let score = typetest::score(&passage, &typed, now() - started);
+        let result = format!("{} WPM, {}% accuracy", score.wpm, score.accuracy);
+        match typetest::record(score).await {
+            Ok(Some(place)) => format!("{}\nNew high score: #{} (typetest scores to see them all)", result, place),
+            Ok(None) => result,
+            Err(e) => format!("{}\ntypetest: {}", result, e),
+        }
+    }));
+}
+
+pub struct Twenty48;
+impl CommandData for Twenty48 {
+    fn name(&self) -> &str { "2048" }
+    fn description(&self) -> &str { "Slide and merge tiles to make 2048 (carries on a saved game)" }
+    fn usage(&self) -> &str { "2048 [new]" }
+    fn category(&self) -> Option<Category> { Some(Category::Games) }
+}
+impl Command for Twenty48 {
+    async fn execute(&self, args: &[&str]) -> String {
+        let fresh = match args {
+            [] => false,
+            ["new"] => true,
+            _ => return self.usage_error(),
+        };
+        let saved = match get_file_content(&TWENTY48_FILE_PATH).await {
+            Ok(json) => match Board::parse(&json) {
+                Ok(board) => Some(board),
+                Err(_) if fresh => None,
+                Err(_) => return format!("2048: {}: Not a saved game (2048 new starts again)", TWENTY48_FILE_PATH.to_string()),
+            },
+            Err(_) => None,
+        };
+        // A finished game isn't carried on, but its best score is
+        let board = match saved {
+            Some(board) if !fresh && board.can_move() => board,
+            saved => Board::new(saved.map_or(0, |board| board.best)),
+        };
+        game::start(board)
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Typetest);
+    registry.add(Twenty48);
+}
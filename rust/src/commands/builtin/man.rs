@@ -0,0 +1,163 @@
+use crate::commands::{COMMANDS, Category, Command, CommandData, Registry, UserDefined};
+use crate::filesystem::helpers::get_file_content;
+use crate::js_interop::add_output_rich;
+use crate::markdown::{Line, Span};
+
+pub struct Man;
+impl CommandData for Man {
+    fn name(&self) -> &str { "man" }
+    fn description(&self) -> &str { "Show a command's manual page (-k to search the commands)" }
+    fn usage(&self) -> &str { "man <command>\nman -k <keyword>" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+impl Command for Man {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            ["-k", keyword] => apropos(keyword),
+            [name] if !name.starts_with('-') => {
+                let Some(page) = page(name).await else {
+                    return format!("man: {}: No manual entry", name);
+                };
+                for line in render(&page) {
+                    let segments: Vec<_> = line.iter()
+                        .map(|span| (span.text.as_str(), span.class.as_str(), span.href.as_deref()))
+                        .collect();
+                    add_output_rich(&segments);
+                }
+                String::new()  // Already output with styling
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+// The verbose manual, or one made up from the brief manual (or the
+// command's description) and its usage
+async fn page(name: &str) -> Option<String> {
+    let builtin = COMMANDS.get(name);
+    let user_defined = UserDefined(name.to_string());
+    let command: &dyn CommandData = match builtin {
+        Some(builtin) => builtin,
+        None => &user_defined,
+    };
+    if let Ok(manual) = get_file_content(&command.manual(true)).await {
+        return Some(manual);
+    }
+    let summary = match get_file_content(&command.manual(false)).await {
+        Ok(brief) => brief.trim().to_string(),
+        Err(_) if builtin.is_some() => command.description().to_string(),
+        Err(_) => return None,
+    };
+    let usage: Vec<String> = command.usage().lines().map(|form| format!("  {}", form)).collect();
+    Some(format!("{} - {}\n\nUSAGE:\n{}\n", name, summary, usage.join("\n")))
+}
+
+// Commands whose name or description mentions `keyword`
+fn apropos(keyword: &str) -> String {
+    let lowercase = keyword.to_lowercase();
+    let mut matches: Vec<_> = COMMANDS.iter()
+        .filter(|command| command.category().is_some())
+        .filter(|command| command.name().contains(&lowercase) || command.description().to_lowercase().contains(&lowercase))
+        .collect();
+    matches.sort_by_key(|command| command.name());
+    let matches: Vec<String> = matches.iter()
+        .map(|command| format!("{:<14} - {}", command.name(), command.description()))
+        .collect();
+    match matches.is_empty() {
+        true => format!("man: {}: Nothing appropriate", keyword),
+        false => matches.join("\n"),
+    }
+}
+
+fn styled(text: &str, class: &str) -> Span {
+    Span { text: text.to_string(), class: class.to_string(), href: None }
+}
+
+/// A manual page laid out for the terminal: the command and section
+/// headings in bold, and the commands in USAGE and EXAMPLES as code, with
+/// the examples indented under their heading
+fn render(page: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut section = "";
+    for (i, line) in page.trim_end().lines().enumerate() {
+        if i == 0 && let Some((name, title)) = line.split_once(" - ") {
+            lines.push(vec![styled(name, "md-strong"), styled(&format!(" - {}", title), "")]);
+        } else if let Some(heading) = line.strip_suffix(':').filter(|heading| is_heading(heading)) {
+            section = heading;
+            lines.push(vec![styled(heading, "md-strong")]);
+        } else if line.trim().is_empty() {
+            lines.push(Vec::new());
+        } else if section == "USAGE" {
+            lines.push(vec![styled("  ", ""), styled(line.trim(), "md-code")]);
+        } else if section == "EXAMPLES" && line.starts_with("  ") {
+            // "command      what it does"
+            let example = line.trim_start();
+            let (command, gap_and_text) = match example.find("  ") {
+                Some(gap) => example.split_at(gap),
+                None => (example, ""),
+            };
+            let mut spans = vec![styled("    ", ""), styled(command, "md-code")];
+            if !gap_and_text.is_empty() {
+                spans.push(styled(gap_and_text, ""));
+            }
+            lines.push(spans);
+        } else {
+            lines.push(vec![styled(line, "")]);
+        }
+    }
+    lines
+}
+
+// "USAGE", "SEE ALSO" and the like
+fn is_heading(text: &str) -> bool {
+    !text.is_empty() && !text.starts_with(' ') && text.chars().all(|c| c.is_ascii_uppercase() || c == ' ')
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Man);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    fn texts(line: &Line) -> Vec<(&str, &str)> {
+        line.iter().map(|span| (span.text.as_str(), span.class.as_str())).collect()
+    }
+
+    #[test]
+    fn test_render_page() {
+        let page = "cat - Display file contents\n\nUSAGE:\n  cat [-n] FILE...\n\nEXAMPLES:\n  cat about.txt           Display about.txt\n  cat -n\n\nNOTES:\n  - USAGE: not a heading\n";
+        let lines = render(page);
+        assert_eq!(texts(&lines[0]), [("cat", "md-strong"), (" - Display file contents", "")]);
+        assert_eq!(texts(&lines[2]), [("USAGE", "md-strong")]);
+        assert_eq!(texts(&lines[3]), [("  ", ""), ("cat [-n] FILE...", "md-code")]);
+        assert_eq!(texts(&lines[6]), [("    ", ""), ("cat about.txt", "md-code"), ("           Display about.txt", "")]);
+        assert_eq!(texts(&lines[7]), [("    ", ""), ("cat -n", "md-code")]);
+        assert_eq!(texts(&lines[10]), [("  - USAGE: not a heading", "")]);
+    }
+
+    #[test]
+    fn test_man() {
+        enable(1);
+        // No manuals are served here, so pages are made from the commands themselves
+        assert_eq!(run("man diff"), [
+            "diff - Show differences between two files, or an edited file and the original",
+            "",
+            "USAGE",
+            "  diff <file1> <file2>",
+            "  diff <file>",
+        ]);
+        assert_eq!(run("man nonsense"), ["man: nonsense: No manual entry"]);
+        assert_eq!(run("man -k ZIP"), [
+            "file           - Say what kind of content a file holds (text, JSON, zip...)",
+            "save           - Download a file (or with -r a directory, as a zip) to your device",
+            "save-session   - Export your files as a gzipped session (downloads with timestamp)",
+            "unzip          - Extract a zip archive (into destination, or here)",
+            "zip            - Bundle a directory into a zip archive",
+        ]);
+        assert_eq!(run("man -k xyzzy"), ["man: xyzzy: Nothing appropriate"]);
+        assert_eq!(run("man"), ["Usage: man <command>", "       man -k <keyword>"]);
+    }
+}
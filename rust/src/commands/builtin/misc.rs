@@ -1,18 +1,73 @@
-use crate::commands::{Command, CommandData};
+use crate::commands::{Category, Command, CommandData, Registry, json_output, take_json_flag};
+use crate::dice;
+use crate::datetime::{DEFAULT_FORMAT, DateTime};
+use crate::features;
+use crate::figlet::Font;
+use crate::filesystem::{CURRENT_DIR, FilePath};
+use crate::filesystem::file_paths::FEATURES_FILE_PATH;
+use crate::filesystem::helpers::{file_exists, get_file_content};
+use crate::input_history::INPUT_HISTORY;
+use crate::preferences::{self, Preferences};
+use crate::rng;
+use crate::js_interop::clear_output;
+use crate::script::{self, Language};
+use crate::theme;
+use crate::i18n::{DEFAULT_LANGUAGE, localized, lookup, pick_language, translations};
+
+// Most rolls one `roll -n` makes
+const MAX_ROLLS: u32 = 100;
+
+const HELLO: &str = "Hello from Rust! This command was processed by WebAssembly.";
+
+pub struct Clear;
+impl CommandData for Clear {
+    fn name(&self) -> &str { "clear" }
+    fn description(&self) -> &str { "Clear the terminal" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+    fn aliases(&self) -> &[&str] { &["cls"] }
+}
+impl Command for Clear {
+    async fn execute(&self, _args: &[&str]) -> String {
+        clear_output();
+        String::new()
+    }
+}
 
 pub struct Hello;
 impl CommandData for Hello {
     fn name(&self) -> &str { "hello" }
+    fn description(&self) -> &str { "Greeting from Rust/WASM in your language (or CODE, e.g. ja)" }
+    fn usage(&self) -> &str { "hello [--lang CODE]" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
 }
 impl Command for Hello {
-    async fn execute(&self, _args: &[&str]) -> String {
-        "Hello from Rust! This command was processed by WebAssembly.".to_string()
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => localized("hello").await.unwrap_or_else(|| HELLO.to_string()),
+            ["--lang", code] => {
+                let translations = match translations().await {
+                    Ok(translations) => translations,
+                    Err(e) => return format!("hello: {}", e),
+                };
+                let language = pick_language(Some(code), &translations);
+                // pick_language falls back to English for anything it doesn't know
+                let asked_for_english = code.to_ascii_lowercase().split('-').next() == Some(DEFAULT_LANGUAGE);
+                if language == DEFAULT_LANGUAGE && !asked_for_english {
+                    let available = translations.keys().cloned().collect::<Vec<_>>().join(", ");
+                    return format!("hello: {}: Unknown language (available: {})", code, available);
+                }
+                lookup(&translations, &language, "hello").unwrap_or_else(|| HELLO.to_string())
+            }
+            _ => self.usage_error(),
+        }
     }
 }
 
 pub struct Info;
 impl CommandData for Info {
     fn name(&self) -> &str { "info" }
+    fn description(&self) -> &str { "WASM module info" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
 }
 impl Command for Info {
     async fn execute(&self, _args: &[&str]) -> String {
@@ -23,9 +78,94 @@ impl Command for Info {
     }
 }
 
+pub struct Roll;
+impl CommandData for Roll {
+    fn name(&self) -> &str { "roll" }
+    fn description(&self) -> &str { "Roll dice, e.g. 3d6+2 or 2d20kh1 (-n to roll several times)" }
+    fn usage(&self) -> &str { "roll [-n <times>] <dice>..." }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Roll {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (times, notations) = match args {
+            ["-n", times, rest @ ..] => match times.parse::<u32>() {
+                Ok(times @ 1..=MAX_ROLLS) => (times, rest),
+                _ => return format!("roll: {}: Roll between 1 and {} times", times, MAX_ROLLS),
+            },
+            _ => (1, args),
+        };
+        if notations.is_empty() {
+            return self.usage_error();
+        }
+        let mut expressions = Vec::new();
+        for notation in notations {
+            match dice::parse(notation) {
+                Ok(expression) => expressions.push(expression),
+                Err(message) => return format!("roll: {}: {}", notation, message),
+            }
+        }
+        let mut lines = Vec::new();
+        for _ in 0..times {
+            for expression in &expressions {
+                let outcome = expression.roll();
+                lines.push(format!("{}: {} = {}", expression, outcome.detail, outcome.total));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+pub struct Random;
+impl CommandData for Random {
+    fn name(&self) -> &str { "random" }
+    fn description(&self) -> &str { "Pick random numbers, choices, orders and UUIDs" }
+    fn usage(&self) -> &str { "random int <low> <high>\nrandom choice <item>...\nrandom shuffle <item>...\nrandom shuffle -f <file>\nrandom uuid" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Random {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            ["int", low, high] => {
+                let (low, high) = match (parse_int(low), parse_int(high)) {
+                    (Ok(low), Ok(high)) => (low, high),
+                    (Err(message), _) | (_, Err(message)) => return message,
+                };
+                if low > high {
+                    return format!("random: {} is more than {}", low, high);
+                }
+                rng::random_range(low..=high).to_string()
+            }
+            ["choice", items @ ..] if !items.is_empty() => rng::choose(items).copied().unwrap_or_default().to_string(),
+            ["shuffle", "-f", path_arg] => {
+                let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+                let Ok(text) = get_file_content(&filepath).await else {
+                    return format!("random: {}: No such file", path_arg);
+                };
+                let mut lines: Vec<&str> = text.lines().collect();
+                rng::shuffle(&mut lines);
+                lines.join("\n")
+            }
+            ["shuffle", items @ ..] if !items.is_empty() => {
+                let mut items = items.to_vec();
+                rng::shuffle(&mut items);
+                items.join("\n")
+            }
+            ["uuid"] => rng::uuid(),
+            _ => self.usage_error(),
+        }
+    }
+}
+
+fn parse_int(arg: &str) -> Result<i64, String> {
+    arg.parse().map_err(|_| format!("random: {}: Not a whole number", arg))
+}
+
 pub struct Echo;
 impl CommandData for Echo {
     fn name(&self) -> &str { "echo" }
+    fn description(&self) -> &str { "Echo back the provided text" }
+    fn usage(&self) -> &str { "echo <text>" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
 }
 impl Command for Echo {
     async fn execute(&self, args: &[&str]) -> String {
@@ -37,6 +177,227 @@ impl Command for Echo {
     }
 }
 
+pub struct Banner;
+impl CommandData for Banner {
+    fn name(&self) -> &str { "banner" }
+    fn description(&self) -> &str { "Show text in large letters (-f to use a FIGlet font file)" }
+    fn usage(&self) -> &str { "banner [-f <font.flf>] <text>" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Banner {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (font, words) = match args {
+            [] | ["-f"] | ["-f", _] => return self.usage_error(),
+            ["-f", path_arg, words @ ..] => {
+                let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+                let Ok(text) = get_file_content(&filepath).await else {
+                    return format!("banner: {}: No such file", path_arg);
+                };
+                match Font::parse(&text) {
+                    Ok(font) => (font, words),
+                    Err(message) => return format!("banner: {}: {}", path_arg, message),
+                }
+            }
+            words => (Font::block(), words),
+        };
+        font.render(&words.join(" "))
+    }
+}
+
+pub struct Date;
+impl CommandData for Date {
+    fn name(&self) -> &str { "date" }
+    fn description(&self) -> &str { "Show the date and time (-u for UTC, +FORMAT to format it)" }
+    fn usage(&self) -> &str { "date [-u] [+FORMAT]" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Date {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (utc, args) = match args {
+            ["-u" | "--utc", rest @ ..] => (true, rest),
+            _ => (false, args),
+        };
+        let format = match args {
+            [] => DEFAULT_FORMAT.to_string(),
+            // Formats with spaces arrive as several arguments
+            [first, ..] if first.starts_with('+') => args.join(" ")[1..].to_string(),
+            _ => return self.usage_error(),
+        };
+        let date = if utc { DateTime::utc() } else { DateTime::local() };
+        date.format(&format)
+    }
+}
+
+pub struct Theme;
+impl CommandData for Theme {
+    fn name(&self) -> &str { "theme" }
+    fn description(&self) -> &str { "Show, list or change the terminal's colour theme" }
+    fn usage(&self) -> &str { "theme\ntheme list\ntheme set <name>" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
+}
+impl Command for Theme {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => theme::current().await,
+            ["list"] => {
+                let current = theme::current().await;
+                theme::THEMES.iter()
+                    .map(|(name, description)| {
+                        let marker = if *name == current { '*' } else { ' ' };
+                        format!("{} {:<12} {}", marker, name, description)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            ["set", name] => match theme::choose(name).await {
+                Ok(()) => format!("Theme set to {}", name),
+                Err(e) => format!("theme: {}", e),
+            },
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub struct Settings;
+impl CommandData for Settings {
+    fn name(&self) -> &str { "settings" }
+    fn description(&self) -> &str { "Show or change your preferences (font size, prompt, paging and more)" }
+    fn usage(&self) -> &str { "settings\nsettings get <key>\nsettings set <key> <value>\nsettings reset <key>" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
+}
+impl Command for Settings {
+    async fn execute(&self, args: &[&str]) -> String {
+        let mut chosen = preferences::current();
+        match args {
+            [] => preferences::KEYS.iter()
+                .map(|(key, _)| match chosen.get(key) {
+                    Some(value) => format!("{:<13} {}", key, value),
+                    None => format!("{:<13} {} (default)", key, effective(key)),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ["get", key] if preferences::KEYS.iter().any(|(known, _)| known == key) => {
+                chosen.get(key).unwrap_or_else(|| effective(key))
+            }
+            ["get", key] => format!("settings: {}: No such setting", key),
+            ["set", key, value @ ..] if !value.is_empty() => {
+                if let Err(e) = chosen.set(key, Some(&value.join(" "))) {
+                    return format!("settings: {}", e);
+                }
+                save(&chosen, key).await
+            }
+            ["reset", key] => {
+                if let Err(e) = chosen.set(key, None) {
+                    return format!("settings: {}", e);
+                }
+                save(&chosen, key).await
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+// The value in effect for a preference the visitor hasn't set
+fn effective(key: &str) -> String {
+    match key {
+        "font_size" => preferences::font_size().to_string(),
+        "prompt" => preferences::prompt(),
+        "pager_lines" => preferences::pager_lines().unwrap_or(0).to_string(),
+        "prefetch" => if preferences::prefetch() { "on" } else { "off" }.to_string(),
+        _ => preferences::history_size().to_string(),
+    }
+}
+
+async fn save(chosen: &Preferences, key: &str) -> String {
+    match preferences::save(chosen).await {
+        Ok(()) => format!("{} is now {}", key, chosen.get(key).unwrap_or_else(|| effective(key))),
+        Err(e) => format!("settings: {}", e),
+    }
+}
+
+pub struct History;
+impl CommandData for History {
+    fn name(&self) -> &str { "history" }
+    fn description(&self) -> &str { "List the commands entered this session" }
+    fn usage(&self) -> &str { "history [--json]" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for History {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (json, args) = take_json_flag(args);
+        if !args.is_empty() {
+            return self.usage_error();
+        }
+
+        let inputs = INPUT_HISTORY.with_borrow(|history| history.inputs().to_vec());
+        if json {
+            return json_output(serde_json::json!(inputs));
+        }
+
+        let width = inputs.len().to_string().len();
+        inputs.iter().enumerate()
+            .map(|(i, input)| format!("{:>width$}  {}", i + 1, input))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub struct Features;
+impl CommandData for Features {
+    fn name(&self) -> &str { "features" }
+    fn description(&self) -> &str { "List experimental feature flags, or override one" }
+    fn usage(&self) -> &str { "features [--json]\nfeatures <name> on|off|reset" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Features {
+    async fn execute(&self, args: &[&str]) -> String {
+        let (json, args) = take_json_flag(args);
+        match args[..] {
+            [] => list_features(json),
+            [name, state] if !json => {
+                let on = match state {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    "reset" => None,
+                    _ => return self.usage_error(),
+                };
+                if !features::names().iter().any(|known| known == name) {
+                    return format!("features: {}: Unknown feature", name);
+                }
+                features::set_override(name, on);
+                let (enabled, source) = features::flag(name);
+                format!("{} is now {} ({})", name, if enabled { "on" } else { "off" }, source.as_str())
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+fn list_features(json: bool) -> String {
+    let names = features::names();
+    if json {
+        let flags: Vec<_> = names.iter()
+            .map(|name| {
+                let (enabled, source) = features::flag(name);
+                serde_json::json!({ "name": name, "enabled": enabled, "source": source.as_str() })
+            })
+            .collect();
+        return json_output(serde_json::json!(flags));
+    }
+
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    let mut lines: Vec<String> = features::config_error()
+        .map(|e| format!("features: {}: {} (using defaults)", FEATURES_FILE_PATH.to_string(), e))
+        .into_iter()
+        .collect();
+    lines.extend(names.iter().map(|name| {
+        let (enabled, source) = features::flag(name);
+        let line = format!("{:<width$}  {:<3}  {:<7}  {}", name, if enabled { "on" } else { "off" }, source.as_str(), features::description(name).unwrap_or(""));
+        line.trim_end().to_string()
+    }));
+    lines.join("\n")
+}
+
 /// Calculate fibonacci number (helper function)
 fn fibonacci(n: u32) -> u64 {
     match n {
@@ -55,14 +416,37 @@ fn fibonacci(n: u32) -> u64 {
     }
 }
 
+pub struct Hsh;
+impl CommandData for Hsh {
+    fn name(&self) -> &str { "hsh" }
+    fn description(&self) -> &str { "Run a script written in hsh (variables, if, loops, string functions)" }
+    fn usage(&self) -> &str { "hsh <script> [arguments...]" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Hsh {
+    async fn execute(&self, args: &[&str]) -> String {
+        let [path_arg, script_args @ ..] = args else {
+            return self.usage_error();
+        };
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+        if !file_exists(&filepath).await {
+            return format!("hsh: {}: No such file", path_arg);
+        }
+        script::run(&filepath, Language::Hsh, path_arg, script_args).await
+    }
+}
+
 pub struct Fib;
 impl CommandData for Fib {
     fn name(&self) -> &str { "fib" }
+    fn description(&self) -> &str { "Calculate Nth fibonacci number" }
+    fn usage(&self) -> &str { "fib <number>" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
 }
 impl Command for Fib {
     async fn execute(&self, args: &[&str]) -> String {
         if args.is_empty() {
-            return "Usage: fib <number>".to_string();
+            return self.usage_error();
         }
 
         match args[0].parse::<u32>() {
@@ -71,7 +455,7 @@ impl Command for Fib {
                 format!("fibonacci({}) = {}", n, result)
             }
             Ok(_) => "Please enter a number between 0 and 93".to_string(),
-            Err(_) => "Usage: fib <number>".to_string(),
+            Err(_) => self.usage_error(),
         }
     }
 }
@@ -79,6 +463,8 @@ impl Command for Fib {
 pub struct Gol;
 impl CommandData for Gol {
     fn name(&self) -> &str { "gol" }
+    fn description(&self) -> &str { "GPU-accelerated Conway's Game of Life (opens in new tab)" }
+    fn category(&self) -> Option<Category> { Some(Category::ByClaude) }
 }
 impl Command for Gol {
     async fn execute(&self, _args: &[&str]) -> String {
@@ -94,3 +480,164 @@ impl Command for Gol {
         }
     }
 }
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Clear);
+    registry.add(Hello);
+    registry.add(Info);
+    registry.add(Fib);
+    registry.add(Roll);
+    registry.add(Random);
+    registry.add(Echo);
+    registry.add(Banner);
+    registry.add(Date);
+    registry.add(Theme);
+    registry.add(Settings);
+    registry.add(History);
+    registry.add(Features);
+    registry.add(Hsh);
+    registry.add(Gol);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{block_on, enable, run};
+
+    #[test]
+    fn test_history() {
+        enable(1);
+        run("pwd");
+        run("echo hi");
+        assert_eq!(run("history"), ["1  pwd", "2  echo hi", "3  history"]);
+        let history: Vec<String> = serde_json::from_str(&run("history --json").join("\n")).unwrap();
+        assert_eq!(history, ["pwd", "echo hi", "history", "history --json"]);
+    }
+
+    #[test]
+    fn test_date() {
+        enable(1);
+        // The clock is fixed at 2023-11-14 22:13:20 UTC outside the browser
+        assert_eq!(run("date"), ["Tue Nov 14 22:13:20 UTC 2023"]);
+        assert_eq!(run("date -u +%F"), ["2023-11-14"]);
+        assert_eq!(run("date +%H:%M on %A, %B %e"), ["22:13 on Tuesday, November 14"]);
+        assert_eq!(run("date today"), ["Usage: date [-u] [+FORMAT]"]);
+
+        crate::write_file("stamp.hsh", "let day = run(\"date +%j\")\nprint \"Day $day of the year\"".to_string()).unwrap();
+        assert_eq!(run("hsh stamp.hsh"), ["Day 318 of the year"]);
+    }
+
+    #[test]
+    fn test_banner() {
+        enable(1);
+        assert_eq!(run("banner Hi"), ["#   # ###", "#   #  #", "#####  #", "#   #  #", "#   # ###"]);
+
+        let font = std::iter::once("flf2a$ 1 1 4 -1 0".to_string())
+            .chain((' '..='~').map(|c| format!("[{}]@@", c)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        crate::write_file("square.flf", font).unwrap();
+        assert_eq!(run("banner -f square.flf ok go"), ["[o][k][ ][g][o]"]);
+        assert_eq!(run("banner -f about.txt hi"), ["banner: about.txt: Not a FIGlet font"]);
+        assert_eq!(run("banner -f missing.flf hi"), ["banner: missing.flf: No such file"]);
+        assert_eq!(run("banner"), ["Usage: banner [-f <font.flf>] <text>"]);
+
+        // The message of the day heads the greeting
+        crate::settings::set_setting("motd", "Hi");
+        assert!(block_on(crate::greeting_banner()).starts_with("#   # ###\n#   #  #\n#####  #\n#   #  #\n#   # ###\n\nTerminal ready."));
+        crate::settings::set_setting("motd", "");
+    }
+
+    #[test]
+    fn test_roll() {
+        enable(1);
+        let rolls = run("roll -n 3 2d20kh1 d6+1");
+        assert_eq!(rolls.len(), 6);
+        assert!(rolls[0].starts_with("2d20kh1: ["));
+        assert!(rolls[1].starts_with("1d6+1: ["));
+        for roll in &rolls {
+            let total: i64 = roll.rsplit(" = ").next().unwrap().parse().unwrap();
+            assert!((1..=20).contains(&total));
+        }
+        assert_eq!(run("roll 3d1+2"), ["3d1+2: [1, 1, 1] + 2 = 5"]);
+        assert_eq!(run("roll 3x6"), ["roll: 3x6: Unexpected 'x'"]);
+        assert_eq!(run("roll -n 0 d6"), ["roll: 0: Roll between 1 and 100 times"]);
+        assert_eq!(run("roll"), ["Usage: roll [-n <times>] <dice>..."]);
+    }
+
+    #[test]
+    fn test_random() {
+        enable(1);
+        for _ in 0..20 {
+            let n: i64 = run("random int -2 2")[0].parse().unwrap();
+            assert!((-2..=2).contains(&n));
+        }
+        assert_eq!(run("random int 5 5"), ["5"]);
+        assert_eq!(run("random int 3 1"), ["random: 3 is more than 1"]);
+        assert_eq!(run("random int 1 ten"), ["random: ten: Not a whole number"]);
+        assert!(["tea", "coffee"].contains(&run("random choice tea coffee")[0].as_str()));
+
+        let mut shuffled = run("random shuffle a b c d");
+        shuffled.sort();
+        assert_eq!(shuffled, ["a", "b", "c", "d"]);
+        crate::write_file("names.txt", "Ada\nAlan\nGrace".to_string()).unwrap();
+        let mut lines = run("random shuffle -f names.txt");
+        lines.sort();
+        assert_eq!(lines, ["Ada", "Alan", "Grace"]);
+
+        assert_eq!(run("random uuid")[0].len(), 36);
+        assert_eq!(run("random choice")[0], "Usage: random int <low> <high>");
+    }
+
+    #[test]
+    fn test_theme() {
+        enable(1);
+        assert_eq!(run("theme"), ["default"]);
+        assert_eq!(run("theme set amber"), ["Theme set to amber"]);
+        assert_eq!(crate::js_interop::applied_theme().as_deref(), Some("amber"));
+        assert_eq!(run("cat /.config/theme"), ["amber"]);
+        let list = run("theme list");
+        assert!(list.contains(&"* amber        Amber on black, like an old monochrome monitor".to_string()));
+        assert!(list[0].starts_with("  default"));
+        assert_eq!(run("theme set neon"), ["theme: neon: No such theme"]);
+        assert_eq!(run("theme"), ["amber"]);
+
+        // After a reload, the restored file brings the theme back
+        crate::js_interop::set_theme("default");
+        block_on(crate::theme::restore());
+        assert_eq!(crate::js_interop::applied_theme().as_deref(), Some("amber"));
+    }
+
+    #[test]
+    fn test_settings() {
+        enable(1);
+        crate::preferences::register();
+        assert!(run("settings").contains(&"font_size     16 (default)".to_string()));
+        assert_eq!(run("settings set prompt guest $"), ["prompt is now guest $"]);
+        assert_eq!(run("settings set history_size 2"), ["history_size is now 2"]);
+        assert_eq!(run("settings get prompt"), ["guest $"]);
+        assert_eq!(run("cat /.config/terminal.toml"), ["prompt = \"guest $\"", "history_size = 2"]);
+
+        assert_eq!(run("settings set font_size 100"), ["settings: 100: font_size must be a whole number from 8 to 40"]);
+        assert_eq!(run("settings set colour red"), ["settings: colour: No such setting"]);
+        assert_eq!(run("settings get colour"), ["settings: colour: No such setting"]);
+
+        // Other modules consult the preferences
+        run("history");
+        assert_eq!(run("history"), ["1  history", "2  history"]);
+        assert_eq!(crate::pager::page_size(), None);
+        run("settings set pager_lines 3");
+        assert_eq!(crate::pager::page_size(), Some(3));
+        crate::settings::set_setting("pager.lines", "10");
+        run("settings reset pager_lines");
+        assert_eq!(crate::pager::page_size(), Some(10));
+        crate::settings::set_setting("pager.lines", "");
+
+        // Editing the file by hand works too
+        crate::write_file("/.config/terminal.toml", "font_size = 20\n".to_string()).unwrap();
+        assert_eq!(run("settings get font_size"), ["20"]);
+        assert_eq!(run("settings get prompt"), [">"]);
+        assert_eq!(block_on(crate::preferences::load()), None);
+        crate::write_file("/.config/terminal.toml", "font_size = \"big\"\n".to_string()).unwrap();
+        assert!(block_on(crate::preferences::load()).unwrap().starts_with("settings: /.config/terminal.toml: invalid type"));
+    }
+}
@@ -1,13 +1,34 @@
 pub mod filesystem;
 pub mod content;
+pub mod man;
 pub mod files;
 pub mod pretty;
 pub mod misc;
+pub mod system;
+pub mod games;
 pub mod secret;
+pub mod text;
+pub mod comments;
+pub mod pick;
+pub mod binary;
+pub mod record;
+pub mod archive;
 
-pub use filesystem::*;
-pub use content::*;
-pub use files::*;
-pub use pretty::*;
-pub use misc::*;
-pub use secret::*;
\ No newline at end of file
+/// Add every builtin module's commands
+pub(super) fn register(registry: &mut super::Registry) {
+    filesystem::register(registry);
+    content::register(registry);
+    man::register(registry);
+    files::register(registry);
+    pretty::register(registry);
+    misc::register(registry);
+    system::register(registry);
+    games::register(registry);
+    secret::register(registry);
+    text::register(registry);
+    comments::register(registry);
+    pick::register(registry);
+    binary::register(registry);
+    record::register(registry);
+    archive::register(registry);
+}
@@ -0,0 +1,78 @@
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::filesystem::{CURRENT_DIR, DirPath, FilePath, NextDir};
+use crate::filesystem::helpers::{dir_exists, get_contents, get_directories, path_in_abyss};
+use crate::js_interop::add_output;
+
+/// Collect file paths under `dir`. Regular directories are walked recursively;
+/// abyss directories only list their own files, since every level is a fetch.
+async fn collect_files(dir: &DirPath) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.clone()];
+
+    while let Some(current) = pending.pop() {
+        for filename in get_contents(&current).await.0.keys() {
+            files.push(FilePath::new(current.clone(), filename.clone()).to_string());
+        }
+        if !path_in_abyss(&current) {
+            for subdir in get_directories(&current).await.0 {
+                let mut path = current.clone();
+                path.cd(&NextDir::In(subdir), true);
+                if !path_in_abyss(&path) {
+                    pending.push(path);
+                }
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Show a numbered list of files and wait for the user to choose one.
+/// `then` holds the command text around a `$(pick ...)` substitution; the chosen
+/// path is placed between the two halves and the resulting command is run.
+pub async fn start_pick(dir_arg: Option<&str>, then: Option<(String, String)>) -> String {
+    let dir = match dir_arg {
+        Some(arg) => CURRENT_DIR.with(|cd| DirPath::parse(arg, &cd.borrow())),
+        None => CURRENT_DIR.with(|cd| cd.borrow().clone()),
+    };
+
+    if !dir_exists(&dir).await {
+        return format!("pick: {}: No such directory", dir_arg.unwrap_or("."));
+    }
+
+    let entries = collect_files(&dir).await;
+    if entries.is_empty() {
+        return format!("pick: {}: No files to pick from", dir.to_string());
+    }
+
+    for (idx, entry) in entries.iter().enumerate() {
+        add_output(&format!("{:>3}) {}", idx + 1, entry));
+    }
+    add_output("Choose a file by number (or use the arrow keys and press Enter), q to cancel:");
+
+    crate::set_next_input_handler(crate::NextInputHandler::Pick {
+        entries,
+        selected: None,
+        then,
+    });
+
+    String::new()  // Prompt already displayed
+}
+
+pub struct Pick;
+impl CommandData for Pick {
+    fn name(&self) -> &str { "pick" }
+    fn description(&self) -> &str { "Choose a file from a numbered list (use as $(pick DIR) in commands)" }
+    fn usage(&self) -> &str { "pick [directory]" }
+    fn category(&self) -> Option<Category> { Some(Category::Navigation) }
+}
+impl Command for Pick {
+    async fn execute(&self, args: &[&str]) -> String {
+        start_pick(args.first().copied(), None).await
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Pick);
+}
@@ -1,6 +1,11 @@
-use crate::commands::{Command, CommandData};
+use crate::commands::{Category, Command, CommandData, Registry};
 use crate::filesystem::{CURRENT_DIR, FilePath};
-use crate::js_interop::add_output;
+use crate::filesystem::events::{emit, FsEvent};
+use crate::filesystem::helpers::get_file_bytes;
+use crate::filesystem::sniff::{sniff, FileType};
+use crate::js_interop::add_output_rich;
+use crate::filesystem::front_matter::{self, FrontMatter};
+use crate::markdown;
 
 // Helper to open pretty page in new tab
 pub fn open_pretty_page(file_path: &str, path_arg: &str) -> String {
@@ -19,11 +24,14 @@ pub fn open_pretty_page(file_path: &str, path_arg: &str) -> String {
 pub struct Pretty;
 impl CommandData for Pretty {
     fn name(&self) -> &str { "pretty" }
+    fn description(&self) -> &str { "Render markdown/html with LaTeX support in new tab" }
+    fn usage(&self) -> &str { "pretty <filename>" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
 }
 impl Command for Pretty {
     async fn execute(&self, args: &[&str]) -> String {
         if args.is_empty() {
-            return "Usage: pretty <filename>".to_string();
+            return self.usage_error();
         }
 
         let path_arg = args[0];
@@ -34,25 +42,190 @@ impl Command for Pretty {
             return format!("pretty: {}: No such file", path_arg);
         }
 
-        // Check if it's a .md file
-        let is_markdown = filepath.file.ends_with(".md");
-        let is_html = filepath.file.ends_with(".html");
+        // Render markdown and html straight away; ask about anything else
+        let file_type = match get_file_bytes(&filepath).await {
+            Ok(bytes) => sniff(&filepath.file, &bytes),
+            Err(_) => return format!("pretty: {}: No such file", path_arg),
+        };
 
-        if is_markdown || is_html {
+        // Structured data is laid out here rather than rendered as a page
+        if matches!(file_type, FileType::Json | FileType::Toml) {
+            return pp_file(&filepath, path_arg, "pretty").await;
+        }
+
+        if file_type.is_renderable() {
             // Open directly
+            emit(FsEvent::FileRead(filepath.clone()));
             open_pretty_page(&filepath.to_string(), path_arg)
         } else {
-            // Ask for confirmation - set handler for next input
-            add_output(&format!("Warning: '{}' is not a markdown or html file. Render anyway? (y/n)", path_arg));
-
-            crate::NEXT_INPUT_HANDLER.with(|h| {
-                *h.borrow_mut() = crate::NextInputHandler::PrettyConfirm {
-                    filepath: filepath.to_string(),
-                    path_arg: path_arg.to_string(),
-                };
-            });
+            // Ask for confirmation - handled on the next input
+            let path_arg = path_arg.to_string();
+            crate::prompt::confirm(
+                &format!("Warning: '{}' is not a markdown or html file ({}). Render anyway?", path_arg, file_type.description()),
+                move || async move { open_pretty_page(&filepath.to_string(), &path_arg) },
+            );
 
             String::new()  // No additional output, prompt already displayed
         }
     }
 }
+
+// A post's title, then its date and tags, as the top of rendered markdown
+fn front_matter_header(front_matter: &FrontMatter) -> Vec<markdown::Line> {
+    let span = |text: String, class: &str| markdown::Span { text, class: class.to_string(), href: None };
+    let mut lines = Vec::new();
+    if let Some(title) = &front_matter.title {
+        lines.push(vec![span(title.clone(), "md-h1")]);
+    }
+    let details: Vec<String> = front_matter.date.iter().cloned()
+        .chain((!front_matter.tags.is_empty()).then(|| format!("tags: {}", front_matter.tags.join(", "))))
+        .collect();
+    if !details.is_empty() {
+        lines.push(vec![span(details.join(" · "), "md-meta")]);
+    }
+    if !lines.is_empty() {
+        lines.push(Vec::new());
+    }
+    lines
+}
+
+pub struct Render;
+impl CommandData for Render {
+    fn name(&self) -> &str { "render" }
+    fn description(&self) -> &str { "Show a markdown file formatted in the terminal" }
+    fn usage(&self) -> &str { "render <file.md>" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+impl Command for Render {
+    async fn execute(&self, args: &[&str]) -> String {
+        let [path_arg] = args else {
+            return self.usage_error();
+        };
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+
+        let bytes = match get_file_bytes(&filepath).await {
+            Ok(bytes) => bytes,
+            Err(_) => return format!("render: {}: No such file", path_arg),
+        };
+        let file_type = sniff(&filepath.file, &bytes);
+        if file_type != FileType::Markdown {
+            return format!("render: {}: Not a markdown file ({}; try cat or pretty)", path_arg, file_type.description());
+        }
+        // Markdown is always text (sniff says Binary otherwise)
+        let text = String::from_utf8_lossy(&bytes);
+
+        emit(FsEvent::FileRead(filepath));
+        let (front_matter, body) = front_matter::split(&text);
+        let mut lines = front_matter.map(|front_matter| front_matter_header(&front_matter)).unwrap_or_default();
+        lines.extend(markdown::render(body));
+        for line in lines {
+            let segments: Vec<_> = line.iter()
+                .map(|span| (span.text.as_str(), span.class.as_str(), span.href.as_deref()))
+                .collect();
+            add_output_rich(&segments);
+        }
+        String::new()  // Already output with styling
+    }
+}
+
+/// JSON or TOML re-serialized with indentation, keys sorted
+pub fn reformat(file_type: FileType, text: &str) -> Result<String, String> {
+    match file_type {
+        FileType::Json => {
+            let value: serde_json::Value = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+            serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+        }
+        FileType::Toml => {
+            let table: toml::Table = text.parse().map_err(|e: toml::de::Error| {
+                let line = e.span().map(|span| text[..span.start].lines().count().max(1));
+                match line {
+                    Some(line) => format!("Invalid TOML at line {}: {}", line, e.message().replace('\n', "; ")),
+                    None => format!("Invalid TOML: {}", e.message().replace('\n', "; ")),
+                }
+            })?;
+            toml::to_string_pretty(&table).map(|text| text.trim_end().to_string()).map_err(|e| e.to_string())
+        }
+        other => Err(format!("Not JSON or TOML ({})", other.description())),
+    }
+}
+
+// The file's text laid out by `reformat`; `command` is the command asking, for errors
+async fn pp_file(filepath: &FilePath, path_arg: &str, command: &str) -> String {
+    let bytes = match get_file_bytes(filepath).await {
+        Ok(bytes) => bytes,
+        Err(_) => return format!("{}: {}: No such file", command, path_arg),
+    };
+    // The extension decides, so a broken .json file gets JSON's error rather than being called text
+    let extension = filepath.file.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    let file_type = match extension.as_str() {
+        "json" => FileType::Json,
+        "toml" => FileType::Toml,
+        _ => sniff(&filepath.file, &bytes),
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    match reformat(file_type, &text) {
+        Ok(laid_out) => {
+            emit(FsEvent::FileRead(filepath.clone()));
+            laid_out
+        }
+        Err(e) => format!("{}: {}: {}", command, path_arg, e),
+    }
+}
+
+pub struct Pp;
+impl CommandData for Pp {
+    fn name(&self) -> &str { "pp" }
+    fn description(&self) -> &str { "Show a JSON or TOML file indented, keys sorted" }
+    fn usage(&self) -> &str { "pp <file>" }
+    fn category(&self) -> Option<Category> { Some(Category::Content) }
+}
+impl Command for Pp {
+    async fn execute(&self, args: &[&str]) -> String {
+        let [path_arg] = args else {
+            return self.usage_error();
+        };
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+        pp_file(&filepath, path_arg, "pp").await
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Pretty);
+    registry.add(Render);
+    registry.add(Pp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_reformat_sorts_and_indents() {
+        assert_eq!(
+            reformat(FileType::Json, r#"{"b": [1, 2], "a": {"d": null, "c": true}}"#).unwrap(),
+            "{\n  \"a\": {\n    \"c\": true,\n    \"d\": null\n  },\n  \"b\": [\n    1,\n    2\n  ]\n}"
+        );
+        assert_eq!(
+            reformat(FileType::Toml, "zeta = 1\n[features]\nb = true\na = false\n").unwrap(),
+            "zeta = 1\n\n[features]\na = false\nb = true"
+        );
+    }
+
+    #[test]
+    fn test_reformat_errors() {
+        assert!(reformat(FileType::Toml, "a = 1\nb = \n").unwrap_err().starts_with("Invalid TOML at line 2: "));
+        assert!(reformat(FileType::Json, "{").unwrap_err().starts_with("Invalid JSON: EOF while parsing an object at line 1"));
+        assert_eq!(reformat(FileType::Text, "hi").unwrap_err(), "Not JSON or TOML (text)");
+    }
+
+    #[test]
+    fn test_render_markdown_in_the_terminal() {
+        enable(1);
+        crate::write_file("post.md", "# Hello\n\nSome **bold** text and [a link](https://example.com).\n\n- one\n- two".to_string()).unwrap();
+        assert_eq!(run("render post.md"), ["Hello", "", "Some bold text and a link.", "", "  • one", "  • two"]);
+        assert_eq!(run("render about.txt"), ["render: about.txt: Not a markdown file (text; try cat or pretty)"]);
+        assert_eq!(run("render missing.md"), ["render: missing.md: No such file"]);
+        assert_eq!(run("render"), ["Usage: render <file.md>"]);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::filesystem::{CURRENT_DIR, FilePath};
+use crate::filesystem::helpers::{get_file_content, write_file};
+use crate::js_interop::page_url;
+use crate::recording;
+
+const DEFAULT_RECORDING: &str = "session.rec";
+
+pub struct Record;
+impl CommandData for Record {
+    fn name(&self) -> &str { "record" }
+    fn description(&self) -> &str { "Record the commands you type (saved on stop, by default to session.rec)" }
+    fn usage(&self) -> &str { "record start\nrecord stop [file]" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
+}
+impl Command for Record {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            ["start"] => {
+                if recording::start() {
+                    "Recording started. Type 'record stop [FILE]' to save it.".to_string()
+                } else {
+                    "record: Already recording".to_string()
+                }
+            }
+            ["stop"] => stop_recording(DEFAULT_RECORDING).await,
+            ["stop", path_arg] => stop_recording(path_arg).await,
+            _ => self.usage_error(),
+        }
+    }
+}
+
+async fn stop_recording(path_arg: &str) -> String {
+    let Some(steps) = recording::stop() else {
+        return "record: Not recording (start with 'record start')".to_string();
+    };
+
+    let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+    match write_file(&filepath, recording::to_file(&steps)).await {
+        Ok(_) => format!("Recorded {} command(s) to {}. Share it with 'cast {}'.", steps.len(), path_arg, path_arg),
+        Err(e) => format!("record: {}: {}", path_arg, e),
+    }
+}
+
+pub struct Cast;
+impl CommandData for Cast {
+    fn name(&self) -> &str { "cast" }
+    fn description(&self) -> &str { "Save a recording as a cast and print a link that replays it" }
+    fn usage(&self) -> &str { "cast <recording>" }
+    fn category(&self) -> Option<Category> { Some(Category::Session) }
+}
+impl Command for Cast {
+    async fn execute(&self, args: &[&str]) -> String {
+        let [path_arg] = args else {
+            return self.usage_error();
+        };
+
+        let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+        let steps = match get_file_content(&filepath).await {
+            Ok(text) => match recording::from_file(&text) {
+                Ok(steps) => steps,
+                Err(e) => return format!("cast: {}: {}", path_arg, e),
+            },
+            Err(_) => return format!("cast: {}: No such file", path_arg),
+        };
+
+        let cast = recording::encode_cast(&steps);
+        let stem = filepath.file.strip_suffix(".rec").unwrap_or(&filepath.file);
+        let cast_path = FilePath::new(filepath.dir.clone(), format!("{}.cast", stem));
+        if let Err(e) = write_file(&cast_path, cast.clone()).await {
+            return format!("cast: {}: {}", cast_path.to_string(), e);
+        }
+
+        format!("Cast written to {}\nReplay link: {}?cast={}", cast_path.to_string(), page_url(), cast)
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Record);
+    registry.add(Cast);
+}
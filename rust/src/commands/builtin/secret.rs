@@ -1,20 +1,21 @@
 use std::io::{Cursor, Read};
 
-use crate::{commands::{Command, CommandData}, filesystem::{AbyssFileSystem, Content, Contents, DirPath, Directories, FilePath, NextDir, VIRTUAL_FS}};
+use crate::js_interop::add_output;
+use crate::prompt::{self, Prompt};
+use crate::{commands::{Command, CommandData, Registry}, filesystem::{AbyssFileSystem, Content, Contents, DirPath, Directories, FileData, FilePath, NextDir, OWNER_MODE, VIRTUAL_FS}};
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{Request, RequestInit, RequestMode, Response};
 use zip::ZipArchive;
 
-const EMPTY_SECRET: &str = 
-r#"Usage: secret <password>
-
-You found my secret hideout, good luck getting in though."#;
+const EMPTY_SECRET: &str = "You found my secret hideout, good luck getting in though.";
 
 const PASSWORD_CORRECT_MESSAGE: &str = r#"Oh no!
 You found my password and now you'll be able to see my secret lair at root!
 
-Good thing I anticipated this and deleted all the things I wouldn't want you to see..."#;
+Good thing I anticipated this and deleted all the things I wouldn't want you to see...
+
+(Owner mode enabled: drafts and scheduled posts are now visible.)"#;
 
 pub struct Secret;
 impl CommandData for Secret {
@@ -23,37 +24,50 @@ impl CommandData for Secret {
 impl Command for Secret {
     async fn execute(&self, args: &[&str]) -> String {
         if args.is_empty() {
-            EMPTY_SECRET.to_string()
+            // Asked for separately, so the password isn't shown or kept in the history
+            add_output(EMPTY_SECRET);
+            prompt::ask(Prompt::new("Password:", |password| async move {
+                if password.is_empty() {
+                    return "Cancelled.".to_string();
+                }
+                try_password(&password).await
+            }).masked());
+            String::new()
         } else {
-            let password = args.join("");
-            let zip_bytes = match fetch_secret_zip().await {
-                Ok(x) => x,
-                Err(_) => return "I failed to fetch the password checker, so I guess my secrets are safe forever!".to_string(),
-            };
-            let zip_cursor = Cursor::new(&zip_bytes);
-            let mut zip_file = ZipArchive::new(zip_cursor).unwrap();
-            match get_zip_contents(&mut zip_file, password.as_bytes()) {
-                Ok(afs) => {
-                    let secret_lair_base = DirPath(vec![NextDir::In("secret_lair".to_string())]);
-                    // Add extracted content to /secret_lair/
-                    VIRTUAL_FS.with_borrow_mut(|vfs| {
-                        for (dir_path, contents) in afs.files {
-                            // Prepend /secret_lair/ to the path
-                            let secret_lair_path = secret_lair_base.concat(&dir_path,true);
-
-                            // Insert the files into the virtual filesystem
-                            vfs.content.insert(secret_lair_path, contents.0);
-                        }
-                    });
-
-                    PASSWORD_CORRECT_MESSAGE.to_string()
-                },
-                Err(_) => "You will never find my true secrets!".to_string(),
-            }
+            try_password(&args.join("")).await
         }
     }
 }
 
+async fn try_password(password: &str) -> String {
+    let zip_bytes = match fetch_secret_zip().await {
+        Ok(x) => x,
+        Err(_) => return "I failed to fetch the password checker, so I guess my secrets are safe forever!".to_string(),
+    };
+    let zip_cursor = Cursor::new(&zip_bytes);
+    let mut zip_file = ZipArchive::new(zip_cursor).unwrap();
+    match get_zip_contents(&mut zip_file, password.as_bytes()) {
+        Ok(afs) => {
+            let secret_lair_base = DirPath(vec![NextDir::In("secret_lair".to_string())]);
+            // Add extracted content to /secret_lair/
+            VIRTUAL_FS.with_borrow_mut(|vfs| {
+                for (dir_path, contents) in afs.files {
+                    // Prepend /secret_lair/ to the path
+                    let secret_lair_path = secret_lair_base.concat(&dir_path,true);
+
+                    // Insert the files into the virtual filesystem
+                    vfs.content.insert(secret_lair_path, contents.0);
+                }
+            });
+
+            OWNER_MODE.with_borrow_mut(|x| *x = true);
+
+            PASSWORD_CORRECT_MESSAGE.to_string()
+        },
+        Err(_) => "You will never find my true secrets!".to_string(),
+    }
+}
+
 struct PasswordError;
 
 /// gets zip contents or returns PasswordError.
@@ -103,7 +117,7 @@ fn get_zip_contents(zip_file: &mut ZipArchive<Cursor<&Vec<u8>>>, password: &[u8]
     }
     for (path, content) in files {
         let contents = out_fs.files.get_mut(&path.dir).expect("malformed zip");
-        contents.0.insert(path.file,Content::InMemory(content));
+        contents.0.insert(path.file,Content::InMemory(FileData::new(content)));
     }
 
     Ok(out_fs)
@@ -142,4 +156,8 @@ async fn fetch_secret_zip() -> Result<Vec<u8>,()> {
     let bytes = uint8_array.to_vec();
 
     Ok(bytes)
-}
\ No newline at end of file
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Secret);
+}
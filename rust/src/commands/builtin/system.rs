@@ -0,0 +1,245 @@
+use std::cell::Cell;
+use crate::commands::{COMMANDS, Category, Command, CommandData, Registry};
+use crate::filesystem::VIRTUAL_FS;
+use crate::filesystem::fetch_cache;
+use crate::filesystem::quota::format_size;
+use crate::js_interop::{now, page_host, user_agent, wasm_memory_bytes};
+use crate::theme;
+use super::files::in_memory_totals;
+
+// Everyone browsing the site is the same user
+const USER: &str = "visitor";
+const KERNEL: &str = "WasmOS";
+const MACHINE: &str = "wasm32";
+
+const LOGO: &[&str] = &[
+    " .---------------.",
+    " | >_            |",
+    " |               |",
+    " |    WasmOS     |",
+    " |               |",
+    " '---------------'",
+    "     __|___|__",
+];
+
+thread_local! {
+    static STARTED: Cell<Option<f64>> = const { Cell::new(None) };
+}
+
+/// Start the uptime clock (when the terminal loads)
+pub fn mark_started() {
+    STARTED.set(Some(now()));
+}
+
+pub struct Whoami;
+impl CommandData for Whoami {
+    fn name(&self) -> &str { "whoami" }
+    fn description(&self) -> &str { "Show who you are logged in as" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Whoami {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => USER.to_string(),
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub struct Hostname;
+impl CommandData for Hostname {
+    fn name(&self) -> &str { "hostname" }
+    fn description(&self) -> &str { "Show the name of the machine (the site's host)" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Hostname {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            [] => page_host(),
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub struct Uname;
+impl CommandData for Uname {
+    fn name(&self) -> &str { "uname" }
+    fn description(&self) -> &str { "Show system information (-a for all of it)" }
+    fn usage(&self) -> &str { "uname [-asnrvmo]" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+}
+impl Command for Uname {
+    async fn execute(&self, args: &[&str]) -> String {
+        let mut wanted = String::new();
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(letters) if !letters.is_empty() && letters.chars().all(|c| "asnrvmo".contains(c)) => wanted.push_str(letters),
+                _ => return self.usage_error(),
+            }
+        }
+        if wanted.is_empty() {
+            wanted.push('s');
+        }
+
+        let agent = user_agent().unwrap_or_default();
+        let fields = [
+            ('s', KERNEL.to_string()),
+            ('n', page_host()),
+            ('r', env!("CARGO_PKG_VERSION").to_string()),
+            ('v', format!("#1 {}", browser(&agent))),
+            ('m', MACHINE.to_string()),
+            ('o', operating_system(&agent).to_string()),
+        ];
+        fields.iter()
+            .filter(|(letter, _)| wanted.contains('a') || wanted.contains(*letter))
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub struct Neofetch;
+impl CommandData for Neofetch {
+    fn name(&self) -> &str { "neofetch" }
+    fn description(&self) -> &str { "Show the terminal's logo and system information" }
+    fn category(&self) -> Option<Category> { Some(Category::Other) }
+    fn aliases(&self) -> &[&str] { &["sysinfo"] }
+}
+impl Command for Neofetch {
+    async fn execute(&self, args: &[&str]) -> String {
+        if !args.is_empty() {
+            return self.usage_error();
+        }
+        let agent = user_agent().unwrap_or_default();
+        let title = format!("{}@{}", USER, page_host());
+        let files = VIRTUAL_FS.with_borrow(|vfs| vfs.content.values().map(|files| files.len()).sum::<usize>());
+        let (in_memory, in_memory_bytes) = in_memory_totals();
+        let (cached, cached_bytes) = fetch_cache::usage();
+        let mut info = vec![
+            title.clone(),
+            "-".repeat(title.chars().count()),
+            format!("OS: {} {} {}", KERNEL, env!("CARGO_PKG_VERSION"), MACHINE),
+            format!("Host: {}", page_host()),
+            format!("Browser: {}", browser(&agent)),
+            format!("Platform: {}", operating_system(&agent)),
+            format!("Uptime: {}", uptime(now() - STARTED.get().unwrap_or_else(now))),
+            format!("Commands: {}", COMMANDS.iter().filter(|command| command.category().is_some()).count()),
+            format!("Files: {} ({} yours, {})", files, in_memory, format_size(in_memory_bytes)),
+            format!("Cache: {} files, {} of {}", cached, format_size(cached_bytes), format_size(fetch_cache::limit())),
+        ];
+        if let Some(bytes) = wasm_memory_bytes() {
+            info.push(format!("Memory: {}", format_size(bytes)));
+        }
+        info.push(format!("Theme: {}", theme::current().await));
+
+        let width = LOGO.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        (0..LOGO.len().max(info.len()))
+            .map(|i| {
+                let logo = LOGO.get(i).copied().unwrap_or("");
+                let text = info.get(i).map(String::as_str).unwrap_or("");
+                format!("{:<width$}   {}", logo, text).trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// "2 hours, 5 mins", or seconds under a minute
+fn uptime(ms: f64) -> String {
+    let secs = (ms / 1000.0).max(0.0) as u64;
+    if secs < 60 {
+        return format!("{} secs", secs);
+    }
+    let parts = [(secs / 86400, "day"), (secs / 3600 % 24, "hour"), (secs / 60 % 60, "min")];
+    parts.iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{} {}{}", n, unit, if *n == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The browser named in a user agent string, with its major version, e.g. "Firefox 131"
+fn browser(agent: &str) -> String {
+    // Checked in order: Edge and Opera also claim to be Chrome, and Chrome to be Safari
+    let known = [("Edg/", "Edge"), ("OPR/", "Opera"), ("Firefox/", "Firefox"), ("Chrome/", "Chrome"), ("Version/", "Safari")];
+    for (marker, name) in known {
+        if let Some((_, rest)) = agent.split_once(marker) {
+            let major = rest.split(['.', ' ']).next().unwrap_or_default();
+            return format!("{} {}", name, major);
+        }
+    }
+    "unknown browser".to_string()
+}
+
+/// The operating system named in a user agent string
+fn operating_system(agent: &str) -> &'static str {
+    // Android agents mention Linux, and iOS ones Mac OS X
+    let known = [("Android", "Android"), ("iPhone", "iOS"), ("iPad", "iOS"), ("CrOS", "ChromeOS"),
+                 ("Windows", "Windows"), ("Mac OS X", "macOS"), ("Linux", "Linux")];
+    known.iter()
+        .find(|(marker, _)| agent.contains(marker))
+        .map(|(_, name)| *name)
+        .unwrap_or("unknown")
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Whoami);
+    registry.add(Hostname);
+    registry.add(Uname);
+    registry.add(Neofetch);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_user_agents() {
+        let agents = [
+            ("Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0", "Firefox 131", "Linux"),
+            ("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36 Edg/129.0.0.0", "Edge 129", "Windows"),
+            ("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Mobile Safari/537.36", "Chrome 129", "Android"),
+            ("Mozilla/5.0 (iPhone; CPU iPhone OS 17_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.6 Mobile/15E148 Safari/604.1", "Safari 17", "iOS"),
+            ("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.0 Safari/605.1.15", "Safari 18", "macOS"),
+            ("", "unknown browser", "unknown"),
+        ];
+        for (agent, expected_browser, expected_os) in agents {
+            assert_eq!(browser(agent), expected_browser);
+            assert_eq!(operating_system(agent), expected_os);
+        }
+    }
+
+    #[test]
+    fn test_uptime() {
+        assert_eq!(uptime(42_500.0), "42 secs");
+        assert_eq!(uptime(60_000.0), "1 min");
+        assert_eq!(uptime(((26 * 60 + 5) * 60) as f64 * 1000.0), "1 day, 2 hours, 5 mins");
+    }
+
+    #[test]
+    fn test_uname() {
+        enable(1);
+        assert_eq!(run("whoami"), ["visitor"]);
+        assert_eq!(run("hostname"), ["localhost"]);
+        assert_eq!(run("uname"), ["WasmOS"]);
+        // No browser outside of it
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(run("uname -a"), [format!("WasmOS localhost {} #1 unknown browser wasm32 unknown", version)]);
+        assert_eq!(run("uname -m -sn"), ["WasmOS localhost wasm32"]);
+        assert_eq!(run("uname -x"), ["Usage: uname [-asnrvmo]"]);
+    }
+
+    #[test]
+    fn test_neofetch() {
+        enable(1);
+        crate::write_file("notes.txt", "milk".to_string()).unwrap();
+        let lines = run("sysinfo");
+        assert_eq!(lines[0], " .---------------.   visitor@localhost");
+        assert_eq!(lines[1], " | >_            |   -----------------");
+        for expected in ["Browser: unknown browser", "Uptime: 0 secs", "Files: 21 (1 yours, 4 B)", "Theme: default"] {
+            assert!(lines.iter().any(|line| line.ends_with(expected)), "{}", expected);
+        }
+        assert_eq!(run("neofetch -v"), ["Usage: neofetch"]);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::filesystem::{CURRENT_DIR, FilePath};
+use crate::filesystem::helpers::{fetch_text, get_file_bytes, get_file_content};
+use crate::textdiff;
+
+/// Line, word and byte counts for a piece of text
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct Counts {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+}
+
+impl Counts {
+    /// Counts for a file's bytes: lines and words of its text (invalid UTF-8
+    /// aside), and the bytes as stored
+    pub fn of(bytes: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(bytes);
+        Counts {
+            lines: text.matches('\n').count(),
+            words: text.split_whitespace().count(),
+            bytes: bytes.len(),
+        }
+    }
+
+    fn accumulate(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+    }
+}
+
+pub struct Wc;
+impl CommandData for Wc {
+    fn name(&self) -> &str { "wc" }
+    fn description(&self) -> &str { "Count lines, words and bytes in files" }
+    fn usage(&self) -> &str { "wc [-l] [-w] [-c] <file>..." }
+    fn category(&self) -> Option<Category> { Some(Category::Text) }
+}
+impl Command for Wc {
+    async fn execute(&self, args: &[&str]) -> String {
+        let mut show_lines = false;
+        let mut show_words = false;
+        let mut show_bytes = false;
+        let mut paths = Vec::new();
+
+        for &arg in args {
+            if arg.starts_with('-') && arg.len() > 1 {
+                for flag in arg[1..].chars() {
+                    match flag {
+                        'l' => show_lines = true,
+                        'w' => show_words = true,
+                        'c' => show_bytes = true,
+                        _ => return format!("wc: invalid option -- '{}'\n{}", flag, self.usage_error()),
+                    }
+                }
+            } else {
+                paths.push(arg);
+            }
+        }
+
+        if paths.is_empty() {
+            return self.usage_error();
+        }
+
+        // No flags means show everything
+        if !show_lines && !show_words && !show_bytes {
+            show_lines = true;
+            show_words = true;
+            show_bytes = true;
+        }
+
+        let mut rows: Vec<(Option<Counts>, String)> = Vec::new();
+        let mut total = Counts::default();
+
+        for path_arg in &paths {
+            let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+            match get_file_bytes(&filepath).await {
+                Ok(bytes) => {
+                    let counts = Counts::of(&bytes);
+                    total.accumulate(&counts);
+                    rows.push((Some(counts), path_arg.to_string()));
+                }
+                Err(_) => rows.push((None, format!("wc: {}: No such file", path_arg))),
+            }
+        }
+
+        if paths.len() > 1 {
+            rows.push((Some(total), "total".to_string()));
+        }
+
+        // Right-align every column to the widest number
+        let width = total.lines.max(total.words).max(total.bytes).to_string().len();
+
+        rows.into_iter()
+            .map(|(counts, label)| match counts {
+                Some(counts) => {
+                    let mut line = String::new();
+                    if show_lines { line.push_str(&format!("{:>width$} ", counts.lines)); }
+                    if show_words { line.push_str(&format!("{:>width$} ", counts.words)); }
+                    if show_bytes { line.push_str(&format!("{:>width$} ", counts.bytes)); }
+                    line.push_str(&label);
+                    line
+                }
+                None => label,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub struct Diff;
+impl CommandData for Diff {
+    fn name(&self) -> &str { "diff" }
+    fn description(&self) -> &str { "Show differences between two files, or an edited file and the original" }
+    fn usage(&self) -> &str { "diff <file1> <file2>\ndiff <file>" }
+    fn category(&self) -> Option<Category> { Some(Category::Text) }
+}
+impl Command for Diff {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            // Compare an edited file against the original on the server
+            [path_arg] => {
+                let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+                let edited = match get_file_content(&filepath).await {
+                    Ok(content) => content,
+                    Err(_) => return format!("diff: {}: No such file", path_arg),
+                };
+                let original = match fetch_text(&filepath.to_url()).await {
+                    Ok(content) => content,
+                    Err(_) => return format!("diff: {}: No original version to compare against", path_arg),
+                };
+
+                let out = textdiff::unified(
+                    &format!("{} (original)", filepath.to_string()),
+                    &filepath.to_string(),
+                    &original,
+                    &edited,
+                    3,
+                );
+                if out.is_empty() { "No differences from the original.".to_string() } else { out }
+            }
+            [a_arg, b_arg] => {
+                let mut texts = Vec::new();
+                for path_arg in [a_arg, b_arg] {
+                    let filepath = CURRENT_DIR.with(|cd| FilePath::parse(path_arg, &cd.borrow()));
+                    match get_file_content(&filepath).await {
+                        Ok(content) => texts.push(content),
+                        Err(_) => return format!("diff: {}: No such file", path_arg),
+                    }
+                }
+
+                textdiff::unified(a_arg, b_arg, &texts[0], &texts[1], 3)
+            }
+            _ => self.usage_error(),
+        }
+    }
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Wc);
+    registry.add(Diff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::DirPath;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_counts() {
+        let counts = Counts::of(b"hello world\nsecond line here\n");
+        assert_eq!(counts, Counts { lines: 2, words: 5, bytes: 29 });
+    }
+
+    #[test]
+    fn test_counts_no_trailing_newline() {
+        let counts = Counts::of(b"one two");
+        assert_eq!(counts, Counts { lines: 0, words: 2, bytes: 7 });
+    }
+
+    #[test]
+    fn test_wc_counts_the_bytes_stored() {
+        enable(1);
+        crate::filesystem::VIRTUAL_FS.with_borrow_mut(|vfs|
+            vfs.write_file(&FilePath::parse("/blob.bin", &DirPath::root()), b"\xff\xfe one\n\x80".to_vec())
+        );
+        assert_eq!(run("wc blob.bin"), ["1 3 8 blob.bin"]);
+        assert_eq!(run("wc -c blob.bin"), ["8 blob.bin"]);
+    }
+}
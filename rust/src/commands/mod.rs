@@ -1,15 +1,74 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
 use wasm_bindgen::prelude::*;
 use crate::filesystem::file_paths::{SIMPLE_MANUAL_DIR_PATH, VERBOSE_MANUAL_DIR_PATH};
-use crate::filesystem::{DirPath, FilePath, VIRTUAL_FS};
+use crate::filesystem::{Content, DirPath, FileData, FilePath, VIRTUAL_FS};
+use crate::filesystem::events::{emit, FsEvent};
+use crate::filesystem::quota::check_write;
 
 // Command implementations organized by type
 pub mod builtin;
-use builtin::*;
+pub mod rpg;
+
+pub mod batch;
+use batch::Batch;
+
+
+/// The sections of the help listing, in the order they're shown
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Navigation,
+    Content,
+    Files,
+    Text,
+    Session,
+    Games,
+    ByClaude,
+    Other,
+}
 
+impl Category {
+    pub const ALL: [Category; 8] = [
+        Category::Navigation, Category::Content, Category::Files, Category::Text,
+        Category::Session, Category::Games, Category::ByClaude, Category::Other,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Category::Navigation => "Navigation",
+            Category::Content => "Content",
+            Category::Files => "File Operations",
+            Category::Text => "Text Tools",
+            Category::Session => "Session",
+            Category::Games => "Games",
+            Category::ByClaude => "By Claude",
+            Category::Other => "Other",
+        }
+    }
+}
 
 pub trait CommandData {
     fn name(&self) -> &str;
 
+    /// One line for the help listing
+    fn description(&self) -> &str { "" }
+
+    /// How to call it, one form per line, e.g. "cat [-n] <file>..."
+    fn usage(&self) -> &str { self.name() }
+
+    /// Where it's listed in help; None keeps it out of the listing
+    fn category(&self) -> Option<Category> { None }
+
+    /// Other names it answers to
+    fn aliases(&self) -> &[&str] { &[] }
+
+    /// The message for arguments the command can't make sense of
+    fn usage_error(&self) -> String {
+        format!("Usage: {}", self.usage().lines().collect::<Vec<_>>().join("\n       "))
+    }
+
     fn manual(&self, verbose: bool) -> FilePath {
         FilePath::new(
             (*if verbose { &VERBOSE_MANUAL_DIR_PATH } else { &SIMPLE_MANUAL_DIR_PATH }).clone(),
@@ -23,8 +82,67 @@ pub trait Command : CommandData {
     async fn execute(&self, args: &[&str]) -> String;
 }
 
+type Running<'a> = Pin<Box<dyn Future<Output = String> + 'a>>;
+
+/// `Command` as a trait object, so commands can be kept in the registry.
+/// Every `Command` is one; there's nothing to implement.
+pub trait DynCommand : CommandData + Send + Sync {
+    fn run<'a>(&'a self, args: &'a [&'a str]) -> Running<'a>;
+}
+
+impl<C: Command + Send + Sync> DynCommand for C {
+    fn run<'a>(&'a self, args: &'a [&'a str]) -> Running<'a> {
+        Box::pin(self.execute(args))
+    }
+}
+
+/// The builtin commands by name, kept in the order they were added
+#[derive(Default)]
+pub struct Registry {
+    commands: Vec<Box<dyn DynCommand>>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Registry {
+    pub fn add(&mut self, command: impl DynCommand + 'static) {
+        for name in std::iter::once(command.name()).chain(command.aliases().iter().copied()) {
+            self.by_name.insert(name.to_string(), self.commands.len());
+        }
+        self.commands.push(Box::new(command));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn DynCommand> {
+        self.by_name.get(name).map(|&i| self.commands[i].as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn DynCommand> {
+        self.commands.iter().map(|command| command.as_ref())
+    }
+}
+
+/// Every builtin. To add a command, implement `Command` and add it in its
+/// module's `register`.
+pub static COMMANDS: LazyLock<Registry> = LazyLock::new(|| {
+    let mut registry = Registry::default();
+    builtin::register(&mut registry);
+    rpg::register(&mut registry);
+    registry
+});
+
+/// Split off the global `--json` flag. Commands that support it print structured
+/// JSON instead of formatted text, so scripts can consume the result.
+pub fn take_json_flag<'a>(args: &[&'a str]) -> (bool, Vec<&'a str>) {
+    let json = args.contains(&"--json");
+    (json, args.iter().copied().filter(|arg| *arg != "--json").collect())
+}
+
+/// Output for `--json` (errors stay as plain text messages)
+pub fn json_output(value: serde_json::Value) -> String {
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
 // User-defined command (for future .kh file system)
-pub struct UserDefined(String);
+pub struct UserDefined(pub String);
 
 impl CommandData for UserDefined {
     fn name(&self) -> &str {
@@ -34,6 +152,12 @@ impl CommandData for UserDefined {
 
 // Export session helper (used by save-session command)
 pub fn export_session() -> String {
+    export_subtree(&DirPath::root())
+}
+
+// Export just the files under `under` (save-session <dir>). Paths stay absolute;
+// the shell's state is only included for the whole filesystem.
+pub fn export_subtree(under: &DirPath) -> String {
     use serde_json::json;
 
     VIRTUAL_FS.with(|vfs| {
@@ -41,9 +165,9 @@ pub fn export_session() -> String {
         let mut files = serde_json::Map::new();
 
         // Collect all InMemory files
-        for (dirpath, dir_contents) in &vfs_ref.content {
+        for (dirpath, dir_contents) in vfs_ref.content.iter().filter(|(dirpath, _)| dirpath.is_within(under)) {
             for (filename, content) in dir_contents {
-                if let crate::filesystem::Content::InMemory(file_content) = content {
+                if let crate::filesystem::Content::InMemory(file_data) = content {
                     let mut path_parts = Vec::new();
                     for component in &dirpath.0 {
                         match component {
@@ -58,63 +182,274 @@ pub fn export_session() -> String {
                         format!("/{}/{}", path_parts.join("/"), filename)
                     };
 
-                    files.insert(full_path, json!(file_content));
+                    let mut entry = json!({
+                        "created": file_data.meta.created,
+                        "modified": file_data.meta.modified,
+                    });
+                    // Binary files are stored as base64 (added in 1.2)
+                    match file_data.text() {
+                        Some(text) => entry["content"] = json!(text),
+                        None => entry["base64"] = json!(crate::base64::encode(&file_data.bytes)),
+                    }
+                    files.insert(full_path, entry);
                 }
             }
         }
 
-        json!({
-            "version": "1.0",
-            "files": files
-        }).to_string()
+        // Edits in the abyss travel as a delta over what the server serves (added in 1.3),
+        // and the shell's own state lets a session resume where it left off (1.4).
+        // The cave of dice goes whole, with the rooms visited, so it comes back as it was (1.5)
+        let mut session = json!({
+            "version": "1.5",
+            "files": files,
+            "abyss": crate::filesystem::abyss_delta::export(crate::reload::content_to_json, under),
+        });
+        let cave = crate::filesystem::cave_of_dice::export(crate::reload::content_to_json, under);
+        if !cave.is_null() {
+            session["cave_of_dice"] = cave;
+        }
+        if under.0.is_empty() {
+            session["shell"] = json!({
+                "cwd": crate::filesystem::CURRENT_DIR.with_borrow(|cd| cd.to_string()),
+                "history": crate::input_history::INPUT_HISTORY.with_borrow(|history| history.inputs().to_vec()),
+            });
+        }
+        session.to_string()
     })
 }
 
+/// Session format v2: the session JSON, gzipped, since binary files and
+/// abyss edits make sessions large
+pub fn compress_session(session_json: &str) -> Vec<u8> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    // Writing to a Vec can't fail
+    encoder.write_all(session_json.as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// A session file's JSON, whether it was saved gzipped (v2) or as plain JSON (v1)
+pub fn session_from_bytes(bytes: Vec<u8>) -> Result<String, String> {
+    use std::io::Read;
+    // The gzip magic number
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut session_json = String::new();
+        return flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut session_json)
+            .map(|_| session_json)
+            .map_err(|e| format!("Error: Failed to decompress session file: {}", e));
+    }
+    String::from_utf8(bytes).map_err(|_| "Error: File is not valid UTF-8 text".to_string())
+}
+
+// A session file entry's content: a plain string (1.0), or an object with
+// "content" or "base64" alongside its timestamps
+fn session_entry_bytes(entry: &serde_json::Value) -> Option<Vec<u8>> {
+    match entry.as_str() {
+        Some(text) => Some(text.as_bytes().to_vec()),
+        None => entry.get("content").and_then(|c| c.as_str()).map(|text| text.as_bytes().to_vec())
+            .or_else(|| entry.get("base64").and_then(|b| b.as_str())
+                .and_then(|encoded| crate::base64::decode(encoded).ok())),
+    }
+}
+
+// Resume a session's shell: its history comes before this one's, and its
+// directory becomes current if it still exists
+async fn restore_shell(shell: &serde_json::Value) {
+    let history: Vec<String> = shell.get("history")
+        .and_then(|history| serde_json::from_value(history.clone()).ok())
+        .unwrap_or_default();
+    crate::input_history::INPUT_HISTORY.with_borrow_mut(|current| current.restore_earlier(history));
+
+    if let Some(cwd) = shell.get("cwd").and_then(|cwd| cwd.as_str()) {
+        let dirpath = DirPath::parse(cwd, &DirPath::root());
+        // Level by level, since listing under a missing abyss directory would fetch nothing
+        for depth in 1..=dirpath.0.len() {
+            if !crate::filesystem::helpers::dir_exists(&DirPath(dirpath.0[..depth].to_vec())).await {
+                return;
+            }
+        }
+        crate::filesystem::helpers::change_dir(dirpath);
+    }
+}
+
+/// The session with its files moved under `into` (load-session --into), so
+/// /notes/a.txt becomes /into/notes/a.txt. Abyss edits, the cave and shell state are
+/// tied to where they happened, so they're dropped.
+pub fn remap_session(session_json: &str, into: &DirPath) -> Result<String, String> {
+    let mut session: serde_json::Value = serde_json::from_str(session_json)
+        .map_err(|e| format!("Error: Failed to parse session file: {}", e))?;
+    let Some(files) = session.get("files").and_then(|files| files.as_object()) else {
+        return Err("Error: Invalid session file: missing or invalid files".to_string());
+    };
+    let prefix = into.to_string();
+    let files: serde_json::Map<String, serde_json::Value> = files.iter()
+        .map(|(path, entry)| (format!("{}{}", prefix.trim_end_matches('/'), path), entry.clone()))
+        .collect();
+    session["files"] = files.into();
+    if let Some(session) = session.as_object_mut() {
+        session.remove("abyss");
+        session.remove("cave_of_dice");
+        session.remove("shell");
+    }
+    Ok(session.to_string())
+}
+
+/// What importing does with a file that already exists with other content
+#[derive(Clone)]
+pub enum OnConflict {
+    Overwrite,
+    SkipExisting,
+    // Skip just these paths (chosen file by file)
+    Skip(std::collections::BTreeSet<String>),
+}
+
+impl OnConflict {
+    fn skips(&self, path: &str) -> bool {
+        match self {
+            OnConflict::Overwrite => false,
+            OnConflict::SkipExisting => true,
+            OnConflict::Skip(paths) => paths.contains(path),
+        }
+    }
+}
+
+// A session's files, once its version is known to be one that can be read
+fn session_files(session: &serde_json::Value) -> Result<&serde_json::Map<String, serde_json::Value>, String> {
+    // Check version (1.0 stored plain strings, 1.1 adds file metadata, 1.2 binary files,
+    // 1.3 abyss edits, 1.4 shell state, 1.5 the cave of dice)
+    if let Some(version) = session.get("version").and_then(|v| v.as_str()) {
+        if !["1.0", "1.1", "1.2", "1.3", "1.4", "1.5"].contains(&version) {
+            return Err(format!("Error: Unsupported session version: {}", version));
+        }
+    } else {
+        return Err("Error: Invalid session file: missing version".to_string());
+    }
+
+    session.get("files").and_then(|f| f.as_object())
+        .ok_or_else(|| "Error: Invalid session file: missing or invalid files".to_string())
+}
+
+/// How a session's files compare with the filesystem: new files, files it
+/// would replace, and files that already match (each sorted by path)
+#[derive(Default, Debug, PartialEq)]
+pub struct SessionChanges {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub identical: Vec<String>,
+}
+
+pub fn diff_session(session_json: &str) -> Result<SessionChanges, String> {
+    let session: serde_json::Value = serde_json::from_str(session_json)
+        .map_err(|e| format!("Error: Failed to parse session file: {}", e))?;
+    let files = session_files(&session)?;
+
+    let mut diff = SessionChanges::default();
+    VIRTUAL_FS.with_borrow(|vfs| {
+        for (path, entry) in files {
+            let bytes = session_entry_bytes(entry);
+            match vfs.get_content(&FilePath::parse(path, &DirPath::root())) {
+                None => diff.added.push(path.clone()),
+                Some(Content::InMemory(data)) if bytes.as_ref().is_some_and(|bytes| data.bytes == *bytes) => diff.identical.push(path.clone()),
+                // Site files are replaced whatever they hold
+                Some(_) => diff.changed.push(path.clone()),
+            }
+        }
+    });
+    Ok(diff)
+}
+
+/// The session's files that would replace existing files with other content
+/// (none if the session can't be read; importing reports that)
+pub fn session_conflicts(session_json: &str) -> Vec<String> {
+    diff_session(session_json).map(|diff| diff.changed).unwrap_or_default()
+}
+
 // Import session helper (used by load-session command)
-pub fn import_session(session_json: String) -> String {
+// Reports each file as it's imported unless `quiet`, then returns a summary
+pub async fn import_session(session_json: String, quiet: bool, on_conflict: &OnConflict) -> String {
     use serde_json::Value;
 
     match serde_json::from_str::<Value>(&session_json) {
         Ok(session) => {
-            // Check version
-            if let Some(version) = session.get("version").and_then(|v| v.as_str()) {
-                if version != "1.0" {
-                    return format!("Error: Unsupported session version: {}", version);
-                }
-            } else {
-                return "Error: Invalid session file: missing version".to_string();
-            }
-
-            // Get files object
-            let files = match session.get("files").and_then(|f| f.as_object()) {
-                Some(f) => f,
-                None => return "Error: Invalid session file: missing or invalid files".to_string(),
+            let files = match session_files(&session) {
+                Ok(files) => files,
+                Err(e) => return e,
             };
 
-            let mut count = 0;
+            let abyss = session.get("abyss").cloned().unwrap_or_default();
+            let abyss_entries: usize = ["removed_dirs", "removed_files", "created_dirs"].iter()
+                .filter_map(|key| abyss.get(key).and_then(|paths| paths.as_array()).map(|paths| paths.len()))
+                .chain(abyss.get("files").and_then(|files| files.as_object()).map(|files| files.len()))
+                .sum();
+
+            let cave = session.get("cave_of_dice");
+            let mut batch = Batch::new("import", files.len() + abyss_entries + cave.iter().count());
+            if quiet {
+                batch = batch.quiet();
+            }
 
             // Import each file
             VIRTUAL_FS.with(|vfs| {
                 for (path, content_value) in files {
-                    if let Some(content_str) = content_value.as_str() {
-                        // Parse the path
-                        let filepath = FilePath::parse(path, &DirPath::root());
-
-                        // Write to virtual filesystem
-                        vfs.borrow_mut().write_file(&filepath, content_str.to_string());
-                        count += 1;
+                    // Parse the path
+                    let filepath = FilePath::parse(path, &DirPath::root());
+
+                    let bytes = session_entry_bytes(content_value);
+                    let unchanged = vfs.borrow().get_content(&filepath).is_some_and(|existing|
+                        matches!((existing, &bytes), (Content::InMemory(data), Some(bytes)) if data.bytes == *bytes)
+                    );
+
+                    if unchanged {
+                        batch.skip(path, "unchanged");
+                    } else if on_conflict.skips(path) && vfs.borrow().get_content(&filepath).is_some() {
+                        batch.skip(path, "exists");
+                    } else if let Some(bytes) = &bytes && let Err(e) = check_write(&filepath, bytes.len()) {
+                        batch.record(path, Err(e));
+                    } else if let Some(bytes) = bytes {
+                        // Parents first, so every level can be listed (and returned to)
+                        for depth in 1..filepath.dir.0.len() {
+                            vfs.borrow_mut().create_dir(DirPath(filepath.dir.0[..depth].to_vec()));
+                        }
+                        if content_value.is_string() {
+                            // Write to virtual filesystem
+                            vfs.borrow_mut().write_file(&filepath, bytes);
+                        } else {
+                            // Keep the original timestamps where present
+                            let now = crate::js_interop::now();
+                            let created = content_value.get("created").and_then(|t| t.as_f64()).unwrap_or(now);
+                            let modified = content_value.get("modified").and_then(|t| t.as_f64()).unwrap_or(now);
+                            vfs.borrow_mut().write_file_data(&filepath, FileData::with_times(bytes, created, modified));
+                        }
+                        emit(FsEvent::FileWritten(filepath));
+                        batch.record(path, Ok(()));
+                    } else {
+                        batch.record(path, Err("invalid file entry".to_string()));
                     }
                 }
             });
 
-            format!("Imported {} file(s)", count)
+            // Before the abyss edits, which may be inside it
+            if let Some(cave) = cave {
+                let entrance = cave.get("entrance").and_then(|entrance| entrance.as_str()).unwrap_or("cave_of_dice");
+                batch.record(entrance, crate::filesystem::cave_of_dice::import(cave, crate::reload::content_from_json));
+            }
+
+            for (path, result) in crate::filesystem::abyss_delta::import(&abyss, session_entry_bytes).await {
+                batch.record(&path, result);
+            }
+
+            if let Some(shell) = session.get("shell") {
+                restore_shell(shell).await;
+            }
+
+            batch.summary()
         }
         Err(e) => format!("Error: Failed to parse session file: {}", e),
     }
 }
 
-/// Main command processor - handles all non-content commands
-/// Add new commands here!
+/// Main command processor - runs the registered command named first
 #[wasm_bindgen]
 pub async fn process_command(command: &str) -> String {
     let parts: Vec<&str> = command.trim().split_whitespace().collect();
@@ -123,93 +458,109 @@ pub async fn process_command(command: &str) -> String {
         return String::new();
     }
 
-    match parts[0] {
-        "help" => Help.execute(&parts[1..]).await,
-        "about" => About.execute(&parts[1..]).await,
-        "contact" => Contact.execute(&parts[1..]).await,
-
-        "pwd" => Pwd.execute(&parts[1..]).await,
-
-        "ls" => Ls.execute(&parts[1..]).await,
-        "cd" => Cd.execute(&parts[1..]).await,
-        "cat" => Cat.execute(&parts[1..]).await,
-
-        "hello" => Hello.execute(&parts[1..]).await,
-
-        "info" => Info.execute(&parts[1..]).await,
-
-        "fib" => Fib.execute(&parts[1..]).await,
-        "secret" => Secret.execute(&parts[1..]).await,
-        "gol" => Gol.execute(&parts[1..]).await,
-
-        "echo" => Echo.execute(&parts[1..]).await,
-
-        "edit" => Edit.execute(&parts[1..]).await,
-        "load" => Load.execute(&parts[1..]).await,
-        "save" => Save.execute(&parts[1..]).await,
-        "save-session" => SaveSession.execute(&parts[1..]).await,
-        "load-session" => LoadSession.execute(&parts[1..]).await,
-
-        "rm" => Rm.execute(&parts[1..]).await,
-        "mkdir" => Mkdir.execute(&parts[1..]).await,
-        "rmdir" => Rmdir.execute(&parts[1..]).await,
-
-        "pretty" => Pretty.execute(&parts[1..]).await,
-
-        // Add more commands here!
+    if let Some(flag) = crate::features::disabled_flag_for(parts[0]) {
+        return format!("{}: This feature is turned off (try 'features {} on')", parts[0], flag);
+    }
 
-        _ => format!("Command not found: {}\nType 'help' for available commands.", command)
+    if let Some(builtin) = COMMANDS.get(parts[0]) {
+        return builtin.run(&parts[1..]).await;
+    }
+    match crate::script::find_command(parts[0]).await {
+        Some(script) => crate::script::run(&script, crate::script::Language::of(&script), parts[0], &parts[1..]).await,
+        None => match did_you_mean(parts[0]) {
+            Some(suggestion) => format!("Command not found: {}\n{}\nType 'help' for available commands.", command, suggestion),
+            None => format!("Command not found: {}\nType 'help' for available commands.", command),
+        }
     }
 }
 
-pub fn command_data(name: &str) -> Box<dyn CommandData> {
-    match name {
-        "help" => Box::new(Help),
-
-        // Other content commands
-        "about" => Box::new(About),
-        "contact" => Box::new(Contact),
-
-        "pwd" => Box::new(Pwd),
-
-        "ls" => Box::new(Ls),
-
-        "cd" => Box::new(Cd),
-
-        "cat" => Box::new(Cat),
-
-        "hello" => Box::new(Hello),
-
-        "info" => Box::new(Info),
-
-        "fib" => Box::new(Fib),
-
-        "secret" => Box::new(Secret),
-
-        "gol" => Box::new(Gol),
-
-        "echo" => Box::new(Echo),
-
-        "edit" => Box::new(Edit),
-
-        "load" => Box::new(Load),
-
-        "save" => Box::new(Save),
-
-        "save-session" => Box::new(SaveSession),
-
-        "load-session" => Box::new(LoadSession),
-
-        "rm" => Box::new(Rm),
-
-        "mkdir" => Box::new(Mkdir),
-
-        "rmdir" => Box::new(Rmdir),
+/// "Did you mean 'pretty'?" for the listed commands (and their aliases)
+/// closest to `name`, if any are close enough to be a typo
+fn did_you_mean(name: &str) -> Option<String> {
+    // A third of the name may be wrong, so short names only get one edit
+    let threshold = (name.chars().count() / 3).max(1);
+    let mut closest: Vec<(usize, &str)> = COMMANDS.iter()
+        .filter(|command| command.category().is_some())
+        .flat_map(|command| std::iter::once(command.name()).chain(command.aliases().iter().copied()))
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    let best = closest.iter().map(|(distance, _)| *distance).min()?;
+    closest.retain(|(distance, _)| *distance == best);
+    closest.sort();
+    let quoted: Vec<String> = closest.iter().take(3).map(|(_, candidate)| format!("'{}'", candidate)).collect();
+    Some(match quoted.split_last() {
+        Some((last, [])) => format!("Did you mean {}?", last),
+        Some((last, rest)) => format!("Did you mean {} or {}?", rest.join(", "), last),
+        None => return None,
+    })
+}
 
-        "pretty" => Box::new(Pretty),
+/// Levenshtein distance, counting two neighbouring characters swapped as
+/// one edit rather than two ("sl" is one edit from "ls")
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows[i][j]: the distance between a[..i] and b[..j]
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in rows[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution.min(rows[i - 1][j] + 1).min(rows[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
 
-        // Add more commands here!
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_help_is_generated_from_the_registry() {
+        enable(1);
+        crate::settings::set_setting("tips", "off");
+        let help = run("help");
+        assert_eq!(help[..4], ["Available commands:", "", "Navigation:", "  ls, cd, pwd, map, cat, stat, pick"]);
+        assert!(help.contains(&"  clear, hello, info, fib, roll, random, echo, banner, date, history, features, hsh, whoami, hostname, uname, neofetch".to_string()));
+        assert!(!help.iter().any(|line| line.contains("secret")));
+        // Commands behind a feature flag that's off aren't listed
+        assert!(!help.iter().any(|line| line.contains("typetest") || line.contains("rpg")));
+        run("features games on");
+        assert!(run("help").contains(&"  typetest, 2048".to_string()));
+
+        let verbose = run("help -v");
+        assert!(verbose.contains(&"  pwd            - Print working directory".to_string()));
+        assert!(verbose.contains(&"  diff <file1> <file2> - Show differences between two files, or an edited file and the original".to_string()));
+        assert!(verbose.contains(&"  diff <file>".to_string()));
+
+        // No manual to fetch here, so help falls back to the command's own usage
+        assert_eq!(run("help tabs"), ["tabs - List the editor and pretty tabs that are open", "", "Usage: tabs"]);
+        assert_eq!(run("help nonsense"), ["Could not find relevant help page"]);
+        assert_eq!(run("features on"), ["Usage: features [--json]", "       features <name> on|off|reset"]);
+    }
 
-        x => Box::new(UserDefined(x.to_string()))
+    #[test]
+    fn test_did_you_mean() {
+        enable(1);
+        assert_eq!(run("prety notes.md"), ["Command not found: prety notes.md", "Did you mean 'pretty'?", "Type 'help' for available commands."]);
+        assert_eq!(run("sl"), ["Command not found: sl", "Did you mean 'ls'?", "Type 'help' for available commands."]);
+        assert_eq!(run("ca"), ["Command not found: ca", "Did you mean 'cat' or 'cd'?", "Type 'help' for available commands."]);
+        assert_eq!(run("clss"), ["Command not found: clss", "Did you mean 'cls'?", "Type 'help' for available commands."]);
+        // Nothing close, and hidden commands are never suggested
+        assert_eq!(run("qwertyuiop"), ["Command not found: qwertyuiop", "Type 'help' for available commands."]);
+        assert_eq!(run("secrte"), ["Command not found: secrte", "Type 'help' for available commands."]);
+
+        assert_eq!(run("dir"), run("ls"));
     }
 }
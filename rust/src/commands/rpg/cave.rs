@@ -0,0 +1,158 @@
+//! Chance in the cave of dice. A location whose directory is somewhere in
+//! /abyss/cave_of_dice rolls the cave's die (the dN.txt of the nearest
+//! directory holding one) as the player arrives: a 1 brings a wandering
+//! enemy out of the dark, and the highest roll turns up loot. The enemy or
+//! item is any of the world's. Some rooms hold a treasure chest too
+//! (treasure.txt), whose loot can be taken once.
+
+use crate::filesystem::{DirPath, FilePath};
+use crate::filesystem::cave_of_dice::{dice_file_sides, loot, loot_in, path_in_cave_of_dice, TREASURE_FILE};
+use crate::filesystem::helpers::{get_contents, get_file_content};
+use crate::rng::choose;
+use super::world::{ItemSpec, World};
+
+/// How the cave's loot is named among the world's items: "cave:tonic"
+pub const LOOT_PREFIX: &str = "cave:";
+
+/// What the die brings
+#[derive(Debug, PartialEq)]
+pub enum Chance {
+    /// The enemy (by name) that wanders in
+    Encounter(String),
+    /// The item (by name) found
+    Loot(String),
+}
+
+/// A roll of the die, and what it brings
+pub struct Roll {
+    pub sides: u32,
+    pub rolled: u32,
+    pub chance: Option<Chance>,
+}
+
+// The die of the cave directory nearest `dir` (it or a parent), with its sides
+async fn nearest_die(dir: &DirPath) -> Option<(FilePath, u32)> {
+    let mut dir = dir.clone();
+    while path_in_cave_of_dice(&dir) {
+        let die = get_contents(&dir).await.0.into_keys()
+            .find_map(|file| dice_file_sides(&file).map(|sides| (file, sides)));
+        if let Some((file, sides)) = die {
+            return Some((FilePath::new(dir, file), sides));
+        }
+        dir = DirPath(dir.0[..dir.0.len() - 1].to_vec());
+    }
+    None
+}
+
+/// Roll for a location, if it's in the cave of dice (and the cave has a die to roll)
+pub async fn roll(world: &World, location: &str) -> Option<Roll> {
+    let (die, sides) = nearest_die(&world.location_dir(location)).await?;
+    // Reading a die rolls it: "You rolled a 4"
+    let rolled: u32 = get_file_content(&die).await.ok()?.rsplit(' ').next()?.trim().parse().ok()?;
+    let chance = match rolled {
+        1 => choose(&world.names("enemies").await).cloned().map(Chance::Encounter),
+        _ if rolled == sides => choose(&world.names("items").await).cloned().map(Chance::Loot),
+        _ => None,
+    };
+    Some(Roll { sides, rolled, chance })
+}
+
+/// The treasure chest in the room `location` is in, if it has one: the
+/// chest's file, what it says, and the item inside
+pub async fn treasure(world: &World, location: &str) -> Option<(FilePath, String, String)> {
+    let (die, _) = nearest_die(&world.location_dir(location)).await?;
+    let chest = FilePath::new(die.dir, TREASURE_FILE.to_string());
+    let text = get_file_content(&chest).await.ok()?;
+    let item = format!("{}{}", LOOT_PREFIX, loot_in(&text)?.id);
+    Some((chest, text, item))
+}
+
+/// The cave's loot `name` (as "cave:ID") as an item
+pub fn loot_item(name: &str) -> Option<ItemSpec> {
+    let loot = loot(name.strip_prefix(LOOT_PREFIX)?)?;
+    Some(ItemSpec {
+        name: loot.name.to_string(),
+        description: loot.description.to_string(),
+        attack: loot.attack,
+        defense: loot.defense,
+        heal: loot.heal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+    use crate::filesystem::{DirPath, FilePath};
+
+    #[test]
+    fn test_loot_item() {
+        assert_eq!(loot_item("cave:tonic").map(|item| item.heal), Some(8));
+        assert!(loot_item("tonic").is_none());
+        assert!(loot_item("cave:nothing").is_none());
+    }
+
+    #[test]
+    fn test_rpg_in_the_cave_of_dice() {
+        enable(1);
+        run("features rpg on");
+        let cave = "/abyss/level1/level2/cave_of_dice/d4/camp";
+        let files = [
+            ("!!player.toml", "name = \"Wren\"\nhp = 100\nattack = 20\ndefense = 0\ndamage = \"1d4\"\nstart = \"north\"\n"),
+            ("locations/north/!!location.toml", "name = \"North Cave\"\n"),
+            ("locations/north/!!connections.txt", "south\n"),
+            ("locations/south/!!location.toml", "name = \"South Cave\"\n"),
+            ("locations/south/!!connections.txt", "north\n"),
+            ("enemies/bat.toml", "name = \"Bat\"\nhp = 1\nattack = 0\ndefense = 0\ndamage = \"1d2\"\nxp = 1\n"),
+            ("items/coin.toml", "name = \"Gold Coin\"\ndescription = \"Warm from somebody's pocket.\"\n"),
+        ];
+        for (path, content) in files {
+            let path = FilePath::parse(&format!("{}/{}", cave, path), &DirPath::root());
+            block_on(crate::commands::builtin::filesystem::create_dir_all(&path.dir)).unwrap();
+            block_on(crate::filesystem::helpers::write_file(&path, content)).unwrap();
+        }
+        run(&format!("rpg start {}", cave));
+        // Each arrival rolls the cave's d4: a 1 brings a wandering enemy, a 4 loot
+        // (after the room's own treasure chest)
+        assert_eq!(run("go south")[4], "The cave's d4 rolls a 4. Something glints in the dark: you find a Gold Coin.");
+        assert_eq!(run("go north")[4], "The cave's d4 rolls a 1. The Bat attacks! (attack, defend, flee or use an item)");
+        assert_eq!(run("attack"), ["You hit the Bat for 21. The Bat is beaten! You gain 1 XP."]);
+        run("go south");
+        run("attack");
+        assert_eq!(run("go north"), ["North Cave", "You see: Treasure chest (treasure)", "Paths lead to: south"]);
+        assert_eq!(run("inventory")[1], "  Gold Coin (coin) - Warm from somebody's pocket.");
+        // Wandering enemies aren't kept beaten where they were met
+        run("save");
+        let saved = block_on(crate::filesystem::helpers::get_file_content(&FilePath::parse("/saves/quicksave.toml", &DirPath::root()))).unwrap();
+        assert!(saved.contains("defeated = []"), "{}", saved);
+    }
+
+    #[test]
+    fn test_rpg_treasure_in_the_cave_of_dice() {
+        enable(1);
+        run("features rpg on");
+        let room = "/abyss/level1/level2/cave_of_dice/d4";
+        let files = [
+            ("camp/!!player.toml", "name = \"Wren\"\nhp = 10\nattack = 1\ndefense = 0\ndamage = \"1d4\"\nstart = \"hollow\"\n"),
+            ("camp/locations/hollow/!!location.toml", "name = \"Hollow\"\n"),
+            ("treasure.txt", "A chest sits in the corner.\nInside: Dicer's Tonic. Tastes of pennies. Heals 8 HP.\n"),
+        ];
+        for (path, content) in files {
+            let path = FilePath::parse(&format!("{}/{}", room, path), &DirPath::root());
+            block_on(crate::commands::builtin::filesystem::create_dir_all(&path.dir)).unwrap();
+            block_on(crate::filesystem::helpers::write_file(&path, content)).unwrap();
+        }
+        assert_eq!(run(&format!("rpg start {}/camp", room))[3], "You see: Treasure chest (treasure)");
+        assert_eq!(run("take chest"), [
+            "A chest sits in the corner.",
+            "Inside: Dicer's Tonic. Tastes of pennies. Heals 8 HP.",
+            "You take the Dicer's Tonic.",
+        ]);
+        assert_eq!(run("inventory")[1], "  Dicer's Tonic (cave:tonic) - Tastes of pennies. Heals 8 HP.");
+        // A chest is only full once
+        assert_eq!(run("look"), ["Hollow", "There's no way on from here."]);
+        assert_eq!(run("take treasure"), ["There's no treasure here."]);
+        run("use cave:tonic");
+        assert_eq!(run("inventory")[1], "You aren't carrying anything.");
+    }
+}
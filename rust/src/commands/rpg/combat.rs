@@ -0,0 +1,59 @@
+//! Fights: the player and an enemy trade blows until one of them is down
+//! (or the player gets away). Each blow rolls the attacker's damage dice
+//! and adds their attack, less the defender's defense; a blow always does
+//! at least 1 damage, or half that (rounded down) against a defending player.
+
+use crate::dice;
+use crate::rng::random_range;
+use super::world::EnemySpec;
+
+/// The chance in 20 of getting away from a fight
+pub const FLEE_CHANCE: u32 = 10;
+
+/// An enemy being fought
+pub struct Fight {
+    /// The enemy's name in the world (its file name)
+    pub enemy: String,
+    pub spec: EnemySpec,
+    pub hp: u32,
+    /// Met by chance rather than where the world put it, so not kept beaten
+    pub wandering: bool,
+}
+
+impl Fight {
+    pub fn new(enemy: &str, spec: EnemySpec, wandering: bool) -> Fight {
+        Fight { enemy: enemy.to_string(), hp: spec.hp, spec, wandering }
+    }
+}
+
+/// The damage of one blow: `damage` dice (checked when the world was read) plus `attack`, less `defense`
+pub fn blow(damage: &str, attack: u32, defense: u32) -> u32 {
+    let rolled = dice::parse(damage).map(|dice| dice.roll().total).unwrap_or(0);
+    (rolled + attack as i64 - defense as i64).max(1) as u32
+}
+
+/// A blow against a player who's defending
+pub fn defended(damage: u32) -> u32 {
+    damage / 2
+}
+
+pub fn flee_succeeds() -> bool {
+    random_range(1..=20) <= FLEE_CHANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blows() {
+        crate::rng::seed(2);
+        for _ in 0..50 {
+            assert!((4..=9).contains(&blow("1d6", 3, 0)));
+        }
+        // However tough the defender, a blow lands
+        assert_eq!(blow("1d4", 0, 100), 1);
+        assert_eq!(defended(1), 0);
+        assert_eq!(defended(7), 3);
+    }
+}
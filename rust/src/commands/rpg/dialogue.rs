@@ -0,0 +1,107 @@
+//! Conversations: an NPC says a node's text, and the player picks what to
+//! say back from its choices (by number, through a prompt), which leads to
+//! another node or ends the conversation. Nodes set flags as they're said,
+//! and choices can need flags set (or not set) to be offered, so what's
+//! been said before changes what can be said next.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use crate::prompt::{self, Prompt};
+use super::world::{ChoiceSpec, NpcSpec, World};
+use super::{whereabouts, with_game};
+
+/// Start talking to someone here
+pub async fn talk(name: &str) -> String {
+    let Some((world, location)) = whereabouts() else {
+        return String::new();
+    };
+    let spec = match world.location(&location).await {
+        Ok(spec) => spec,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let npc = match find_npc(&world, &spec.npcs, name).await {
+        Ok(Some(npc)) => npc,
+        Ok(None) => return format!("There's no {} here to talk to.", name),
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let start = npc.start.clone();
+    say(Rc::new(npc), &start)
+}
+
+// The NPC among `npcs` called `name` (its file name, or what they're called)
+async fn find_npc(world: &World, npcs: &[String], name: &str) -> Result<Option<NpcSpec>, String> {
+    for npc in npcs {
+        let spec = world.npc(npc).await?;
+        if npc == name || spec.name.eq_ignore_ascii_case(name) {
+            return Ok(Some(spec));
+        }
+    }
+    Ok(None)
+}
+
+/// The choices at a node the player can make, given the flags set
+pub fn offered<'a>(choices: &'a [ChoiceSpec], flags: &BTreeSet<String>) -> Vec<&'a ChoiceSpec> {
+    choices.iter()
+        .filter(|choice| choice.requires.iter().all(|flag| flags.contains(flag)))
+        .filter(|choice| !choice.unless.iter().any(|flag| flags.contains(flag)))
+        .collect()
+}
+
+// Say a node (checked to exist when the NPC was read), asking what to say back if there's a choice
+fn say(npc: Rc<NpcSpec>, node: &str) -> String {
+    let Some(node) = npc.nodes.get(node) else {
+        return String::new();
+    };
+    let flags = with_game(|game| {
+        game.state.flags.extend(node.sets.iter().cloned());
+        game.state.flags.clone()
+    }).unwrap_or_default();
+    let said = format!("{}: {}", npc.name, node.text.trim());
+    let choices: Vec<ChoiceSpec> = offered(&node.choices, &flags).into_iter().cloned().collect();
+    if choices.is_empty() {
+        return said;
+    }
+
+    let mut question = vec![said];
+    question.extend(choices.iter().enumerate().map(|(i, choice)| format!("  {}. {}", i + 1, choice.text)));
+    question.push(format!("What do you say? (1-{})", choices.len()));
+    let count = choices.len();
+    prompt::ask(Prompt::new(&question.join("\n"), move |answer| async move {
+        let Some(choice) = answer.parse::<usize>().ok().and_then(|n| choices.get(n - 1)) else {
+            return String::new();
+        };
+        match &choice.next {
+            Some(next) => say(npc.clone(), next),
+            None => format!("You leave {} be.", npc.name),
+        }
+    }).with_check(move |answer| match answer.trim().parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Ok(n.to_string()),
+        _ => Err(format!("Choose a number from 1 to {}", count)),
+    }));
+    String::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choice(text: &str, requires: &[&str], unless: &[&str]) -> ChoiceSpec {
+        ChoiceSpec {
+            text: text.to_string(),
+            next: None,
+            requires: requires.iter().map(|flag| flag.to_string()).collect(),
+            unless: unless.iter().map(|flag| flag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_offered() {
+        let choices = [choice("always", &[], &[]), choice("after", &["asked"], &[]), choice("before", &[], &["asked"])];
+        let texts = |flags: &[&str]| -> Vec<String> {
+            let flags = flags.iter().map(|flag| flag.to_string()).collect();
+            offered(&choices, &flags).iter().map(|choice| choice.text.clone()).collect()
+        };
+        assert_eq!(texts(&[]), ["always", "before"]);
+        assert_eq!(texts(&["asked"]), ["always", "after"]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Experience and levels. Beating an enemy gives its XP; the levels in
+//! !!player.toml each take more XP than the last, and reaching one brings
+//! the stats it lists along with points for the player to spend as they
+//! like, asked for then and there (or later, with `spend`).
+
+use crate::prompt::{self, Prompt};
+use super::state::State;
+use super::world::{LevelSpec, World};
+use super::with_game;
+
+/// The health a point spent on health gives
+pub const POINT_HP: u32 = 3;
+
+/// The level `xp` reaches
+pub fn level_for(levels: &[LevelSpec], xp: u32) -> u32 {
+    1 + levels.iter().take_while(|level| level.xp <= xp).count() as u32
+}
+
+/// The XP the next level takes, if there is one
+pub fn next_level_xp(levels: &[LevelSpec], level: u32) -> Option<u32> {
+    (level as usize).checked_sub(1).and_then(|index| levels.get(index)).map(|next| next.xp)
+}
+
+/// Add `xp`, levelling up as far as it goes: what happens (nothing for no XP)
+pub fn gain(world: &World, xp: u32) -> String {
+    if xp == 0 {
+        return String::new();
+    }
+    let mut lines = vec![format!("You gain {} XP.", xp)];
+    with_game(|game| {
+        let state = &mut game.state;
+        state.xp += xp;
+        let reached = level_for(&world.player.levels, state.xp);
+        while state.level < reached {
+            let Some(level) = (state.level as usize).checked_sub(1).and_then(|index| world.player.levels.get(index)) else {
+                break;
+            };
+            state.level += 1;
+            state.growth.hp += level.hp;
+            state.growth.attack += level.attack;
+            state.growth.defense += level.defense;
+            state.hp += level.hp;
+            state.points += level.points;
+            lines.push(format!("You reach level {}!{}", state.level, gains(level)));
+        }
+    });
+    lines.join(" ")
+}
+
+// What a level brings, as " Max HP +5, attack +1." (or nothing)
+fn gains(level: &LevelSpec) -> String {
+    let gains: Vec<String> = [("Max HP", level.hp), ("attack", level.attack), ("defense", level.defense)].iter()
+        .filter(|(_, gain)| *gain > 0)
+        .map(|(stat, gain)| format!("{} +{}", stat, gain))
+        .collect();
+    match gains.is_empty() {
+        true => String::new(),
+        false => format!(" {}.", gains.join(", ")),
+    }
+}
+
+/// Ask what to spend the next point on, until they're all spent
+pub fn ask_points() {
+    let Some(points) = with_game(|game| game.state.points).filter(|points| *points > 0) else {
+        return;
+    };
+    let question = format!("Spend a point on attack, defense or health (+{} HP)? ({} left: a/d/h)", POINT_HP, points);
+    prompt::ask(Prompt::new(&question, |answer| async move {
+        let Some(result) = with_game(|game| spend(&mut game.state, &answer)) else {
+            return String::new();
+        };
+        ask_points();
+        result
+    }).with_check(|answer| match answer.trim().to_ascii_lowercase().as_str() {
+        "a" | "attack" => Ok("a".to_string()),
+        "d" | "defense" => Ok("d".to_string()),
+        "h" | "health" | "hp" => Ok("h".to_string()),
+        _ => Err("Choose a (attack), d (defense) or h (health)".to_string()),
+    }));
+}
+
+// Spend a point on "a", "d" or "h"
+fn spend(state: &mut State, stat: &str) -> String {
+    state.points -= 1;
+    match stat {
+        "a" => {
+            state.growth.attack += 1;
+            "Your attack goes up by 1.".to_string()
+        }
+        "d" => {
+            state.growth.defense += 1;
+            "Your defense goes up by 1.".to_string()
+        }
+        _ => {
+            state.growth.hp += POINT_HP;
+            state.hp += POINT_HP;
+            format!("Your max HP goes up by {}.", POINT_HP)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(xps: &[u32]) -> Vec<LevelSpec> {
+        xps.iter().map(|&xp| LevelSpec { xp, hp: 5, attack: 0, defense: 1, points: 1 }).collect()
+    }
+
+    #[test]
+    fn test_levels() {
+        let levels = levels(&[10, 30]);
+        assert_eq!(level_for(&levels, 0), 1);
+        assert_eq!(level_for(&levels, 10), 2);
+        assert_eq!(level_for(&levels, 29), 2);
+        assert_eq!(level_for(&levels, 100), 3);
+        assert_eq!(next_level_xp(&levels, 1), Some(10));
+        assert_eq!(next_level_xp(&levels, 3), None);
+        assert_eq!(next_level_xp(&levels, 0), None);
+        assert_eq!(gains(&levels[0]), " Max HP +5, defense +1.");
+    }
+}
@@ -0,0 +1,951 @@
+//! The RPG engine. `rpg start` plays a world made by rpg-generator: a
+//! directory (anywhere in the filesystem, the abyss included) holding
+//!
+//!     !!player.toml      who the player is, and where they start
+//!     locations/NAME/    a directory for each location
+//!     enemies/NAME.toml  each enemy's stats
+//!     items/NAME.toml    each item
+//!     npcs/NAME.toml     each person to talk to, and what they say
+//!     quests/NAME.toml   each quest, its objectives and reward
+//!
+//! While a game is on, lines typed go to the game (after its own prompt)
+//! instead of being run as commands, until `quit` or Escape. `save` keeps
+//! the game in /saves for `rpg load` to carry on.
+
+mod cave;
+mod combat;
+mod dialogue;
+mod level;
+mod quest;
+mod state;
+mod world;
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use crate::commands::{Category, Command, CommandData, Registry};
+use crate::commands::builtin::filesystem::create_dir_all;
+use crate::filesystem::{CURRENT_DIR, DirPath};
+use crate::filesystem::helpers::{get_contents, get_file_content, write_file};
+use crate::js_interop::{add_output, set_prompt};
+use crate::{set_next_input_handler, NextInputHandler};
+use combat::Fight;
+use state::State;
+use world::{ItemSpec, World};
+
+/// The world `rpg start` plays if not given one
+pub const DEFAULT_WORLD: &str = "/rpg/lantern_road";
+
+/// What a treasure chest in the cave of dice is taken as
+const TREASURE: &str = "treasure";
+
+/// The save slot `save` uses if not given one
+pub const DEFAULT_SLOT: &str = "quicksave";
+
+/// The game being played
+struct Game {
+    world: Rc<World>,
+    state: State,
+    fight: Option<Fight>,
+}
+
+thread_local! {
+    static GAME: RefCell<Option<Game>> = const { RefCell::new(None) };
+}
+
+// Run `f` on the game being played
+fn with_game<R>(f: impl FnOnce(&mut Game) -> R) -> Option<R> {
+    GAME.with_borrow_mut(|game| game.as_mut().map(f))
+}
+
+pub fn is_playing() -> bool {
+    GAME.with_borrow(Option::is_some)
+}
+
+/// Shown before each line typed while a game is on: where the player is
+pub fn prompt() -> String {
+    GAME.with_borrow(|game| match game {
+        Some(game) => format!("[{}] rpg>", game.state.location),
+        None => "rpg>".to_string(),
+    })
+}
+
+pub struct Rpg;
+impl CommandData for Rpg {
+    fn name(&self) -> &str { "rpg" }
+    fn description(&self) -> &str { "Play a role-playing game world (the Lantern Road unless given another)" }
+    fn usage(&self) -> &str { "rpg start [world]\nrpg load <slot>\nrpg saves" }
+    fn category(&self) -> Option<Category> { Some(Category::Games) }
+}
+impl Command for Rpg {
+    async fn execute(&self, args: &[&str]) -> String {
+        match args {
+            ["start"] => start(DEFAULT_WORLD).await,
+            ["start", path] => start(path).await,
+            ["load", slot] => load(slot).await,
+            ["saves"] => saves().await,
+            _ => self.usage_error(),
+        }
+    }
+}
+
+async fn start(path: &str) -> String {
+    let dir = CURRENT_DIR.with(|cd| DirPath::parse(path, &cd.borrow()));
+    let world = match World::load(&dir).await {
+        Ok(world) => world,
+        Err(e) => return format!("rpg: {}: {}", path, e),
+    };
+    let mut welcome = match world.player.intro.trim() {
+        "" => String::new(),
+        intro => format!("{}\n\n", intro),
+    };
+    welcome.push_str(&format!("You are {}. Type help for what you can do, or quit to stop.\n\n", world.player.name));
+    let state = State::new(&dir, &world.player);
+    welcome + &play(world, state).await
+}
+
+/// Carry on a saved game
+async fn load(slot: &str) -> String {
+    let path = match state::save_path(slot) {
+        Ok(path) => path,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let Ok(text) = get_file_content(&path).await else {
+        return format!("rpg: {}: No such save (rpg saves lists them)", slot);
+    };
+    let state = match State::parse(&text) {
+        Ok(state) => state,
+        Err(e) => return format!("rpg: {}: {}", path.to_string(), e),
+    };
+    let world = match World::load(&DirPath::parse(&state.world, &DirPath::root())).await {
+        Ok(world) => world,
+        Err(e) => return format!("rpg: {}: {}", state.world, e),
+    };
+    if let Err(e) = world.location(&state.location).await {
+        return format!("rpg: {}: {}", state.world, e);
+    }
+    // Saves are files anyone can edit: a level the world doesn't have can't be played from
+    let levels = world.player.levels.len() as u32 + 1;
+    if !(1..=levels).contains(&state.level) {
+        return format!("rpg: {}: Level {} isn't one of this world's (1 to {})", path.to_string(), state.level, levels);
+    }
+    let welcome = format!("You carry on as {} from {}.\n\n", world.player.name, slot);
+    welcome + &play(world, state).await
+}
+
+/// The saved games, one a line
+async fn saves() -> String {
+    let contents = get_contents(&state::saves_dir()).await;
+    let mut slots: Vec<&str> = contents.0.keys().filter_map(|file| file.strip_suffix(".toml")).collect();
+    slots.sort();
+    match slots.is_empty() {
+        true => "No saved games (type save while playing to save one)".to_string(),
+        false => slots.join("\n"),
+    }
+}
+
+// Play `world` from `state`
+async fn play(world: World, state: State) -> String {
+    GAME.set(Some(Game { world: Rc::new(world), state, fight: None }));
+    set_next_input_handler(NextInputHandler::Rpg);
+    set_prompt(&prompt());
+    arrive().await
+}
+
+/// Save the game to a slot, to carry on later with `rpg load`
+async fn save(slot: &str) -> String {
+    let path = match state::save_path(slot) {
+        Ok(path) => path,
+        Err(e) => return e,
+    };
+    let Some(text) = with_game(|game| game.state.to_toml()) else {
+        return String::new();
+    };
+    let written = match create_dir_all(&path.dir).await {
+        Ok(()) => write_file(&path, text).await,
+        Err(e) => Err(e),
+    };
+    match written {
+        Ok(()) => format!("Saved to {} (rpg load {} carries on from here).", slot, slot),
+        Err(e) => format!("rpg: {}: {}", path.to_string(), e),
+    }
+}
+
+// The world being played and where the player is in it
+fn whereabouts() -> Option<(Rc<World>, String)> {
+    GAME.with_borrow(|game| game.as_ref().map(|game| (game.world.clone(), game.state.location.clone())))
+}
+
+/// Describe where the player is, and where they can go
+async fn look() -> String {
+    let Some((world, location)) = whereabouts() else {
+        return String::new();
+    };
+    let spec = match world.location(&location).await {
+        Ok(spec) => spec,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let mut lines = vec![spec.name];
+    if !spec.description.trim().is_empty() {
+        lines.push(spec.description.trim().to_string());
+    }
+    let taken = with_game(|game| game.state.taken.clone()).unwrap_or_default();
+    let mut items = Vec::new();
+    for item in spec.items.iter().filter(|item| !taken.contains(&format!("{}/{}", location, item))) {
+        match world.item(item).await {
+            Ok(spec) => items.push(format!("{} ({})", spec.name, item)),
+            Err(e) => return format!("rpg: {}", e),
+        }
+    }
+    if let Some((chest, _, _)) = cave::treasure(&world, &location).await && !taken.contains(&chest.to_string()) {
+        items.push(format!("Treasure chest ({})", TREASURE));
+    }
+    if !items.is_empty() {
+        lines.push(format!("You see: {}", items.join(", ")));
+    }
+    let mut npcs = Vec::new();
+    for npc in &spec.npcs {
+        match world.npc(npc).await {
+            Ok(spec) => npcs.push(format!("{} ({})", spec.name, npc)),
+            Err(e) => return format!("rpg: {}", e),
+        }
+    }
+    if !npcs.is_empty() {
+        lines.push(format!("Here: {}", npcs.join(", ")));
+    }
+    let connections = match world.connections(&location).await {
+        Ok(connections) => connections,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let inventory = with_game(|game| game.state.inventory.clone()).unwrap_or_default();
+    let paths: Vec<String> = connections.iter()
+        .map(|connection| match &connection.key {
+            Some(key) if !inventory.contains(key) => format!("{} (locked)", connection.to),
+            _ => connection.to.clone(),
+        })
+        .collect();
+    lines.push(match paths.is_empty() {
+        true => "There's no way on from here.".to_string(),
+        false => format!("Paths lead to: {}", paths.join(", ")),
+    });
+    lines.join("\n")
+}
+
+/// Move to a location connected to this one
+async fn go(destination: &str) -> String {
+    let Some((world, location)) = whereabouts() else {
+        return String::new();
+    };
+    if destination == location {
+        return "You're already here.".to_string();
+    }
+    let connections = match world.connections(&location).await {
+        Ok(connections) => connections,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let Some(connection) = connections.into_iter().find(|connection| connection.to == destination) else {
+        return format!("You can't get to {} from here.", destination);
+    };
+    if let Some(key) = &connection.key {
+        let carried = with_game(|game| game.state.inventory.contains(key)).unwrap_or(false);
+        if !carried {
+            let name = world.item(key).await.map(|spec| spec.name).unwrap_or_else(|_| key.clone());
+            return format!("The way to {} is locked. You need the {}.", destination, name);
+        }
+    }
+    // Make sure there's somewhere to arrive before leaving
+    if let Err(e) = world.location(destination).await {
+        return format!("rpg: {}", e);
+    }
+    with_game(|game| {
+        game.state.previous = Some(std::mem::replace(&mut game.state.location, destination.to_string()));
+    });
+    set_prompt(&prompt());
+    match connection.description.trim() {
+        "" => arrive().await,
+        description => format!("{}\n\n{}", description, arrive().await),
+    }
+}
+
+/// Look around somewhere just arrived at, and meet the first enemy still there
+async fn arrive() -> String {
+    with_game(|game| game.state.visited.insert(game.state.location.clone()));
+    let mut result = look().await;
+    let encounter = match encounter().await {
+        Some(encounter) => encounter,
+        None => chance().await.unwrap_or_default(),
+    };
+    for next in [quest::progress().await, encounter] {
+        if !next.is_empty() {
+            result.push_str("\n\n");
+            result.push_str(&next);
+        }
+    }
+    result
+}
+
+// Start a fight with the next enemy at this location that hasn't been beaten, if any
+async fn encounter() -> Option<String> {
+    let (world, location) = whereabouts()?;
+    let spec = world.location(&location).await.ok()?;
+    let defeated = with_game(|game| game.state.defeated.clone())?;
+    let enemy = spec.enemies.iter().find(|enemy| !defeated.contains(&format!("{}/{}", location, enemy)))?;
+    Some(fight(&world, enemy, false).await)
+}
+
+// Start a fight with `enemy`, announcing it
+async fn fight(world: &World, enemy: &str, wandering: bool) -> String {
+    let spec = match world.enemy(enemy).await {
+        Ok(spec) => spec,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let mut announcement = format!("The {} attacks!", spec.name);
+    if !spec.description.trim().is_empty() {
+        announcement.push(' ');
+        announcement.push_str(spec.description.trim());
+    }
+    announcement.push_str(" (attack, defend, flee or use an item)");
+    with_game(|game| game.fight = Some(Fight::new(enemy, spec, wandering)));
+    announcement
+}
+
+// Roll the cave's die somewhere in the cave of dice, for a wandering enemy or loot
+async fn chance() -> Option<String> {
+    let (world, location) = whereabouts()?;
+    let roll = cave::roll(&world, &location).await?;
+    let rolled = format!("The cave's d{} rolls a {}.", roll.sides, roll.rolled);
+    match roll.chance? {
+        cave::Chance::Encounter(enemy) => Some(format!("{} {}", rolled, fight(&world, &enemy, true).await)),
+        cave::Chance::Loot(item) => {
+            let spec = match world.item(&item).await {
+                Ok(spec) => spec,
+                Err(e) => return Some(format!("rpg: {}", e)),
+            };
+            with_game(|game| game.state.inventory.push(item));
+            Some(format!("{} Something glints in the dark: you find a {}.", rolled, spec.name))
+        }
+    }
+}
+
+// The player's attack and defense, with what they carry
+async fn player_stats(world: &World) -> (u32, u32) {
+    let inventory = with_game(|game| game.state.inventory.clone()).unwrap_or_default();
+    let growth = with_game(|game| game.state.growth.clone()).unwrap_or_default();
+    let (mut attack, mut defense) = (world.player.attack + growth.attack, world.player.defense + growth.defense);
+    for item in inventory {
+        if let Ok(spec) = world.item(&item).await {
+            attack += spec.attack;
+            defense += spec.defense;
+        }
+    }
+    (attack, defense)
+}
+
+// The player's most health, with what they've gained by levelling up
+fn max_hp(world: &World) -> u32 {
+    world.player.hp + with_game(|game| game.state.growth.hp).unwrap_or(0)
+}
+
+fn fighting() -> bool {
+    with_game(|game| game.fight.is_some()).unwrap_or(false)
+}
+
+/// Strike at the enemy being fought, who strikes back if still standing
+async fn attack() -> String {
+    let Some((world, location)) = whereabouts() else {
+        return String::new();
+    };
+    let (attack, _) = player_stats(&world).await;
+    let Some((name, damage, hp)) = with_game(|game| {
+        let fight = game.fight.as_mut()?;
+        let damage = combat::blow(&game.world.player.damage, attack, fight.spec.defense);
+        fight.hp = fight.hp.saturating_sub(damage);
+        Some((fight.spec.name.clone(), damage, fight.hp))
+    }).flatten() else {
+        return "There's nothing to attack.".to_string();
+    };
+    let blow = format!("You hit the {} for {}.", name, damage);
+    if hp > 0 {
+        return format!("{} {}", blow, enemy_turn(&world, false).await);
+    }
+    let xp = with_game(|game| {
+        let fight = game.fight.take()?;
+        if !fight.wandering {
+            game.state.defeated.insert(format!("{}/{}", location, fight.enemy));
+        }
+        Some(fight.spec.xp)
+    }).flatten().unwrap_or(0);
+    let points = with_game(|game| game.state.points).unwrap_or(0);
+    let mut result = format!("{} The {} is beaten!", blow, name);
+    let gained = level::gain(&world, xp);
+    if !gained.is_empty() {
+        result.push(' ');
+        result.push_str(&gained);
+    }
+    let progress = quest::progress().await;
+    if !progress.is_empty() {
+        result.push_str("\n\n");
+        result.push_str(&progress);
+    }
+    if let Some(encounter) = encounter().await {
+        result.push_str("\n\n");
+        result.push_str(&encounter);
+    }
+    // Levelling up brings points to spend, asked for after the rest is shown
+    if with_game(|game| game.state.points).unwrap_or(0) > points {
+        for line in result.lines() {
+            add_output(line);
+        }
+        level::ask_points();
+        return String::new();
+    }
+    result
+}
+
+/// Brace against the enemy's next blow, halving it
+async fn defend() -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    if !fighting() {
+        return "There's nothing to defend against.".to_string();
+    }
+    format!("You raise your guard. {}", enemy_turn(&world, true).await)
+}
+
+/// Try to get away to where the player came from
+async fn flee() -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    if !fighting() {
+        return "There's nothing to run from.".to_string();
+    }
+    let previous = with_game(|game| game.state.previous.clone()).flatten();
+    let Some(previous) = previous else {
+        return format!("There's nowhere to run! {}", enemy_turn(&world, false).await);
+    };
+    if !combat::flee_succeeds() {
+        return format!("You can't get away! {}", enemy_turn(&world, false).await);
+    }
+    with_game(|game| {
+        game.fight = None;
+        game.state.location = previous;
+        game.state.previous = None;
+    });
+    set_prompt(&prompt());
+    format!("You get away.\n\n{}", arrive().await)
+}
+
+// The enemy strikes: what happens, including the player being beaten
+async fn enemy_turn(world: &World, defending: bool) -> String {
+    let (_, defense) = player_stats(world).await;
+    let Some((name, damage, hp)) = with_game(|game| {
+        let fight = game.fight.as_ref()?;
+        let mut damage = combat::blow(&fight.spec.damage, fight.spec.attack, defense);
+        if defending {
+            damage = combat::defended(damage);
+        }
+        game.state.hp = game.state.hp.saturating_sub(damage);
+        Some((fight.spec.name.clone(), damage, game.state.hp))
+    }).flatten() else {
+        return String::new();
+    };
+    let max = max_hp(world);
+    let blow = format!("The {} hits you for {} ({}/{} HP).", name, damage, hp, max);
+    if hp > 0 {
+        return blow;
+    }
+    // Beaten: back to the start, healed, with whatever was carried
+    with_game(|game| {
+        game.fight = None;
+        game.state.hp = max;
+        game.state.location = world.player.start.clone();
+        game.state.previous = None;
+    });
+    set_prompt(&prompt());
+    format!("{}\n\nYou are beaten, and wake up back where you started.\n\n{}", blow, look().await)
+}
+
+// The item in the world called `name` (its file name, or what it's called) from among `items`
+async fn find_item(world: &World, items: &[String], name: &str) -> Option<(String, ItemSpec)> {
+    for item in items {
+        if let Ok(spec) = world.item(item).await && (item == name || spec.name.eq_ignore_ascii_case(name)) {
+            return Some((item.clone(), spec));
+        }
+    }
+    None
+}
+
+/// Pick up an item lying here
+async fn take(name: &str) -> String {
+    let Some((world, location)) = whereabouts() else {
+        return String::new();
+    };
+    let spec = match world.location(&location).await {
+        Ok(spec) => spec,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let taken = with_game(|game| game.state.taken.clone()).unwrap_or_default();
+    let here: Vec<String> = spec.items.into_iter()
+        .filter(|item| !taken.contains(&format!("{}/{}", location, item)))
+        .collect();
+    let (taking, name) = match find_item(&world, &here, name).await {
+        Some((item, spec)) => {
+            with_game(|game| game.state.taken.insert(format!("{}/{}", location, item)));
+            (format!("You take the {}.", spec.name), item)
+        }
+        None => match open_chest(&world, &location, name, &taken).await {
+            Some(opened) => opened,
+            None => return format!("There's no {} here.", name),
+        },
+    };
+    with_game(|game| game.state.inventory.push(name));
+    let progress = quest::progress().await;
+    match progress.is_empty() {
+        true => taking,
+        false => format!("{}\n\n{}", taking, progress),
+    }
+}
+
+// Open the treasure chest here, if that's what's being taken and it's still
+// full: what it says, and the item inside (which is then taken)
+async fn open_chest(world: &World, location: &str, name: &str, taken: &BTreeSet<String>) -> Option<(String, String)> {
+    if !matches!(name.to_ascii_lowercase().as_str(), TREASURE | "chest" | "treasure chest") {
+        return None;
+    }
+    let (chest, text, item) = cave::treasure(world, location).await?;
+    if taken.contains(&chest.to_string()) {
+        return None;
+    }
+    let spec = world.item(&item).await.ok()?;
+    with_game(|game| game.state.taken.insert(chest.to_string()));
+    Some((format!("{}\nYou take the {}.", text.trim_end(), spec.name), item))
+}
+
+/// The player's level and stats
+async fn stats() -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    let Some(state) = with_game(|game| game.state.clone()) else {
+        return String::new();
+    };
+    let (attack, defense) = player_stats(&world).await;
+    let mut lines = vec![
+        format!("{}, level {}", world.player.name, state.level),
+        format!("HP {}/{}, attack {}, defense {}", state.hp, max_hp(&world), attack, defense),
+        match level::next_level_xp(&world.player.levels, state.level) {
+            Some(next) => format!("XP {} (level {} at {})", state.xp, state.level + 1, next),
+            None => format!("XP {}", state.xp),
+        },
+    ];
+    if state.points > 0 {
+        lines.push(format!("Points to spend: {} (type spend)", state.points));
+    }
+    lines.join("\n")
+}
+
+/// Spend points left from levelling up
+fn spend() -> String {
+    match with_game(|game| game.state.points).unwrap_or(0) {
+        0 => "You have no points to spend.".to_string(),
+        _ => {
+            level::ask_points();
+            String::new()
+        }
+    }
+}
+
+async fn inventory() -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    let (items, hp) = with_game(|game| (game.state.inventory.clone(), game.state.hp)).unwrap_or_default();
+    let (attack, defense) = player_stats(&world).await;
+    let mut lines = vec![format!("{}: {}/{} HP, attack {}, defense {}", world.player.name, hp, max_hp(&world), attack, defense)];
+    if items.is_empty() {
+        lines.push("You aren't carrying anything.".to_string());
+    }
+    for item in items {
+        match world.item(&item).await {
+            Ok(spec) => lines.push(format!("  {} ({}) - {}", spec.name, item, spec.description.trim())),
+            Err(e) => lines.push(format!("  rpg: {}", e)),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Use something carried: a potion heals, and is used up. In a fight this takes a turn.
+async fn use_item(name: &str) -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    let items = with_game(|game| game.state.inventory.clone()).unwrap_or_default();
+    let Some((item, spec)) = find_item(&world, &items, name).await else {
+        return format!("You aren't carrying a {}.", name);
+    };
+    if spec.heal == 0 {
+        return format!("You can't use the {} like that.", spec.name);
+    }
+    let max = max_hp(&world);
+    let hp = with_game(|game| {
+        if let Some(i) = game.state.inventory.iter().position(|carried| *carried == item) {
+            game.state.inventory.remove(i);
+        }
+        game.state.hp = (game.state.hp + spec.heal).min(max);
+        game.state.hp
+    }).unwrap_or(0);
+    let result = format!("You use the {} ({}/{} HP).", spec.name, hp, max);
+    match fighting() {
+        true => format!("{} {}", result, enemy_turn(&world, false).await),
+        false => result,
+    }
+}
+
+/// Play a line typed while a game is on, returning what happens
+pub async fn handle_line(line: &str) -> String {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [] => String::new(),
+        ["help"] => [
+            "look        Describe where you are",
+            "go PLACE    Go somewhere connected to here",
+            "take ITEM   Pick something up",
+            "inventory   Show your health and what you're carrying",
+            "use ITEM    Use something you're carrying",
+            "talk NAME   Talk to someone here",
+            "quests      Show what you've set out to do, and what's done",
+            "stats       Show your level, experience and stats",
+            "spend       Spend points from levelling up",
+            "attack      Strike the enemy you're fighting",
+            "defend      Guard against the enemy's next blow",
+            "flee        Try to run back the way you came",
+            "save [SLOT] Save the game, to carry on with rpg load",
+            "help        Show this list",
+            "quit        Stop playing",
+        ].join("\n"),
+        ["look" | "l"] => look().await,
+        ["inventory" | "i"] => inventory().await,
+        ["quests"] => quest::quests().await,
+        ["stats"] => stats().await,
+        ["spend"] => spend(),
+        ["use"] => "Use what?".to_string(),
+        ["use", item @ ..] => use_item(&item.join(" ")).await,
+        ["attack" | "a"] => attack().await,
+        ["defend" | "d"] => defend().await,
+        ["flee" | "f"] => flee().await,
+        ["go" | "take" | "talk" | "save", ..] if fighting() => "You can't do that in the middle of a fight! (attack, defend, flee or use an item)".to_string(),
+        ["go"] => "Go where?".to_string(),
+        ["go", destination] => go(destination).await,
+        ["take"] => "Take what?".to_string(),
+        ["take", item @ ..] => take(&item.join(" ")).await,
+        ["talk"] | ["talk", "to"] => "Talk to whom?".to_string(),
+        ["talk", "to", name @ ..] | ["talk", name @ ..] => dialogue::talk(&name.join(" ")).await,
+        ["save"] => save(DEFAULT_SLOT).await,
+        ["save", slot] => save(slot).await,
+        ["quit" | "exit"] => {
+            leave();
+            "You stop playing. Type rpg start to play again.".to_string()
+        }
+        [verb, ..] => format!("I don't know how to {} (type help for what you can do)", verb),
+    }
+}
+
+/// Stop playing, and go back to running commands
+pub fn leave() {
+    GAME.set(None);
+    set_next_input_handler(NextInputHandler::None);
+    set_prompt(&crate::preferences::prompt());
+}
+
+pub(super) fn register(registry: &mut Registry) {
+    registry.add(Rpg);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{block_on, enable, overwrite, run};
+    use crate::commands::OnConflict;
+    use crate::filesystem::{DirPath, FilePath};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_rpg_start() {
+        enable(1);
+        assert_eq!(run("rpg start"), ["rpg: This feature is turned off (try 'features rpg on')"]);
+        run("features rpg on");
+        assert_eq!(run("rpg start"), [
+            "The lanterns along the road went out three nights ago, and nobody in",
+            "Ashby will walk it after dark. You've offered to find out why.",
+            "",
+            "You are Wren. Type help for what you can do, or quit to stop.",
+            "",
+            "Ashby",
+            "A handful of cottages around a well. The road north runs into the",
+            "forest, its lanterns dark.",
+            "You see: Healing Potion (potion)",
+            "Here: Old Maud (maud)",
+            "Paths lead to: forest",
+        ]);
+        assert!(crate::commands::rpg::is_playing());
+        // Lines are for the game now, typed at its prompt
+        block_on(crate::handle_input("ls"));
+        assert_eq!(take_output(), ["[village] rpg> ls", "I don't know how to ls (type help for what you can do)"]);
+        assert_eq!(run("quit"), ["You stop playing. Type rpg start to play again."]);
+        assert_eq!(run("pwd"), ["/"]);
+
+        run("rpg start");
+        crate::cancel_input();
+        assert_eq!(take_output(), ["You stop playing."]);
+        assert!(!crate::commands::rpg::is_playing());
+
+        assert_eq!(run("rpg start /nowhere"), ["rpg: /nowhere: No such directory"]);
+        assert_eq!(run("rpg start /config"), ["rpg: /config: !!player.toml: No such file (is this a world?)"]);
+        let player = "name = \"Nobody\"\nhp = 5\nattack = 1\ndefense = 1\nstart = \"home\"\n";
+        crate::write_file("/world/!!player.toml", format!("{}damage = \"1d6\"\n", player)).unwrap();
+        assert_eq!(run("rpg start /world"), ["rpg: /world: !!player.toml: start: No location called 'home'"]);
+        crate::write_file("/world/!!player.toml", format!("{}damage = \"1x6\"\n", player)).unwrap();
+        assert_eq!(run("rpg start /world"), ["rpg: /world: !!player.toml: damage: Unexpected 'x'"]);
+        crate::write_file("/world/!!player.toml", format!("{}damage = \"1d6\"\n[[levels]]\nxp = 10\n[[levels]]\nxp = 10\n", player)).unwrap();
+        assert_eq!(run("rpg start /world"), ["rpg: /world: !!player.toml: levels: Each level needs more XP than the last"]);
+        crate::write_file("/world/!!player.toml", "name = 3".to_string()).unwrap();
+        assert!(run("rpg start /world")[0].starts_with("rpg: /world: !!player.toml: invalid type"));
+        assert_eq!(run("rpg begin"), ["Usage: rpg start [world]", "       rpg load <slot>", "       rpg saves"]);
+    }
+
+    #[test]
+    fn test_rpg_movement() {
+        enable(1);
+        run("features rpg on");
+        run("rpg start");
+        assert_eq!(run("go forest"), [
+            "Lantern Road",
+            "Tall pines close in over the road. Every lantern post has been",
+            "knocked over, and the mud is full of paw prints.",
+            "Paths lead to: village, old_mill",
+            "",
+            "The Grey Wolf attacks! Thin and hungry, and not afraid of you. (attack, defend, flee or use an item)",
+        ]);
+        assert_eq!(crate::commands::rpg::prompt(), "[forest] rpg>");
+        assert!((0..10).any(|_| run("attack")[0].contains("The Grey Wolf is beaten!")));
+        assert_eq!(run("go cellar"), ["You can't get to cellar from here."]);
+        assert_eq!(run("go forest"), ["You're already here."]);
+        assert_eq!(run("go"), ["Go where?"]);
+        // The way there can say something about the journey
+        assert_eq!(run("go old_mill")[0], "You leave the road and push through the brambles towards the creak of the mill wheel.");
+        assert_eq!(run("look").last().unwrap(), "Paths lead to: forest, cellar");
+
+        // A way can need a key, and needn't lead back
+        crate::write_file("/rpg/lantern_road/locations/old_mill/!!connections.toml", "[[connections]]\nto = \"cellar\"\nkey = \"potion\"\n".to_string()).unwrap();
+        assert_eq!(run("look").last().unwrap(), "Paths lead to: cellar (locked)");
+        assert_eq!(run("go cellar"), ["The way to cellar is locked. You need the Healing Potion."]);
+        assert_eq!(run("go forest"), ["You can't get to forest from here."]);
+        crate::write_file("/rpg/lantern_road/locations/old_mill/!!connections.toml", "connections = 3\n".to_string()).unwrap();
+        assert!(run("look").last().unwrap().starts_with("rpg: locations/old_mill/!!connections.toml: invalid type"));
+        block_on(crate::filesystem::helpers::remove_file(&FilePath::parse("/rpg/lantern_road/locations/old_mill/!!connections.toml", &DirPath::root()))).unwrap();
+
+        // A connection to a location that isn't there goes nowhere
+        overwrite("/rpg/lantern_road/locations/old_mill/!!connections.txt", "forest\ntunnel\n");
+        assert_eq!(run("go tunnel"), ["rpg: locations/tunnel/!!location.toml: No such file"]);
+        assert_eq!(crate::commands::rpg::prompt(), "[old_mill] rpg>");
+        overwrite("/rpg/lantern_road/locations/old_mill/!!connections.txt", "");
+        assert_eq!(run("look").last().unwrap(), "There's no way on from here.");
+    }
+
+    #[test]
+    fn test_rpg_combat() {
+        enable(1);
+        run("features rpg on");
+        run("rpg start");
+        assert_eq!(run("take potion"), ["You take the Healing Potion."]);
+        assert_eq!(run("take potion"), ["There's no potion here."]);
+        assert_eq!(run("attack"), ["There's nothing to attack."]);
+        assert_eq!(run("go forest").last().unwrap(), "The Grey Wolf attacks! Thin and hungry, and not afraid of you. (attack, defend, flee or use an item)");
+        assert_eq!(run("go village"), ["You can't do that in the middle of a fight! (attack, defend, flee or use an item)"]);
+        assert_eq!(run("attack"), ["You hit the Grey Wolf for 8. The Grey Wolf is beaten! You gain 6 XP."]);
+        // Beaten enemies stay beaten
+        assert_eq!(run("look").last().unwrap(), "Paths lead to: village, old_mill");
+
+        run("go old_mill");
+        assert_eq!(run("take Rusty Sword"), ["You take the Rusty Sword."]);
+        assert_eq!(run("inventory"), [
+            "Wren: 20/20 HP, attack 5, defense 1",
+            "  Healing Potion (potion) - A stoppered bottle of something red. It smells of mint.",
+            "  Rusty Sword (rusty_sword) - Old, but the edge is still good.",
+        ]);
+        assert_eq!(run("use rusty_sword"), ["You can't use the Rusty Sword like that."]);
+        run("go cellar");
+        assert_eq!(run("attack"), ["You hit the Rat King for 10. The Rat King hits you for 7 (13/20 HP)."]);
+        assert_eq!(run("use potion"), ["You use the Healing Potion (20/20 HP). The Rat King hits you for 7 (13/20 HP)."]);
+        assert_eq!(run("use potion"), ["You aren't carrying a potion."]);
+        assert_eq!(run("defend"), ["You raise your guard. The Rat King hits you for 2 (11/20 HP)."]);
+        // Levelling up asks where to spend the points it brings
+        assert_eq!(run("attack"), [
+            "You hit the Rat King for 7. The Rat King is beaten! You gain 20 XP. You reach level 2! Max HP +5.",
+            "Spend a point on attack, defense or health (+3 HP)? (1 left: a/d/h)",
+        ]);
+        assert_eq!(run("x")[0], "Choose a (attack), d (defense) or h (health)");
+        assert_eq!(run("a"), ["Your attack goes up by 1."]);
+        assert_eq!(run("stats"), ["Wren, level 2", "HP 16/25, attack 6, defense 1", "XP 26 (level 3 at 30)"]);
+        assert_eq!(run("spend"), ["You have no points to spend."]);
+        assert_eq!(run("flee"), ["There's nothing to run from."]);
+
+        // A player who's beaten wakes up at the start, healed
+        overwrite("/rpg/lantern_road/!!player.toml", "name = \"Pip\"\nhp = 3\nattack = 0\ndefense = 0\ndamage = \"1d2\"\nstart = \"village\"\n");
+        run("quit");
+        run("rpg start");
+        run("go forest");
+        assert_eq!(run("attack")[..3], ["You hit the Grey Wolf for 1. The Grey Wolf hits you for 3 (0/3 HP).", "", "You are beaten, and wake up back where you started."]);
+        assert_eq!(crate::commands::rpg::prompt(), "[village] rpg>");
+        assert_eq!(run("inventory")[0], "Pip: 3/3 HP, attack 0, defense 0");
+
+        // Fleeing goes back the way the player came, leaving the enemy for later
+        overwrite("/rpg/lantern_road/!!player.toml", "name = \"Pip\"\nhp = 50\nattack = 0\ndefense = 0\ndamage = \"1d2\"\nstart = \"village\"\n");
+        run("quit");
+        run("rpg start");
+        run("go forest");
+        let escape = (0..10).map(|_| run("flee")).find(|result| result[0] == "You get away.").unwrap();
+        assert_eq!(escape[2], "Ashby");
+        assert_eq!(crate::commands::rpg::prompt(), "[village] rpg>");
+        assert_eq!(run("go forest").last().unwrap(), "The Grey Wolf attacks! Thin and hungry, and not afraid of you. (attack, defend, flee or use an item)");
+    }
+
+    #[test]
+    fn test_rpg_saves() {
+        enable(1);
+        run("features rpg on");
+        assert_eq!(run("rpg saves"), ["No saved games (type save while playing to save one)"]);
+        assert_eq!(run("rpg load nothing"), ["rpg: nothing: No such save (rpg saves lists them)"]);
+        run("rpg start");
+        run("take potion");
+        run("go forest");
+        assert_eq!(run("save"), ["You can't do that in the middle of a fight! (attack, defend, flee or use an item)"]);
+        run("attack");
+        assert_eq!(run("save ../up"), ["../up: Save slots are named with letters, digits, - and _"]);
+        assert_eq!(run("save"), ["Saved to quicksave (rpg load quicksave carries on from here)."]);
+        run("go old_mill");
+        assert_eq!(run("save mill"), ["Saved to mill (rpg load mill carries on from here)."]);
+        run("quit");
+        assert_eq!(run("rpg saves"), ["mill", "quicksave"]);
+
+        // Saves are files, so they go along with the session
+        let session = crate::commands::export_session();
+        enable(1);
+        run("features rpg on");
+        block_on(crate::commands::import_session(session, true, &OnConflict::Overwrite));
+        assert_eq!(run("rpg load quicksave")[..3], ["You carry on as Wren from quicksave.", "", "Lantern Road"]);
+        assert_eq!(crate::commands::rpg::prompt(), "[forest] rpg>");
+        // The wolf stays beaten and the potion stays taken
+        assert_eq!(run("look").last().unwrap(), "Paths lead to: village, old_mill");
+        assert_eq!(run("inventory")[1], "  Healing Potion (potion) - A stoppered bottle of something red. It smells of mint.");
+        run("go village");
+        assert!(!run("look").iter().any(|line| line.starts_with("You see")));
+        run("quit");
+
+        crate::write_file("/saves/broken.toml", "hp = 3\n".to_string()).unwrap();
+        assert_eq!(run("rpg load broken"), ["rpg: /saves/broken.toml: missing field `world`"]);
+    }
+
+    #[test]
+    fn test_rpg_load_checks_the_level() {
+        enable(1);
+        run("features rpg on");
+        let save = |level: u32| format!("world = \"/rpg/lantern_road\"\nlocation = \"village\"\nhp = 10\nlevel = {}\n", level);
+        crate::write_file("/saves/zero.toml", save(0)).unwrap();
+        assert_eq!(run("rpg load zero"), ["rpg: /saves/zero.toml: Level 0 isn't one of this world's (1 to 3)"]);
+        crate::write_file("/saves/high.toml", save(4)).unwrap();
+        assert_eq!(run("rpg load high"), ["rpg: /saves/high.toml: Level 4 isn't one of this world's (1 to 3)"]);
+        assert!(!crate::commands::rpg::is_playing());
+
+        crate::write_file("/saves/top.toml", save(3)).unwrap();
+        assert_eq!(run("rpg load top")[0], "You carry on as Wren from top.");
+        run("quit");
+    }
+
+    #[test]
+    fn test_rpg_dialogue() {
+        enable(1);
+        run("features rpg on");
+        run("rpg start");
+        assert_eq!(run("talk"), ["Talk to whom?"]);
+        assert_eq!(run("talk to nobody"), ["There's no nobody here to talk to."]);
+        let greeting = "Old Maud: Evening. Not many come down the Lantern Road since the lights went out.";
+        assert_eq!(run("talk maud"), [format!("{}\n  1. Why did the lanterns go out?\n  2. Goodbye.\nWhat do you say? (1-2)", greeting)]);
+        assert_eq!(run("3")[0], "Choose a number from 1 to 2");
+        assert_eq!(run("2"), ["You leave Old Maud be."]);
+
+        // Asking about the lanterns sets a flag, which offers a new choice
+        run("talk to Old Maud");
+        assert!(run("1")[0].ends_with("  1. I'll find out what's taking it.\n  2. That's a shame.\nWhat do you say? (1-2)"));
+        assert_eq!(run("2"), [format!("{}\n  1. Why did the lanterns go out?\n  2. Where does the oil go?\n  3. Goodbye.\nWhat do you say? (1-3)", greeting)]);
+        assert_eq!(run("2"), ["Old Maud: Follow the squeaking. Rats love lantern oil, and there's an old mill past the forest."]);
+
+        // Flags are saved with the game
+        run("save");
+        let saved = block_on(crate::filesystem::helpers::get_file_content(&FilePath::parse("/saves/quicksave.toml", &DirPath::root()))).unwrap();
+        assert!(saved.contains("flags = [\"asked_about_lanterns\"]"), "{}", saved);
+
+        // Escape ends a conversation, but not the game
+        run("talk maud");
+        crate::cancel_input();
+        assert_eq!(crate::js_interop::take_output(), ["Cancelled."]);
+        assert_eq!(run("look")[0], "Ashby");
+
+        overwrite("/rpg/lantern_road/npcs/maud.toml", "name = \"Maud\"\n[nodes.start]\ntext = \"Hi\"\nchoices = [{ text = \"Go\", next = \"gone\" }]\n");
+        assert_eq!(run("talk maud"), ["rpg: npcs/maud.toml: nodes.start: No node called 'gone'"]);
+    }
+
+    #[test]
+    fn test_rpg_quests() {
+        enable(1);
+        run("features rpg on");
+        run("rpg start");
+        assert_eq!(run("quests"), ["You haven't taken on any quests."]);
+        // Promise Maud to find the oil
+        run("talk maud");
+        run("1");
+        run("1");
+        assert_eq!(run("quests"), [
+            "Active:",
+            "  Lights Out - Find out what's taking Ashby's lantern oil.",
+            "    [ ] Search the old mill",
+            "    [ ] Deal with whatever's in the cellar",
+        ]);
+        run("go forest");
+        run("attack");
+        assert_eq!(run("go old_mill").last().unwrap(), "Paths lead to: forest, cellar");
+        assert_eq!(run("quests")[2], "    [x] Search the old mill");
+        run("go cellar");
+        let won = (0..10).map(|_| run("attack")).find(|result| result[0].contains("The Rat King is beaten!")).unwrap();
+        assert_eq!(won[2], "Quest complete: Lights Out! The oil can go back up the road, and the lanterns will be lit again.");
+        run("h");
+        assert_eq!(run("quests"), ["Completed:", "  Lights Out"]);
+
+        // The reward's flag opens up more to say
+        run("go old_mill");
+        run("go forest");
+        run("go village");
+        assert!(run("talk maud")[0].contains("3. The Rat King won't be taking any more oil."));
+        assert_eq!(run("3"), ["Old Maud: A rat? With a crown? Well, I never. Ashby owes you, traveller."]);
+
+        crate::write_file("/rpg/lantern_road/quests/broken.toml", "name = \"B\"\n[[objectives]]\ntext = \"Nothing\"\n".to_string()).unwrap();
+        assert_eq!(run("quests"), ["rpg: quests/broken.toml: objectives 1: Needs one of visit, defeat or take"]);
+    }
+
+    #[test]
+    fn test_rpg_quests_count_what_was_done_before() {
+        enable(1);
+        run("features rpg on");
+        crate::write_file("/rpg/lantern_road/quests/potion.toml", "name = \"Stock Up\"\n[[objectives]]\ntext = \"Find a potion\"\ntake = \"potion\"\n[reward]\nitems = [\"rusty_sword\"]\n".to_string()).unwrap();
+        crate::write_file("/rpg/lantern_road/quests/after.toml", "name = \"Then Explore\"\nafter = [\"potion\"]\n[[objectives]]\ntext = \"See the village\"\nvisit = \"village\"\n".to_string()).unwrap();
+        run("rpg start");
+        assert_eq!(run("take potion"), [
+            "You take the Healing Potion.",
+            "",
+            "Quest complete: Stock Up! You get: Rusty Sword.",
+            "Quest complete: Then Explore!",
+        ]);
+        assert_eq!(run("inventory")[0], "Wren: 20/20 HP, attack 5, defense 1");
+    }
+}
@@ -0,0 +1,150 @@
+//! Quests: things to do, from the world's quests/ directory. A quest can be
+//! taken on once the flags it requires are set and the quests it comes after
+//! are done. Its objectives are checked against what the player has done
+//! (where they've been, what they've beaten and taken), after each move,
+//! win and pickup, so something done before the quest was taken on counts.
+//! Completing a quest gives its reward.
+
+use super::state::State;
+use super::world::{ObjectiveSpec, QuestSpec};
+use super::{whereabouts, with_game};
+
+/// Whether the player has done what an objective asks
+pub fn done(objective: &ObjectiveSpec, state: &State) -> bool {
+    // Beaten and taken are kept by "location/name"
+    let anywhere = |names: &std::collections::BTreeSet<String>, name: &str| {
+        names.iter().any(|key| key.rsplit('/').next() == Some(name))
+    };
+    match (&objective.visit, &objective.defeat, &objective.take) {
+        (Some(location), _, _) => state.visited.contains(location),
+        (_, Some(enemy), _) => anywhere(&state.defeated, enemy),
+        (_, _, Some(item)) => anywhere(&state.taken, item),
+        _ => false,
+    }
+}
+
+/// Whether a quest can be taken on (it may be done already)
+pub fn available(quest: &QuestSpec, state: &State) -> bool {
+    quest.requires.iter().all(|flag| state.flags.contains(flag))
+        && quest.after.iter().all(|name| state.completed.contains(name))
+}
+
+/// The quests taken on, and those done
+pub async fn quests() -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    let quests = match world.quests().await {
+        Ok(quests) => quests,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let Some(state) = with_game(|game| game.state.clone()) else {
+        return String::new();
+    };
+    let (mut active, mut completed) = (Vec::new(), Vec::new());
+    for (name, quest) in &quests {
+        if state.completed.contains(name) {
+            completed.push(format!("  {}", quest.name));
+        } else if available(quest, &state) {
+            active.push(match quest.description.trim() {
+                "" => format!("  {}", quest.name),
+                description => format!("  {} - {}", quest.name, description),
+            });
+            for objective in &quest.objectives {
+                let mark = if done(objective, &state) { "x" } else { " " };
+                active.push(format!("    [{}] {}", mark, objective.text));
+            }
+        }
+    }
+    if active.is_empty() && completed.is_empty() {
+        return "You haven't taken on any quests.".to_string();
+    }
+    let mut lines = Vec::new();
+    if !active.is_empty() {
+        lines.push("Active:".to_string());
+        lines.extend(active);
+    }
+    if !completed.is_empty() {
+        lines.push("Completed:".to_string());
+        lines.extend(completed);
+    }
+    lines.join("\n")
+}
+
+/// Complete any quests whose objectives are all done, giving their rewards.
+/// Returns what was completed (empty if nothing was).
+pub async fn progress() -> String {
+    let Some((world, _)) = whereabouts() else {
+        return String::new();
+    };
+    let quests = match world.quests().await {
+        Ok(quests) => quests,
+        Err(e) => return format!("rpg: {}", e),
+    };
+    let mut announcements = Vec::new();
+    // A reward can make another quest available, and complete it too
+    loop {
+        let Some(state) = with_game(|game| game.state.clone()) else {
+            return String::new();
+        };
+        let Some((name, quest)) = quests.iter().find(|(name, quest)| {
+            !state.completed.contains(name)
+                && available(quest, &state)
+                && quest.objectives.iter().all(|objective| done(objective, &state))
+        }) else {
+            break;
+        };
+        let mut announcement = format!("Quest complete: {}!", quest.name);
+        if !quest.reward.text.trim().is_empty() {
+            announcement.push(' ');
+            announcement.push_str(quest.reward.text.trim());
+        }
+        let mut items = Vec::new();
+        for item in &quest.reward.items {
+            match world.item(item).await {
+                Ok(spec) => items.push(spec.name),
+                Err(e) => return format!("rpg: {}", e),
+            }
+        }
+        if !items.is_empty() {
+            announcement.push_str(&format!(" You get: {}.", items.join(", ")));
+        }
+        with_game(|game| {
+            game.state.completed.insert(name.clone());
+            game.state.inventory.extend(quest.reward.items.iter().cloned());
+            game.state.flags.extend(quest.reward.sets.iter().cloned());
+        });
+        announcements.push(announcement);
+    }
+    announcements.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn objective(visit: Option<&str>, defeat: Option<&str>, take: Option<&str>) -> ObjectiveSpec {
+        ObjectiveSpec {
+            text: String::new(),
+            visit: visit.map(str::to_string),
+            defeat: defeat.map(str::to_string),
+            take: take.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_objectives_and_prerequisites() {
+        let mut state: State = toml::from_str("world = \"/w\"\nlocation = \"mill\"\nhp = 1\nvisited = [\"mill\"]\ndefeated = [\"cellar/rat\"]\n").unwrap();
+        assert!(done(&objective(Some("mill"), None, None), &state));
+        assert!(!done(&objective(Some("cellar"), None, None), &state));
+        assert!(done(&objective(None, Some("rat"), None), &state));
+        assert!(!done(&objective(None, None, Some("rat")), &state));
+
+        let quest: QuestSpec = toml::from_str("name = \"Q\"\nrequires = [\"asked\"]\nafter = [\"first\"]\nobjectives = []\n").unwrap();
+        assert!(!available(&quest, &state));
+        state.flags.insert("asked".to_string());
+        assert!(!available(&quest, &state));
+        state.completed.insert("first".to_string());
+        assert!(available(&quest, &state));
+    }
+}
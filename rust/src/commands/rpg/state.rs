@@ -0,0 +1,138 @@
+//! Where a game stands: everything about it but the world, which is read
+//! from its directory again when a saved game is loaded. Games are saved
+//! as TOML in /saves/SLOT.toml, which goes along with the session.
+
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+use crate::filesystem::{DirPath, FilePath, NextDir};
+use super::world::PlayerSpec;
+
+pub const SAVES_DIR: &str = "saves";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    /// The world's directory
+    pub world: String,
+    pub location: String,
+    /// Where the player came from, to flee back to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    pub hp: u32,
+    #[serde(default)]
+    pub xp: u32,
+    #[serde(default = "first_level")]
+    pub level: u32,
+    /// What's been gained by levelling up, on top of the player's own stats
+    #[serde(default)]
+    pub growth: Growth,
+    /// Points from levelling up still to spend
+    #[serde(default)]
+    pub points: u32,
+    /// Items carried, by name in the world
+    #[serde(default)]
+    pub inventory: Vec<String>,
+    /// Items taken and enemies beaten, as "location/name", so they stay gone
+    #[serde(default)]
+    pub taken: BTreeSet<String>,
+    #[serde(default)]
+    pub defeated: BTreeSet<String>,
+    /// Locations the player has been to
+    #[serde(default)]
+    pub visited: BTreeSet<String>,
+    /// Quests done, by name
+    #[serde(default)]
+    pub completed: BTreeSet<String>,
+    /// Things that have happened, which can change what happens next
+    #[serde(default)]
+    pub flags: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Growth {
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+}
+
+fn first_level() -> u32 {
+    1
+}
+
+impl State {
+    /// A new game in the world at `world`
+    pub fn new(world: &DirPath, player: &PlayerSpec) -> State {
+        State {
+            world: world.to_string(),
+            location: player.start.clone(),
+            previous: None,
+            hp: player.hp,
+            xp: 0,
+            level: first_level(),
+            growth: Growth::default(),
+            points: 0,
+            inventory: Vec::new(),
+            taken: BTreeSet::new(),
+            defeated: BTreeSet::new(),
+            visited: BTreeSet::new(),
+            completed: BTreeSet::new(),
+            flags: BTreeSet::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<State, String> {
+        toml::from_str(text).map_err(|e: toml::de::Error| e.message().trim_end().to_string())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap_or_default()
+    }
+}
+
+pub fn saves_dir() -> DirPath {
+    DirPath(vec![NextDir::In(SAVES_DIR.to_string())])
+}
+
+/// The file a slot is saved in. Slots are named with letters, digits, - and _.
+pub fn save_path(slot: &str) -> Result<FilePath, String> {
+    if slot.is_empty() || !slot.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("{}: Save slots are named with letters, digits, - and _", slot));
+    }
+    Ok(FilePath::new(saves_dir(), format!("{}.toml", slot)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut state = State {
+            world: "/rpg/lantern_road".to_string(),
+            location: "forest".to_string(),
+            previous: Some("village".to_string()),
+            hp: 12,
+            xp: 14,
+            level: 2,
+            growth: Growth { hp: 5, attack: 1, defense: 0 },
+            points: 1,
+            inventory: vec!["potion".to_string()],
+            taken: BTreeSet::from(["village/potion".to_string()]),
+            defeated: BTreeSet::new(),
+            visited: BTreeSet::from(["village".to_string(), "forest".to_string()]),
+            completed: BTreeSet::new(),
+            flags: BTreeSet::new(),
+        };
+        assert_eq!(State::parse(&state.to_toml()), Ok(state.clone()));
+        state.previous = None;
+        assert!(!state.to_toml().contains("previous"));
+        assert_eq!(State::parse("world = \"/w\"\nlocation = \"x\"\nhp = 3\n").unwrap().level, 1);
+        assert!(State::parse("world = \"/w\"\n").unwrap_err().contains("missing field"));
+    }
+
+    #[test]
+    fn test_save_path() {
+        assert_eq!(save_path("slot-1").unwrap().to_string(), "/saves/slot-1.toml");
+        assert!(save_path("../escape").is_err());
+        assert!(save_path("").is_err());
+    }
+}
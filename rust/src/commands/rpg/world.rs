@@ -0,0 +1,323 @@
+//! A world as rpg-generator lays it out, read from the filesystem.
+//! Locations are named by their directories, and enemies and items by their
+//! files; each is read when it's first needed.
+
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use crate::filesystem::{DirPath, FilePath, NextDir};
+use crate::filesystem::helpers::{dir_exists, get_contents, get_directories, get_file_content};
+
+pub const PLAYER_FILE: &str = "!!player.toml";
+pub const LOCATION_FILE: &str = "!!location.toml";
+pub const CONNECTIONS_FILE: &str = "!!connections.txt";
+pub const CONNECTIONS_TOML_FILE: &str = "!!connections.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerSpec {
+    pub name: String,
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+    /// Dice rolled for each hit, e.g. "1d6"
+    pub damage: String,
+    /// The location the game starts in
+    pub start: String,
+    #[serde(default)]
+    pub intro: String,
+    /// The levels after the first, in order
+    #[serde(default)]
+    pub levels: Vec<LevelSpec>,
+}
+
+/// A level: the XP it takes, and what the player gains on reaching it
+#[derive(Debug, Clone, Deserialize)]
+pub struct LevelSpec {
+    pub xp: u32,
+    #[serde(default)]
+    pub hp: u32,
+    #[serde(default)]
+    pub attack: u32,
+    #[serde(default)]
+    pub defense: u32,
+    /// Points for the player to spend on whichever stats they like
+    #[serde(default)]
+    pub points: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocationSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Enemies here, fought in turn as the player arrives
+    #[serde(default)]
+    pub enemies: Vec<String>,
+    /// Items lying here to be taken
+    #[serde(default)]
+    pub items: Vec<String>,
+    /// People here to talk to
+    #[serde(default)]
+    pub npcs: Vec<String>,
+}
+
+/// A way from one location to another. Ways only go one way: the location
+/// at the other end lists its own.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConnectionSpec {
+    pub to: String,
+    /// Shown on the way
+    #[serde(default)]
+    pub description: String,
+    /// An item the player must carry to go this way
+    pub key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConnectionsSpec {
+    #[serde(default)]
+    connections: Vec<ConnectionSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemySpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+    pub damage: String,
+    /// Experience for beating it
+    #[serde(default)]
+    pub xp: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Added to the player's attack while carried (weapons)
+    #[serde(default)]
+    pub attack: u32,
+    /// Added to the player's defense while carried (armour)
+    #[serde(default)]
+    pub defense: u32,
+    /// Health restored when used, which uses it up (potions)
+    #[serde(default)]
+    pub heal: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NpcSpec {
+    pub name: String,
+    /// The node a conversation starts at
+    #[serde(default = "default_start")]
+    pub start: String,
+    pub nodes: BTreeMap<String, NodeSpec>,
+}
+
+fn default_start() -> String {
+    "start".to_string()
+}
+
+/// One thing an NPC says, and what the player can say back
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeSpec {
+    pub text: String,
+    /// Flags set when this is said
+    #[serde(default)]
+    pub sets: Vec<String>,
+    /// No choices (or none the player can make) ends the conversation
+    #[serde(default)]
+    pub choices: Vec<ChoiceSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChoiceSpec {
+    pub text: String,
+    /// The node this leads to; none ends the conversation
+    pub next: Option<String>,
+    /// Flags that must all be set for the choice to be offered
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Flags any of which hides the choice
+    #[serde(default)]
+    pub unless: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuestSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Flags that must be set for the quest to be taken on
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Quests that must be completed first
+    #[serde(default)]
+    pub after: Vec<String>,
+    pub objectives: Vec<ObjectiveSpec>,
+    #[serde(default)]
+    pub reward: RewardSpec,
+}
+
+/// Something to do for a quest: one of going somewhere, beating an enemy or taking an item
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObjectiveSpec {
+    pub text: String,
+    pub visit: Option<String>,
+    pub defeat: Option<String>,
+    pub take: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RewardSpec {
+    /// Shown when the quest is completed
+    #[serde(default)]
+    pub text: String,
+    /// Items given to the player
+    #[serde(default)]
+    pub items: Vec<String>,
+    /// Flags set
+    #[serde(default)]
+    pub sets: Vec<String>,
+}
+
+pub struct World {
+    /// The directory the world was loaded from
+    pub dir: DirPath,
+    pub player: PlayerSpec,
+}
+
+pub fn subdir(dir: &DirPath, name: &str) -> DirPath {
+    let mut dir = dir.clone();
+    dir.cd(&NextDir::In(name.to_string()), true);
+    dir
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(text: &str, path: &str) -> Result<T, String> {
+    toml::from_str(text).map_err(|e: toml::de::Error| format!("{}: {}", path, e.message().trim_end()))
+}
+
+impl World {
+    pub async fn load(dir: &DirPath) -> Result<World, String> {
+        if !dir_exists(dir).await {
+            return Err("No such directory".to_string());
+        }
+        let player = get_file_content(&FilePath::new(dir.clone(), PLAYER_FILE.to_string())).await
+            .map_err(|_| format!("{}: No such file (is this a world?)", PLAYER_FILE))?;
+        let player: PlayerSpec = parse(&player, PLAYER_FILE)?;
+        crate::dice::parse(&player.damage).map_err(|e| format!("{}: damage: {}", PLAYER_FILE, e))?;
+        if player.levels.windows(2).any(|pair| pair[0].xp >= pair[1].xp) {
+            return Err(format!("{}: levels: Each level needs more XP than the last", PLAYER_FILE));
+        }
+
+        let locations_dir = subdir(dir, "locations");
+        let locations: Vec<String> = match dir_exists(&locations_dir).await {
+            true => get_directories(&locations_dir).await.0.into_iter().collect(),
+            false => Vec::new(),
+        };
+        if !locations.contains(&player.start) {
+            return Err(format!("{}: start: No location called '{}'", PLAYER_FILE, player.start));
+        }
+        Ok(World { dir: dir.clone(), player })
+    }
+
+    pub fn location_dir(&self, location: &str) -> DirPath {
+        subdir(&subdir(&self.dir, "locations"), location)
+    }
+
+    /// A location's !!location.toml
+    pub async fn location(&self, location: &str) -> Result<LocationSpec, String> {
+        let path = format!("locations/{}/{}", location, LOCATION_FILE);
+        let text = get_file_content(&FilePath::new(self.location_dir(location), LOCATION_FILE.to_string())).await
+            .map_err(|_| format!("{}: No such file", path))?;
+        parse(&text, &path)
+    }
+
+    /// An enemy's enemies/NAME.toml
+    pub async fn enemy(&self, name: &str) -> Result<EnemySpec, String> {
+        let path = format!("enemies/{}.toml", name);
+        let enemy: EnemySpec = self.entity("enemies", name).await?;
+        crate::dice::parse(&enemy.damage).map_err(|e| format!("{}: damage: {}", path, e))?;
+        Ok(enemy)
+    }
+
+    /// An NPC's npcs/NAME.toml, with every node it leads to checked
+    pub async fn npc(&self, name: &str) -> Result<NpcSpec, String> {
+        let path = format!("npcs/{}.toml", name);
+        let npc: NpcSpec = self.entity("npcs", name).await?;
+        if !npc.nodes.contains_key(&npc.start) {
+            return Err(format!("{}: start: No node called '{}'", path, npc.start));
+        }
+        for (id, node) in &npc.nodes {
+            if let Some(next) = node.choices.iter().filter_map(|choice| choice.next.as_ref()).find(|next| !npc.nodes.contains_key(*next)) {
+                return Err(format!("{}: nodes.{}: No node called '{}'", path, id, next));
+            }
+        }
+        Ok(npc)
+    }
+
+    /// The names of everything of a kind ("enemies", "items", ...), from its directory's files
+    pub async fn names(&self, kind: &str) -> Vec<String> {
+        let dir = subdir(&self.dir, kind);
+        if !dir_exists(&dir).await {
+            return Vec::new();
+        }
+        let mut names: Vec<String> = get_contents(&dir).await.0.into_keys()
+            .filter_map(|file| file.strip_suffix(".toml").map(str::to_string))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Every quest in quests/, by name
+    pub async fn quests(&self) -> Result<Vec<(String, QuestSpec)>, String> {
+        let mut quests = Vec::new();
+        for name in self.names("quests").await {
+            let quest: QuestSpec = self.entity("quests", &name).await?;
+            for (i, objective) in quest.objectives.iter().enumerate() {
+                let kinds = [&objective.visit, &objective.defeat, &objective.take].iter().filter(|kind| kind.is_some()).count();
+                if kinds != 1 {
+                    return Err(format!("quests/{}.toml: objectives {}: Needs one of visit, defeat or take", name, i + 1));
+                }
+            }
+            quests.push((name, quest));
+        }
+        Ok(quests)
+    }
+
+    /// An item's items/NAME.toml
+    pub async fn item(&self, name: &str) -> Result<ItemSpec, String> {
+        // Loot from the cave of dice's treasure chests isn't in the world's items
+        if let Some(item) = super::cave::loot_item(name) {
+            return Ok(item);
+        }
+        self.entity("items", name).await
+    }
+
+    async fn entity<T: for<'de> Deserialize<'de>>(&self, kind: &str, name: &str) -> Result<T, String> {
+        let path = format!("{}/{}.toml", kind, name);
+        let text = get_file_content(&FilePath::new(subdir(&self.dir, kind), format!("{}.toml", name))).await
+            .map_err(|_| format!("{}: No such file", path))?;
+        parse(&text, &path)
+    }
+
+    /// The ways on from `location`: its !!connections.toml, or failing that
+    /// its !!connections.txt (one location a line)
+    pub async fn connections(&self, location: &str) -> Result<Vec<ConnectionSpec>, String> {
+        let dir = self.location_dir(location);
+        if let Ok(text) = get_file_content(&FilePath::new(dir.clone(), CONNECTIONS_TOML_FILE.to_string())).await {
+            let path = format!("locations/{}/{}", location, CONNECTIONS_TOML_FILE);
+            return parse::<ConnectionsSpec>(&text, &path).map(|spec| spec.connections);
+        }
+        Ok(get_file_content(&FilePath::new(dir, CONNECTIONS_FILE.to_string())).await
+            .map(|text| text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| ConnectionSpec { to: line.to_string(), description: String::new(), key: None })
+                .collect())
+            .unwrap_or_default())
+    }
+}
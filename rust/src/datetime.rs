@@ -0,0 +1,168 @@
+//! Dates and times for `date` (and anything else showing a clock): a
+//! moment broken down in some timezone, formatted strftime-style.
+
+use crate::filesystem::helpers::civil_from_days;
+use crate::js_interop::{now, timezone_name, timezone_offset_minutes};
+
+const DAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+/// The format `date` uses when not given one, e.g. "Tue Nov 14 22:13:20 UTC 2023"
+pub const DEFAULT_FORMAT: &str = "%a %b %e %H:%M:%S %Z %Y";
+
+pub struct DateTime {
+    /// Milliseconds since the epoch
+    pub ms: f64,
+    /// Minutes ahead of UTC
+    pub offset: i64,
+    pub zone: String,
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    /// 0 for Sunday
+    pub weekday: i64,
+    /// 1 for January 1st
+    pub day_of_year: i64,
+}
+
+impl DateTime {
+    /// `ms` as seen in a timezone `offset` minutes ahead of UTC
+    pub fn at(ms: f64, offset: i64, zone: &str) -> DateTime {
+        let secs = (ms / 1000.0).floor() as i64 + offset * 60;
+        let days = secs.div_euclid(86400);
+        let time = secs.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let before_month = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334][month as usize - 1];
+        let day_of_year = before_month + day + if leap && month > 2 { 1 } else { 0 };
+        DateTime {
+            ms,
+            offset,
+            zone: zone.to_string(),
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time % 3600 / 60,
+            second: time % 60,
+            weekday: (days + 4).rem_euclid(7),  // 1970-01-01 was a Thursday
+            day_of_year,
+        }
+    }
+
+    /// Now, in the browser's timezone
+    pub fn local() -> DateTime {
+        let ms = now();
+        DateTime::at(ms, timezone_offset_minutes(ms), &timezone_name())
+    }
+
+    /// Now, in UTC
+    pub fn utc() -> DateTime {
+        DateTime::at(now(), 0, "UTC")
+    }
+
+    /// `format` with each `%` directive replaced, as strftime does. Unknown
+    /// directives are left as they are.
+    pub fn format(&self, format: &str) -> String {
+        let mut out = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let Some(directive) = chars.next() else {
+                out.push('%');
+                break;
+            };
+            match directive {
+                'Y' => out.push_str(&self.year.to_string()),
+                'C' => out.push_str(&format!("{:02}", self.year.div_euclid(100))),
+                'y' => out.push_str(&format!("{:02}", self.year.rem_euclid(100))),
+                'm' => out.push_str(&format!("{:02}", self.month)),
+                'd' => out.push_str(&format!("{:02}", self.day)),
+                'e' => out.push_str(&format!("{:2}", self.day)),
+                'j' => out.push_str(&format!("{:03}", self.day_of_year)),
+                'H' => out.push_str(&format!("{:02}", self.hour)),
+                'k' => out.push_str(&format!("{:2}", self.hour)),
+                'I' => out.push_str(&format!("{:02}", self.hour_12())),
+                'l' => out.push_str(&format!("{:2}", self.hour_12())),
+                'M' => out.push_str(&format!("{:02}", self.minute)),
+                'S' => out.push_str(&format!("{:02}", self.second)),
+                'p' => out.push_str(if self.hour < 12 { "AM" } else { "PM" }),
+                'P' => out.push_str(if self.hour < 12 { "am" } else { "pm" }),
+                'A' => out.push_str(DAYS[self.weekday as usize]),
+                'a' => out.push_str(&DAYS[self.weekday as usize][..3]),
+                'B' => out.push_str(MONTHS[self.month as usize - 1]),
+                'b' | 'h' => out.push_str(&MONTHS[self.month as usize - 1][..3]),
+                'u' => out.push_str(&(if self.weekday == 0 { 7 } else { self.weekday }).to_string()),
+                'w' => out.push_str(&self.weekday.to_string()),
+                's' => out.push_str(&((self.ms / 1000.0).floor() as i64).to_string()),
+                'Z' => out.push_str(&self.zone),
+                'z' => {
+                    let sign = if self.offset < 0 { '-' } else { '+' };
+                    out.push_str(&format!("{}{:02}{:02}", sign, self.offset.abs() / 60, self.offset.abs() % 60));
+                }
+                'F' => out.push_str(&self.format("%Y-%m-%d")),
+                'D' => out.push_str(&self.format("%m/%d/%y")),
+                'T' => out.push_str(&self.format("%H:%M:%S")),
+                'R' => out.push_str(&self.format("%H:%M")),
+                'r' => out.push_str(&self.format("%I:%M:%S %p")),
+                'c' => out.push_str(&self.format("%a %b %e %H:%M:%S %Y")),
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                '%' => out.push('%'),
+                other => {
+                    out.push('%');
+                    out.push(other);
+                }
+            }
+        }
+        out
+    }
+
+    fn hour_12(&self) -> i64 {
+        match self.hour % 12 {
+            0 => 12,
+            hour => hour,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOMENT: f64 = 1_700_000_000_000.0;  // 2023-11-14 22:13:20 UTC
+
+    #[test]
+    fn test_format() {
+        let utc = DateTime::at(MOMENT, 0, "UTC");
+        assert_eq!(utc.format(DEFAULT_FORMAT), "Tue Nov 14 22:13:20 UTC 2023");
+        assert_eq!(utc.format("%F %T %z"), "2023-11-14 22:13:20 +0000");
+        assert_eq!(utc.format("%A %B %-d %j %u %w %s"), "Tuesday November %-d 318 2 2 1700000000");
+        assert_eq!(utc.format("%I:%M %p, %D, 100%%"), "10:13 PM, 11/14/23, 100%");
+        assert_eq!(utc.format("trailing %"), "trailing %");
+    }
+
+    #[test]
+    fn test_timezones() {
+        let tokyo = DateTime::at(MOMENT, 9 * 60, "Asia/Tokyo");
+        assert_eq!(tokyo.format("%c %Z %z"), "Wed Nov 15 07:13:20 2023 Asia/Tokyo +0900");
+        let newfoundland = DateTime::at(MOMENT, -(3 * 60 + 30), "America/St_Johns");
+        assert_eq!(newfoundland.format("%F %R %z"), "2023-11-14 18:43 -0330");
+    }
+
+    #[test]
+    fn test_day_of_year() {
+        // 2024 is a leap year
+        assert_eq!(DateTime::at(1_709_251_200_000.0, 0, "UTC").format("%F %j %a"), "2024-03-01 061 Fri");
+        assert_eq!(DateTime::at(1_735_603_200_000.0, 0, "UTC").format("%F %j"), "2024-12-31 366");
+    }
+}
@@ -0,0 +1,234 @@
+//! Dice notation: "3d6+2", "2d20kh1" (roll two, keep the highest), "d%".
+//! Used by `roll` and by the dice in the cave of dice.
+//!
+//! An expression is dice and whole numbers added or taken away. Dice are
+//! written `NdS` (N may be left out for one die, and `%` stands for 100)
+//! and may end in `khK`/`klK` to keep only the K highest or lowest, or
+//! `dhK`/`dlK` to drop them. K defaults to 1.
+
+use std::fmt;
+use crate::rng::random_range;
+
+pub const MAX_DICE: u32 = 100;
+pub const MAX_SIDES: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Dice { count: u32, sides: u32, keep: Option<Keep> },
+    Number(i64),
+}
+
+/// A parsed dice expression: terms, each added (true) or taken away (false)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub terms: Vec<(bool, Term)>,
+}
+
+/// The result of rolling an expression
+pub struct Outcome {
+    pub total: i64,
+    /// Each die rolled, with dropped dice in brackets, e.g. "[17, (3)] + 2"
+    pub detail: String,
+}
+
+/// One roll of a die with `sides` sides
+pub fn roll_die(sides: u32) -> u32 {
+    random_range(1..=sides)
+}
+
+pub fn parse(notation: &str) -> Result<Expression, String> {
+    let notation = notation.to_lowercase();
+    let mut rest = notation.as_str();
+    let mut terms = Vec::new();
+    let mut adding = match rest.strip_prefix('-') {
+        Some(after) => {
+            rest = after;
+            false
+        }
+        None => true,
+    };
+    loop {
+        let (term, after) = parse_term(rest)?;
+        terms.push((adding, term));
+        rest = after;
+        match rest.chars().next() {
+            None => break,
+            Some('+') => adding = true,
+            Some('-') => adding = false,
+            Some(c) => return Err(format!("Unexpected '{}'", c)),
+        }
+        rest = &rest[1..];
+    }
+    Ok(Expression { terms })
+}
+
+// A number or dice, and what follows it
+fn parse_term(text: &str) -> Result<(Term, &str), String> {
+    let (count, rest) = take_number(text);
+    let Some(rest) = rest.strip_prefix('d') else {
+        return match count {
+            Some(n) => Ok((Term::Number(n as i64), rest)),
+            None => Err(match text.chars().next() {
+                Some(c) => format!("Unexpected '{}'", c),
+                None => "Expected dice or a number".to_string(),
+            }),
+        };
+    };
+    let (sides, rest) = match rest.strip_prefix('%') {
+        Some(after) => (Some(100), after),
+        None => take_number(rest),
+    };
+    let sides = sides.ok_or("Expected the number of sides after 'd'")?;
+    let count = count.unwrap_or(1);
+    if count == 0 || count > MAX_DICE as u64 {
+        return Err(format!("Roll between 1 and {} dice at a time", MAX_DICE));
+    }
+    if sides == 0 || sides > MAX_SIDES as u64 {
+        return Err(format!("Dice have between 1 and {} sides", MAX_SIDES));
+    }
+    let (count, sides) = (count as u32, sides as u32);
+
+    let (keep, rest) = match ["kh", "kl", "dh", "dl"].iter().find(|suffix| rest.starts_with(**suffix)) {
+        Some(suffix) => {
+            let (n, after) = take_number(&rest[2..]);
+            let n = n.unwrap_or(1).min(u32::MAX as u64) as u32;
+            if n > count {
+                return Err(format!("Can't {} {} of {} dice", if suffix.starts_with('k') { "keep" } else { "drop" }, n, count));
+            }
+            let keep = match *suffix {
+                "kh" => Keep::Highest(n),
+                "kl" => Keep::Lowest(n),
+                "dh" => Keep::Lowest(count - n),
+                _ => Keep::Highest(count - n),
+            };
+            (Some(keep), after)
+        }
+        None => (None, rest),
+    };
+    Ok((Term::Dice { count, sides, keep }, rest))
+}
+
+fn take_number(text: &str) -> (Option<u64>, &str) {
+    let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    // Too large to mean anything: saturate, and let the limits reject it
+    let number = (digits > 0).then(|| text[..digits].parse().unwrap_or(u64::MAX));
+    (number, &text[digits..])
+}
+
+impl Expression {
+    pub fn roll(&self) -> Outcome {
+        let mut total = 0;
+        let mut detail = String::new();
+        for (i, (adding, term)) in self.terms.iter().enumerate() {
+            let (value, shown) = match term {
+                Term::Number(n) => (*n, n.to_string()),
+                Term::Dice { count, sides, keep } => roll_dice(*count, *sides, *keep),
+            };
+            total += if *adding { value } else { -value };
+            match (i, adding) {
+                (0, true) => {}
+                (0, false) => detail.push('-'),
+                (_, true) => detail.push_str(" + "),
+                (_, false) => detail.push_str(" - "),
+            }
+            detail.push_str(&shown);
+        }
+        Outcome { total, detail }
+    }
+}
+
+// The kept dice's total, and every die rolled (dropped ones in brackets)
+fn roll_dice(count: u32, sides: u32, keep: Option<Keep>) -> (i64, String) {
+    let rolls: Vec<u32> = (0..count).map(|_| roll_die(sides)).collect();
+    let mut order: Vec<usize> = (0..rolls.len()).collect();
+    order.sort_by_key(|&i| rolls[i]);
+    let kept: Vec<usize> = match keep {
+        None => order,
+        Some(Keep::Lowest(n)) => order[..n as usize].to_vec(),
+        Some(Keep::Highest(n)) => order[order.len() - n as usize..].to_vec(),
+    };
+    let total = kept.iter().map(|&i| rolls[i] as i64).sum();
+    let shown: Vec<String> = rolls.iter().enumerate()
+        .map(|(i, roll)| match kept.contains(&i) {
+            true => roll.to_string(),
+            false => format!("({})", roll),
+        })
+        .collect();
+    (total, format!("[{}]", shown.join(", ")))
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (adding, term)) in self.terms.iter().enumerate() {
+            if !adding {
+                write!(f, "-")?;
+            } else if i > 0 {
+                write!(f, "+")?;
+            }
+            match term {
+                Term::Number(n) => write!(f, "{}", n)?,
+                Term::Dice { count, sides, keep } => {
+                    write!(f, "{}d{}", count, sides)?;
+                    match keep {
+                        Some(Keep::Highest(n)) => write!(f, "kh{}", n)?,
+                        Some(Keep::Lowest(n)) => write!(f, "kl{}", n)?,
+                        None => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dice(count: u32, sides: u32, keep: Option<Keep>) -> Term {
+        Term::Dice { count, sides, keep }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("3d6+2").unwrap().terms, [(true, dice(3, 6, None)), (true, Term::Number(2))]);
+        assert_eq!(parse("2D20kh1").unwrap().terms, [(true, dice(2, 20, Some(Keep::Highest(1))))]);
+        assert_eq!(parse("4d6dl").unwrap().terms, [(true, dice(4, 6, Some(Keep::Highest(3))))]);
+        assert_eq!(parse("-d%-1d4").unwrap().terms, [(false, dice(1, 100, None)), (false, dice(1, 4, None))]);
+        assert_eq!(parse("4d6dl").unwrap().to_string(), "4d6kh3");
+        assert_eq!(parse("d8-1+2d4kl1").unwrap().to_string(), "1d8-1+2d4kl1");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("").err().unwrap(), "Expected dice or a number");
+        assert_eq!(parse("3x6").err().unwrap(), "Unexpected 'x'");
+        assert_eq!(parse("2d").err().unwrap(), "Expected the number of sides after 'd'");
+        assert_eq!(parse("3d6+").err().unwrap(), "Expected dice or a number");
+        assert_eq!(parse("0d6").err().unwrap(), "Roll between 1 and 100 dice at a time");
+        assert_eq!(parse("1d99999999999999999999").err().unwrap(), "Dice have between 1 and 1000 sides");
+        assert_eq!(parse("2d20kh3").err().unwrap(), "Can't keep 3 of 2 dice");
+        assert_eq!(parse("2d20dl3").err().unwrap(), "Can't drop 3 of 2 dice");
+    }
+
+    #[test]
+    fn test_roll() {
+        crate::rng::seed(3);
+        for _ in 0..50 {
+            let outcome = parse("4d6kh3+2").unwrap().roll();
+            assert!((5..=20).contains(&outcome.total));
+            // One die dropped, in brackets
+            assert_eq!(outcome.detail.matches('(').count(), 1);
+            assert!(outcome.detail.ends_with("] + 2"));
+        }
+        let outcome = parse("-3").unwrap().roll();
+        assert_eq!((outcome.total, outcome.detail.as_str()), (-3, "-3"));
+        assert_eq!(parse("5d1-2").unwrap().roll().detail, "[1, 1, 1, 1, 1] - 2");
+    }
+}
@@ -0,0 +1,175 @@
+//! Feature flags for experimental subsystems. A deployment switches them on in
+//! content/config/features.toml; a visitor can override any flag with the
+//! setting feature.NAME=on|off (see the `features` command).
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use crate::filesystem::file_paths::FEATURES_FILE_PATH;
+use crate::filesystem::helpers::get_file_content;
+use crate::settings::{get_setting, set_setting};
+
+/// Flags the terminal knows about, with what they switch on. All default to off.
+pub const KNOWN: &[(&str, &str)] = &[
+    ("games", "Terminal games"),
+    ("rpg", "The RPG engine"),
+];
+
+/// Commands that only run while a flag is on: (command, flag)
+const GATED_COMMANDS: &[(&str, &str)] = &[
+    ("typetest", "games"),
+    ("2048", "games"),
+    ("rpg", "rpg"),
+];
+
+/// Where a flag's current value comes from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Source {
+    Default,
+    Config,
+    Setting,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Default => "default",
+            Source::Config => "config",
+            Source::Setting => "setting",
+        }
+    }
+}
+
+struct Config {
+    flags: BTreeMap<String, bool>,
+    error: Option<String>,
+}
+
+thread_local! {
+    static CONFIG: RefCell<Config> = const { RefCell::new(Config { flags: BTreeMap::new(), error: None }) };
+}
+
+/// Flags from features.toml: `name = true|false`, top level or in a [features] table
+pub fn parse_flags(text: &str) -> Result<BTreeMap<String, bool>, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+    let table = match table.get("features") {
+        Some(toml::Value::Table(features)) => features.clone(),
+        _ => table,
+    };
+    table.into_iter()
+        .map(|(name, value)| match value {
+            toml::Value::Boolean(on) => Ok((name, on)),
+            _ => Err(format!("{}: expected true or false", name)),
+        })
+        .collect()
+}
+
+/// Read the config file (at startup). A missing file leaves every flag at its default.
+pub async fn load() {
+    let config = match get_file_content(&FEATURES_FILE_PATH).await {
+        Ok(text) => match parse_flags(&text) {
+            Ok(flags) => Config { flags, error: None },
+            Err(e) => Config { flags: BTreeMap::new(), error: Some(e) },
+        },
+        Err(_) => Config { flags: BTreeMap::new(), error: None },
+    };
+    CONFIG.with_borrow_mut(|current| *current = config);
+}
+
+/// The problem with features.toml, if it couldn't be parsed
+pub fn config_error() -> Option<String> {
+    CONFIG.with_borrow(|config| config.error.clone())
+}
+
+fn setting_key(name: &str) -> String {
+    format!("feature.{}", name)
+}
+
+/// A flag's value and where it came from
+pub fn flag(name: &str) -> (bool, Source) {
+    match get_setting(&setting_key(name)).as_deref() {
+        Some("on") => return (true, Source::Setting),
+        Some("off") => return (false, Source::Setting),
+        _ => {}
+    }
+    match CONFIG.with_borrow(|config| config.flags.get(name).copied()) {
+        Some(on) => (on, Source::Config),
+        None => (false, Source::Default),
+    }
+}
+
+pub fn is_enabled(name: &str) -> bool {
+    flag(name).0
+}
+
+/// The flag `command` needs, if it is gated and that flag is off
+pub fn disabled_flag_for(command: &str) -> Option<&'static str> {
+    GATED_COMMANDS.iter()
+        .find(|(gated, _)| *gated == command)
+        .map(|(_, flag)| *flag)
+        .filter(|flag| !is_enabled(flag))
+}
+
+/// Override a flag for this visitor (None goes back to the deployment's value)
+pub fn set_override(name: &str, on: Option<bool>) {
+    let value = match on {
+        Some(true) => "on",
+        Some(false) => "off",
+        None => "",
+    };
+    set_setting(&setting_key(name), value);
+}
+
+/// Every known flag plus any others in the config, sorted by name
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = KNOWN.iter().map(|(name, _)| name.to_string()).collect();
+    CONFIG.with_borrow(|config| names.extend(config.flags.keys().cloned()));
+    names.sort();
+    names.dedup();
+    names
+}
+
+pub fn description(name: &str) -> Option<&'static str> {
+    KNOWN.iter().find(|(known, _)| *known == name).map(|(_, description)| *description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+
+    #[test]
+    fn test_parse_flags() {
+        let flags = parse_flags("# Flags\nrpg = true\ngames = false\n").unwrap();
+        assert_eq!(flags.into_iter().collect::<Vec<_>>(), [("games".to_string(), false), ("rpg".to_string(), true)]);
+
+        let flags = parse_flags("[features]\ngames = true\n").unwrap();
+        assert_eq!(flags.get("games"), Some(&true));
+
+        assert_eq!(parse_flags("rpg = \"yes\""), Err("rpg: expected true or false".to_string()));
+        assert!(parse_flags("rpg = ").is_err());
+    }
+
+    #[test]
+    fn test_feature_flags() {
+        enable(1);
+        block_on(crate::features::load());
+        assert!(!crate::features::is_enabled("rpg"));
+        assert_eq!(run("features"), [
+            "games  off  config   Terminal games",
+            "rpg    off  config   The RPG engine",
+        ]);
+
+        assert_eq!(run("features rpg on"), ["rpg is now on (setting)"]);
+        assert!(crate::features::is_enabled("rpg"));
+        assert_eq!(run("features rpg reset"), ["rpg is now off (config)"]);
+        assert_eq!(run("features jetpack on"), ["features: jetpack: Unknown feature"]);
+
+        // Deployments can add flags of their own
+        crate::VIRTUAL_FS.with_borrow_mut(|vfs| {
+            vfs.write_file(&crate::filesystem::file_paths::FEATURES_FILE_PATH, "games = true\nbeta = true\n")
+        });
+        block_on(crate::features::load());
+        assert!(crate::features::is_enabled("games"));
+        assert_eq!(run("features")[0], "beta   on   config");
+    }
+}
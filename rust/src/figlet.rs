@@ -0,0 +1,142 @@
+//! Large letters from FIGlet fonts (.flf files), for `banner` and the
+//! message of the day. The block font in content/fonts is built in; others
+//! can be loaded from the filesystem.
+//!
+//! Glyphs are set side by side without FIGlet's smushing, so fonts look as
+//! they were drawn, letter spacing and all.
+
+use std::collections::HashMap;
+
+/// The font used unless another is asked for
+pub const BLOCK_FONT: &str = include_str!("../../site/content/fonts/block.flf");
+
+// Lines of banner wider than this are wrapped between words
+pub const MAX_WIDTH: usize = 80;
+
+pub struct Font {
+    height: usize,
+    glyphs: HashMap<char, Vec<String>>,
+}
+
+impl Font {
+    /// Parse a FIGlet font: a header line ("flf2a$ height ..."), comment
+    /// lines, then glyphs for ' ' to '~', each `height` lines ending in an
+    /// end mark (@) repeated on the glyph's last line
+    pub fn parse(text: &str) -> Result<Font, String> {
+        let mut lines = text.lines();
+        let header = lines.next().unwrap_or_default();
+        let Some(settings) = header.strip_prefix("flf2a") else {
+            return Err("Not a FIGlet font".to_string());
+        };
+        let mut chars = settings.chars();
+        let hardblank = chars.next().ok_or("Missing hard blank")?;
+        let numbers: Vec<usize> = chars.as_str().split_whitespace()
+            .take(5)
+            .map(|number| number.parse::<i64>().map(|n| n.max(0) as usize))
+            .collect::<Result<_, _>>()
+            .map_err(|_| "Bad header".to_string())?;
+        let [height, _baseline, _max_length, _old_layout, comment_lines] = numbers[..] else {
+            return Err("Bad header".to_string());
+        };
+        if height == 0 {
+            return Err("Bad header".to_string());
+        }
+
+        let mut lines = lines.skip(comment_lines);
+        let mut glyphs = HashMap::new();
+        for c in ' '..='~' {
+            let mut rows = Vec::with_capacity(height);
+            for _ in 0..height {
+                let line = lines.next().ok_or_else(|| format!("Missing glyph for '{}'", c))?;
+                let end_mark = line.chars().last().unwrap_or(' ');
+                rows.push(line.trim_end_matches(end_mark).replace(hardblank, " "));
+            }
+            glyphs.insert(c, rows);
+        }
+        Ok(Font { height, glyphs })
+    }
+
+    pub fn block() -> Font {
+        Font::parse(BLOCK_FONT).expect("the block font is valid")
+    }
+
+    fn glyph(&self, c: char) -> Option<&Vec<String>> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn width(&self, text: &str) -> usize {
+        text.chars().filter_map(|c| self.glyph(c)).map(|glyph| glyph[0].chars().count()).sum()
+    }
+
+    /// `text` in large letters, one row per line. Text too wide for
+    /// `MAX_WIDTH` is split into several banners between words.
+    pub fn render(&self, text: &str) -> String {
+        let mut pieces: Vec<String> = Vec::new();
+        for word in text.split_whitespace() {
+            match pieces.last_mut() {
+                Some(piece) if self.width(&format!("{} {}", piece, word)) <= MAX_WIDTH => {
+                    piece.push(' ');
+                    piece.push_str(word);
+                }
+                _ => pieces.push(word.to_string()),
+            }
+        }
+        pieces.iter()
+            .map(|piece| self.render_line(piece))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn render_line(&self, text: &str) -> String {
+        (0..self.height)
+            .map(|row| {
+                let line: String = text.chars()
+                    .filter_map(|c| self.glyph(c))
+                    .map(|glyph| glyph[row].as_str())
+                    .collect();
+                line.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let font = Font::block();
+        assert_eq!(font.render("Hi!"), [
+            "#   # ### #",
+            "#   #  #  #",
+            "#####  #  #",
+            "#   #  #",
+            "#   # ### #",
+        ].join("\n"));
+        // Characters the font lacks are drawn as '?'
+        assert_eq!(font.render("é"), font.render("?"));
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let font = Font::block();
+        let rendered = font.render("a long banner that cannot fit on one line");
+        let pieces: Vec<&str> = rendered.split("\n\n").collect();
+        assert_eq!(pieces.len(), 3);
+        assert_eq!(pieces[0], font.render("a long banner"));
+        assert!(rendered.lines().all(|line| line.chars().count() <= MAX_WIDTH));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Font::parse("hello").err(), Some("Not a FIGlet font".to_string()));
+        assert_eq!(Font::parse("flf2a$ 2 1 4 -1 0\n#@\n#@@\n").err(), Some("Missing glyph for '!'".to_string()));
+        let tiny: String = std::iter::once("flf2a$ 1 1 4 -1 1\ncomment".to_string())
+            .chain((' '..='~').map(|c| format!("{}$@@", c)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(Font::parse(&tiny).unwrap().render("a b"), "a   b");
+    }
+}
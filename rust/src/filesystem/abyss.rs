@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use crate::filesystem::{FilePath, helpers::fetch_text};
 
-use super::types::{DirPath, Content};
+use super::types::{DirPath, Content, FileData};
 
 /// Error indicating that an operation requires data that isn't cached yet
 #[derive(Debug)]
@@ -165,6 +165,38 @@ impl AbyssFileSystem {
         }
     }
 
+    /// Try to remove a directory and everything below it using cached data only
+    pub fn sync_remove_dir_all(&mut self, dirpath: &DirPath) -> Result<(), NeedsFetch> {
+        let parent = dirpath.super_dir().ok_or(NeedsFetch)?;
+        let parent_dirs = self.dirs.get(&parent).ok_or(NeedsFetch)?.clone();
+        self.sync_remove_dir_all_with_data(dirpath, parent_dirs).map_err(|_| NeedsFetch)
+    }
+
+    /// Remove a directory and everything below it using provided parent directories data.
+    /// Uncached levels below it were never fetched, so unlinking from the parent is enough.
+    pub fn sync_remove_dir_all_with_data(
+        &mut self,
+        dirpath: &DirPath,
+        mut parent_dirs: Directories,
+    ) -> Result<(), String> {
+        match (dirpath.super_dir(), dirpath.final_component()) {
+            (Some(parent), Some(dirname)) => {
+                if !parent_dirs.0.remove(dirname) {
+                    return Err("No such directory".to_string());
+                }
+
+                // Update cache
+                self.dirs.insert(parent, parent_dirs);
+                // Clean up cached entries for the removed tree
+                self.files.retain(|dir, _| !dir.is_within(dirpath));
+                self.dirs.retain(|dir, _| !dir.is_within(dirpath));
+
+                Ok(())
+            }
+            _ => Err("Invalid path".to_string())
+        }
+    }
+
     /// Try to create a directory using cached data only
     pub fn sync_create_dir(&mut self, dirpath: &DirPath) -> Result<(), NeedsFetch> {
         // Get parent directory and directory name
@@ -208,10 +240,11 @@ impl AbyssFileSystem {
     }
 
     /// Try to write a file using cached data only
-    pub fn sync_write_file(&mut self, filepath: &FilePath, content: String) -> Result<(), NeedsFetch> {
+    pub fn sync_write_file(&mut self, filepath: &FilePath, content: Vec<u8>) -> Result<(), NeedsFetch> {
         if let Some(contents) = self.files.get_mut(&filepath.dir) {
             // Cached - modify in place
-            contents.0.insert(filepath.file.clone(), Content::InMemory(content));
+            let data = FileData::overwriting(contents.get(&filepath.file), content);
+            contents.0.insert(filepath.file.clone(), Content::InMemory(data));
             Ok(())
         } else {
             Err(NeedsFetch)
@@ -219,8 +252,9 @@ impl AbyssFileSystem {
     }
 
     /// Write a file using provided contents data
-    pub fn sync_write_file_with_data(&mut self, filepath: &FilePath, mut contents: Contents, content: String) {
-        contents.0.insert(filepath.file.clone(), Content::InMemory(content));
+    pub fn sync_write_file_with_data(&mut self, filepath: &FilePath, mut contents: Contents, content: Vec<u8>) {
+        let data = FileData::overwriting(contents.get(&filepath.file), content);
+        contents.0.insert(filepath.file.clone(), Content::InMemory(data));
         self.files.insert(filepath.dir.clone(), contents);
     }
 
@@ -0,0 +1,255 @@
+//! Edits made inside the abyss, kept as a layer over what the server serves.
+//! The abyss itself is fetched level by level rather than stored, so sessions
+//! carry just this delta and replay it on import.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use serde_json::{json, Map, Value};
+use super::{ABYSS_FS, Content, DirPath, FilePath};
+use super::events::{subscribe, FsEvent};
+use super::helpers::{create_dir, dir_exists, file_exists, path_in_abyss, remove_dir_all, remove_file, write_file};
+
+/// Paths (as strings) changed in the abyss. Replaying removals, then created
+/// directories, then written files reproduces the edits.
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct AbyssDelta {
+    pub removed_dirs: BTreeSet<String>,
+    pub removed_files: BTreeSet<String>,
+    pub created_dirs: BTreeSet<String>,
+    pub written: BTreeSet<String>,
+}
+
+thread_local! {
+    pub static ABYSS_DELTA: RefCell<AbyssDelta> = RefCell::new(AbyssDelta::default());
+}
+
+// `path` is `dir` or somewhere below it
+fn within(path: &str, dir: &str) -> bool {
+    path == dir || path.starts_with(&format!("{}/", dir))
+}
+
+impl AbyssDelta {
+    /// Fold one filesystem change into the delta
+    pub fn apply_event(&mut self, event: &FsEvent) {
+        match event {
+            FsEvent::FileWritten(filepath) => {
+                let path = filepath.to_string();
+                self.removed_files.remove(&path);
+                self.written.insert(path);
+            }
+            FsEvent::FileRemoved(filepath) => {
+                let path = filepath.to_string();
+                self.written.remove(&path);
+                // Directories the visitor made never had anything from the server
+                if !self.created_dirs.contains(&filepath.dir.to_string()) {
+                    self.removed_files.insert(path);
+                }
+            }
+            FsEvent::DirCreated(dirpath) => {
+                // A directory removed and then recreated stays in removed_dirs,
+                // so replaying clears out what the server had there
+                self.created_dirs.insert(dirpath.to_string());
+            }
+            FsEvent::DirRemoved(dirpath) => {
+                let dir = dirpath.to_string();
+                let ours = self.created_dirs.contains(&dir) && !self.removed_dirs.contains(&dir);
+                self.created_dirs.retain(|path| !within(path, &dir));
+                self.written.retain(|path| !within(path, &dir));
+                self.removed_files.retain(|path| !within(path, &dir));
+                self.removed_dirs.retain(|path| !within(path, &dir));
+                if !ours {
+                    self.removed_dirs.insert(dir);
+                }
+            }
+            FsEvent::FileRead(_) | FsEvent::DirEntered(_) => {}
+        }
+    }
+
+    /// Drop the edits made at or below `dir`, as if they'd never been
+    pub fn forget(&mut self, dir: &str) {
+        self.removed_dirs.retain(|path| !within(path, dir));
+        self.removed_files.retain(|path| !within(path, dir));
+        self.created_dirs.retain(|path| !within(path, dir));
+        self.written.retain(|path| !within(path, dir));
+    }
+
+    /// The paths alone (for carrying the delta across a reload, where the
+    /// abyss contents travel separately)
+    pub fn paths_to_json(&self) -> Value {
+        json!({
+            "removed_dirs": self.removed_dirs,
+            "removed_files": self.removed_files,
+            "created_dirs": self.created_dirs,
+            "written": self.written,
+        })
+    }
+
+    pub fn paths_from_json(value: &Value) -> AbyssDelta {
+        let set = |key: &str| -> BTreeSet<String> {
+            value.get(key)
+                .and_then(|paths| serde_json::from_value(paths.clone()).ok())
+                .unwrap_or_default()
+        };
+        AbyssDelta {
+            removed_dirs: set("removed_dirs"),
+            removed_files: set("removed_files"),
+            created_dirs: set("created_dirs"),
+            written: set("written"),
+        }
+    }
+}
+
+/// Track abyss edits as they happen
+pub fn register() {
+    subscribe(|event| {
+        let in_abyss = match event {
+            FsEvent::FileWritten(filepath) | FsEvent::FileRemoved(filepath) | FsEvent::FileRead(filepath) => path_in_abyss(&filepath.dir),
+            FsEvent::DirCreated(dirpath) | FsEvent::DirRemoved(dirpath) | FsEvent::DirEntered(dirpath) => path_in_abyss(dirpath),
+        };
+        if in_abyss {
+            ABYSS_DELTA.with_borrow_mut(|delta| delta.apply_event(event));
+        }
+    });
+}
+
+/// The delta under `under` for a session file: the paths, plus the written
+/// files' contents (in the session's file entry format, via `content_to_json`)
+pub fn export(content_to_json: impl Fn(&Content) -> Value, under: &DirPath) -> Value {
+    let under = under.to_string();
+    let mut delta = ABYSS_DELTA.with_borrow(|delta| delta.clone());
+    if under != "/" {
+        for paths in [&mut delta.removed_dirs, &mut delta.removed_files, &mut delta.created_dirs, &mut delta.written] {
+            paths.retain(|path| within(path, &under));
+        }
+    }
+    let root = DirPath::root();
+    let files: Map<String, Value> = ABYSS_FS.with_borrow(|afs| {
+        delta.written.iter()
+            .filter_map(|path| {
+                let filepath = FilePath::parse(path, &root);
+                let content = afs.files.get(&filepath.dir)?.get(&filepath.file)?;
+                matches!(content, Content::InMemory(_)).then(|| (path.clone(), content_to_json(content)))
+            })
+            .collect()
+    });
+    json!({
+        "removed_dirs": delta.removed_dirs,
+        "removed_files": delta.removed_files,
+        "created_dirs": delta.created_dirs,
+        "files": files,
+    })
+}
+
+/// Whether a session's abyss section records any edits
+pub fn has_edits(value: &Value) -> bool {
+    value.as_object().is_some_and(|abyss| abyss.values().any(|section|
+        section.as_array().is_some_and(|paths| !paths.is_empty()) || section.as_object().is_some_and(|files| !files.is_empty())
+    ))
+}
+
+/// Replay a session's abyss delta, returning each path with how it went.
+/// `file_bytes` reads a file entry's content.
+pub async fn import(value: &Value, file_bytes: impl Fn(&Value) -> Option<Vec<u8>>) -> Vec<(String, Result<(), String>)> {
+    let root = DirPath::root();
+    let paths = |key: &str| -> Vec<String> {
+        value.get(key)
+            .and_then(|paths| serde_json::from_value::<BTreeSet<String>>(paths.clone()).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|path| path_in_abyss(&DirPath::parse(path, &root)))
+            .collect()
+    };
+    let mut results = Vec::new();
+
+    // Already gone (or already there) counts as done
+    for path in paths("removed_dirs") {
+        let dirpath = DirPath::parse(&path, &root);
+        let result = if dir_exists(&dirpath).await { remove_dir_all(&dirpath).await } else { Ok(()) };
+        results.push((path, result));
+    }
+    for path in paths("removed_files") {
+        let filepath = FilePath::parse(&path, &root);
+        let result = if file_exists(&filepath).await { remove_file(&filepath).await } else { Ok(()) };
+        results.push((path, result));
+    }
+    // Sorted, so parents come before their subdirectories
+    for path in paths("created_dirs") {
+        let dirpath = DirPath::parse(&path, &root);
+        let result = if dir_exists(&dirpath).await { Ok(()) } else { create_dir(&dirpath).await };
+        results.push((path, result));
+    }
+    if let Some(files) = value.get("files").and_then(|files| files.as_object()) {
+        for (path, entry) in files {
+            let filepath = FilePath::parse(path, &root);
+            if !path_in_abyss(&filepath.dir) {
+                continue;
+            }
+            let result = match file_bytes(entry) {
+                Some(bytes) => write_file(&filepath, bytes).await,
+                None => Err("invalid file entry".to_string()),
+            };
+            results.push((path.clone(), result));
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+    use crate::commands::OnConflict;
+    use crate::filesystem::{DirPath, FilePath};
+
+    #[test]
+    fn test_delta_tracks_edits() {
+        let root = DirPath::root();
+        let file = |path: &str| FilePath::parse(path, &root);
+        let dir = |path: &str| DirPath::parse(path, &root);
+
+        let mut delta = AbyssDelta::default();
+        delta.apply_event(&FsEvent::FileRemoved(file("/abyss/warning.txt")));
+        delta.apply_event(&FsEvent::DirCreated(dir("/abyss/camp")));
+        delta.apply_event(&FsEvent::FileWritten(file("/abyss/camp/log.txt")));
+        delta.apply_event(&FsEvent::FileWritten(file("/abyss/warning.txt")));
+        assert_eq!(delta.removed_files, BTreeSet::new());
+        assert_eq!(delta.written, BTreeSet::from(["/abyss/camp/log.txt".to_string(), "/abyss/warning.txt".to_string()]));
+
+        // Removing a directory subsumes everything recorded inside it
+        delta.apply_event(&FsEvent::FileWritten(file("/abyss/level1/note.txt")));
+        delta.apply_event(&FsEvent::DirRemoved(dir("/abyss/level1")));
+        assert_eq!(delta.written, BTreeSet::from(["/abyss/camp/log.txt".to_string(), "/abyss/warning.txt".to_string()]));
+        assert_eq!(delta.removed_dirs, BTreeSet::from(["/abyss/level1".to_string()]));
+
+        // What the visitor made and then removed leaves no trace
+        delta.apply_event(&FsEvent::FileRemoved(file("/abyss/camp/log.txt")));
+        assert_eq!(delta.removed_files, BTreeSet::new());
+        delta.apply_event(&FsEvent::DirRemoved(dir("/abyss/camp")));
+        assert_eq!(delta.created_dirs, BTreeSet::new());
+        assert_eq!(delta.written, BTreeSet::from(["/abyss/warning.txt".to_string()]));
+        assert_eq!(delta.removed_dirs, BTreeSet::from(["/abyss/level1".to_string()]));
+
+        assert_eq!(AbyssDelta::paths_from_json(&delta.paths_to_json()), delta);
+    }
+
+    #[test]
+    fn test_abyss_edits_round_trip_through_sessions() {
+        enable(1);
+        crate::filesystem::abyss_delta::register();
+        run("cd /abyss");
+        run("rm warning.txt");
+        run("mkdir camp");
+        block_on(crate::filesystem::helpers::write_file(&FilePath::parse("/abyss/camp/log.txt", &DirPath::root()), "day 1")).unwrap();
+        run("rm -r level1/level2/level3");
+        let session = crate::commands::export_session();
+
+        // A fresh visit sees the abyss as the server serves it
+        enable(1);
+        assert_eq!(run("ls /abyss"), ["level1/", "mirror_halls/", "warning.txt"]);
+        let summary = block_on(crate::commands::import_session(session, true, &OnConflict::Overwrite));
+        assert!(summary.contains("succeeded     4"), "{}", summary);
+        assert_eq!(run("ls /abyss"), ["camp/", "level1/", "mirror_halls/"]);
+        assert_eq!(run("cat /abyss/camp/log.txt"), ["day 1"]);
+        assert_eq!(run("ls /abyss/level1/level2"), ["cave_of_dice/", "echo.txt"]);
+    }
+}
@@ -1,23 +1,120 @@
-use std::{collections::{HashMap, HashSet}, sync::LazyLock};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::filesystem::{ABYSS_FS, AbyssFileSystem, CAVE_OF_DICE_INITIALISED, Content, Contents, DirPath, Directories, NextDir, helpers::path_in_abyss};
+use crate::filesystem::{ABYSS_FS, AbyssFileSystem, CAVE_OF_DICE_INITIALISED, Content, Contents, FileData, DirPath, Directories, NextDir, helpers::path_in_abyss};
 
-use rand::{prelude::*, random, random_range};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde_json::{json, Map, Value};
+use crate::dice::roll_die;
+use super::abyss_delta::ABYSS_DELTA;
+use super::events::{subscribe, FsEvent};
+use super::regions::Region;
 
 const DICE_SIZES : [u8; 6] = [4, 6, 8, 10, 12, 20];
 
+/// The seed the cave is built from, unless the visitor sets cave_seed
+pub const CAVE_SEED: u64 = 6;
+
+/// The seed the cave is built from: the same every visit, so routes can be shared
+pub fn seed() -> u64 {
+    crate::preferences::current().cave_seed.unwrap_or(CAVE_SEED)
+}
+
+// The generator the cave is built with. ChaCha8 is named outright (unlike
+// StdRng, whose algorithm can change with rand) so a seed keeps its cave.
+fn rng() -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed())
+}
+
+thread_local! {
+    // The rooms visited so far, as routes from the cave's entrance: ["d4", "route_2"]
+    static VISITED: RefCell<HashSet<Vec<String>>> = RefCell::new(HashSet::new());
+    // Where the cave was built, once it has been
+    static ENTRANCE: RefCell<Option<DirPath>> = const { RefCell::new(None) };
+}
+
+/// The cave as a region of the abyss: reading a die rolls it
+pub const REGION: Region = Region {
+    contains: path_in_cave_of_dice,
+    generates: |filepath| dice_file_sides(&filepath.file).is_some(),
+    generate: |filepath| format!("You rolled a {}", roll_die(dice_file_sides(&filepath.file).unwrap_or(1))),
+};
+
+/// The sides of a die file's die: "d6.txt" is 6
+pub fn dice_file_sides(file_name: &str) -> Option<u32> {
+    file_name.strip_prefix('d')?.strip_suffix(".txt")?.parse().ok()
+}
+
 pub fn path_in_cave_of_dice(path: &DirPath) -> bool {
     // note that if you the user decides to create their own cave_of_dice, outside the abyss, then some weird things could happen in memory.
     // I don't think it'll show up for the user besides stopping the main cave_of_dice from working as an abyss directory.
     let v = path.0.iter().position(|x|x == &NextDir::In("cave_of_dice".to_string()));
     if let Some(x) = v {
-        initialise_with_file_structure(&DirPath(path.0[..=x].to_vec()), &CAVE_OF_DICE);
+        if !CAVE_OF_DICE_INITIALISED.with_borrow(|x| *x) {
+            initialise_with_file_structure(&DirPath(path.0[..=x].to_vec()), &generate_cave_of_dice(&mut rng()));
+        }
         path_in_abyss(path)
     } else {
         false
     }
 }
 
+/// The file a room's treasure is described in
+pub const TREASURE_FILE: &str = "treasure.txt";
+
+/// Something to be found in a treasure chest, which can be carried in the RPG
+pub struct Loot {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub attack: u32,
+    pub defense: u32,
+    pub heal: u32,
+    /// The shallowest rooms it's found in
+    pub depth: usize,
+    /// How often it's found, against the rest
+    pub weight: u32,
+}
+
+/// Everything that can be found, commonest first
+pub const LOOT: &[Loot] = &[
+    Loot { id: "bone_die", name: "Bone Die", description: "Worn smooth. Every face shows a 1.", attack: 0, defense: 0, heal: 0, depth: 1, weight: 6 },
+    Loot { id: "tonic", name: "Dicer's Tonic", description: "Tastes of pennies. Heals 8 HP.", attack: 0, defense: 0, heal: 8, depth: 1, weight: 5 },
+    Loot { id: "pip_shield", name: "Pip Shield", description: "A round shield studded like a six.", attack: 0, defense: 1, heal: 0, depth: 2, weight: 3 },
+    Loot { id: "loaded_blade", name: "Loaded Blade", description: "Heavier on one side, and it always lands well.", attack: 2, defense: 0, heal: 0, depth: 3, weight: 2 },
+    Loot { id: "crit_crown", name: "Crown of Twenties", description: "Twenty points, each one a natural twenty.", attack: 3, defense: 2, heal: 0, depth: 4, weight: 1 },
+];
+
+/// The loot called `id`
+pub fn loot(id: &str) -> Option<&'static Loot> {
+    LOOT.iter().find(|loot| loot.id == id)
+}
+
+/// The loot a treasure file describes
+pub fn loot_in(treasure: &str) -> Option<&'static Loot> {
+    LOOT.iter().find(|loot| treasure.contains(&format!("Inside: {}.", loot.name)))
+}
+
+// Some loot for a room `depth` rooms in: deeper rooms can hold rarer things,
+// and the deeper the room, the likelier they are
+fn pick_loot(rng: &mut ChaCha8Rng, depth: usize) -> &'static Loot {
+    let weight = |loot: &Loot| loot.weight * (1 + depth.saturating_sub(loot.depth)) as u32;
+    let found: Vec<&Loot> = LOOT.iter().filter(|loot| loot.depth <= depth).collect();
+    let mut pick = rng.random_range(0..found.iter().map(|loot| weight(loot)).sum::<u32>());
+    for loot in &found {
+        if pick < weight(loot) {
+            return loot;
+        }
+        pick -= weight(loot);
+    }
+    &LOOT[0]
+}
+
+fn treasure_text(loot: &Loot) -> String {
+    format!("A chest with dice for hinges sits against the wall.\nInside: {}. {}\n", loot.name, loot.description)
+}
+
 const README: &str =
 r#"Hope you like dice, there are a lot of them here."#;
 
@@ -25,22 +122,23 @@ fn path_from_name(name: String) -> DirPath {
     DirPath(vec![NextDir::In(name)])
 }
 
-pub static CAVE_OF_DICE : LazyLock<AbyssFileSystem> = LazyLock::new(|| {
+// Generated on first visit, from `rng`
+fn generate_cave_of_dice(rng: &mut ChaCha8Rng) -> AbyssFileSystem {
     let mut filesystem = AbyssFileSystem::new();
 
     // depth 0
     let dir_names = DICE_SIZES.iter().map(|n|format!("d{n}")).collect::<HashSet<_>>();
-    let dirs = Directories(dir_names.clone());
+    let dirs = Directories(dir_names);
     filesystem.dirs.insert(DirPath::root(), dirs);
 
     let mut files = Contents(HashMap::new());
-    files.0.insert("README.md".into(),Content::InMemory(README.into()));
+    files.0.insert("README.md".into(),Content::InMemory(FileData::new(README)));
     filesystem.files.insert(DirPath::root(), files);
 
-    // remainder setup
-    let mut remaining_paths = dir_names
-        .into_iter()
-        .map(|x| DirPath(vec![NextDir::In(x)]))
+    // remainder setup (in a fixed order, so the same seed always builds the same cave)
+    let mut remaining_paths = DICE_SIZES
+        .iter()
+        .map(|n| DirPath(vec![NextDir::In(format!("d{n}"))]))
         .collect::<Vec<_>>();
 
     // main build loop
@@ -54,21 +152,12 @@ pub static CAVE_OF_DICE : LazyLock<AbyssFileSystem> = LazyLock::new(|| {
             Some("d12") => 12,
             Some("d20") => 20,
             None => unreachable!(),
-            _ => {
-                match random_range(0u8..6) {
-                    0 => 4,
-                    1 => 6,
-                    2 => 8,
-                    3 => 10,
-                    4 => 12,
-                    _ => 20
-                }
-            }
+            _ => DICE_SIZES[rng.random_range(0..DICE_SIZES.len())],
         };
 
         let mut subdirectories = Directories(HashSet::new());
         for i in 1..=n {
-            if random_range(0..(n*(depth as u8))) < 3 {
+            if rng.random_range(0..(n*(depth as u8))) < 3 {
                 let name = format!("route_{i}");
                 remaining_paths.push(path.concat(&path_from_name(name.clone()), false));
                 subdirectories.0.insert(name);
@@ -79,13 +168,17 @@ pub static CAVE_OF_DICE : LazyLock<AbyssFileSystem> = LazyLock::new(|| {
         let mut contents = Contents(HashMap::new());
         contents.0.insert(format!("d{n}.txt"), Content::ToFetch);
 
-        
+        // Treasure is rare near the entrance, and commoner further in
+        if rng.random_range(0..8) < depth.min(4) {
+            let loot = pick_loot(rng, depth);
+            contents.0.insert(TREASURE_FILE.to_string(), Content::InMemory(FileData::new(treasure_text(loot))));
+        }
+
         filesystem.files.insert(path, contents);
-        
     }
 
     filesystem
-});
+}
 
 pub fn initialise_with_file_structure(cod_path: &DirPath, cod_fs: &AbyssFileSystem) {
     if !CAVE_OF_DICE_INITIALISED.with_borrow(|x| *x) {
@@ -98,5 +191,311 @@ pub fn initialise_with_file_structure(cod_path: &DirPath, cod_fs: &AbyssFileSyst
             }
         });
         CAVE_OF_DICE_INITIALISED.with_borrow_mut(|x| {*x = true;});
+        ENTRANCE.with_borrow_mut(|entrance| *entrance = Some(cod_path.clone()));
+    }
+}
+
+// The route from the cave's entrance to `path`, if it's in the cave
+fn route(path: &DirPath) -> Option<Vec<String>> {
+    let entrance = path.0.iter().position(|x| x == &NextDir::In("cave_of_dice".to_string()))?;
+    path.0[entrance + 1..].iter()
+        .map(|next| match next {
+            NextDir::In(name) => Some(name.clone()),
+            NextDir::Out => None,
+        })
+        .collect()
+}
+
+/// Remember the rooms of the cave that are entered, for the map
+pub fn register() {
+    subscribe(|event| {
+        if let FsEvent::DirEntered(path) = event && path_in_cave_of_dice(path) && let Some(route) = route(path) {
+            VISITED.with_borrow_mut(|visited| visited.insert(route));
+        }
+    });
+}
+
+// Rooms in a map, each with the rooms leading on from it
+#[derive(Default)]
+struct Rooms(BTreeMap<((String, u32), String), Rooms>);
+
+// Rooms in the order they're numbered: "d4" before "d10", "route_2" before "route_10"
+fn room_order(name: &str) -> (String, u32) {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    (prefix.to_string(), name[prefix.len()..].parse().unwrap_or(0))
+}
+
+/// A map of the rooms visited so far, from the entrance, with the room at
+/// `here` marked. None if `here` isn't in the cave.
+pub fn map(here: &DirPath) -> Option<String> {
+    if !path_in_cave_of_dice(here) {
+        return None;
+    }
+    let here = route(here)?;
+    let mut routes: Vec<Vec<String>> = VISITED.with_borrow(|visited| visited.iter().cloned().collect());
+    routes.push(here.clone());
+    let mut rooms = Rooms::default();
+    for route in &routes {
+        let mut next = &mut rooms;
+        for room in route {
+            next = next.0.entry((room_order(room), room.clone())).or_default();
+        }
+    }
+    let mut lines = vec![mark("cave_of_dice", here.is_empty())];
+    draw(&rooms, "", &mut Vec::new(), &here, &mut lines);
+    Some(lines.join("\n"))
+}
+
+fn mark(name: &str, here: bool) -> String {
+    match here {
+        true => format!("{} <- you are here", name),
+        false => name.to_string(),
+    }
+}
+
+// Add the lines for `rooms`, which are along `route`, each line after `indent`
+fn draw(rooms: &Rooms, indent: &str, route: &mut Vec<String>, here: &[String], lines: &mut Vec<String>) {
+    for (i, ((_, room), next)) in rooms.0.iter().enumerate() {
+        let last = i + 1 == rooms.0.len();
+        route.push(room.clone());
+        lines.push(format!("{}{}{}", indent, if last { "`-- " } else { "|-- " }, mark(room, route.as_slice() == here)));
+        draw(next, &format!("{}{}", indent, if last { "    " } else { "|   " }), route, here, lines);
+        route.pop();
+    }
+}
+
+// Where the cave was built, if it has been
+fn entrance() -> Option<DirPath> {
+    if !CAVE_OF_DICE_INITIALISED.with_borrow(|x| *x) {
+        return None;
+    }
+    ENTRANCE.with_borrow(|entrance| entrance.clone())
+}
+
+/// The cave as it stands, for a session file: where it is, each room's
+/// routes on and files (in the session's file entry format, via
+/// `content_to_json`), and the rooms visited. Null if it hasn't been built
+/// or isn't under `under`.
+pub fn export(content_to_json: impl Fn(&Content) -> Value, under: &DirPath) -> Value {
+    let Some(entrance) = entrance().filter(|entrance| entrance.is_within(under)) else {
+        return Value::Null;
+    };
+    let key = |route: Vec<String>| format!("/{}", route.join("/"));
+    let rooms: Map<String, Value> = ABYSS_FS.with_borrow(|afs| afs.dirs.iter()
+        .filter(|(path, _)| path.is_within(&entrance))
+        .filter_map(|(path, dirs)| {
+            let mut routes: Vec<&String> = dirs.0.iter().collect();
+            routes.sort();
+            let files: Map<String, Value> = afs.files.get(path).map(|contents| contents.0.iter()
+                .map(|(name, content)| (name.clone(), content_to_json(content)))
+                .collect()).unwrap_or_default();
+            Some((key(route(path)?), json!({ "routes": routes, "files": files })))
+        })
+        .collect());
+    let mut visited: Vec<String> = VISITED.with_borrow(|visited| visited.iter().cloned().map(key).collect());
+    visited.sort();
+    json!({
+        "entrance": entrance.to_string(),
+        "rooms": rooms,
+        "visited": visited,
+    })
+}
+
+/// Rebuild the cave from a session file's (see `export`), replacing any
+/// built already. `content_from_json` reads a file entry.
+pub fn import(value: &Value, content_from_json: impl Fn(&Value) -> Option<Content>) -> Result<(), String> {
+    let root = DirPath::root();
+    let entrance = value.get("entrance").and_then(|entrance| entrance.as_str())
+        .map(|entrance| DirPath::parse(entrance, &root))
+        .filter(|entrance| path_in_abyss(entrance) && route(entrance).is_some_and(|route| route.is_empty()))
+        .ok_or("invalid entrance")?;
+    let rooms = value.get("rooms").and_then(|rooms| rooms.as_object()).ok_or("invalid rooms")?;
+    let mut cave = AbyssFileSystem::new();
+    for (room, spec) in rooms {
+        let path = DirPath::parse(room, &root);
+        let routes: HashSet<String> = spec.get("routes")
+            .and_then(|routes| serde_json::from_value(routes.clone()).ok())
+            .ok_or_else(|| format!("{}: invalid routes", room))?;
+        let mut contents = Contents(HashMap::new());
+        for (name, entry) in spec.get("files").and_then(|files| files.as_object()).into_iter().flatten() {
+            let content = content_from_json(entry).ok_or_else(|| format!("{}/{}: invalid file entry", room.trim_end_matches('/'), name))?;
+            contents.0.insert(name.clone(), content);
+        }
+        cave.dirs.insert(path.clone(), Directories(routes));
+        cave.files.insert(path, contents);
+    }
+    let visited: Vec<String> = value.get("visited")
+        .and_then(|visited| serde_json::from_value(visited.clone()).ok())
+        .unwrap_or_default();
+
+    forget();
+    initialise_with_file_structure(&entrance, &cave);
+    VISITED.with_borrow_mut(|rooms| rooms.extend(visited.iter()
+        .map(|room| DirPath::parse(room, &root).0.into_iter()
+            .filter_map(|next| match next {
+                NextDir::In(name) => Some(name),
+                NextDir::Out => None,
+            })
+            .collect())));
+    Ok(())
+}
+
+/// Where the cave was built and the rooms visited, to carry across a reload
+/// (the rooms themselves go with the rest of the abyss)
+pub fn reload_to_json() -> Value {
+    let visited: Vec<Vec<String>> = VISITED.with_borrow(|visited| visited.iter().cloned().collect());
+    json!({
+        "entrance": ENTRANCE.with_borrow(|entrance| entrance.as_ref().map(|entrance| entrance.to_string())),
+        "visited": visited,
+    })
+}
+
+/// Take back what `reload_to_json` carried
+pub fn reload_from_json(value: &Value) {
+    let entrance = value.get("entrance").and_then(|entrance| entrance.as_str())
+        .map(|entrance| DirPath::parse(entrance, &DirPath::root()));
+    let visited: Vec<Vec<String>> = value.get("visited")
+        .and_then(|visited| serde_json::from_value(visited.clone()).ok())
+        .unwrap_or_default();
+    ENTRANCE.with_borrow_mut(|current| *current = entrance);
+    VISITED.with_borrow_mut(|current| *current = visited.into_iter().collect());
+}
+
+/// Forget the cave (and anything changed in it), so it's built again on the next visit
+pub fn forget() {
+    if let Some(entrance) = entrance() {
+        ABYSS_FS.with_borrow_mut(|afs| {
+            afs.dirs.retain(|path, _| !path.is_within(&entrance));
+            afs.files.retain(|path, _| !path.is_within(&entrance));
+        });
+        ABYSS_DELTA.with_borrow_mut(|delta| delta.forget(&entrance.to_string()));
+    }
+    ENTRANCE.with_borrow_mut(|entrance| *entrance = None);
+    CAVE_OF_DICE_INITIALISED.with_borrow_mut(|x| *x = false);
+    VISITED.with_borrow_mut(|visited| visited.clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+    use crate::commands::OnConflict;
+    use crate::filesystem::{DirPath, FilePath};
+
+    #[test]
+    fn test_loot_by_depth() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let found = |rng: &mut ChaCha8Rng, depth| (0..200).map(|_| pick_loot(rng, depth).id).collect::<HashSet<_>>();
+        assert_eq!(found(&mut rng, 1), HashSet::from(["bone_die", "tonic"]));
+        assert_eq!(found(&mut rng, 5).len(), LOOT.len());
+        for loot in LOOT {
+            assert_eq!(loot_in(&treasure_text(loot)).map(|found| found.id), Some(loot.id));
+        }
+    }
+
+    #[test]
+    fn test_dice_file_sides() {
+        assert_eq!(dice_file_sides("d20.txt"), Some(20));
+        assert_eq!(dice_file_sides("d6.md"), None);
+        assert_eq!(dice_file_sides("readme.txt"), None);
+    }
+
+    #[test]
+    fn test_room_order() {
+        let mut rooms = vec!["route_10", "d20", "route_2", "d4"];
+        rooms.sort_by_key(|room| room_order(room));
+        assert_eq!(rooms, ["d4", "d20", "route_2", "route_10"]);
+    }
+
+    #[test]
+    fn test_cave_of_dice_round_trips_through_sessions() {
+        enable(1);
+        crate::filesystem::abyss_delta::register();
+        crate::filesystem::cave_of_dice::register();
+        run("settings set cave_seed 12345");
+        run("cd /abyss/level1/level2/cave_of_dice/d20/route_10");
+        block_on(crate::filesystem::helpers::write_file(&FilePath::parse("/abyss/level1/level2/cave_of_dice/d20/route_10/note.txt", &DirPath::root()), "left of the door")).unwrap();
+        let room = run("ls");
+        let session = crate::commands::export_session();
+
+        // A fresh visit builds the cave from its own seed
+        enable(1);
+        run("settings reset cave_seed");
+        assert_eq!(run("ls /abyss/level1/level2/cave_of_dice/d20"), ["d20.txt", "route_11/", "route_14/", "route_16/", "route_17/", "route_19/", "treasure.txt"]);
+        let summary = block_on(crate::commands::import_session(session, true, &OnConflict::Overwrite));
+        assert!(!summary.contains("Failures"), "{}", summary);
+
+        // But the session's cave comes back as it was, visits and all
+        assert_eq!(run("ls /abyss/level1/level2/cave_of_dice/d20"), ["d20.txt", "route_10/", "route_15/", "route_2/", "route_4/"]);
+        run("cd /abyss/level1/level2/cave_of_dice/d20/route_10");
+        assert_eq!(run("ls"), room);
+        assert_eq!(run("cat note.txt"), ["left of the door"]);
+        assert_eq!(run("map"), ["cave_of_dice", "`-- d20", "    `-- route_10 <- you are here"]);
+    }
+
+    #[test]
+    fn test_cave_of_dice_is_reproducible() {
+        let explore = || std::thread::spawn(|| {
+            enable(42);
+            run("cd /abyss/level1/level2/cave_of_dice/d20");
+            (run("ls"), run("cat d20.txt"))
+        }).join().unwrap();
+
+        let first = explore();
+        assert_eq!(first, explore());
+        assert_eq!(first.1.len(), 1);
+        assert!(first.1[0].starts_with("You rolled a "));
+    }
+
+    #[test]
+    fn test_cave_of_dice_seed() {
+        // The cave is built the same whatever else is random
+        let layout = |fixture_seed: u64| std::thread::spawn(move || {
+            enable(fixture_seed);
+            run("ls /abyss/level1/level2/cave_of_dice/d20")
+        }).join().unwrap();
+        let cave = layout(1);
+        assert_eq!(cave, layout(2));
+
+        // Until the visitor picks another seed, which builds another cave
+        enable(1);
+        assert_eq!(run("settings set cave_seed many"), ["settings: many: cave_seed must be a whole number"]);
+        run("ls /abyss/level1/level2/cave_of_dice/d20");
+        assert_eq!(run("settings set cave_seed 12345"), ["cave_seed is now 12345"]);
+        assert_eq!(run("ls /abyss/level1/level2/cave_of_dice/d20"), ["d20.txt", "route_10/", "route_15/", "route_2/", "route_4/"]);
+        run("settings reset cave_seed");
+        assert_eq!(run("ls /abyss/level1/level2/cave_of_dice/d20"), cave);
+    }
+
+    #[test]
+    fn test_cave_of_dice_map() {
+        enable(1);
+        crate::filesystem::cave_of_dice::register();
+        assert_eq!(run("map"), ["map: Only works in the cave of dice (/abyss/level1/level2/cave_of_dice)"]);
+        run("cd /abyss/level1/level2/cave_of_dice/d20/route_11");
+        run("cd ../../d4");
+        // Rooms come in numbered order, with the way to each
+        assert_eq!(run("map"), ["cave_of_dice", "|-- d4 <- you are here", "`-- d20", "    `-- route_11"]);
+        run("cd ..");
+        assert_eq!(run("map")[0], "cave_of_dice <- you are here");
+        // Another cave is another map
+        run("settings set cave_seed 12345");
+        assert_eq!(run("map"), ["cave_of_dice <- you are here"]);
+    }
+
+    #[test]
+    fn test_forget_only_the_cave() {
+        enable(1);
+        crate::filesystem::abyss_delta::register();
+        run("cd /abyss/level1/level2/cave_of_dice/d4");
+        run("mkdir /abyss/level1/level2/cave_of_dice/d4/camp");
+        run("mkdir /abyss/level1/cave_of_dice");
+        forget();
+
+        // A cave_of_dice of the visitor's own, elsewhere in the abyss, stays
+        assert_eq!(run("ls /abyss/level1"), ["cave_of_dice/", "level2/", "note.txt"]);
+        let delta = ABYSS_DELTA.with_borrow(|delta| delta.created_dirs.clone());
+        assert_eq!(delta.into_iter().collect::<Vec<_>>(), ["/abyss/level1/cave_of_dice"]);
+        assert_eq!(run("ls /abyss/level1/level2/cave_of_dice/d4"), ["d4.txt", "route_2/", "route_3/", "treasure.txt"]);
     }
 }
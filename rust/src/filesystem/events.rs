@@ -0,0 +1,57 @@
+//! Filesystem event bus.
+//! Subsystems subscribe here to react to changes, rather than being called
+//! from the individual commands that cause them.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use super::{DirPath, FilePath};
+
+#[derive(Clone, PartialEq)]
+pub enum FsEvent {
+    FileWritten(FilePath),
+    FileRemoved(FilePath),
+    // A file was shown to the visitor (cat, pretty)
+    FileRead(FilePath),
+    DirCreated(DirPath),
+    DirRemoved(DirPath),
+    DirEntered(DirPath),
+}
+
+type Listener = Rc<dyn Fn(&FsEvent)>;
+
+thread_local! {
+    static LISTENERS: RefCell<Vec<Listener>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Call `listener` for every event emitted from now on
+pub fn subscribe(listener: impl Fn(&FsEvent) + 'static) {
+    LISTENERS.with_borrow_mut(|listeners| listeners.push(Rc::new(listener)));
+}
+
+/// Notify all subscribers of an event.
+/// Emit after the change is made and any filesystem borrows are released.
+pub fn emit(event: FsEvent) {
+    // Listeners are cloned out so they can subscribe or emit themselves
+    let listeners = LISTENERS.with_borrow(|listeners| listeners.clone());
+    for listener in listeners {
+        listener(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribers_receive_events() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        subscribe(move |event| log.borrow_mut().push(event.clone()));
+
+        let file = FilePath::parse("/notes.txt", &DirPath::root());
+        emit(FsEvent::FileWritten(file.clone()));
+        emit(FsEvent::DirEntered(DirPath::root()));
+
+        assert!(seen.borrow().as_slice() == [FsEvent::FileWritten(file), FsEvent::DirEntered(DirPath::root())]);
+    }
+}
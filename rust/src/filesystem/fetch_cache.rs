@@ -0,0 +1,188 @@
+//! Keeping the text of fetched site files, so reading one again (cat, pretty,
+//! the editor) doesn't download it again. Cached files become
+//! `Content::Fetched`; when the cache grows past its limit the least recently
+//! read go back to `Content::ToFetch`. Sites can set the limit with the
+//! setting cache.bytes, and visitors can empty it with `cache clear`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use crate::settings::get_setting;
+use super::{ABYSS_FS, VIRTUAL_FS};
+use super::events::{subscribe, FsEvent};
+use super::helpers::path_in_abyss;
+use super::types::{Content, FilePath};
+
+pub const DEFAULT_LIMIT: usize = 4 * 1024 * 1024;
+const LIMIT_KEY: &str = "cache.bytes";
+
+thread_local! {
+    // Cached files with their sizes, least recently read first
+    static CACHED: RefCell<VecDeque<(FilePath, usize)>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// The most the cache holds, in bytes
+pub fn limit() -> usize {
+    get_setting(LIMIT_KEY)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_LIMIT)
+}
+
+/// Number of cached files and their total size
+pub fn usage() -> (usize, usize) {
+    CACHED.with_borrow(|cached| (cached.len(), cached.iter().map(|(_, size)| size).sum()))
+}
+
+// Apply `change` to the content at `filepath`, wherever it lives
+fn with_content(filepath: &FilePath, change: impl FnOnce(&mut Content)) {
+    if path_in_abyss(&filepath.dir) {
+        ABYSS_FS.with_borrow_mut(|afs| {
+            if let Some(content) = afs.files.get_mut(&filepath.dir).and_then(|contents| contents.0.get_mut(&filepath.file)) {
+                change(content);
+            }
+        });
+    } else {
+        VIRTUAL_FS.with_borrow_mut(|vfs| {
+            if let Some(content) = vfs.content.get_mut(&filepath.dir).and_then(|files| files.get_mut(&filepath.file)) {
+                change(content);
+            }
+        });
+    }
+}
+
+// Forget a cached copy (if the file still has one: it may have been written since)
+fn uncache(filepath: &FilePath) {
+    with_content(filepath, |content| {
+        if matches!(content, Content::Fetched(_)) {
+            *content = Content::ToFetch;
+        }
+    });
+}
+
+/// Forget cached copies that are replaced or removed
+pub fn register() {
+    subscribe(|event| match event {
+        FsEvent::FileWritten(filepath) | FsEvent::FileRemoved(filepath) => {
+            CACHED.with_borrow_mut(|cached| cached.retain(|(path, _)| path != filepath));
+        }
+        FsEvent::DirRemoved(dirpath) => {
+            CACHED.with_borrow_mut(|cached| cached.retain(|(path, _)| !path.dir.is_within(dirpath)));
+        }
+        _ => {}
+    });
+}
+
+/// Keep the text just fetched for `filepath`, making room if needed.
+/// Files bigger than the whole cache aren't kept.
+pub fn store(filepath: &FilePath, text: &str) {
+    let size = text.len();
+    if size > limit() {
+        return;
+    }
+    let mut stored = false;
+    with_content(filepath, |content| {
+        if matches!(content, Content::ToFetch) {
+            *content = Content::Fetched(text.to_string());
+            stored = true;
+        }
+    });
+    if !stored {
+        return;
+    }
+
+    CACHED.with_borrow_mut(|cached| {
+        cached.retain(|(path, _)| path != filepath);
+        cached.push_back((filepath.clone(), size));
+    });
+    evict(limit());
+}
+
+/// Note that `filepath` was read from the cache (it's kept longest)
+pub fn touch(filepath: &FilePath) {
+    CACHED.with_borrow_mut(|cached| {
+        if let Some(index) = cached.iter().position(|(path, _)| path == filepath) {
+            let entry = cached.remove(index).unwrap();
+            cached.push_back(entry);
+        }
+    });
+}
+
+// Drop least recently read files until the cache fits in `limit`
+fn evict(limit: usize) {
+    loop {
+        let oldest = CACHED.with_borrow_mut(|cached| {
+            let total: usize = cached.iter().map(|(_, size)| size).sum();
+            if total > limit { cached.pop_front() } else { None }
+        });
+        match oldest {
+            Some((filepath, _)) => uncache(&filepath),
+            None => break,
+        }
+    }
+}
+
+/// Empty the cache, returning how many files and bytes it held
+pub fn clear() -> (usize, usize) {
+    let usage = usage();
+    evict(0);
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::DirPath;
+    use crate::settings::set_setting;
+    use crate::fixtures::{enable, offline, overwrite, run};
+
+    fn content(path: &str) -> Option<Content> {
+        VIRTUAL_FS.with_borrow(|vfs| vfs.get_content(&FilePath::parse(path, &DirPath::root())).cloned())
+    }
+
+    #[test]
+    fn test_least_recently_read_are_evicted() {
+        let root = DirPath::root();
+        VIRTUAL_FS.with_borrow_mut(|vfs| {
+            for name in ["a.txt", "b.txt", "c.txt"] {
+                vfs.content.entry(root.clone()).or_default().insert(name.to_string(), Content::ToFetch);
+            }
+        });
+        set_setting(LIMIT_KEY, "10");
+
+        store(&FilePath::parse("/a.txt", &root), "aaaa");
+        store(&FilePath::parse("/b.txt", &root), "bbbb");
+        touch(&FilePath::parse("/a.txt", &root));
+        store(&FilePath::parse("/c.txt", &root), "cccc");
+        assert!(matches!(content("/a.txt"), Some(Content::Fetched(text)) if text == "aaaa"));
+        assert!(matches!(content("/b.txt"), Some(Content::ToFetch)));
+        assert_eq!(usage(), (2, 8));
+
+        // Too big to keep at all
+        store(&FilePath::parse("/b.txt", &root), "bbbbbbbbbbbb");
+        assert!(matches!(content("/b.txt"), Some(Content::ToFetch)));
+
+        assert_eq!(clear(), (2, 8));
+        assert!(matches!(content("/a.txt"), Some(Content::ToFetch)));
+        assert_eq!(usage(), (0, 0));
+    }
+
+    #[test]
+    fn test_fetched_files_are_cached() {
+        enable(1);
+        crate::filesystem::fetch_cache::register();
+        assert_eq!(run("cache"), ["Cache: 0 file(s), 0 B of 4.0 MB used"]);
+        let about = run("cat about.txt");
+        run("cat /abyss/warning.txt");
+        let (files, _) = crate::filesystem::fetch_cache::usage();
+        assert_eq!(files, 2);
+
+        // Served from the cache, even with the site unreachable
+        assert_eq!(offline(|| run("cat about.txt")), about);
+
+        // Written files leave the cache, and stay read-only site files until then
+        overwrite("about.txt", "mine");
+        assert_eq!(crate::filesystem::fetch_cache::usage().0, 1);
+        assert!(run("cache clear")[0].starts_with("Cleared 1 cached file(s)"));
+        assert_eq!(run("cat about.txt"), ["mine"]);
+        assert_eq!(run("cache status"), ["Cache: 0 file(s), 0 B of 4.0 MB used"]);
+    }
+}
@@ -0,0 +1,148 @@
+//! One network request per URL at a time. When something asks for a URL
+//! that's already being fetched (the editor's request_file while a `cat` of
+//! the same file is still downloading, say), it waits for that fetch and
+//! gets a copy of its result instead of starting another.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// A fetched text and its Last-Modified time, or the error
+pub type FetchResult = Result<(String, Option<f64>), String>;
+
+// A fetch in flight: its result once there is one, and who is waiting for it
+#[derive(Default)]
+struct Pending {
+    result: Option<FetchResult>,
+    waiters: Vec<Waker>,
+}
+
+thread_local! {
+    static PENDING: RefCell<HashMap<String, Rc<RefCell<Pending>>>> = RefCell::new(HashMap::new());
+}
+
+// Hands the result to the waiters when the fetch finishes, or an error if
+// the fetch was dropped before it could (so nobody waits forever)
+struct Finish {
+    url: String,
+    pending: Rc<RefCell<Pending>>,
+    result: Option<FetchResult>,
+}
+
+impl Drop for Finish {
+    fn drop(&mut self) {
+        PENDING.with_borrow_mut(|pending| pending.remove(&self.url));
+        let result = self.result.take().unwrap_or_else(|| Err(format!("Fetch of {} was cancelled", self.url)));
+        let waiters = {
+            let mut pending = self.pending.borrow_mut();
+            pending.result = Some(result);
+            std::mem::take(&mut pending.waiters)
+        };
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+}
+
+// Waiting for someone else's fetch of the same URL
+struct Wait(Rc<RefCell<Pending>>);
+
+impl Future for Wait {
+    type Output = FetchResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<FetchResult> {
+        let mut pending = self.0.borrow_mut();
+        match &pending.result {
+            Some(result) => Poll::Ready(result.clone()),
+            None => {
+                pending.waiters.push(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Fetch `url` with `fetch`, unless it's already being fetched, in which case
+/// wait for that result instead
+pub async fn fetch_shared<F, Fut>(url: &str, fetch: F) -> FetchResult
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = FetchResult>,
+{
+    if let Some(pending) = PENDING.with_borrow(|pending| pending.get(url).cloned()) {
+        return Wait(pending).await;
+    }
+
+    let pending = Rc::new(RefCell::new(Pending::default()));
+    PENDING.with_borrow_mut(|map| map.insert(url.to_string(), pending.clone()));
+    let mut finish = Finish { url: url.to_string(), pending, result: None };
+
+    let result = fetch().await;
+    finish.result = Some(result.clone());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::pin::pin;
+
+    // Ready once `open` is set
+    struct Gate<'a>(&'a Cell<bool>);
+
+    impl Future for Gate<'_> {
+        type Output = ();
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if self.0.get() { Poll::Ready(()) } else { Poll::Pending }
+        }
+    }
+
+    #[test]
+    fn test_simultaneous_fetches_share_one_request() {
+        let open = Cell::new(false);
+        let requests = Cell::new(0);
+        let fetch = || async {
+            requests.set(requests.get() + 1);
+            Gate(&open).await;
+            Ok(("text".to_string(), None))
+        };
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut first = pin!(fetch_shared("a.txt", fetch));
+        let mut second = pin!(fetch_shared("a.txt", fetch));
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        open.set(true);
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Ready(Ok(("text".to_string(), None))));
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(Ok(("text".to_string(), None))));
+        assert_eq!(requests.get(), 1);
+
+        // Once it's done, the next fetch is a new request
+        let mut third = pin!(fetch_shared("a.txt", fetch));
+        assert!(third.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(requests.get(), 2);
+    }
+
+    #[test]
+    fn test_waiters_hear_about_a_cancelled_fetch() {
+        let open = Cell::new(false);
+        let fetch = || async {
+            Gate(&open).await;
+            Ok(("text".to_string(), None))
+        };
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut second = Box::pin(fetch_shared("b.txt", fetch));
+        {
+            let mut first = Box::pin(fetch_shared("b.txt", fetch));
+            assert!(first.as_mut().poll(&mut cx).is_pending());
+            assert!(second.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(Err("Fetch of b.txt was cancelled".to_string())));
+    }
+}
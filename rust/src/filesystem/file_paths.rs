@@ -20,16 +20,65 @@ pub static SIMPLE_MANUAL_DIR_PATH: LazyLock<DirPath> = LazyLock::new(|| {
     DirPath(vec)
 });
 
-pub static HELP_FILE_PATH: LazyLock<FilePath> = LazyLock::new(|| 
+pub static HELP_NOTES_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
     FilePath {
-        dir: DirPath(Vec::new()), 
-        file: "help.txt".into() 
+        dir: DirPath(Vec::new()),
+        file: "help-notes.txt".into()
     }
 );
 
-pub static HELP_VERBOSE_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+pub static TIPS_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
     FilePath {
-        dir: DirPath(Vec::new()), 
-        file: "help-verbose.txt".into() 
+        dir: DirPath(Vec::new()),
+        file: ".tips.txt".into()
+    }
+);
+
+pub static FORTUNES_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(Vec::new()),
+        file: "fortunes.txt".into()
+    }
+);
+
+pub static TYPETEST_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(Vec::new()),
+        file: ".typetest.txt".into()
+    }
+);
+
+pub static FEATURES_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(vec![NextDir::In("config".into())]),
+        file: "features.toml".into()
+    }
+);
+
+pub static THEME_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(vec![NextDir::In(".config".into())]),
+        file: "theme".into()
+    }
+);
+
+pub static PREFERENCES_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(vec![NextDir::In(".config".into())]),
+        file: "terminal.toml".into()
+    }
+);
+
+pub static TYPETEST_SCORES_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(vec![NextDir::In(".config".into())]),
+        file: "typetest-scores".into()
+    }
+);
+
+pub static TWENTY48_FILE_PATH: LazyLock<FilePath> = LazyLock::new(||
+    FilePath {
+        dir: DirPath(vec![NextDir::In(".config".into())]),
+        file: "2048.json".into()
     }
 );
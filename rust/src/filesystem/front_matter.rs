@@ -0,0 +1,188 @@
+//! Front matter: the block of fields at the top of a markdown post, either
+//! YAML between `---` lines or TOML between `+++` lines. The title, date and
+//! tags are what the terminal shows (ls -l, blog, render and pretty); other
+//! fields are ignored. Site posts have theirs read into the manifest, so they
+//! can be listed without fetching each post.
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct FrontMatter {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl FrontMatter {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.date.is_none() && self.tags.is_empty()
+    }
+}
+
+/// Whether a file's name says it's markdown (the only files with front matter)
+pub fn is_markdown_name(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower.ends_with(".md") || lower.ends_with(".markdown")
+}
+
+/// The front matter of `text` (if it has any) and the text after it
+pub fn split(text: &str) -> (Option<FrontMatter>, &str) {
+    let text_start = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(first_line_end) = text_start.find('\n') else {
+        return (None, text);
+    };
+    let fence = text_start[..first_line_end].trim_end();
+    if fence != "---" && fence != "+++" {
+        return (None, text);
+    }
+
+    // The block runs to the next line holding just the fence
+    let rest = &text_start[first_line_end + 1..];
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == fence {
+            let block = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            let front_matter = if fence == "---" { parse_yaml(block) } else { parse_toml(block) };
+            return (Some(front_matter), body);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
+// tags: [a, "b"] or tags: a, b
+fn inline_list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let value = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(value);
+    value.split(',').map(unquote).filter(|item| !item.is_empty()).map(str::to_string).collect()
+}
+
+// The simple YAML posts use: `key: value` lines, with tags also allowed as
+// a list of `- item` lines under `tags:`
+fn parse_yaml(block: &str) -> FrontMatter {
+    let mut front_matter = FrontMatter::default();
+    let mut in_tags = false;
+    for line in block.lines() {
+        if in_tags && let Some(item) = line.trim_start().strip_prefix("- ") {
+            front_matter.tags.push(unquote(item).to_string());
+            continue;
+        }
+        in_tags = false;
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = unquote(value);
+        match key.trim() {
+            "title" if !value.is_empty() => front_matter.title = Some(value.to_string()),
+            "date" if !value.is_empty() => front_matter.date = Some(value.to_string()),
+            "tags" if value.is_empty() => in_tags = true,
+            "tags" => front_matter.tags = inline_list(value),
+            _ => {}
+        }
+    }
+    front_matter
+}
+
+fn parse_toml(block: &str) -> FrontMatter {
+    let Ok(table) = block.parse::<toml::Table>() else {
+        return FrontMatter::default();
+    };
+    let text = |value: &toml::Value| match value {
+        toml::Value::String(text) => Some(text.clone()),
+        toml::Value::Datetime(date) => Some(date.to_string()),
+        _ => None,
+    };
+    FrontMatter {
+        title: table.get("title").and_then(text),
+        date: table.get("date").and_then(text),
+        tags: match table.get("tags") {
+            Some(toml::Value::Array(tags)) => tags.iter().filter_map(text).collect(),
+            _ => Vec::new(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_yaml_and_toml() {
+        let (front_matter, body) = split("---\ntitle: \"Hello, world\"\ndate: 2024-03-01\ntags:\n  - rust\n  - wasm\ndraft: true\n---\n# Hello\n");
+        assert_eq!(front_matter, Some(FrontMatter {
+            title: Some("Hello, world".to_string()),
+            date: Some("2024-03-01".to_string()),
+            tags: vec!["rust".to_string(), "wasm".to_string()],
+        }));
+        assert_eq!(body, "# Hello\n");
+
+        let (front_matter, body) = split("+++\ntitle = 'Maths'\ndate = 2024-05-02\ntags = [\"proofs\"]\n+++\nbody");
+        assert_eq!(front_matter, Some(FrontMatter {
+            title: Some("Maths".to_string()),
+            date: Some("2024-05-02".to_string()),
+            tags: vec!["proofs".to_string()],
+        }));
+        assert_eq!(body, "body");
+    }
+
+    #[test]
+    fn test_manifest_entries_carry_front_matter() {
+        let entry: crate::filesystem::types::FileEntry = serde_json::from_str(
+            r#"{ "name": "a.md", "path": "blog", "draft": true, "title": "A", "tags": ["x"] }"#
+        ).unwrap();
+        assert!(entry.schedule.draft);
+        assert_eq!(entry.front_matter.title.as_deref(), Some("A"));
+        assert_eq!(entry.front_matter.tags, ["x"]);
+    }
+
+    #[test]
+    fn test_no_front_matter() {
+        assert_eq!(split("# Just a post\n---\n"), (None, "# Just a post\n---\n"));
+        // Never closed: a rule at the top, not front matter
+        assert_eq!(split("---\nsome text\n"), (None, "---\nsome text\n"));
+        let (front_matter, _) = split("---\ntags: [a, 'b']\n---\n");
+        assert_eq!(front_matter.unwrap().tags, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_front_matter_titles_and_dates() {
+        enable(1);
+        run("mkdir -p blog/old");
+        crate::write_file("blog/first.md", "---\ntitle: First post\ndate: 2024-01-05\ntags: [intro]\n---\nHello.".to_string()).unwrap();
+        crate::write_file("blog/old/second.md", "+++\ntitle = \"Second post\"\ndate = 2024-02-10\n+++\nMore.".to_string()).unwrap();
+        crate::write_file("blog/untitled.md", "No front matter".to_string()).unwrap();
+
+        assert_eq!(run("blog"), [
+            "2024-02-10  Second post  (/blog/old/second.md)",
+            "2024-01-05  First post  (/blog/first.md)",
+            "-           untitled.md  (/blog/untitled.md)",
+        ]);
+        assert_eq!(run("blog /abyss/level1"), ["blog: /abyss/level1: No posts"]);
+        assert_eq!(run("blog /").len(), 3);
+
+        let listing = run("ls -l blog");
+        assert!(listing[0].ends_with("DATE        TITLE"), "{:?}", listing);
+        assert!(listing[1].starts_with("first.md") && listing[1].ends_with("2024-01-05  First post"), "{:?}", listing);
+        assert!(listing[3].starts_with("untitled.md") && listing[3].ends_with("-           -"), "{:?}", listing);
+
+        let ls: serde_json::Value = serde_json::from_str(&run("ls --json blog").join("\n")).unwrap();
+        assert_eq!(ls[0]["title"], "First post");
+        assert_eq!(ls[0]["tags"], serde_json::json!(["intro"]));
+        assert!(ls[2].get("title").is_none());
+
+        assert_eq!(run("render blog/first.md"), ["First post", "2024-01-05 · tags: intro", "", "Hello."]);
+    }
+}
@@ -1,15 +1,34 @@
-use rand::random_range;
-use wasm_bindgen::JsCast;
+use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, RequestMode, Response, console::log_1};
-use crate::filesystem::cave_of_dice::path_in_cave_of_dice;
-use crate::filesystem::{ABYSS_FS, CURRENT_DIR, Contents, Directories, NextDir};
+use web_sys::{Request, RequestInit, RequestMode, Response};
+use crate::js_interop::{log, now};
+use crate::filesystem::regions::{self, in_region};
+use crate::filesystem::{ABYSS_FS, CURRENT_DIR, FETCHED_METADATA, OWNER_MODE, Contents, Directories, Metadata, NextDir};
 
 use super::types::{DirPath, FilePath, Content};
 use super::VIRTUAL_FS;
+use super::events::{emit, FsEvent};
+use super::quota::check_write;
+use super::fetch_cache;
+use super::front_matter::{self, FrontMatter, is_markdown_name};
+use super::fetch_coordinator::fetch_shared;
 
 // Async fetch helper
 pub async fn fetch_text(url: &str) -> Result<String, String> {
+    fetch_text_with_modified(url).await.map(|(text, _)| text)
+}
+
+// Fetch text along with the server's Last-Modified time (ms since epoch), if it sent one
+pub async fn fetch_text_with_modified(url: &str) -> Result<(String, Option<f64>), String> {
+    #[cfg(any(test, feature = "fixtures"))]
+    if let Some(response) = crate::fixtures::fetch(url) {
+        return response.map(|text| (text, None));
+    }
+    fetch_shared(url, || fetch_from_network(url)).await
+}
+
+// The request itself (see fetch_text_with_modified, which avoids duplicates)
+async fn fetch_from_network(url: &str) -> Result<(String, Option<f64>), String> {
     let window = web_sys::window().ok_or("No window object")?;
 
     let opts = RequestInit::new();
@@ -30,34 +49,86 @@ pub async fn fetch_text(url: &str) -> Result<String, String> {
         return Err(format!("Failed to fetch {}: HTTP {}", url, resp.status()));
     }
 
+    let modified = resp.headers().get("Last-Modified").ok().flatten()
+        .map(|date| js_sys::Date::parse(&date))
+        .filter(|ms| !ms.is_nan());
+
     let text_promise = resp.text().map_err(|_| "Failed to get response text")?;
     let text = JsFuture::from(text_promise)
         .await
         .map_err(|_| "Failed to read response text")?;
 
-    text.as_string().ok_or_else(|| "Response text is not a string".to_string())
+    let text = text.as_string().ok_or_else(|| "Response text is not a string".to_string())?;
+    Ok((text, modified))
 }
 
-fn is_dice_file_name(file_name: &str) -> Option<u8> {
-    if file_name.chars().nth(0) == Some('d') {
-        match file_name[1..].split('.').collect::<Vec<_>>().as_slice() {
-            [x, "txt"] => x.parse().ok(),
-            _ => None
-        }
-    } else {
-        None
+// Async POST helper - sends a JSON body and returns the response text
+pub async fn post_json(url: &str, body: &str) -> Result<String, String> {
+    #[cfg(any(test, feature = "fixtures"))]
+    if crate::fixtures::fetch(url).is_some() {
+        return Err(format!("Failed to post to {}: offline in fixture mode", url));
+    }
+
+    let window = web_sys::window().ok_or("No window object")?;
+
+    let opts = RequestInit::new();
+    opts.set_method("POST");
+    opts.set_mode(RequestMode::Cors);
+    opts.set_body(&JsValue::from_str(body));
+
+    let request = Request::new_with_str_and_init(url, &opts)
+        .map_err(|_| format!("Failed to create request for {}", url))?;
+    request.headers().set("Content-Type", "application/json")
+        .map_err(|_| "Failed to set request headers")?;
+
+    let resp_value = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(|_| format!("Failed to post to {}", url))?;
+
+    let resp: Response = resp_value.dyn_into()
+        .map_err(|_| "Response is not a Response object")?;
+
+    if !resp.ok() {
+        return Err(format!("Failed to post to {}: HTTP {}", url, resp.status()));
     }
+
+    let text_promise = resp.text().map_err(|_| "Failed to get response text")?;
+    let text = JsFuture::from(text_promise)
+        .await
+        .map_err(|_| "Failed to read response text")?;
+
+    text.as_string().ok_or_else(|| "Response text is not a string".to_string())
 }
 
-// Read content from a Content variant
-async fn read_content_at(content: Option<&Content>, filepath: &FilePath) -> Result<String, String> {
-    match content {
-        Some(Content::InMemory(text)) => Ok(text.clone()),
+// Read content from a Content variant.
+// Binary files are read as text with invalid UTF-8 replaced; see get_file_bytes.
+// `contents` is the listing of the file's directory.
+async fn read_content_at(contents: &Contents, filepath: &FilePath) -> Result<String, String> {
+    match contents.get(&filepath.file) {
+        Some(Content::InMemory(data)) => Ok(data.text_lossy().into_owned()),
+        Some(Content::Fetched(text)) => {
+            fetch_cache::touch(filepath);
+            Ok(text.clone())
+        }
         Some(Content::ToFetch) => {
-            if path_in_cave_of_dice(&filepath.dir) && let Some(n) = is_dice_file_name(&filepath.file) {
-                Ok(format!("You rolled a {}", random_range(1..=n)))
+            if let Some(text) = regions::generate(filepath) {
+                Ok(text)
             } else {
-                fetch_text(&filepath.to_url()).await
+                let (text, modified) = fetch_text_with_modified(&filepath.to_url()).await?;
+                // Without a Last-Modified header, the fetch time is the best we know
+                let time = modified.unwrap_or_else(now);
+                FETCHED_METADATA.with_borrow_mut(|meta| meta.insert(
+                    filepath.clone(),
+                    Metadata { size: text.len(), created: time, modified: time }
+                ));
+                // Abyss levels are only kept once something needs them to be
+                if path_in_abyss(&filepath.dir) {
+                    ABYSS_FS.with_borrow_mut(|afs| {
+                        afs.files.entry(filepath.dir.clone()).or_insert_with(|| contents.clone());
+                    });
+                }
+                fetch_cache::store(filepath, &text);
+                Ok(text)
             }
         },
         None => Err(format!("{}: No such file", filepath.to_string())),
@@ -67,7 +138,95 @@ async fn read_content_at(content: Option<&Content>, filepath: &FilePath) -> Resu
 // Get file content (fetch if needed)
 pub async fn get_file_content(filepath: &FilePath) -> Result<String, String> {
     let contents = get_contents(&filepath.dir).await;
-    read_content_at(contents.get(&filepath.file), filepath).await
+    read_content_at(&contents, filepath).await
+}
+
+// Get a file's raw bytes (fetch if needed), for binary files
+pub async fn get_file_bytes(filepath: &FilePath) -> Result<Vec<u8>, String> {
+    let contents = get_contents(&filepath.dir).await;
+    match contents.get(&filepath.file) {
+        Some(Content::InMemory(data)) => Ok(data.bytes.clone()),
+        _ => read_content_at(&contents, filepath).await.map(String::into_bytes),
+    }
+}
+
+// Today's date as YYYY-MM-DD, for comparing against publish dates
+pub fn today_iso() -> String {
+    format_timestamp(now())[..10].to_string()
+}
+
+// Format a millisecond timestamp as "YYYY-MM-DD HH:MM:SS" (UTC)
+pub fn format_timestamp(ms: f64) -> String {
+    let secs = (ms / 1000.0).floor() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let time = secs.rem_euclid(86400);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+// (year, month, day) of a count of days since 1970-01-01 (Howard Hinnant's civil_from_days)
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;  // March = 0
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Metadata for a file, if known. Static files only have metadata once fetched.
+pub fn file_metadata(filepath: &FilePath, content: &Content) -> Option<Metadata> {
+    match content {
+        Content::InMemory(data) => Some(data.meta.clone()),
+        Content::ToFetch | Content::Fetched(_) => FETCHED_METADATA.with_borrow(|meta| meta.get(filepath).cloned()),
+    }
+}
+
+// Front matter of a markdown file, if it has any. Site posts not yet fetched
+// have the manifest's copy; anything else is read from the text.
+pub fn file_front_matter(filepath: &FilePath, content: &Content) -> Option<FrontMatter> {
+    if !is_markdown_name(&filepath.file) {
+        return None;
+    }
+    match content {
+        Content::InMemory(data) => front_matter::split(&data.text_lossy()).0,
+        Content::Fetched(text) => front_matter::split(text).0,
+        Content::ToFetch => VIRTUAL_FS.with_borrow(|vfs| vfs.front_matter.get(filepath).cloned()),
+    }
+    .filter(|front_matter| !front_matter.is_empty())
+}
+
+// URL a file's content is fetched from, or None if it is generated locally
+pub fn origin_url(filepath: &FilePath) -> Option<String> {
+    if regions::is_generated(filepath) {
+        None
+    } else {
+        Some(filepath.to_url())
+    }
+}
+
+// Whether a file holds content written in this session, which overwriting would lose
+pub async fn has_in_memory_content(filepath: &FilePath) -> bool {
+    matches!(get_contents(&filepath.dir).await.0.get(&filepath.file), Some(Content::InMemory(_)))
+}
+
+// Whether a file is site content, which commands only change when passed -f
+// (a post not published yet isn't there at all)
+pub fn is_read_only(filepath: &FilePath) -> bool {
+    VIRTUAL_FS.with_borrow(|vfs| vfs.is_read_only(filepath)) && !is_unpublished(filepath)
+}
+
+// Whether a site post is a draft or dated after today: only the owner can see it
+pub fn is_unpublished(filepath: &FilePath) -> bool {
+    !OWNER_MODE.with_borrow(|x| *x) && VIRTUAL_FS.with_borrow(|vfs| vfs.is_unpublished(filepath, &today_iso()))
+}
+
+// Whether removing a directory would remove read-only files
+pub fn has_read_only_within(dirpath: &DirPath) -> bool {
+    VIRTUAL_FS.with_borrow(|vfs| vfs.has_read_only_within(dirpath))
 }
 
 // Helper to get current directory path as string
@@ -118,6 +277,11 @@ pub async fn list_directory(path: &DirPath) -> Vec<String> {
     entries
 }
 
+// Dotfiles and the abyss's !! bookkeeping files are left out of listings by default
+pub fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with("!!")
+}
+
 pub fn in_abyss() -> bool {
     CURRENT_DIR.with(|dir|
         path_in_abyss(&dir.borrow())
@@ -135,7 +299,7 @@ pub fn path_in_abyss(path: &DirPath) -> bool {
 
 /// Remove a file from the abyss filesystem
 pub async fn remove_file_abyss(filepath: &FilePath) -> Result<(), String> {
-    path_in_cave_of_dice(&filepath.dir); // Initialize cave_of_dice if needed
+    in_region(&filepath.dir); // Build the region it's in, if needed
     // Try cached path first
     match ABYSS_FS.with_borrow_mut(|afs| afs.sync_remove_file(filepath)) {
         Ok(_) => Ok(()),
@@ -151,7 +315,7 @@ pub async fn remove_file_abyss(filepath: &FilePath) -> Result<(), String> {
 
 /// Remove a directory from the abyss filesystem
 pub async fn remove_dir_abyss(dirpath: &DirPath) -> Result<(), String> {
-    path_in_cave_of_dice(dirpath); // Initialize cave_of_dice if needed
+    in_region(dirpath); // Build the region it's in, if needed
     // Try cached path first
     match ABYSS_FS.with_borrow_mut(|afs| afs.sync_remove_dir(dirpath)) {
         Ok(_) => Ok(()),
@@ -170,9 +334,28 @@ pub async fn remove_dir_abyss(dirpath: &DirPath) -> Result<(), String> {
     }
 }
 
+/// Remove a directory and everything below it from the abyss filesystem
+pub async fn remove_dir_all_abyss(dirpath: &DirPath) -> Result<(), String> {
+    in_region(dirpath); // Build the region it's in, if needed
+    // Try cached path first
+    match ABYSS_FS.with_borrow_mut(|afs| afs.sync_remove_dir_all(dirpath)) {
+        Ok(_) => Ok(()),
+        Err(_) => {
+            // Fetch parent directories
+            let parent = dirpath.super_dir().ok_or("Invalid path")?;
+            let parent_dirs = get_directories(&parent).await;
+
+            // Retry with data
+            ABYSS_FS.with_borrow_mut(|afs|
+                afs.sync_remove_dir_all_with_data(dirpath, parent_dirs)
+            )
+        }
+    }
+}
+
 /// Create a directory in the abyss filesystem
 pub async fn create_dir_abyss(dirpath: &DirPath) -> Result<(), String> {
-    path_in_cave_of_dice(dirpath); // Initialize cave_of_dice if needed
+    in_region(dirpath); // Build the region it's in, if needed
     // Try cached path first
     match ABYSS_FS.with_borrow_mut(|afs| afs.sync_create_dir(dirpath)) {
         Ok(_) => Ok(()),
@@ -190,8 +373,8 @@ pub async fn create_dir_abyss(dirpath: &DirPath) -> Result<(), String> {
 }
 
 /// Write a file to the abyss filesystem
-pub async fn write_file_abyss(filepath: &FilePath, content: String) {
-    path_in_cave_of_dice(&filepath.dir); // Initialize cave_of_dice if needed
+pub async fn write_file_abyss(filepath: &FilePath, content: Vec<u8>) {
+    in_region(&filepath.dir); // Build the region it's in, if needed
     // Try cached path first
     match ABYSS_FS.with_borrow_mut(|afs| afs.sync_write_file(filepath, content.clone())) {
         Ok(_) => {},
@@ -207,12 +390,102 @@ pub async fn write_file_abyss(filepath: &FilePath, content: String) {
     }
 }
 
+// Filesystem changes, for both the abyss and the regular filesystem.
+// These emit the matching FsEvent once the change is made.
+
+/// Write a file (text or bytes), creating or overwriting it, within the write quota
+pub async fn write_file(filepath: &FilePath, content: impl Into<Vec<u8>>) -> Result<(), String> {
+    let content = content.into();
+    check_write(filepath, content.len())?;
+    if path_in_abyss(&filepath.dir) {
+        write_file_abyss(filepath, content).await;
+    } else {
+        VIRTUAL_FS.with_borrow_mut(|vfs| vfs.write_file(filepath, content));
+    }
+    emit(FsEvent::FileWritten(filepath.clone()));
+    Ok(())
+}
+
+/// Remove a file
+pub async fn remove_file(filepath: &FilePath) -> Result<(), String> {
+    if path_in_abyss(&filepath.dir) {
+        remove_file_abyss(filepath).await?;
+    } else if is_unpublished(filepath) || !VIRTUAL_FS.with_borrow_mut(|vfs| vfs.remove_file(filepath)) {
+        return Err("No such file".to_string());
+    }
+    emit(FsEvent::FileRemoved(filepath.clone()));
+    Ok(())
+}
+
+/// Move a file to another path in an existing directory, which mustn't
+/// already hold a file by that name
+pub async fn rename_file(from: &FilePath, to: &FilePath) -> Result<(), String> {
+    if from == to {
+        return Ok(());
+    }
+    let bytes = get_file_bytes(from).await.map_err(|_| "No such file".to_string())?;
+    if file_exists(to).await {
+        return Err(format!("{}: File already exists", to.to_string()));
+    }
+    if !dir_exists(&to.dir).await {
+        return Err(format!("{}: No such directory", to.dir.to_string()));
+    }
+    write_file(to, bytes).await?;
+    remove_file(from).await
+}
+
+/// Create a directory (its parent must exist)
+pub async fn create_dir(dirpath: &DirPath) -> Result<(), String> {
+    if path_in_abyss(dirpath) {
+        create_dir_abyss(dirpath).await?;
+    } else {
+        VIRTUAL_FS.with_borrow_mut(|vfs| {
+            if vfs.dir_exists(dirpath) {
+                Err("Directory already exists".to_string())
+            } else {
+                vfs.create_dir(dirpath.clone());
+                Ok(())
+            }
+        })?;
+    }
+    emit(FsEvent::DirCreated(dirpath.clone()));
+    Ok(())
+}
+
+/// Remove a directory
+pub async fn remove_dir(dirpath: &DirPath) -> Result<(), String> {
+    if path_in_abyss(dirpath) {
+        remove_dir_abyss(dirpath).await?;
+    } else {
+        VIRTUAL_FS.with_borrow_mut(|vfs| vfs.remove_dir(dirpath))?;
+    }
+    emit(FsEvent::DirRemoved(dirpath.clone()));
+    Ok(())
+}
+
+/// Remove a directory and everything below it
+pub async fn remove_dir_all(dirpath: &DirPath) -> Result<(), String> {
+    if path_in_abyss(dirpath) {
+        remove_dir_all_abyss(dirpath).await?;
+    } else {
+        VIRTUAL_FS.with_borrow_mut(|vfs| vfs.remove_dir_all(dirpath))?;
+    }
+    emit(FsEvent::DirRemoved(dirpath.clone()));
+    Ok(())
+}
+
+/// Make `dirpath` the current directory (it must exist)
+pub fn change_dir(dirpath: DirPath) {
+    CURRENT_DIR.with_borrow_mut(|cd| *cd = dirpath.clone());
+    emit(FsEvent::DirEntered(dirpath));
+}
+
 // assumes path is valid
 pub async fn get_directories(path: &DirPath) -> Directories {
     if path_in_abyss(path) {
-        path_in_cave_of_dice(path); // Initialize cave_of_dice if needed
+        in_region(path); // Build the region it's in, if needed
         let msg = format!("{} is in abyss", path.to_string());
-        log_1(&msg.into());
+        log(&msg);
 
         match ABYSS_FS.with_borrow(|afs|
             afs.dirs.get(path).cloned()
@@ -226,7 +499,7 @@ pub async fn get_directories(path: &DirPath) -> Directories {
         }
     } else {
         let msg = format!("{} is not in abyss", path.to_string());
-        log_1(&msg.into());
+        log(&msg);
         Directories(
             VIRTUAL_FS
             .with_borrow(|vfs| vfs.list_subdirs_in_dir(path))
@@ -240,7 +513,7 @@ pub async fn get_directories(path: &DirPath) -> Directories {
 // Assumes path is valid
 pub async fn get_contents(path: &DirPath) -> Contents {
     if path_in_abyss(path) {
-        path_in_cave_of_dice(path); // Initialize cave_of_dice if needed
+        in_region(path); // Build the region it's in, if needed
         match ABYSS_FS.with_borrow(|afs|
             afs.files.get(path).cloned()
         ) {
@@ -252,10 +525,12 @@ pub async fn get_contents(path: &DirPath) -> Contents {
             )
         }
     } else {
+        // Drafts and future-dated posts only exist for the owner
         Contents(
             VIRTUAL_FS
             .with_borrow(|vfs| vfs.list_files_in_dir(path))
             .iter()
+            .filter(|file| !is_unpublished(&FilePath {dir: path.clone(), file: (*file).clone()}))
             .map(|file|
                 VIRTUAL_FS.with_borrow(|vfs|
                     (
@@ -272,3 +547,34 @@ pub async fn get_contents(path: &DirPath) -> Contents {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+    use crate::filesystem::{DirPath, FilePath};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0.0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400_000.0), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_700_000_000_000.0), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn test_rename_file() {
+        use crate::filesystem::helpers::rename_file;
+        enable(1);
+        let file = |path: &str| FilePath::parse(path, &DirPath::root());
+        let warning = run("cat /abyss/warning.txt");
+        run("ls /abyss/level1");
+        assert_eq!(block_on(rename_file(&file("/abyss/warning.txt"), &file("/abyss/level1/moved.txt"))), Ok(()));
+        assert_eq!(run("ls /abyss"), ["level1/", "mirror_halls/"]);
+        assert_eq!(run("cat /abyss/level1/moved.txt"), warning);
+        assert_eq!(
+            block_on(rename_file(&file("/abyss/level1/moved.txt"), &file("/nowhere/moved.txt"))),
+            Err("/nowhere: No such directory".to_string())
+        );
+        assert_eq!(block_on(rename_file(&file("/missing.txt"), &file("/other.txt"))), Err("No such file".to_string()));
+    }
+}
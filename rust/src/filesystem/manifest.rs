@@ -0,0 +1,135 @@
+//! Checking the site manifest before it becomes the filesystem. A manifest
+//! written by hand, or by an older or newer generator, can list files in
+//! directories it doesn't declare, list things twice, or carry paths that
+//! can't be walked to. Each problem is reported, and the manifest is mended
+//! or the entry dropped, so the filesystem still comes up whole.
+
+use std::collections::{BTreeSet, HashSet};
+use super::types::{FileEntry, Manifest};
+
+/// The manifest version this build understands (manifests without one are
+/// from before versioning, and read the same way)
+pub const MANIFEST_VERSION: u64 = 1;
+
+// A relative directory path, as in the manifest: "" for the root, else "a/b"
+fn valid_dir(path: &str) -> bool {
+    path.is_empty() || path.split('/').all(valid_name)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+// "a/b/c" -> ["a", "a/b", "a/b/c"]
+fn with_ancestors(path: &str) -> impl Iterator<Item = String> + '_ {
+    path.match_indices('/').map(|(i, _)| path[..i].to_string()).chain(std::iter::once(path.to_string()))
+}
+
+fn file_path(entry: &FileEntry) -> String {
+    if entry.path.is_empty() { entry.name.clone() } else { format!("{}/{}", entry.path, entry.name) }
+}
+
+/// The manifest with its problems mended, and a message for each problem
+/// ("manifest: PATH: message")
+pub fn check(manifest: Manifest) -> (Manifest, Vec<String>) {
+    let mut problems = Vec::new();
+    if let Some(version) = manifest.version.filter(|version| *version > MANIFEST_VERSION) {
+        problems.push(format!(
+            "manifest: Version {} is newer than this terminal understands ({}); loading what it can",
+            version, MANIFEST_VERSION
+        ));
+    }
+
+    let mut directories = BTreeSet::new();
+    for dir in &manifest.directories {
+        if !valid_dir(dir) || dir.is_empty() {
+            problems.push(format!("manifest: {}: Bad directory path (skipped)", dir));
+        } else if !directories.insert(dir.clone()) {
+            problems.push(format!("manifest: {}: Directory listed twice", dir));
+        }
+    }
+    // Every directory's parents must be there for it to be reached
+    for dir in directories.clone() {
+        for ancestor in with_ancestors(&dir) {
+            if directories.insert(ancestor.clone()) {
+                problems.push(format!("manifest: {}: Directory '{}' is not listed (added)", dir, ancestor));
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for entry in manifest.files {
+        let path = file_path(&entry);
+        if !valid_dir(&entry.path) || !valid_name(&entry.name) {
+            problems.push(format!("manifest: {}: Bad file path (skipped)", path));
+            continue;
+        }
+        if !seen.insert(path.clone()) {
+            problems.push(format!("manifest: {}: File listed twice (skipped)", path));
+            continue;
+        }
+        if !entry.path.is_empty() {
+            for ancestor in with_ancestors(&entry.path) {
+                if directories.insert(ancestor.clone()) {
+                    problems.push(format!("manifest: {}: Directory '{}' is not listed (added)", path, ancestor));
+                }
+            }
+        }
+        files.push(entry);
+    }
+
+    let manifest = Manifest {
+        version: manifest.version,
+        files,
+        directories: directories.into_iter().collect(),
+    };
+    (manifest, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(json: &str) -> Manifest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_clean_manifest_has_no_problems() {
+        let (checked, problems) = check(manifest(r#"{
+            "version": 1,
+            "files": [{ "name": "about.txt", "path": "" }, { "name": "post.md", "path": "blog/2024" }],
+            "directories": ["blog", "blog/2024"]
+        }"#));
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(checked.files.len(), 2);
+        assert_eq!(checked.directories, ["blog", "blog/2024"]);
+    }
+
+    #[test]
+    fn test_problems_are_reported_and_mended() {
+        let (checked, problems) = check(manifest(r#"{
+            "version": 2,
+            "files": [
+                { "name": "a.txt", "path": "notes" },
+                { "name": "a.txt", "path": "notes" },
+                { "name": "..", "path": "" },
+                { "name": "b.txt", "path": "x//y" }
+            ],
+            "directories": ["docs/guides", "docs/guides", "/etc"]
+        }"#));
+        assert_eq!(problems, [
+            "manifest: Version 2 is newer than this terminal understands (1); loading what it can",
+            "manifest: docs/guides: Directory listed twice",
+            "manifest: /etc: Bad directory path (skipped)",
+            "manifest: docs/guides: Directory 'docs' is not listed (added)",
+            "manifest: notes/a.txt: Directory 'notes' is not listed (added)",
+            "manifest: notes/a.txt: File listed twice (skipped)",
+            "manifest: ..: Bad file path (skipped)",
+            "manifest: x//y/b.txt: Bad file path (skipped)",
+        ]);
+        assert_eq!(checked.files.len(), 1);
+        assert_eq!(checked.directories, ["docs", "docs/guides", "notes"]);
+    }
+}
@@ -0,0 +1,140 @@
+//! The mirror halls (/abyss/mirror_halls): halls leading to more halls,
+//! without end. Nothing of a hall is kept until something asks about it,
+//! and what it holds comes from a hash of its path, so every hall is the
+//! same on every visit, however deep.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use crate::digest::sha256;
+use super::{ABYSS_FS, Content, Contents, DirPath, Directories, FileData, NextDir};
+use super::regions::Region;
+
+/// The ways on a hall can have
+const WAYS: &[&str] = &["left", "right", "ahead", "behind", "through_the_glass"];
+
+/// The file describing each hall
+pub const MIRROR_FILE: &str = "mirror.txt";
+
+const GLIMPSES: &[&str] = &[
+    "One of them is waving.",
+    "One of them has stopped looking at you.",
+    "They're all holding a die.",
+    "The farthest one is a little late.",
+    "One of them is facing the other way.",
+    "None of them blink.",
+];
+
+/// The halls as a region of the abyss
+pub const REGION: Region = Region {
+    contains: path_in_mirror_halls,
+    generates: |_| false,
+    generate: |_| String::new(),
+};
+
+fn entrance() -> DirPath {
+    DirPath(vec![NextDir::In("abyss".to_string()), NextDir::In("mirror_halls".to_string())])
+}
+
+pub fn path_in_mirror_halls(path: &DirPath) -> bool {
+    if !path.is_within(&entrance()) {
+        return false;
+    }
+    build(path);
+    true
+}
+
+// Keep the hall at `path`, and the halls on the way to it, if it's there to
+// be kept (a hall only leads where its parent says, and stays gone once
+// removed). Returns whether it's there.
+fn build(path: &DirPath) -> bool {
+    if ABYSS_FS.with_borrow(|afs| afs.dirs.contains_key(path)) {
+        return true;
+    }
+    if *path != entrance() {
+        let (Some(parent), Some(name)) = (path.super_dir(), path.final_component()) else {
+            return false;
+        };
+        if !build(&parent) || !ABYSS_FS.with_borrow(|afs| afs.dirs.get(&parent).is_some_and(|dirs| dirs.contains(name))) {
+            return false;
+        }
+    }
+    let (dirs, contents) = hall(path);
+    ABYSS_FS.with_borrow_mut(|afs| {
+        afs.dirs.insert(path.clone(), dirs);
+        afs.files.entry(path.clone()).or_insert(contents);
+    });
+    true
+}
+
+// What's in the hall at `path`, from its path alone
+fn hall(path: &DirPath) -> (Directories, Contents) {
+    let mut rng = rng_for(path);
+    let depth = path.0.len() - entrance().0.len();
+
+    // Always one way on, and maybe more
+    let first = rng.random_range(0..WAYS.len());
+    let ways = WAYS.iter().enumerate()
+        .filter(|&(i, _)| i == first || rng.random_range(0..3) == 0)
+        .map(|(_, way)| way.to_string())
+        .collect();
+
+    let glimpse = GLIMPSES[rng.random_range(0..GLIMPSES.len())];
+    let text = format!("Mirrors line the walls. In the nearest, {} of you look back. {}\n", depth + 1, glimpse);
+    let mut contents = Contents(Default::default());
+    contents.0.insert(MIRROR_FILE.to_string(), Content::InMemory(FileData::new(text)));
+    (Directories(ways), contents)
+}
+
+// A generator seeded from the hash of `path` (ChaCha8, like the cave of
+// dice, so a hall doesn't change with the rand version)
+fn rng_for(path: &DirPath) -> ChaCha8Rng {
+    ChaCha8Rng::from_seed(sha256(path.to_string().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_halls_come_from_their_paths() {
+        let deep = DirPath::parse("/abyss/mirror_halls/left/left/left", &DirPath::root());
+        assert_eq!(hall(&deep).0.0, hall(&deep).0.0);
+        let mirror = |path: &DirPath| match hall(path).1.0.remove(MIRROR_FILE) {
+            Some(Content::InMemory(data)) => data.text_lossy().into_owned(),
+            _ => String::new(),
+        };
+        assert!(mirror(&deep).starts_with("Mirrors line the walls. In the nearest, 4 of you look back."));
+        assert!(!hall(&entrance()).0.0.is_empty());
+    }
+
+    #[test]
+    fn test_mirror_halls_go_on_forever() {
+        enable(1);
+        run("cd /abyss/mirror_halls");
+        // Always somewhere further to go, however deep
+        let mut route = Vec::new();
+        for _ in 0..40 {
+            let ways = run("ls");
+            let way = ways.iter().find(|entry| entry.ends_with('/')).unwrap().clone();
+            assert_eq!(run(&format!("cd {}", way)), Vec::<String>::new());
+            route.push(way);
+        }
+        assert!(run("cat mirror.txt")[0].starts_with("Mirrors line the walls. In the nearest, 41 of you look back."));
+        let here = run("ls");
+
+        // And the same halls on another visit
+        let path = format!("/abyss/mirror_halls/{}", route.concat());
+        std::thread::spawn(move || {
+            enable(2);
+            assert_eq!(run(&format!("cd {}", path)), Vec::<String>::new());
+            assert_eq!(run("ls"), here);
+        }).join().unwrap();
+
+        // A hall that's removed stays gone
+        run("cd /abyss/mirror_halls");
+        let way = run("ls").into_iter().find(|entry| entry.ends_with('/')).unwrap();
+        run(&format!("rm -r {}", way));
+        assert_eq!(run(&format!("cd {}", way)), [format!("cd: {}: No such directory", way)]);
+    }
+}
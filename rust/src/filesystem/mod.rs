@@ -1,13 +1,26 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 
 pub mod file_paths;
 pub mod types;
 pub mod virtual_fs;
 pub mod helpers;
 pub mod abyss;
+pub mod abyss_delta;
 pub mod cave_of_dice;
+pub mod schedule;
+pub mod events;
+pub mod fetch_cache;
+pub mod fetch_coordinator;
+pub mod front_matter;
+pub mod manifest;
+pub mod mirror_halls;
+pub mod prefetch;
+pub mod quota;
+pub mod regions;
+pub mod sniff;
 
-pub use types::{Manifest, Content, NextDir, DirPath, FilePath};
+pub use types::{Manifest, Content, EntryKind, FileData, Metadata, NextDir, DirPath, FilePath};
 pub use virtual_fs::VirtualFilesystem;
 pub use abyss::{AbyssFileSystem, Contents, Directories};
 
@@ -17,4 +30,8 @@ thread_local! {
     pub static VIRTUAL_FS: RefCell<VirtualFilesystem> = RefCell::new(VirtualFilesystem::new());
     pub static ABYSS_FS: RefCell<AbyssFileSystem> = RefCell::new(AbyssFileSystem::new());
     pub static CAVE_OF_DICE_INITIALISED : RefCell<bool> = RefCell::new(false);
+    // Owner mode reveals drafts and future-dated posts (unlocked through `secret`)
+    pub static OWNER_MODE: RefCell<bool> = const { RefCell::new(false) };
+    // Metadata of static files, recorded when they are fetched
+    pub static FETCHED_METADATA: RefCell<HashMap<FilePath, Metadata>> = RefCell::new(HashMap::new());
 }
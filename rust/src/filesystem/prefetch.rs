@@ -0,0 +1,109 @@
+//! Fetching an abyss directory's listings in the background as soon as it's
+//! entered, so the `ls` or `cat` that usually follows doesn't wait on the
+//! network. The setting prefetch turns it off ("off"), or has it fetch the
+//! first few files of the directory into the cache as well ("files").
+
+use crate::preferences;
+use crate::settings::get_setting;
+use super::{ABYSS_FS, Content, DirPath, FilePath};
+use super::abyss::{Contents, Directories};
+use super::regions::in_region;
+use super::events::{subscribe, FsEvent};
+use super::helpers::{fetch_text, get_file_content, path_in_abyss};
+
+const PREFETCH_KEY: &str = "prefetch";
+
+/// With prefetch = files, how many files of each directory are fetched
+pub const PREFETCH_FILES: usize = 8;
+
+// The visitor's prefetch preference overrides the site's setting
+fn enabled() -> bool {
+    preferences::prefetch()
+}
+
+fn prefetch_files() -> bool {
+    get_setting(PREFETCH_KEY).as_deref() == Some("files")
+}
+
+/// Prefetch directories as they're entered
+pub fn register() {
+    subscribe(|event| {
+        if let FsEvent::DirEntered(dirpath) = event
+            && enabled()
+            && path_in_abyss(dirpath)
+        {
+            let dirpath = dirpath.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                prefetch(&dirpath).await;
+            });
+        }
+    });
+}
+
+/// Fetch whatever of `dirpath`'s listings (and, if asked, files) isn't kept yet.
+/// Failures are left for the command that needs the data to report.
+pub async fn prefetch(dirpath: &DirPath) {
+    // Regions are made up on the spot, there's nothing to fetch
+    if in_region(dirpath) {
+        return;
+    }
+    let (has_dirs, has_files) = ABYSS_FS.with_borrow(|afs|
+        (afs.dirs.contains_key(dirpath), afs.files.contains_key(dirpath))
+    );
+    if !has_dirs && let Ok(text) = fetch_text(&format!("content{}/!!directories.txt", dirpath.to_string())).await {
+        // Something may have been created here while the listing was on its way
+        ABYSS_FS.with_borrow_mut(|afs| {
+            afs.dirs.entry(dirpath.clone()).or_insert_with(|| Directories::from_file(&text));
+        });
+    }
+    if !has_files && let Ok(text) = fetch_text(&format!("content{}/!!contents.txt", dirpath.to_string())).await {
+        ABYSS_FS.with_borrow_mut(|afs| {
+            afs.files.entry(dirpath.clone()).or_insert_with(|| Contents::from_file(&text));
+        });
+    }
+
+    if !prefetch_files() {
+        return;
+    }
+    let mut names: Vec<String> = ABYSS_FS.with_borrow(|afs| {
+        afs.files.get(dirpath).map(|contents|
+            contents.0.iter()
+                .filter(|(_, content)| matches!(content, Content::ToFetch))
+                .map(|(name, _)| name.clone())
+                .collect()
+        ).unwrap_or_default()
+    });
+    names.sort();
+    for name in names.into_iter().take(PREFETCH_FILES) {
+        get_file_content(&FilePath { dir: dirpath.clone(), file: name }).await.ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{DirPath, FilePath};
+    use crate::fixtures::{block_on, enable, offline, run};
+
+    #[test]
+    fn test_entered_abyss_directories_are_prefetched() {
+        enable(1);
+        let level1 = DirPath::parse("/abyss/level1", &DirPath::root());
+        block_on(prefetch(&DirPath::parse("/abyss", &DirPath::root())));
+        block_on(prefetch(&level1));
+
+        // Listings are kept, so ls doesn't need the network
+        assert!(offline(|| run("ls /abyss/level1")).concat().contains("note.txt"));
+
+        // With prefetch = files, small files are fetched into the cache too
+        enable(1);
+        crate::settings::set_setting("prefetch", "files");
+        block_on(prefetch(&level1));
+        crate::settings::set_setting("prefetch", "");
+        let note = FilePath::parse("/abyss/level1/note.txt", &DirPath::root());
+        assert!(matches!(
+            crate::filesystem::ABYSS_FS.with_borrow(|afs| afs.files[&level1].get(&note.file).cloned()),
+            Some(crate::filesystem::Content::Fetched(_))
+        ));
+    }
+}
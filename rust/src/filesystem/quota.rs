@@ -0,0 +1,99 @@
+use super::{ABYSS_FS, Content, FilePath, VIRTUAL_FS};
+use super::helpers::path_in_abyss;
+use crate::settings::get_setting;
+
+/// Settings key for the write quota in bytes
+pub const QUOTA_KEY: &str = "quota.bytes";
+pub const DEFAULT_QUOTA: usize = 5 * 1024 * 1024;
+
+/// The write quota in bytes
+pub fn limit() -> usize {
+    get_setting(QUOTA_KEY)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_QUOTA)
+}
+
+fn in_memory_size(content: &Content) -> usize {
+    match content {
+        Content::InMemory(data) => data.bytes.len(),
+        // Cached site files have their own limit (see fetch_cache)
+        Content::ToFetch | Content::Fetched(_) => 0,
+    }
+}
+
+/// Bytes held in memory by files written this session
+pub fn used() -> usize {
+    let vfs_used: usize = VIRTUAL_FS.with_borrow(|vfs|
+        vfs.content.values().flat_map(|files| files.values()).map(in_memory_size).sum()
+    );
+    let abyss_used: usize = ABYSS_FS.with_borrow(|afs|
+        afs.files.values().flat_map(|contents| contents.0.values()).map(in_memory_size).sum()
+    );
+    vfs_used + abyss_used
+}
+
+// Size of the in-memory copy of a file, which a write would replace
+fn existing_size(filepath: &FilePath) -> usize {
+    if path_in_abyss(&filepath.dir) {
+        ABYSS_FS.with_borrow(|afs|
+            afs.files.get(&filepath.dir)
+                .and_then(|contents| contents.get(&filepath.file))
+                .map(in_memory_size)
+                .unwrap_or(0)
+        )
+    } else {
+        VIRTUAL_FS.with_borrow(|vfs| vfs.get_content(filepath).map(in_memory_size).unwrap_or(0))
+    }
+}
+
+fn fits(used: usize, replaced: usize, added: usize, limit: usize) -> bool {
+    used.saturating_sub(replaced) + added <= limit
+}
+
+/// Check that writing `size` bytes to `filepath` stays within the quota
+pub fn check_write(filepath: &FilePath, size: usize) -> Result<(), String> {
+    let (used, limit) = (used(), limit());
+    if fits(used, existing_size(filepath), size, limit) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Write quota exceeded: {} would need {}, but {} of {} is already used (see 'quota')",
+            filepath.to_string(), format_size(size), format_size(used), format_size(limit)
+        ))
+    }
+}
+
+/// Human readable byte count, e.g. "1.5 KB"
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fits() {
+        assert!(fits(90, 0, 10, 100));
+        assert!(!fits(90, 0, 11, 100));
+        // Overwriting a file frees its old size first
+        assert!(fits(90, 50, 60, 100));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+}
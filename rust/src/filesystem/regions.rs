@@ -0,0 +1,68 @@
+//! Procedural regions of the abyss: places made up on the spot instead of
+//! fetched from the server. Each region claims its paths with a predicate,
+//! which builds the region into ABYSS_FS the first time it's asked about
+//! one, and can generate some of its files afresh each time they're read
+//! (the cave of dice's dice).
+//!
+//! A new region is a `Region` added to REGIONS; the filesystem helpers ask
+//! here rather than knowing about any region themselves.
+
+use super::{cave_of_dice, mirror_halls, DirPath, FilePath};
+
+pub struct Region {
+    /// Whether `path` is in the region, building the region if it hasn't been
+    pub contains: fn(&DirPath) -> bool,
+    /// Whether the region makes up a file's content when it's read (the
+    /// file is in the region)
+    pub generates: fn(&FilePath) -> bool,
+    /// A generated file's content this time it's read
+    pub generate: fn(&FilePath) -> String,
+}
+
+/// Every region, checked in order
+pub const REGIONS: &[Region] = &[
+    cave_of_dice::REGION,
+    mirror_halls::REGION,
+];
+
+/// The region `path` is in, if any (building it if need be)
+pub fn region_of(path: &DirPath) -> Option<&'static Region> {
+    REGIONS.iter().find(|region| (region.contains)(path))
+}
+
+/// Whether `path` is in a region, so made up rather than fetched
+pub fn in_region(path: &DirPath) -> bool {
+    region_of(path).is_some()
+}
+
+/// Whether a file's content is generated when read, rather than fetched
+pub fn is_generated(filepath: &FilePath) -> bool {
+    region_of(&filepath.dir).is_some_and(|region| (region.generates)(filepath))
+}
+
+/// A generated file's content, read now (None if it isn't generated)
+pub fn generate(filepath: &FilePath) -> Option<String> {
+    region_of(&filepath.dir)
+        .filter(|region| (region.generates)(filepath))
+        .map(|region| (region.generate)(filepath))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_claim_their_paths() {
+        let root = DirPath::root();
+        let die = FilePath::parse("/abyss/level1/level2/cave_of_dice/d6/d6.txt", &root);
+        assert!(in_region(&die.dir));
+        assert!(is_generated(&die));
+        assert!(generate(&die).is_some_and(|rolled| rolled.starts_with("You rolled a ")));
+
+        let readme = FilePath::parse("/abyss/level1/level2/cave_of_dice/README.md", &root);
+        assert!(in_region(&readme.dir) && !is_generated(&readme));
+        let outside = FilePath::parse("/abyss/level1/d6.txt", &root);
+        assert!(!in_region(&outside.dir));
+        assert_eq!(generate(&outside), None);
+    }
+}
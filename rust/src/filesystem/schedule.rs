@@ -0,0 +1,59 @@
+use serde::Deserialize;
+
+/// Publication state of a static post, taken from its front matter by the manifest generator
+#[derive(Deserialize, Clone, Default)]
+pub struct Schedule {
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub publish_date: Option<String>,
+}
+
+impl Schedule {
+    /// Whether the post should be visible to regular visitors on `today` (YYYY-MM-DD)
+    pub fn is_published(&self, today: &str) -> bool {
+        if self.draft {
+            return false;
+        }
+        match &self.publish_date {
+            // ISO dates compare correctly as strings
+            Some(date) => date.as_str() <= today,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+    use crate::filesystem::{DirPath, FilePath, OWNER_MODE, VIRTUAL_FS};
+
+    #[test]
+    fn test_draft_hidden() {
+        let schedule = Schedule { draft: true, publish_date: None };
+        assert!(!schedule.is_published("2025-01-01"));
+    }
+
+    #[test]
+    fn test_publish_date() {
+        let schedule = Schedule { draft: false, publish_date: Some("2025-06-01".to_string()) };
+        assert!(!schedule.is_published("2025-05-31"));
+        assert!(schedule.is_published("2025-06-01"));
+        assert!(schedule.is_published("2026-01-01"));
+    }
+
+    #[test]
+    fn test_drafts_cant_be_read() {
+        enable(1);
+        let draft = Schedule { draft: true, publish_date: None };
+        VIRTUAL_FS.with_borrow_mut(|vfs| vfs.schedules.insert(FilePath::parse("/about.txt", &DirPath::root()), draft));
+        assert_eq!(run("cat about.txt"), ["cat: about.txt: No such file"]);
+        assert_eq!(crate::read_file("/about.txt"), "NotFound");
+        assert_eq!(run("rm about.txt"), ["rm: about.txt: No such file"]);
+
+        OWNER_MODE.with_borrow_mut(|x| *x = true);
+        assert_eq!(crate::read_file("/about.txt"), "ToFetch:./content/about.txt");
+        assert_eq!(run("rm about.txt"), ["rm: about.txt: Read-only file (use rm -f to remove it anyway)"]);
+    }
+}
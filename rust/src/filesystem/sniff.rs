@@ -0,0 +1,134 @@
+//! Working out what a file holds from its content (used by `file` and `pretty`).
+//! Magic bytes identify binary formats; text is recognised by its structure,
+//! with the file extension only breaking ties for markdown, HTML and TOML.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileType {
+    Empty,
+    Zip,
+    Png,
+    Jpeg,
+    Gif,
+    Pdf,
+    Binary,
+    Json,
+    Html,
+    Toml,
+    Markdown,
+    Text,
+}
+
+impl FileType {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FileType::Empty => "empty",
+            FileType::Zip => "Zip archive",
+            FileType::Png => "PNG image",
+            FileType::Jpeg => "JPEG image",
+            FileType::Gif => "GIF image",
+            FileType::Pdf => "PDF document",
+            FileType::Binary => "binary data",
+            FileType::Json => "JSON data",
+            FileType::Html => "HTML document",
+            FileType::Toml => "TOML document",
+            FileType::Markdown => "Markdown document",
+            FileType::Text => "text",
+        }
+    }
+
+    /// Whether `pretty` can render it
+    pub fn is_renderable(&self) -> bool {
+        matches!(self, FileType::Markdown | FileType::Html)
+    }
+}
+
+const MAGIC: &[(&[u8], FileType)] = &[
+    (b"PK\x03\x04", FileType::Zip),
+    (b"PK\x05\x06", FileType::Zip), // empty archive
+    (b"\x89PNG\r\n\x1a\n", FileType::Png),
+    (b"\xff\xd8\xff", FileType::Jpeg),
+    (b"GIF87a", FileType::Gif),
+    (b"GIF89a", FileType::Gif),
+    (b"%PDF-", FileType::Pdf),
+];
+
+/// What the file `name` holds, judging by `bytes`
+pub fn sniff(name: &str, bytes: &[u8]) -> FileType {
+    if bytes.is_empty() {
+        return FileType::Empty;
+    }
+    if let Some((_, file_type)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return *file_type;
+    }
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return FileType::Binary;
+    };
+
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    let start = text.trim_start().to_ascii_lowercase();
+    if (start.starts_with('{') || start.starts_with('[')) && serde_json::from_str::<serde_json::Value>(text).is_ok() {
+        FileType::Json
+    } else if extension == "html" || extension == "htm" || start.starts_with("<!doctype html") || start.starts_with("<html") {
+        FileType::Html
+    } else if extension == "md" || extension == "markdown" {
+        FileType::Markdown
+    } else if extension == "toml" || looks_like_toml(text) {
+        // Before markdown: a "# comment" line would pass for a heading
+        FileType::Toml
+    } else if looks_like_markdown(text) {
+        FileType::Markdown
+    } else {
+        FileType::Text
+    }
+}
+
+// Front matter, a heading, or a fenced code block
+fn looks_like_markdown(text: &str) -> bool {
+    text.starts_with("---\n") || text.lines().any(|line| {
+        let heading = line.trim_start_matches('#');
+        (heading.len() < line.len() && line.len() - heading.len() <= 6 && heading.starts_with(' '))
+            || line.starts_with("```")
+    })
+}
+
+// Every line is a [table], a key = value pair, a comment or blank, with at least one pair
+fn looks_like_toml(text: &str) -> bool {
+    let mut pairs = 0;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || (line.starts_with('[') && line.ends_with(']')) {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) if is_toml_key(key.trim()) && !value.trim().is_empty() => pairs += 1,
+            _ => return false,
+        }
+    }
+    pairs > 0
+}
+
+fn is_toml_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || "_-.\"".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff("a.txt", b""), FileType::Empty);
+        assert_eq!(sniff("a.bin", b"PK\x03\x04rest"), FileType::Zip);
+        assert_eq!(sniff("a.bin", b"\x89PNG\r\n\x1a\n...."), FileType::Png);
+        assert_eq!(sniff("a.bin", b"\xff\x00\xfe"), FileType::Binary);
+        assert_eq!(sniff("a.txt", br#"{"a": [1, 2]}"#), FileType::Json);
+        assert_eq!(sniff("a.txt", b"{ not json"), FileType::Text);
+        assert_eq!(sniff("page", b"<!DOCTYPE html>\n<p>hi</p>"), FileType::Html);
+        assert_eq!(sniff("fragment.html", b"<p>hi</p>"), FileType::Html);
+        assert_eq!(sniff("notes.md", b"just words"), FileType::Markdown);
+        assert_eq!(sniff("notes.txt", b"intro\n\n## Section\ntext"), FileType::Markdown);
+        assert_eq!(sniff("notes.txt", b"#hashtag\nnot a heading"), FileType::Text);
+        assert_eq!(sniff("config", b"# settings\n[site]\ntitle = \"Blog\"\nposts = 3\n"), FileType::Toml);
+        assert_eq!(sniff("empty.toml", b"# nothing yet\n"), FileType::Toml);
+        assert_eq!(sniff("about.txt", b"Hello, I write about maths = fun.\nMore text"), FileType::Text);
+    }
+}
@@ -1,14 +1,23 @@
 use serde::Deserialize;
 use super::VIRTUAL_FS;
+use super::schedule::Schedule;
+use super::front_matter::FrontMatter;
 
 #[derive(Deserialize, Clone)]
 pub struct FileEntry {
     pub name: String,
     pub path: String,
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    #[serde(flatten)]
+    pub front_matter: FrontMatter,
 }
 
 #[derive(Deserialize, Clone)]
 pub struct Manifest {
+    // Missing in manifests from before versioning (see manifest::MANIFEST_VERSION)
+    #[serde(default)]
+    pub version: Option<u64>,
     pub files: Vec<FileEntry>,
     pub directories: Vec<String>,
 }
@@ -16,8 +25,105 @@ pub struct Manifest {
 /// Content can either be in memory or needs to be fetched
 #[derive(Clone)]
 pub enum Content {
-    InMemory(String),
+    InMemory(FileData),
     ToFetch,
+    // A site file whose text has been fetched and kept (see fetch_cache)
+    Fetched(String),
+}
+
+/// Size and timestamps of a file (times in milliseconds since the epoch)
+#[derive(Clone)]
+pub struct Metadata {
+    pub size: usize,
+    pub created: f64,
+    pub modified: f64,
+}
+
+/// An in-memory file along with its metadata.
+/// Files hold raw bytes, so binary uploads survive; most are UTF-8 text.
+#[derive(Clone)]
+pub struct FileData {
+    pub bytes: Vec<u8>,
+    pub meta: Metadata,
+}
+
+impl FileData {
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        let now = crate::js_interop::now();
+        Self::with_times(bytes, now, now)
+    }
+
+    pub fn with_times(bytes: impl Into<Vec<u8>>, created: f64, modified: f64) -> Self {
+        let bytes = bytes.into();
+        Self {
+            meta: Metadata { size: bytes.len(), created, modified },
+            bytes,
+        }
+    }
+
+    /// New data for a file being written over `previous`, keeping its creation time
+    pub fn overwriting(previous: Option<&Content>, bytes: impl Into<Vec<u8>>) -> Self {
+        match previous {
+            Some(Content::InMemory(old)) => Self::with_times(bytes, old.meta.created, crate::js_interop::now()),
+            _ => Self::new(bytes),
+        }
+    }
+
+    /// The content as text, or None for a binary (non-UTF-8) file
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.bytes).ok()
+    }
+
+    /// The content as text, with invalid UTF-8 replaced
+    pub fn text_lossy(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+}
+
+// What a directory entry is, judged from its listed name (directories end in '/')
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EntryKind {
+    Dir,
+    Markdown,
+    Text,
+    Binary,
+    Script,
+}
+
+impl EntryKind {
+    pub fn of(name: &str) -> Self {
+        if name.ends_with('/') {
+            return EntryKind::Dir;
+        }
+        let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "md" | "markdown" => EntryKind::Markdown,
+            "sh" | "hsh" => EntryKind::Script,
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "ico" | "pdf" | "zip" | "gz" | "wasm" | "mp3" | "mp4" | "woff" | "woff2" | "bin" => EntryKind::Binary,
+            _ => EntryKind::Text,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EntryKind::Dir => "dir",
+            EntryKind::Markdown => "markdown",
+            EntryKind::Text => "text",
+            EntryKind::Binary => "binary",
+            EntryKind::Script => "script",
+        }
+    }
+
+    // CSS class used to colour the entry in the terminal
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            EntryKind::Dir => "ls-dir",
+            EntryKind::Markdown => "ls-markdown",
+            EntryKind::Text => "ls-text",
+            EntryKind::Binary => "ls-binary",
+            EntryKind::Script => "ls-script",
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -216,6 +322,11 @@ impl DirPath {
         }
     }
 
+    // Check if this is `ancestor` or somewhere below it
+    pub fn is_within(&self, ancestor: &DirPath) -> bool {
+        self.0.starts_with(&ancestor.0)
+    }
+
     // Get final component (directory name)
     pub fn final_component(&self) -> Option<&str> {
         match self.0.last() {
@@ -229,6 +340,25 @@ impl DirPath {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_is_within() {
+        let blog = DirPath::parse("/blog", &DirPath::root());
+        assert!(DirPath::parse("/blog/maths", &DirPath::root()).is_within(&blog));
+        assert!(blog.is_within(&blog));
+        assert!(blog.is_within(&DirPath::root()));
+        assert!(!DirPath::parse("/blogroll", &DirPath::root()).is_within(&blog));
+    }
+
+    #[test]
+    fn test_entry_kind() {
+        assert_eq!(EntryKind::of("blog/"), EntryKind::Dir);
+        assert_eq!(EntryKind::of("post.MD"), EntryKind::Markdown);
+        assert_eq!(EntryKind::of("notes.txt"), EntryKind::Text);
+        assert_eq!(EntryKind::of("README"), EntryKind::Text);
+        assert_eq!(EntryKind::of("photo.png"), EntryKind::Binary);
+        assert_eq!(EntryKind::of("setup.hsh"), EntryKind::Script);
+    }
+
     #[test]
     fn test_root_path() {
         let path = DirPath::root();
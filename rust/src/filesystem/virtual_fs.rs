@@ -1,15 +1,26 @@
-use std::collections::HashMap;
-use super::types::{DirPath, FilePath, Content, Manifest, NextDir};
+use std::collections::{HashMap, HashSet};
+use super::types::{DirPath, FilePath, Content, FileData, Manifest, NextDir};
+use super::schedule::Schedule;
+use super::front_matter::FrontMatter;
 
 /// Virtual filesystem stored in WASM memory
 pub struct VirtualFilesystem {
     pub content: HashMap<DirPath, HashMap<String, Content>>,
+    /// Draft/publish_date info for static posts that aren't public yet
+    pub schedules: HashMap<FilePath, Schedule>,
+    /// Title, date and tags of static posts, from the manifest
+    pub front_matter: HashMap<FilePath, FrontMatter>,
+    /// Files from the manifest (site content), which need -f to overwrite or remove
+    pub read_only: HashSet<FilePath>,
 }
 
 impl VirtualFilesystem {
     pub fn new() -> Self {
         Self {
             content: HashMap::new(),
+            schedules: HashMap::new(),
+            front_matter: HashMap::new(),
+            read_only: HashSet::new(),
         }
     }
 
@@ -34,6 +45,21 @@ impl VirtualFilesystem {
                 dir.cd(&NextDir::In(component.to_string()), true);
             }
 
+            if file_entry.schedule.draft || file_entry.schedule.publish_date.is_some() {
+                self.schedules.insert(
+                    FilePath::new(dir.clone(), file_entry.name.clone()),
+                    file_entry.schedule.clone()
+                );
+            }
+
+            if !file_entry.front_matter.is_empty() {
+                self.front_matter.insert(
+                    FilePath::new(dir.clone(), file_entry.name.clone()),
+                    file_entry.front_matter.clone()
+                );
+            }
+
+            self.read_only.insert(FilePath::new(dir.clone(), file_entry.name.clone()));
             self.content
                 .entry(dir)
                 .or_insert_with(HashMap::new)
@@ -41,12 +67,40 @@ impl VirtualFilesystem {
         }
     }
 
+    /// Check if a static file is an unpublished draft or scheduled for after `today`.
+    /// Files the user has overwritten in memory are always visible.
+    pub fn is_unpublished(&self, filepath: &FilePath, today: &str) -> bool {
+        match (self.schedules.get(filepath), self.get_content(filepath)) {
+            (Some(schedule), Some(Content::ToFetch | Content::Fetched(_))) => !schedule.is_published(today),
+            _ => false,
+        }
+    }
+
+    /// Check if a file is site content (stays read-only even once overwritten with -f)
+    pub fn is_read_only(&self, filepath: &FilePath) -> bool {
+        self.read_only.contains(filepath)
+    }
+
+    /// Check if a directory or anything below it holds read-only files
+    pub fn has_read_only_within(&self, dirpath: &DirPath) -> bool {
+        self.read_only.iter().any(|file| file.dir.is_within(dirpath))
+    }
+
     /// Write a file to the virtual filesystem (in memory)
-    pub fn write_file(&mut self, filepath: &FilePath, content: String) {
+    pub fn write_file(&mut self, filepath: &FilePath, content: impl Into<Vec<u8>>) {
+        let files = self.content
+            .entry(filepath.dir.clone())
+            .or_insert_with(HashMap::new);
+        let data = FileData::overwriting(files.get(&filepath.file), content);
+        files.insert(filepath.file.clone(), Content::InMemory(data));
+    }
+
+    /// Write a file with existing metadata (e.g. restored from a session)
+    pub fn write_file_data(&mut self, filepath: &FilePath, data: FileData) {
         self.content
             .entry(filepath.dir.clone())
-            .or_insert_with(HashMap::new)
-            .insert(filepath.file.clone(), Content::InMemory(content));
+            .or_default()
+            .insert(filepath.file.clone(), Content::InMemory(data));
     }
 
     /// Get content type for a file
@@ -65,6 +119,8 @@ impl VirtualFilesystem {
     /// Remove a file from the virtual filesystem
     pub fn remove_file(&mut self, filepath: &FilePath) -> bool {
         if let Some(files) = self.content.get_mut(&filepath.dir) {
+            // A new file created in its place belongs to the user
+            self.read_only.remove(filepath);
             files.remove(&filepath.file).is_some()
         } else {
             false
@@ -112,6 +168,18 @@ impl VirtualFilesystem {
         Ok(())
     }
 
+    /// Remove a directory and everything below it
+    pub fn remove_dir_all(&mut self, dirpath: &DirPath) -> Result<(), String> {
+        if !self.content.contains_key(dirpath) {
+            return Err("Directory does not exist".to_string());
+        }
+
+        self.content.retain(|dir, _| !dir.is_within(dirpath));
+        self.schedules.retain(|file, _| !file.dir.is_within(dirpath));
+        self.read_only.retain(|file| !file.dir.is_within(dirpath));
+        Ok(())
+    }
+
     /// List all files in a given directory (returns just filenames)
     pub fn list_files_in_dir(&self, dirpath: &DirPath) -> Vec<String> {
         if let Some(files) = self.content.get(dirpath) {
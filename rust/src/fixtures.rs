@@ -0,0 +1,160 @@
+//! Fixture mode: a deterministic terminal for tests and reproducible demos.
+//! Random numbers (dice, `random`) come from a fixed seed and fetches
+//! are served from the map below instead of the network. Only built for
+//! tests and with the `fixtures` feature, so release builds stay without it.
+
+use std::cell::RefCell;
+use crate::filesystem::{ABYSS_FS, AbyssFileSystem, CAVE_OF_DICE_INITIALISED, CURRENT_DIR, DirPath, Manifest, VIRTUAL_FS, VirtualFilesystem};
+use crate::filesystem::abyss_delta::{AbyssDelta, ABYSS_DELTA};
+
+// A site file, served at its content/ URL
+macro_rules! fixture {
+    ($path:literal) => {
+        (concat!("content/", $path), include_str!(concat!("../../site/content/", $path)))
+    };
+}
+
+const FIXTURES: &[(&str, &str)] = &[
+    fixture!(".tips.txt"),
+    fixture!(".translations.json"),
+    fixture!(".typetest.txt"),
+    fixture!("about.txt"),
+    fixture!("fortunes.txt"),
+    fixture!("config/features.toml"),
+    fixture!("rpg/lantern_road/!!player.toml"),
+    fixture!("rpg/lantern_road/enemies/rat_king.toml"),
+    fixture!("rpg/lantern_road/enemies/wolf.toml"),
+    fixture!("rpg/lantern_road/items/potion.toml"),
+    fixture!("rpg/lantern_road/items/rusty_sword.toml"),
+    fixture!("rpg/lantern_road/locations/cellar/!!connections.txt"),
+    fixture!("rpg/lantern_road/locations/cellar/!!location.toml"),
+    fixture!("rpg/lantern_road/locations/forest/!!connections.toml"),
+    fixture!("rpg/lantern_road/locations/forest/!!location.toml"),
+    fixture!("rpg/lantern_road/locations/old_mill/!!connections.txt"),
+    fixture!("rpg/lantern_road/locations/old_mill/!!location.toml"),
+    fixture!("rpg/lantern_road/locations/village/!!connections.txt"),
+    fixture!("rpg/lantern_road/locations/village/!!location.toml"),
+    fixture!("rpg/lantern_road/npcs/maud.toml"),
+    fixture!("rpg/lantern_road/quests/lights_out.toml"),
+    fixture!("abyss/!!contents.txt"),
+    fixture!("abyss/!!directories.txt"),
+    fixture!("abyss/warning.txt"),
+    fixture!("abyss/level1/!!contents.txt"),
+    fixture!("abyss/level1/!!directories.txt"),
+    fixture!("abyss/level1/note.txt"),
+    fixture!("abyss/level1/level2/!!contents.txt"),
+    fixture!("abyss/level1/level2/!!directories.txt"),
+    fixture!("abyss/level1/level2/echo.txt"),
+    fixture!("abyss/level1/level2/cave_of_dice/!!contents.txt"),
+    fixture!("abyss/level1/level2/cave_of_dice/!!directories.txt"),
+    fixture!("abyss/level1/level2/level3/!!contents.txt"),
+    fixture!("abyss/level1/level2/level3/!!directories.txt"),
+    fixture!("abyss/level1/level2/level3/deeper.txt"),
+    fixture!("abyss/level1/level2/level3/level4/!!contents.txt"),
+    fixture!("abyss/level1/level2/level3/level4/!!directories.txt"),
+    fixture!("abyss/level1/level2/level3/level4/almost.txt"),
+    fixture!("abyss/level1/level2/level3/level4/level5/!!contents.txt"),
+    fixture!("abyss/level1/level2/level3/level4/level5/!!directories.txt"),
+    fixture!("abyss/level1/level2/level3/level4/level5/treasure.txt"),
+    // Somewhere else, for fetch
+    ("https://raw.githubusercontent.com/HaineSensei/examples/main/hello.txt", "Hello from elsewhere\n"),
+];
+
+const MANIFEST: &str = r#"{
+    "version": 1,
+    "files": [
+        { "name": ".tips.txt", "path": "" },
+        { "name": ".typetest.txt", "path": "" },
+        { "name": "about.txt", "path": "" },
+        { "name": "fortunes.txt", "path": "" },
+        { "name": "features.toml", "path": "config" },
+        { "name": "!!player.toml", "path": "rpg/lantern_road" },
+        { "name": "rat_king.toml", "path": "rpg/lantern_road/enemies" },
+        { "name": "wolf.toml", "path": "rpg/lantern_road/enemies" },
+        { "name": "potion.toml", "path": "rpg/lantern_road/items" },
+        { "name": "rusty_sword.toml", "path": "rpg/lantern_road/items" },
+        { "name": "!!connections.txt", "path": "rpg/lantern_road/locations/cellar" },
+        { "name": "!!location.toml", "path": "rpg/lantern_road/locations/cellar" },
+        { "name": "!!connections.toml", "path": "rpg/lantern_road/locations/forest" },
+        { "name": "!!location.toml", "path": "rpg/lantern_road/locations/forest" },
+        { "name": "!!connections.txt", "path": "rpg/lantern_road/locations/old_mill" },
+        { "name": "!!location.toml", "path": "rpg/lantern_road/locations/old_mill" },
+        { "name": "!!connections.txt", "path": "rpg/lantern_road/locations/village" },
+        { "name": "!!location.toml", "path": "rpg/lantern_road/locations/village" },
+        { "name": "maud.toml", "path": "rpg/lantern_road/npcs" },
+        { "name": "lights_out.toml", "path": "rpg/lantern_road/quests" }
+    ],
+    "directories": ["abyss", "config", "rpg", "rpg/lantern_road", "rpg/lantern_road/enemies", "rpg/lantern_road/items", "rpg/lantern_road/locations", "rpg/lantern_road/locations/cellar", "rpg/lantern_road/locations/forest", "rpg/lantern_road/locations/old_mill", "rpg/lantern_road/locations/village", "rpg/lantern_road/npcs", "rpg/lantern_road/quests"]
+}"#;
+
+thread_local! {
+    static ENABLED: RefCell<bool> = const { RefCell::new(false) };
+}
+
+/// Switch this thread's terminal to fixture mode, with a fresh filesystem
+pub fn enable(seed: u64) {
+    ENABLED.with_borrow_mut(|enabled| *enabled = true);
+    crate::rng::seed(seed);
+
+    let manifest: Manifest = serde_json::from_str(MANIFEST).expect("fixture manifest is valid");
+    VIRTUAL_FS.with_borrow_mut(|vfs| {
+        *vfs = VirtualFilesystem::new();
+        vfs.initialize_from_manifest(&manifest);
+    });
+    ABYSS_FS.with_borrow_mut(|afs| *afs = AbyssFileSystem::new());
+    CAVE_OF_DICE_INITIALISED.with_borrow_mut(|x| *x = false);
+    ABYSS_DELTA.with_borrow_mut(|delta| *delta = AbyssDelta::default());
+    CURRENT_DIR.with_borrow_mut(|cd| *cd = DirPath::root());
+}
+
+/// In fixture mode, the response to fetching `url`: the fixture text, or a 404
+pub fn fetch(url: &str) -> Option<Result<String, String>> {
+    if !ENABLED.with_borrow(|enabled| *enabled) {
+        return None;
+    }
+
+    let path = url.trim_start_matches("./");
+    Some(
+        FIXTURES.iter()
+            .find(|(fixture_url, _)| *fixture_url == path)
+            .map(|(_, text)| text.to_string())
+            .ok_or_else(|| format!("Failed to fetch {}: HTTP 404", url))
+    )
+}
+
+/// Run `f` with the site unreachable: fetches fail as if offline
+#[cfg(test)]
+pub fn offline<T>(f: impl FnOnce() -> T) -> T {
+    ENABLED.with_borrow_mut(|enabled| *enabled = false);
+    let result = f();
+    ENABLED.with_borrow_mut(|enabled| *enabled = true);
+    result
+}
+
+// Fixture fetches resolve immediately, so a future never has to wait
+#[cfg(test)]
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+    let mut future = std::pin::pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Type a line into the terminal and return what it printed (minus the echoed input)
+#[cfg(test)]
+pub fn run(input: &str) -> Vec<String> {
+    block_on(crate::handle_input(input));
+    crate::js_interop::take_output().into_iter().skip(1).collect()
+}
+
+/// Save over a site file, as the editor does once it's been opened with edit -f
+#[cfg(test)]
+pub fn overwrite(path: &str, content: &str) {
+    let filepath = CURRENT_DIR.with_borrow(|cd| crate::filesystem::FilePath::parse(path, cd));
+    crate::channels::allow_forced_edit(&filepath);
+    crate::write_file(path, content.to_string()).unwrap();
+}
@@ -0,0 +1,105 @@
+//! Fortunes: sayings picked at random from content/fortunes.txt, in the
+//! format of the Unix fortune files. Entries are separated by lines holding
+//! just `%`; a separator with a name after it (`% maths`) also starts a
+//! category, which holds every entry up to the next named separator.
+//!
+//! The file is fetched the first time `fortune` runs, and the filesystem
+//! keeps it from then on.
+
+use crate::filesystem::file_paths::FORTUNES_FILE_PATH;
+use crate::filesystem::helpers::get_file_content;
+use crate::rng::choose;
+
+#[derive(Debug, PartialEq)]
+pub struct Fortune {
+    /// Empty for entries before the first named separator
+    pub category: String,
+    pub text: String,
+}
+
+pub fn parse_fortunes(text: &str) -> Vec<Fortune> {
+    let mut fortunes = Vec::new();
+    let mut category = String::new();
+    let mut entry: Vec<&str> = Vec::new();
+    let mut finish = |category: &str, entry: &mut Vec<&str>| {
+        let text = entry.join("\n").trim_matches('\n').trim_end().to_string();
+        if !text.is_empty() {
+            fortunes.push(Fortune { category: category.to_string(), text });
+        }
+        entry.clear();
+    };
+    for line in text.lines() {
+        match line.strip_prefix('%') {
+            Some(name) => {
+                finish(&category, &mut entry);
+                if !name.trim().is_empty() {
+                    category = name.trim().to_lowercase();
+                }
+            }
+            None => entry.push(line),
+        }
+    }
+    finish(&category, &mut entry);
+    fortunes
+}
+
+pub async fn fortunes() -> Vec<Fortune> {
+    get_file_content(&FORTUNES_FILE_PATH).await
+        .map(|text| parse_fortunes(&text))
+        .unwrap_or_default()
+}
+
+/// A random fortune, from `category` if given
+pub async fn pick(category: Option<&str>) -> Option<String> {
+    let fortunes: Vec<Fortune> = fortunes().await.into_iter()
+        .filter(|fortune| category.is_none_or(|category| fortune.category == category.to_lowercase()))
+        .collect();
+    choose(&fortunes).map(|fortune| fortune.text.clone())
+}
+
+/// The named categories, in the file's order, with how many fortunes each has
+pub async fn categories() -> Vec<(String, usize)> {
+    let mut categories: Vec<(String, usize)> = Vec::new();
+    for fortune in fortunes().await.into_iter().filter(|fortune| !fortune.category.is_empty()) {
+        match categories.iter_mut().find(|(name, _)| *name == fortune.category) {
+            Some((_, count)) => *count += 1,
+            None => categories.push((fortune.category, 1)),
+        }
+    }
+    categories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    fn fortune(category: &str, text: &str) -> Fortune {
+        Fortune { category: category.to_string(), text: text.to_string() }
+    }
+
+    #[test]
+    fn test_parse_fortunes() {
+        let text = "Uncategorised.\n%\n% Maths\nA proof\n  over two lines.\n\n%\nQED.\n%\n%\n% computing\nIt works on my machine.\n%\n";
+        assert_eq!(parse_fortunes(text), [
+            fortune("", "Uncategorised."),
+            fortune("maths", "A proof\n  over two lines."),
+            fortune("maths", "QED."),
+            fortune("computing", "It works on my machine."),
+        ]);
+    }
+
+    #[test]
+    fn test_fortune() {
+        enable(1);
+        let fortune = run("fortune").join("\n");
+        let all = crate::fortune::parse_fortunes(include_str!("../../site/content/fortunes.txt"));
+        assert!(all.iter().any(|entry| entry.text == fortune));
+
+        assert_eq!(run("fortune -l"), ["maths          5", "computing      5", "terminal       4"]);
+        let terminal = run("fortune -c Terminal").join("\n");
+        assert!(all.iter().any(|entry| entry.category == "terminal" && entry.text == terminal));
+        assert_eq!(run("fortune -c poetry"), ["fortune: poetry: No such category"]);
+        assert_eq!(run("fortune -c"), ["Usage: fortune [-c <category>]", "       fortune -l"]);
+    }
+}
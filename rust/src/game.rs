@@ -0,0 +1,147 @@
+//! Game mode: a command starts a `Game` and from then on each key pressed
+//! goes straight to it, instead of being typed into the command line. The
+//! board is drawn again in place after every move. Escape, Ctrl+C or q
+//! leaves the game.
+//!
+//! A game can be saved to a file, written after every move, so it can be
+//! carried on later (and goes along with the rest of the session).
+
+use std::cell::RefCell;
+use crate::filesystem::FilePath;
+use crate::filesystem::helpers::write_file;
+use crate::js_interop::{add_output, remove_output_lines};
+use crate::{set_next_input_handler, NextInputHandler};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Char(char),
+}
+
+impl Key {
+    /// A key from its browser name ("ArrowUp", "w") or as typed ("up")
+    pub fn parse(name: &str) -> Option<Key> {
+        match name {
+            "ArrowUp" | "up" => Some(Key::Up),
+            "ArrowDown" | "down" => Some(Key::Down),
+            "ArrowLeft" | "left" => Some(Key::Left),
+            "ArrowRight" | "right" => Some(Key::Right),
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(Key::Char(c.to_ascii_lowercase())),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+pub trait Game {
+    /// The screen, a line at a time
+    fn draw(&self) -> Vec<String>;
+
+    /// Play a key (keys the game doesn't use are ignored)
+    fn play(&mut self, key: Key);
+
+    /// What to say once the game is over, or None while it goes on
+    fn over(&self) -> Option<String>;
+
+    /// The file the game is kept in and what to write there, if it's saved
+    fn save(&self) -> Option<(FilePath, String)>;
+}
+
+struct Playing {
+    game: Box<dyn Game>,
+    // Lines on screen from the last draw, to take away before the next
+    drawn: usize,
+}
+
+thread_local! {
+    static PLAYING: RefCell<Option<Playing>> = const { RefCell::new(None) };
+}
+
+/// Start playing `game`. Commands return this: the board is already shown.
+pub fn start(game: impl Game + 'static) -> String {
+    let drawn = draw(&game);
+    PLAYING.set(Some(Playing { game: Box::new(game), drawn }));
+    set_next_input_handler(NextInputHandler::Game);
+    String::new()
+}
+
+fn draw(game: &dyn Game) -> usize {
+    let lines = game.draw();
+    for line in &lines {
+        add_output(line);
+    }
+    lines.len()
+}
+
+pub fn is_playing() -> bool {
+    PLAYING.with_borrow(Option::is_some)
+}
+
+/// Handle a key pressed (or a line typed) while a game is being played
+pub async fn handle_key(name: &str) {
+    if matches!(name, "Escape" | "q" | "Q") {
+        stop();
+        return;
+    }
+    let Some(key) = Key::parse(name.trim()) else {
+        return;
+    };
+    let (save, over) = PLAYING.with_borrow_mut(|playing| {
+        let Some(playing) = playing else {
+            return (None, None);
+        };
+        playing.game.play(key);
+        remove_output_lines(playing.drawn);
+        playing.drawn = draw(playing.game.as_ref());
+        (playing.game.save(), playing.game.over())
+    });
+    if let Some((filepath, content)) = save {
+        let written = match crate::commands::builtin::filesystem::create_dir_all(&filepath.dir).await {
+            Ok(()) => write_file(&filepath, content).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            add_output(&format!("Couldn't save the game: {}", e));
+        }
+    }
+    if let Some(message) = over {
+        add_output(&message);
+        leave();
+    }
+}
+
+/// Leave the game part way through
+pub fn stop() {
+    let saved = PLAYING.with_borrow(|playing| playing.as_ref().is_some_and(|playing| playing.game.save().is_some()));
+    if saved {
+        add_output("Game saved: run it again to carry on.");
+    } else {
+        add_output("Game over.");
+    }
+    leave();
+}
+
+fn leave() {
+    PLAYING.set(None);
+    set_next_input_handler(NextInputHandler::None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_names() {
+        assert_eq!(Key::parse("ArrowLeft"), Some(Key::Left));
+        assert_eq!(Key::parse("down"), Some(Key::Down));
+        assert_eq!(Key::parse("W"), Some(Key::Char('w')));
+        assert_eq!(Key::parse("Shift"), None);
+    }
+}
@@ -0,0 +1,445 @@
+//! Syntax highlighting for the editor: a small lexer per language that
+//! splits a file's text into runs, each tagged with a kind ("keyword",
+//! "string", "comment" and so on, or "" for plain text). The runs cover the
+//! text exactly and in order, so the editor can lay them out behind its
+//! textarea. Markdown, TOML, JSON and Rust are known; anything else is one
+//! plain run.
+
+use crate::filesystem::front_matter;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Markdown,
+    Toml,
+    Json,
+    Rust,
+}
+
+impl Language {
+    /// The language a file's extension says it's written in
+    pub fn from_name(name: &str) -> Option<Language> {
+        let (_, extension) = name.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(Language::Markdown),
+            "toml" => Some(Language::Toml),
+            "json" => Some(Language::Json),
+            "rs" => Some(Language::Rust),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Markdown => "markdown",
+            Language::Toml => "toml",
+            Language::Json => "json",
+            Language::Rust => "rust",
+        }
+    }
+}
+
+/// A kind and the text it covers
+pub type Run<'a> = (&'static str, &'a str);
+
+/// `text` split into runs for `language` (one plain run if there's none)
+pub fn highlight(language: Option<Language>, text: &str) -> Vec<Run<'_>> {
+    let mut lexer = Lexer { text, pos: 0, runs: Vec::new() };
+    match language {
+        Some(Language::Markdown) => lexer.markdown(),
+        Some(Language::Toml) => lexer.toml(),
+        Some(Language::Json) => lexer.json(),
+        Some(Language::Rust) => lexer.rust(),
+        None => lexer.emit("", text.len()),
+    }
+    lexer.runs.into_iter().map(|(kind, start, end)| (kind, &text[start..end])).collect()
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+
+// Length of the number at the start of `s` (0 if there isn't one). With
+// `dates`, dashes and colons carry on a number so TOML dates are one run.
+fn number_len(s: &str, dates: bool) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = usize::from(matches!(bytes.first(), Some(b'-' | b'+')));
+    if !bytes.get(i).is_some_and(u8::is_ascii_digit) {
+        return 0;
+    }
+    while i < bytes.len() {
+        let b = bytes[i];
+        let continues = b.is_ascii_alphanumeric() || b == b'_'
+            || (b == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+            || (matches!(b, b'-' | b'+') && matches!(bytes[i - 1], b'e' | b'E'))
+            || (dates && matches!(b, b'-' | b':') && bytes[i - 1].is_ascii_digit());
+        if !continues {
+            break;
+        }
+        i += 1;
+    }
+    i
+}
+
+fn word_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(s.len())
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    // (kind, start, end)
+    runs: Vec<(&'static str, usize, usize)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn line_end(&self) -> usize {
+        self.rest().find('\n').map_or(self.text.len(), |i| self.pos + i)
+    }
+
+    // Tag the text up to `end` as `kind`, joining it to the last run if that's the same kind
+    fn emit(&mut self, kind: &'static str, end: usize) {
+        if end <= self.pos {
+            return;
+        }
+        match self.runs.last_mut() {
+            Some(last) if last.0 == kind => last.2 = end,
+            _ => self.runs.push((kind, self.pos, end)),
+        }
+        self.pos = end;
+    }
+
+    fn emit_len(&mut self, kind: &'static str, len: usize) {
+        self.emit(kind, self.pos + len);
+    }
+
+    fn emit_char(&mut self, kind: &'static str) {
+        let len = self.peek().map_or(0, char::len_utf8);
+        self.emit_len(kind, len);
+    }
+
+    // Where a string opened by `open_len` bytes and closed by `close` ends.
+    // An unclosed string runs to the end of the line (or text, if `multiline`).
+    fn string_end(&self, open_len: usize, close: &str, escapes: bool, multiline: bool) -> usize {
+        let start = self.pos + open_len;
+        let mut escaped = false;
+        for (i, c) in self.text[start..].char_indices() {
+            if escaped {
+                escaped = false;
+            } else if escapes && c == '\\' {
+                escaped = true;
+            } else if self.text[start + i..].starts_with(close) {
+                return start + i + close.len();
+            } else if c == '\n' && !multiline {
+                return start + i;
+            }
+        }
+        self.text.len()
+    }
+
+    fn rust(&mut self) {
+        while let Some(c) = self.peek() {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                let end = self.line_end();
+                self.emit("comment", end);
+            } else if let Some(body) = rest.strip_prefix("/*") {
+                let end = body.find("*/").map_or(self.text.len(), |i| self.pos + i + 4);
+                self.emit("comment", end);
+            } else if rest.starts_with("#[") || rest.starts_with("#![") {
+                let line = &rest[..self.line_end() - self.pos];
+                let end = line.find(']').map_or(line.len(), |i| i + 1);
+                self.emit_len("attr", end);
+            } else if let Some(hashes) = raw_string_hashes(rest) {
+                let open = rest.find('"').unwrap_or(0) + 1;
+                let close = format!("\"{}", "#".repeat(hashes));
+                let end = self.string_end(open, &close, false, true);
+                self.emit("string", end);
+            } else if rest.starts_with('"') || rest.starts_with("b\"") {
+                let end = self.string_end(rest.find('"').unwrap_or(0) + 1, "\"", true, true);
+                self.emit("string", end);
+            } else if c == '\'' {
+                // 'x' and '\n' are characters; anything else is a lifetime
+                let mut chars = rest[1..].chars();
+                let is_char = match chars.next() {
+                    Some('\\') => true,
+                    Some(_) => chars.next() == Some('\''),
+                    None => false,
+                };
+                if is_char {
+                    let end = self.string_end(1, "'", true, false);
+                    self.emit("string", end);
+                } else {
+                    self.emit_len("type", 1 + word_len(&rest[1..]));
+                }
+            } else if c.is_ascii_digit() {
+                self.emit_len("number", number_len(rest, false));
+            } else if c.is_alphabetic() || c == '_' {
+                let len = word_len(rest);
+                let word = &rest[..len];
+                let after = rest[len..].chars().next();
+                if RUST_KEYWORDS.contains(&word) {
+                    self.emit_len("keyword", len);
+                } else if after == Some('!') {
+                    self.emit_len("function", len + 1);
+                } else if after == Some('(') {
+                    self.emit_len("function", len);
+                } else if c.is_uppercase() {
+                    self.emit_len("type", len);
+                } else {
+                    self.emit_len("", len);
+                }
+            } else {
+                self.emit_char("");
+            }
+        }
+    }
+
+    fn json(&mut self) {
+        while let Some(c) = self.peek() {
+            let rest = self.rest();
+            if c == '"' {
+                let end = self.string_end(1, "\"", true, false);
+                // A string followed by a colon is an object's key
+                let kind = if self.text[end..].trim_start().starts_with(':') { "key" } else { "string" };
+                self.emit(kind, end);
+            } else if number_len(rest, false) > 0 {
+                self.emit_len("number", number_len(rest, false));
+            } else if c.is_alphabetic() {
+                let len = word_len(rest);
+                let kind = if matches!(&rest[..len], "true" | "false" | "null") { "keyword" } else { "" };
+                self.emit_len(kind, len);
+            } else {
+                self.emit_char("");
+            }
+        }
+    }
+
+    fn toml(&mut self) {
+        let mut line_start = true;
+        while let Some(c) = self.peek() {
+            let rest = self.rest();
+            if c == '\n' {
+                self.emit_len("", 1);
+                line_start = true;
+                continue;
+            }
+            if c.is_whitespace() {
+                self.emit_char("");
+                continue;
+            }
+            if c == '#' {
+                let end = self.line_end();
+                self.emit("comment", end);
+                continue;
+            }
+            if std::mem::take(&mut line_start) {
+                let line = &rest[..self.line_end() - self.pos];
+                // [table] and [[array.of.tables]]
+                if c == '[' {
+                    let close = if line.starts_with("[[") { "]]" } else { "]" };
+                    let end = line.find(close).map_or(line.len(), |i| i + close.len());
+                    self.emit_len("heading", end);
+                    continue;
+                }
+                // key = value, the key possibly dotted or quoted
+                if (c.is_alphanumeric() || matches!(c, '_' | '-' | '"' | '\''))
+                    && let Some(eq) = key_end(line)
+                {
+                    self.emit_len("key", line[..eq].trim_end().len());
+                    continue;
+                }
+            }
+
+            if rest.starts_with("\"\"\"") {
+                let end = self.string_end(3, "\"\"\"", true, true);
+                self.emit("string", end);
+            } else if rest.starts_with("'''") {
+                let end = self.string_end(3, "'''", false, true);
+                self.emit("string", end);
+            } else if c == '"' || c == '\'' {
+                let end = self.string_end(1, if c == '"' { "\"" } else { "'" }, c == '"', false);
+                self.emit("string", end);
+            } else if number_len(rest, true) > 0 {
+                self.emit_len("number", number_len(rest, true));
+            } else if c.is_alphabetic() {
+                let len = word_len(rest);
+                let kind = match &rest[..len] {
+                    "true" | "false" | "inf" | "nan" => "keyword",
+                    // Keys inside { inline = "tables" }
+                    _ if rest[len..].trim_start().starts_with('=') => "key",
+                    _ => "",
+                };
+                self.emit_len(kind, len);
+            } else {
+                self.emit_char("");
+            }
+        }
+    }
+
+    fn markdown(&mut self) {
+        // Front matter reads as a comment on the post
+        if let (Some(_), body) = front_matter::split(self.text) {
+            self.emit("comment", self.text.len() - body.len());
+        }
+        let mut fence: Option<&str> = None;
+        while self.pos < self.text.len() {
+            let line_end = self.line_end();
+            let line = &self.text[self.pos..line_end];
+            let trimmed = line.trim_start();
+            if let Some(open) = fence {
+                if trimmed.starts_with(open) {
+                    fence = None;
+                }
+                self.emit("code", line_end);
+            } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = Some(&trimmed[..3]);
+                self.emit("code", line_end);
+            } else if trimmed.starts_with('#') {
+                self.emit("heading", line_end);
+            } else if trimmed.starts_with('>') {
+                self.emit("quote", line_end);
+            } else {
+                self.emit_len("", line.len() - trimmed.len());
+                let digits = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+                if ["- ", "* ", "+ "].iter().any(|marker| trimmed.starts_with(marker)) {
+                    self.emit_len("punct", 1);
+                } else if digits > 0 && trimmed[digits..].starts_with(". ") {
+                    self.emit_len("punct", digits + 1);
+                }
+                self.inline(line_end);
+            }
+            self.emit_len("", usize::from(self.pos < self.text.len()));
+        }
+    }
+
+    // Inline code, emphasis and links, up to `end`
+    fn inline(&mut self, end: usize) {
+        while self.pos < end {
+            let rest = &self.text[self.pos..end];
+            let after_word = self.text[..self.pos].chars().next_back().is_some_and(char::is_alphanumeric);
+            let closing = |open: &str| {
+                rest[open.len()..].find(open).filter(|&i| i > 0).map(|i| i + 2 * open.len())
+            };
+            let styled = if rest.starts_with('`') {
+                closing("`").map(|len| ("code", len))
+            } else if rest.starts_with("**") || (rest.starts_with("__") && !after_word) {
+                closing(&rest[..2]).map(|len| ("strong", len))
+            } else if rest.starts_with('*') || (rest.starts_with('_') && !after_word) {
+                closing(&rest[..1]).map(|len| ("em", len))
+            } else if rest.starts_with('[') || rest.starts_with("![") {
+                rest.find("](")
+                    .and_then(|mid| rest[mid..].find(')').map(|close| mid + close + 1))
+                    .map(|len| ("link", len))
+            } else if rest.starts_with("https://") || rest.starts_with("http://") {
+                Some(("link", rest.find(char::is_whitespace).unwrap_or(rest.len())))
+            } else {
+                None
+            };
+            match styled {
+                Some((kind, len)) => self.emit_len(kind, len),
+                None => self.emit_char(""),
+            }
+        }
+    }
+}
+
+// How many #s a raw string (r"..", r#".."#, br"..") at the start of `s` uses
+fn raw_string_hashes(s: &str) -> Option<usize> {
+    let after_r = s.strip_prefix("br").or_else(|| s.strip_prefix('r'))?;
+    let hashes = after_r.len() - after_r.trim_start_matches('#').len();
+    after_r[hashes..].starts_with('"').then_some(hashes)
+}
+
+// Where the `=` after a TOML key is, skipping any in quoted keys
+fn key_end(line: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '=') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(runs: &[Run<'a>]) -> Vec<(&'static str, &'a str)> {
+        runs.iter().copied().filter(|(kind, _)| !kind.is_empty()).collect()
+    }
+
+    #[test]
+    fn test_runs_cover_the_text() {
+        let samples = [
+            "---\ntitle: Hi\n---\n# Hi\n\n- a `b` **c** [d](e)\n```rs\nlet x;\n```\nunclosed `tick and _snake_case_ é",
+            "name = \"x\" # comment\n[deps]\nserde = { version = \"1\" }\ndate = 1979-05-27T07:32:00Z\ns = '''\nmulti\n'''\n\"unterminated",
+            "{\"a\": [1, -2.5e3, true, null], \"b\": \"\\\"q\\\"\"}\n{\"open",
+            "#[derive(Debug)]\nfn main() { let s = r#\"raw\"#; println!(\"{}\", 'c'); } // é\n/* open",
+        ];
+        for language in [Language::Markdown, Language::Toml, Language::Json, Language::Rust] {
+            for sample in samples {
+                let runs = highlight(Some(language), sample);
+                assert_eq!(runs.iter().map(|(_, text)| *text).collect::<String>(), sample, "{:?}", language);
+                assert!(runs.windows(2).all(|pair| pair[0].0 != pair[1].0));
+            }
+        }
+        assert_eq!(highlight(None, "plain"), [("", "plain")]);
+    }
+
+    #[test]
+    fn test_rust_and_json() {
+        let runs = highlight(Some(Language::Rust), "pub fn go<'a>(x: &'a Path) { vec![1.5, 'x']; } // done");
+        assert_eq!(kinds(&runs), [
+            ("keyword", "pub"),
+            ("keyword", "fn"),
+            ("type", "'a"),
+            ("type", "'a"),
+            ("type", "Path"),
+            ("function", "vec!"),
+            ("number", "1.5"),
+            ("string", "'x'"),
+            ("comment", "// done"),
+        ]);
+
+        let runs = highlight(Some(Language::Json), r#"{"a": [1, "b", false]}"#);
+        assert_eq!(kinds(&runs), [("key", r#""a""#), ("number", "1"), ("string", r#""b""#), ("keyword", "false")]);
+    }
+
+    #[test]
+    fn test_toml_and_markdown() {
+        let runs = highlight(Some(Language::Toml), "[package]\nname = \"site\" # ok\nopt = { debug = true }\n");
+        assert_eq!(kinds(&runs), [
+            ("heading", "[package]"),
+            ("key", "name"),
+            ("string", "\"site\""),
+            ("comment", "# ok"),
+            ("key", "opt"),
+            ("key", "debug"),
+            ("keyword", "true"),
+        ]);
+
+        let runs = highlight(Some(Language::Markdown), "# Title\n1. see `code` and *this* at [a](b)\n");
+        assert_eq!(kinds(&runs), [
+            ("heading", "# Title"),
+            ("punct", "1."),
+            ("code", "`code`"),
+            ("em", "*this*"),
+            ("link", "[a](b)"),
+        ]);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use crate::filesystem::{Content, FilePath, VIRTUAL_FS};
+use crate::filesystem::events::{subscribe, FsEvent};
+use crate::filesystem::helpers::{fetch_text, post_json};
+use crate::settings::get_setting;
+
+/// Settings key holding the counting endpoint. Counting is off while it's unset.
+pub const HIT_ENDPOINT_KEY: &str = "hit.endpoint";
+
+#[derive(Deserialize)]
+pub struct PageViews {
+    pub path: String,
+    pub views: u64,
+}
+
+fn endpoint() -> Option<String> {
+    get_setting(HIT_ENDPOINT_KEY).map(|url| url.trim_end_matches('/').to_string())
+}
+
+/// Count views of files shown with cat or pretty
+pub fn register() {
+    subscribe(|event| {
+        if let FsEvent::FileRead(filepath) = event {
+            record_view(filepath);
+        }
+    });
+}
+
+/// Record a view of a file, if counting is enabled.
+/// Only static site content is counted - visitors' own files never leave the browser.
+fn record_view(filepath: &FilePath) {
+    let Some(endpoint) = endpoint() else { return };
+
+    let is_static = VIRTUAL_FS.with_borrow(|vfs|
+        matches!(vfs.get_content(filepath), Some(Content::ToFetch | Content::Fetched(_)))
+    );
+    if !is_static {
+        return;
+    }
+
+    let body = serde_json::json!({ "path": filepath.to_string() }).to_string();
+    wasm_bindgen_futures::spawn_local(async move {
+        // Fire and forget - a failed ping shouldn't bother the visitor
+        post_json(&format!("{}/hit", endpoint), &body).await.ok();
+    });
+}
+
+/// Fetch view counts for the most viewed files, most viewed first
+pub async fn fetch_popular() -> Result<Vec<PageViews>, String> {
+    let endpoint = endpoint().ok_or("View counting is disabled on this site")?;
+    let text = fetch_text(&format!("{}/popular", endpoint)).await?;
+    let mut views: Vec<PageViews> = serde_json::from_str(&text)
+        .map_err(|e| format!("Malformed response: {}", e))?;
+    views.sort_by_key(|page| std::cmp::Reverse(page.views));
+    Ok(views)
+}
@@ -0,0 +1,784 @@
+//! hsh, a small scripting language for scripts in the VFS: variables,
+//! if/elif/else, while and for loops, string functions and builtin commands.
+//!
+//! ```text
+//! # Roll until a six comes up
+//! let rolls = 0
+//! let roll = 0
+//! while $roll != 6
+//!     let roll = random(1, 6)
+//!     let rolls = $rolls + 1
+//!     echo Rolled a $roll
+//! end
+//! print "Took $rolls rolls"
+//! ```
+//!
+//! A line starting with a keyword (let, print, if, elif, else, while, for,
+//! break, continue, end) is a statement; any other line runs as a command,
+//! after `$name` is replaced by the variable's value. Strings in double
+//! quotes are filled in the same way. A script's arguments are `$1`, `$2`...,
+//! with `$@` all of them, `$#` how many and `$0` the script's name.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use crate::commands::process_command;
+use crate::rng::random_range;
+
+// Statements run before a script is taken to be stuck in a loop
+const MAX_STEPS: usize = 100_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Num(i64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Num(n) => *n != 0,
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+        }
+    }
+
+    // Numbers, and strings that hold one
+    fn as_num(&self) -> Option<i64> {
+        match self {
+            Value::Num(n) => Some(*n),
+            Value::Str(s) => s.trim().parse().ok(),
+            Value::Bool(_) => None,
+        }
+    }
+
+    fn num(&self) -> Result<i64, String> {
+        self.as_num().ok_or_else(|| format!("'{}' isn't a number", self))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+// Expressions
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(i64),
+    // Still to be filled in, as it may name variables
+    Str(String),
+    Var(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+// Longest first, so "<=" isn't read as "<"
+const OPERATORS: &[&str] = &["==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "(", ")", ","];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_ascii_digit() {
+            let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let n = rest[..end].parse().map_err(|_| format!("{} is too big", &rest[..end]))?;
+            tokens.push(Token::Num(n));
+            rest = &rest[end..];
+        } else if c == '"' {
+            let mut text = String::new();
+            let mut chars = rest[1..].char_indices();
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        end = Some(i + 2);
+                        break;
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => text.push('\n'),
+                        // Left for `interpolate`, to mean a plain $
+                        Some((_, '$')) => text.push_str("\\$"),
+                        Some((_, c)) => text.push(c),
+                        None => {}
+                    },
+                    c => text.push(c),
+                }
+            }
+            let end = end.ok_or("A string is missing its closing \"")?;
+            tokens.push(Token::Str(text));
+            rest = &rest[end..];
+        } else if c == '$' {
+            let (name, len) = variable_name(&rest[1..]).ok_or("Expected a variable name after $")?;
+            tokens.push(Token::Var(name.to_string()));
+            rest = &rest[1 + len..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let op = OPERATORS.iter().find(|op| rest.starts_with(**op)).ok_or_else(|| format!("Unexpected '{}'", c))?;
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// The variable named at the start of `text` (just after a $), and how much
+// of `text` it takes: `name`, `{name}`, a digit, `#` or `@`
+fn variable_name(text: &str) -> Option<(&str, usize)> {
+    let first = text.chars().next()?;
+    if first.is_ascii_digit() || first == '#' || first == '@' {
+        return Some((&text[..1], 1));
+    }
+    if first == '{' {
+        let end = text.find('}')?;
+        return Some((&text[1..end], end + 1));
+    }
+    if !(first.is_alphabetic() || first == '_') {
+        return None;
+    }
+    let end = text.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(text.len());
+    Some((&text[..end], end))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Value(Value),
+    Str(String),
+    Var(String),
+    Call(String, Vec<Expr>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // Takes the next token if it's the operator or word `word`
+    fn eat(&mut self, word: &str) -> bool {
+        let found = match self.peek() {
+            Some(Token::Op(op)) => *op == word,
+            Some(Token::Ident(ident)) => ident == word,
+            _ => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat("or") {
+            left = Expr::Binary("or", Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.eat("and") {
+            left = Expr::Binary("and", Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.sum()?;
+        for op in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat(op) {
+                return Ok(Expr::Binary(op, Box::new(left), Box::new(self.sum()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat("+") { "+" } else if self.eat("-") { "-" } else { return Ok(left) };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat("*") { "*" } else if self.eat("/") { "/" } else if self.eat("%") { "%" } else { return Ok(left) };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Value(Value::Num(n))),
+            Some(Token::Str(s)) => Ok(Expr::Str(s)),
+            Some(Token::Var(name)) => Ok(Expr::Var(name)),
+            Some(Token::Ident(word)) if word == "true" => Ok(Expr::Value(Value::Bool(true))),
+            Some(Token::Ident(word)) if word == "false" => Ok(Expr::Value(Value::Bool(false))),
+            Some(Token::Ident(function)) => {
+                if !self.eat("(") {
+                    return Err(format!("Expected ( after {} (variables need a $)", function));
+                }
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.or()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            return Err(format!("Expected , or ) in the call to {}", function));
+                        }
+                    }
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Some(Token::Op("(")) => {
+                let inner = self.or()?;
+                if !self.eat(")") {
+                    return Err("Missing )".to_string());
+                }
+                Ok(inner)
+            }
+            Some(Token::Op(op)) => Err(format!("Unexpected '{}'", op)),
+            None => Err("Expected a value".to_string()),
+        }
+    }
+}
+
+fn parse_expr(text: &str) -> Result<Expr, String> {
+    let mut parser = ExprParser { tokens: tokenize(text)?, pos: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(format!("Unexpected text in '{}'", text.trim())),
+    }
+}
+
+// Statements
+
+#[derive(Debug, PartialEq)]
+enum Stmt {
+    Let(String, Expr),
+    Print(Expr),
+    Command(String),
+    If(Vec<(Expr, Block)>, Block),
+    While(Expr, Block),
+    For(String, String, Block),
+    Break,
+    Continue,
+}
+
+/// Statements with their line numbers, for errors
+type Block = Vec<(usize, Stmt)>;
+
+struct Parser<'a> {
+    lines: Vec<(usize, &'a str)>,
+    pos: usize,
+    // Loops around the line being parsed, so break can be checked
+    loops: usize,
+}
+
+// An error at a line
+type LineError = (usize, String);
+
+// The keyword line that ended a block (None at the end of the script)
+type Ending<'a> = Option<(usize, &'a str)>;
+
+impl<'a> Parser<'a> {
+    // Statements up to one of the keywords `ends`, which is returned with its line
+    fn block(&mut self, ends: &[&str]) -> Result<(Block, Ending<'a>), LineError> {
+        let mut block = Vec::new();
+        while let Some(&(number, line)) = self.lines.get(self.pos) {
+            self.pos += 1;
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            if ends.contains(&keyword) {
+                return Ok((block, Some((number, line))));
+            }
+            let at = |e: String| (number, e);
+            let stmt = match keyword {
+                "let" => {
+                    let (name, value) = rest.split_once('=').ok_or_else(|| at("Expected let NAME = VALUE".to_string()))?;
+                    let name = name.trim();
+                    if variable_name(name).map(|(found, len)| found == name && len == name.len()) != Some(true) {
+                        return Err(at(format!("'{}' can't be a variable name", name)));
+                    }
+                    Stmt::Let(name.to_string(), parse_expr(value).map_err(at)?)
+                }
+                "print" => Stmt::Print(parse_expr(rest).map_err(at)?),
+                "if" => {
+                    let mut branches = Vec::new();
+                    let mut condition = parse_expr(rest).map_err(at)?;
+                    loop {
+                        let (body, end) = self.block(&["elif", "else", "end"])?;
+                        branches.push((condition, body));
+                        let (end_number, end_line) = end.ok_or_else(|| at("This if is missing its end".to_string()))?;
+                        match end_line.split_once(char::is_whitespace) {
+                            Some(("elif", elif)) => condition = parse_expr(elif).map_err(|e| (end_number, e))?,
+                            _ if end_line == "else" => {
+                                let (otherwise, end) = self.block(&["end"])?;
+                                end.ok_or_else(|| at("This if is missing its end".to_string()))?;
+                                break Stmt::If(branches, otherwise);
+                            }
+                            _ if end_line == "end" => break Stmt::If(branches, Vec::new()),
+                            _ => return Err((end_number, format!("Unexpected '{}'", end_line))),
+                        }
+                    }
+                }
+                "while" => {
+                    let condition = parse_expr(rest).map_err(at)?;
+                    Stmt::While(condition, self.loop_body(number, "while")?)
+                }
+                "for" => {
+                    let (name, items) = rest.split_once(" in ").ok_or_else(|| at("Expected for NAME in ITEMS...".to_string()))?;
+                    let name = name.trim().to_string();
+                    Stmt::For(name, items.trim().to_string(), self.loop_body(number, "for")?)
+                }
+                "break" | "continue" if self.loops == 0 => return Err(at(format!("{} outside a loop", keyword))),
+                "break" => Stmt::Break,
+                "continue" => Stmt::Continue,
+                "elif" | "else" | "end" => return Err(at(format!("'{}' without an if or loop to go with", keyword))),
+                _ => Stmt::Command(line.to_string()),
+            };
+            block.push((number, stmt));
+        }
+        Ok((block, None))
+    }
+
+    fn loop_body(&mut self, number: usize, keyword: &str) -> Result<Block, LineError> {
+        self.loops += 1;
+        let (body, end) = self.block(&["end"])?;
+        self.loops -= 1;
+        end.ok_or_else(|| (number, format!("This {} is missing its end", keyword)))?;
+        Ok(body)
+    }
+}
+
+fn parse(source: &str) -> Result<Block, LineError> {
+    let lines = source.lines().enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let mut parser = Parser { lines, pos: 0, loops: 0 };
+    let (block, _) = parser.block(&[])?;
+    Ok(block)
+}
+
+// Running
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+}
+
+type Running<'a, T, E = String> = Pin<Box<dyn Future<Output = Result<T, E>> + 'a>>;
+
+struct Interpreter {
+    vars: HashMap<String, Value>,
+    output: Vec<String>,
+    steps: usize,
+}
+
+impl Interpreter {
+    /// `text` with `$name` replaced by the variable's value (and `\$` by $)
+    fn interpolate(&self, text: &str) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(i) = rest.find(['$', '\\']) {
+            out.push_str(&rest[..i]);
+            if rest[i..].starts_with("\\$") {
+                out.push('$');
+                rest = &rest[i + 2..];
+                continue;
+            }
+            if rest[i..].starts_with('\\') {
+                out.push('\\');
+                rest = &rest[i + 1..];
+                continue;
+            }
+            match variable_name(&rest[i + 1..]) {
+                Some((name, len)) => {
+                    let value = self.vars.get(name).ok_or_else(|| format!("${} isn't set", name))?;
+                    out.push_str(&value.to_string());
+                    rest = &rest[i + 1 + len..];
+                }
+                None => {
+                    out.push('$');
+                    rest = &rest[i + 1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn block<'a>(&'a mut self, block: &'a Block) -> Running<'a, Flow, LineError> {
+        Box::pin(async move {
+            for (number, stmt) in block {
+                self.steps += 1;
+                if self.steps > MAX_STEPS {
+                    return Err((*number, format!("Stopped after {} steps (is a loop never ending?)", MAX_STEPS)));
+                }
+                let flow = self.stmt(stmt).await.map_err(|e| match e {
+                    // Errors inside a nested block already have their line
+                    StmtError::At(number, e) => (number, e),
+                    StmtError::Here(e) => (*number, e),
+                })?;
+                if !matches!(flow, Flow::Next) {
+                    return Ok(flow);
+                }
+            }
+            Ok(Flow::Next)
+        })
+    }
+
+    async fn stmt(&mut self, stmt: &Stmt) -> Result<Flow, StmtError> {
+        match stmt {
+            Stmt::Let(name, expr) => {
+                let value = self.eval(expr).await?;
+                self.vars.insert(name.clone(), value);
+            }
+            Stmt::Print(expr) => {
+                let value = self.eval(expr).await?;
+                self.output.push(value.to_string());
+            }
+            Stmt::Command(line) => {
+                let result = process_command(&self.interpolate(line)?).await;
+                if !result.is_empty() {
+                    self.output.push(result);
+                }
+            }
+            Stmt::If(branches, otherwise) => {
+                for (condition, body) in branches {
+                    if self.eval(condition).await?.truthy() {
+                        return Ok(self.block(body).await?);
+                    }
+                }
+                return Ok(self.block(otherwise).await?);
+            }
+            Stmt::While(condition, body) => {
+                while self.eval(condition).await?.truthy() {
+                    if let Flow::Break = self.block(body).await? {
+                        break;
+                    }
+                }
+            }
+            Stmt::For(name, items, body) => {
+                for item in expand(&self.interpolate(items)?)? {
+                    self.vars.insert(name.clone(), Value::Str(item));
+                    if let Flow::Break = self.block(body).await? {
+                        break;
+                    }
+                }
+            }
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Continue => return Ok(Flow::Continue),
+        }
+        Ok(Flow::Next)
+    }
+
+    fn eval<'a>(&'a mut self, expr: &'a Expr) -> Running<'a, Value> {
+        Box::pin(async move {
+            Ok(match expr {
+                Expr::Value(value) => value.clone(),
+                Expr::Str(text) => Value::Str(self.interpolate(text)?),
+                Expr::Var(name) => self.vars.get(name).cloned().ok_or_else(|| format!("${} isn't set", name))?,
+                Expr::Not(inner) => Value::Bool(!self.eval(inner).await?.truthy()),
+                Expr::Negate(inner) => Value::Num(self.eval(inner).await?.num()?.checked_neg().ok_or("Number too big")?),
+                Expr::Binary("and", left, right) => Value::Bool(self.eval(left).await?.truthy() && self.eval(right).await?.truthy()),
+                Expr::Binary("or", left, right) => Value::Bool(self.eval(left).await?.truthy() || self.eval(right).await?.truthy()),
+                Expr::Binary(op, left, right) => {
+                    let left = self.eval(left).await?;
+                    let right = self.eval(right).await?;
+                    binary(op, &left, &right)?
+                }
+                Expr::Call(function, args) => {
+                    let mut values = Vec::new();
+                    for arg in args {
+                        values.push(self.eval(arg).await?);
+                    }
+                    self.call(function, &values).await?
+                }
+            })
+        })
+    }
+
+    async fn call(&mut self, function: &str, args: &[Value]) -> Result<Value, String> {
+        let text = |i: usize| args[i].to_string();
+        let arity = match function {
+            "len" | "upper" | "lower" | "trim" | "num" | "words" | "run" => 1,
+            "contains" | "starts_with" | "ends_with" | "word" | "repeat" | "random" => 2,
+            "replace" | "slice" => 3,
+            _ => return Err(format!("There's no function called {}", function)),
+        };
+        if args.len() != arity {
+            return Err(format!("{} takes {} argument{}", function, arity, if arity == 1 { "" } else { "s" }));
+        }
+        Ok(match function {
+            "len" => Value::Num(text(0).chars().count() as i64),
+            "upper" => Value::Str(text(0).to_uppercase()),
+            "lower" => Value::Str(text(0).to_lowercase()),
+            "trim" => Value::Str(text(0).trim().to_string()),
+            "num" => Value::Num(args[0].num()?),
+            "words" => Value::Num(text(0).split_whitespace().count() as i64),
+            "run" => Value::Str(Box::pin(process_command(&text(0))).await),
+            "contains" => Value::Bool(text(0).contains(&text(1))),
+            "starts_with" => Value::Bool(text(0).starts_with(&text(1))),
+            "ends_with" => Value::Bool(text(0).ends_with(&text(1))),
+            // Counting from 1, as the arguments are
+            "word" => {
+                let n = usize::try_from(args[1].num()?).ok().and_then(|n| n.checked_sub(1));
+                let word = n.and_then(|i| text(0).split_whitespace().nth(i).map(str::to_string));
+                Value::Str(word.unwrap_or_default())
+            }
+            "repeat" => Value::Str(text(0).repeat(usize::try_from(args[1].num()?).unwrap_or(0).min(10_000))),
+            "random" => {
+                let (low, high) = (args[0].num()?, args[1].num()?);
+                if low > high {
+                    return Err(format!("random({}, {}): the first number is bigger", low, high));
+                }
+                Value::Num(random_range(low..=high))
+            }
+            "replace" => Value::Str(text(0).replace(&text(1), &text(2))),
+            // slice(text, start, length), counting characters from 0
+            "slice" => {
+                let start = usize::try_from(args[1].num()?).unwrap_or(0);
+                let length = usize::try_from(args[2].num()?).unwrap_or(0);
+                Value::Str(text(0).chars().skip(start).take(length).collect())
+            }
+            _ => unreachable!("arity checked above"),
+        })
+    }
+}
+
+// A statement's error: its own, or one from a line inside it
+enum StmtError {
+    Here(String),
+    At(usize, String),
+}
+
+impl From<String> for StmtError {
+    fn from(e: String) -> Self {
+        StmtError::Here(e)
+    }
+}
+
+impl From<&str> for StmtError {
+    fn from(e: &str) -> Self {
+        StmtError::Here(e.to_string())
+    }
+}
+
+impl From<LineError> for StmtError {
+    fn from((number, e): LineError) -> Self {
+        StmtError::At(number, e)
+    }
+}
+
+fn binary(op: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    let numbers = left.as_num().zip(right.as_num());
+    let too_big = || "Number too big".to_string();
+    Ok(match op {
+        "==" => Value::Bool(match numbers {
+            Some((l, r)) => l == r,
+            None => left.to_string() == right.to_string(),
+        }),
+        "!=" => Value::Bool(!binary("==", left, right)?.truthy()),
+        "<" | "<=" | ">" | ">=" => {
+            let ordering = match numbers {
+                Some((l, r)) => l.cmp(&r),
+                None => left.to_string().cmp(&right.to_string()),
+            };
+            Value::Bool(match op {
+                "<" => ordering.is_lt(),
+                "<=" => ordering.is_le(),
+                ">" => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+        _ => {
+            let (l, r) = (left.num()?, right.num()?);
+            Value::Num(match op {
+                "+" => l.checked_add(r).ok_or_else(too_big)?,
+                "-" => l.checked_sub(r).ok_or_else(too_big)?,
+                "*" => l.checked_mul(r).ok_or_else(too_big)?,
+                _ if r == 0 => return Err("Division by zero".to_string()),
+                "/" => l.checked_div_euclid(r).ok_or_else(too_big)?,
+                _ => l.checked_rem_euclid(r).ok_or_else(too_big)?,
+            })
+        }
+    })
+}
+
+// A for loop's items: its words, with A..B standing for the numbers from A to B
+// (no more of them than a script could step through)
+fn expand(items: &str) -> Result<Vec<String>, String> {
+    let mut expanded = Vec::new();
+    for item in items.split_whitespace() {
+        let range = item.split_once("..").and_then(|(from, to)| from.parse::<i64>().ok().zip(to.parse::<i64>().ok()));
+        match range {
+            Some((from, to)) => {
+                if to as i128 - from as i128 >= MAX_STEPS as i128 {
+                    return Err(format!("{}: Too many numbers (at most {})", item, MAX_STEPS));
+                }
+                expanded.extend((from..=to).map(|n| n.to_string()));
+            }
+            None => expanded.push(item.to_string()),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Run `source` as an hsh script called `name` with `args`, returning what
+/// it printed (and the error that stopped it, if one did)
+pub async fn run(source: &str, name: &str, args: &[&str]) -> String {
+    let block = match parse(source) {
+        Ok(block) => block,
+        Err((number, e)) => return format!("{}: line {}: {}", name, number, e),
+    };
+    let mut vars = HashMap::new();
+    vars.insert("0".to_string(), Value::Str(name.to_string()));
+    for (i, arg) in args.iter().take(9).enumerate() {
+        vars.insert((i + 1).to_string(), Value::Str(arg.to_string()));
+    }
+    vars.insert("#".to_string(), Value::Num(args.len() as i64));
+    vars.insert("@".to_string(), Value::Str(args.join(" ")));
+
+    let mut interpreter = Interpreter { vars, output: Vec::new(), steps: 0 };
+    if let Err((number, e)) = interpreter.block(&block).await {
+        interpreter.output.push(format!("{}: line {}: {}", name, number, e));
+    }
+    interpreter.output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::fixtures::enable;
+
+    fn parse_error(source: &str) -> LineError {
+        parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse() {
+        let block = parse("# Count\nfor n in 1..3\n    if $n == 2\n        break\n    end\n    echo $n\nend").unwrap();
+        assert_eq!(block.len(), 1);
+        let (number, Stmt::For(name, items, body)) = &block[0] else { panic!("expected a for loop") };
+        assert_eq!((*number, name.as_str(), items.as_str()), (2, "n", "1..3"));
+        assert_eq!(body[1], (6, Stmt::Command("echo $n".to_string())));
+
+        assert_eq!(parse_expr("1 + 2 * -3"), parse_expr("1 + (2 * (-3))"));
+        assert_eq!(parse_expr("not $a == 1 or $b"), parse_expr("(not ($a == 1)) or $b"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_error("if true\nprint 1"), (1, "This if is missing its end".to_string()));
+        assert_eq!(parse_error("print 1\nend"), (2, "'end' without an if or loop to go with".to_string()));
+        assert_eq!(parse_error("break"), (1, "break outside a loop".to_string()));
+        assert_eq!(parse_error("let 2x = 1"), (1, "'2x' can't be a variable name".to_string()));
+        assert_eq!(parse_error("print (1"), (1, "Missing )".to_string()));
+        assert_eq!(parse_error("print \"open"), (1, "A string is missing its closing \"".to_string()));
+        assert_eq!(parse_error("print x"), (1, "Expected ( after x (variables need a $)".to_string()));
+    }
+
+    #[test]
+    fn test_binary() {
+        let num = |n| Value::Num(n);
+        let text = |s: &str| Value::Str(s.to_string());
+        assert_eq!(binary("/", &num(-7), &num(2)), Ok(num(-4)));
+        assert_eq!(binary("==", &text(" 3"), &num(3)), Ok(Value::Bool(true)));
+        assert_eq!(binary("<", &text("apple"), &text("banana")), Ok(Value::Bool(true)));
+        assert_eq!(binary("<", &text("9"), &text("10")), Ok(Value::Bool(true)));
+        assert_eq!(binary("+", &text("a"), &num(1)), Err("'a' isn't a number".to_string()));
+        assert_eq!(binary("%", &num(1), &num(0)), Err("Division by zero".to_string()));
+        assert_eq!(binary("*", &num(i64::MAX), &num(2)), Err("Number too big".to_string()));
+        assert_eq!(binary("/", &num(i64::MIN), &num(-1)), Err("Number too big".to_string()));
+        assert_eq!(binary("%", &num(i64::MIN), &num(-1)), Err("Number too big".to_string()));
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("a 1..3 b 5..4").unwrap(), ["a", "1", "2", "3", "b"]);
+        assert_eq!(expand("1..99999999999"), Err("1..99999999999: Too many numbers (at most 100000)".to_string()));
+    }
+
+    #[test]
+    fn test_hsh() {
+        enable(1);
+        crate::write_file("count.hsh", [
+            "# Odd numbers up to $1, and how many words the notes have",
+            "let total = 0",
+            "for n in 1..$1",
+            "    if $n % 2 == 0",
+            "        continue",
+            "    end",
+            "    let total = $total + $n",
+            "end",
+            "print \"Sum of odd numbers to $1: $total\"",
+            "let count = run(\"wc -w notes.txt\")",
+            "if word($count, 1) > 2",
+            "    echo The notes have $count",
+            "else",
+            "    print upper(\"short notes\")",
+            "end",
+        ].join("\n")).unwrap();
+        crate::write_file("notes.txt", "three short words".to_string()).unwrap();
+
+        assert_eq!(fixtures::run("hsh count.hsh 9"), ["Sum of odd numbers to 9: 25", "The notes have 3 notes.txt"]);
+        crate::write_file("notes.txt", "two words".to_string()).unwrap();
+        assert_eq!(fixtures::run("hsh count.hsh 4"), ["Sum of odd numbers to 4: 4", "SHORT NOTES"]);
+        assert_eq!(fixtures::run("hsh count.hsh"), ["count.hsh: line 3: $1 isn't set"]);
+        assert_eq!(fixtures::run("hsh missing.hsh"), ["hsh: missing.hsh: No such file"]);
+
+        // In /bin, .hsh scripts are commands run as hsh
+        fixtures::run("mkdir /bin");
+        crate::write_file("/bin/twice.hsh", "for i in 1..2\n    echo $@\nend".to_string()).unwrap();
+        assert_eq!(fixtures::run("twice hi there"), ["hi there", "hi there"]);
+    }
+}
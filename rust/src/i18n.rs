@@ -0,0 +1,95 @@
+//! Translations of user-facing messages, loaded from content/.translations.json.
+//! Each language maps message keys to text; English is the fallback.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use crate::filesystem::helpers::fetch_text;
+use crate::js_interop::browser_language;
+
+const TRANSLATIONS_URL: &str = "./content/.translations.json";
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+// language code -> message key -> text
+pub type Translations = BTreeMap<String, BTreeMap<String, String>>;
+
+thread_local! {
+    static TRANSLATIONS: RefCell<Option<Translations>> = const { RefCell::new(None) };
+}
+
+/// The translations file, fetched on first use
+pub async fn translations() -> Result<Translations, String> {
+    if let Some(translations) = TRANSLATIONS.with_borrow(|t| t.clone()) {
+        return Ok(translations);
+    }
+    let text = fetch_text(TRANSLATIONS_URL).await?;
+    let translations: Translations = serde_json::from_str(&text)
+        .map_err(|e| format!("Malformed translations: {}", e))?;
+    TRANSLATIONS.with_borrow_mut(|t| *t = Some(translations.clone()));
+    Ok(translations)
+}
+
+/// Best available language for a preference like "ja-JP": an exact match,
+/// then the primary subtag ("ja"), then English
+pub fn pick_language(preferred: Option<&str>, translations: &Translations) -> String {
+    if let Some(preferred) = preferred {
+        let preferred = preferred.to_ascii_lowercase();
+        let primary = preferred.split('-').next().unwrap_or_default();
+        for candidate in [preferred.as_str(), primary] {
+            if translations.contains_key(candidate) {
+                return candidate.to_string();
+            }
+        }
+    }
+    DEFAULT_LANGUAGE.to_string()
+}
+
+/// Look up `key` in `language`, falling back to English
+pub fn lookup(translations: &Translations, language: &str, key: &str) -> Option<String> {
+    [language, DEFAULT_LANGUAGE].iter()
+        .find_map(|lang| translations.get(*lang)?.get(key).cloned())
+}
+
+/// `key` in the visitor's browser language
+pub async fn localized(key: &str) -> Option<String> {
+    let translations = translations().await.ok()?;
+    let language = pick_language(browser_language().as_deref(), &translations);
+    lookup(&translations, &language, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    fn sample() -> Translations {
+        serde_json::from_str(r#"{
+            "en": { "hello": "Hello", "bye": "Bye" },
+            "ja": { "hello": "こんにちは" }
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_pick_language() {
+        let translations = sample();
+        assert_eq!(pick_language(Some("ja-JP"), &translations), "ja");
+        assert_eq!(pick_language(Some("JA"), &translations), "ja");
+        assert_eq!(pick_language(Some("fr-FR"), &translations), "en");
+        assert_eq!(pick_language(None, &translations), "en");
+    }
+
+    #[test]
+    fn test_lookup_falls_back_to_english() {
+        let translations = sample();
+        assert_eq!(lookup(&translations, "ja", "hello").as_deref(), Some("こんにちは"));
+        assert_eq!(lookup(&translations, "ja", "bye").as_deref(), Some("Bye"));
+        assert_eq!(lookup(&translations, "ja", "missing"), None);
+    }
+
+    #[test]
+    fn test_hello_languages() {
+        enable(1);
+        assert_eq!(run("hello"), ["Hello from Rust! This command was processed by WebAssembly."]);
+        assert_eq!(run("hello --lang ja-JP"), ["Rustからこんにちは！このコマンドはWebAssemblyで処理されました。"]);
+        assert_eq!(run("hello --lang xx"), ["hello: xx: Unknown language (available: de, en, es, fr, ja)"]);
+    }
+}
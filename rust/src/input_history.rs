@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use crate::preferences::history_size;
 
 /// Input history for arrow key navigation through previously entered commands
 pub struct InputHistory {
@@ -18,10 +19,30 @@ impl InputHistory {
     pub fn add_input(&mut self, input: String) {
         if !input.is_empty() {
             self.inputs.push(input);
-            self.index = self.inputs.len();
+            self.keep_latest();
         }
     }
 
+    /// Put inputs from an earlier session before this one's (skips empty strings)
+    pub fn restore_earlier(&mut self, earlier: Vec<String>) {
+        let mut inputs: Vec<String> = earlier.into_iter().filter(|input| !input.is_empty()).collect();
+        inputs.append(&mut self.inputs);
+        self.inputs = inputs;
+        self.keep_latest();
+    }
+
+    // Forget the oldest inputs past the history_size preference
+    fn keep_latest(&mut self) {
+        let excess = self.inputs.len().saturating_sub(history_size());
+        self.inputs.drain(..excess);
+        self.index = self.inputs.len();
+    }
+
+    /// All inputs, oldest first
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
     /// Navigate backward in history (arrow up)
     /// Returns the previous input, or None if already at the beginning
     pub fn arrow_up(&mut self) -> Option<String> {
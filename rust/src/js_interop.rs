@@ -1,14 +1,26 @@
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 // External JavaScript functions that Rust can call
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_name = addOutput)]
     pub fn add_output(text: &str);
 
+    #[wasm_bindgen(js_name = addOutputStyled)]
+    fn add_output_segments(segments: &js_sys::Array);
+
+    #[wasm_bindgen(js_name = addOutputRich)]
+    fn add_output_rich_segments(segments: &js_sys::Array);
+
     #[wasm_bindgen(js_name = clearOutput)]
     pub fn clear_output();
 
+    /// Take the last `count` lines off the screen (so they can be drawn again)
+    #[wasm_bindgen(js_name = removeOutputLines)]
+    pub fn remove_output_lines(count: usize);
+
     #[wasm_bindgen(js_name = promptFilePicker)]
     pub fn prompt_file_picker(accept: &str) -> js_sys::Promise;
 
@@ -17,4 +29,307 @@ extern "C" {
 
     #[wasm_bindgen(js_name = scrollToBottom)]
     pub fn scroll_to_bottom();
+
+    #[wasm_bindgen(js_name = scheduleAutosave)]
+    pub fn schedule_autosave();
+
+    /// Restyle the page with a theme from index.html (remembered for the next visit)
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(name: &str);
+
+    /// Set the terminal's text size in pixels
+    #[wasm_bindgen(js_name = setFontSize)]
+    pub fn set_font_size(pixels: u32);
+
+    /// Set the text shown before the command line
+    #[wasm_bindgen(js_name = setPrompt)]
+    pub fn set_prompt(prompt: &str);
+
+    #[wasm_bindgen(js_name = persistPut)]
+    fn persist_put_js(key: &str, value: &str) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = persistDelete)]
+    fn persist_delete_js(key: &str) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = persistClear)]
+    fn persist_clear_js() -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = persistLoad)]
+    fn persist_load_js() -> js_sys::Promise;
+}
+
+// Browser storage for `persist` (IndexedDB, see index.html). Writes are fire
+// and forget: IndexedDB applies them in order, and a failure is only logged.
+#[cfg(target_arch = "wasm32")]
+fn persist_spawn(promise: js_sys::Promise) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = wasm_bindgen_futures::JsFuture::from(promise).await {
+            log(&format!("persist: {:?}", e));
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn persist_put(key: &str, value: &str) {
+    persist_spawn(persist_put_js(key, value));
+}
+
+/// Remove a record (a key ending in '/' removes everything below it too)
+#[cfg(target_arch = "wasm32")]
+pub fn persist_delete(key: &str) {
+    persist_spawn(persist_delete_js(key));
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn persist_clear() {
+    persist_spawn(persist_clear_js());
+}
+
+/// Every stored record as a JSON object (None if storage is unavailable)
+#[cfg(target_arch = "wasm32")]
+pub async fn persist_load() -> Option<String> {
+    wasm_bindgen_futures::JsFuture::from(persist_load_js()).await.ok()?.as_string()
+}
+
+/// Output one line made of (text, CSS class) segments.
+/// An empty class leaves the segment unstyled.
+#[cfg(target_arch = "wasm32")]
+pub fn add_output_styled(segments: &[(&str, &str)]) {
+    let array = js_sys::Array::new();
+    for (text, class) in segments {
+        array.push(&js_sys::Array::of2(&JsValue::from_str(text), &JsValue::from_str(class)));
+    }
+    add_output_segments(&array);
+}
+
+/// Output one line made of (text, CSS classes, link) segments: like
+/// add_output_styled, but a segment with a link is shown as one.
+#[cfg(target_arch = "wasm32")]
+pub fn add_output_rich(segments: &[(&str, &str, Option<&str>)]) {
+    let array = js_sys::Array::new();
+    for (text, class, href) in segments {
+        let href = href.map(JsValue::from_str).unwrap_or(JsValue::NULL);
+        array.push(&js_sys::Array::of3(&JsValue::from_str(text), &JsValue::from_str(class), &href));
+    }
+    add_output_rich_segments(&array);
+}
+
+/// Current time in milliseconds since the epoch
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Minutes the browser's timezone is ahead of UTC at `ms` (negative behind it)
+#[cfg(target_arch = "wasm32")]
+pub fn timezone_offset_minutes(ms: f64) -> i64 {
+    -js_sys::Date::new(&JsValue::from_f64(ms)).get_timezone_offset() as i64
+}
+
+/// The browser's timezone, e.g. "Europe/London"
+#[cfg(target_arch = "wasm32")]
+pub fn timezone_name() -> String {
+    let options = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new()).resolved_options();
+    js_sys::Reflect::get(&options, &JsValue::from_str("timeZone"))
+        .ok()
+        .and_then(|zone| zone.as_string())
+        .unwrap_or_else(|| "UTC".to_string())
+}
+
+/// The browser's preferred language, e.g. "ja-JP"
+#[cfg(target_arch = "wasm32")]
+pub fn browser_language() -> Option<String> {
+    web_sys::window()?.navigator().language()
+}
+
+/// The browser's user agent string, e.g. "Mozilla/5.0 (X11; Linux x86_64) ..."
+#[cfg(target_arch = "wasm32")]
+pub fn user_agent() -> Option<String> {
+    web_sys::window()?.navigator().user_agent().ok()
+}
+
+/// The host the site is served from, e.g. "example.github.io"
+#[cfg(target_arch = "wasm32")]
+pub fn page_host() -> String {
+    web_sys::window()
+        .and_then(|window| window.location().hostname().ok())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Bytes of memory the WebAssembly module has reserved
+#[cfg(target_arch = "wasm32")]
+pub fn wasm_memory_bytes() -> Option<usize> {
+    let memory: js_sys::WebAssembly::Memory = wasm_bindgen::memory().dyn_into().ok()?;
+    let buffer: js_sys::ArrayBuffer = memory.buffer().dyn_into().ok()?;
+    Some(buffer.byte_length() as usize)
+}
+
+/// The page's address without any query string, for building links back to it
+#[cfg(target_arch = "wasm32")]
+pub fn page_url() -> String {
+    web_sys::window()
+        .and_then(|window| {
+            let location = window.location();
+            Some(format!("{}{}", location.origin().ok()?, location.pathname().ok()?))
+        })
+        .unwrap_or_default()
+}
+
+/// Let the browser run (render, handle keys) before carrying on
+#[cfg(target_arch = "wasm32")]
+pub async fn yield_to_browser() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            window.set_timeout_with_callback(&resolve).ok();
+        }
+    });
+    wasm_bindgen_futures::JsFuture::from(promise).await.ok();
+}
+
+/// Log to the browser console
+#[cfg(target_arch = "wasm32")]
+pub fn log(message: &str) {
+    web_sys::console::log_1(&JsValue::from_str(message));
+}
+
+// Native stand-ins, so the terminal can run without a browser (see fixtures.rs).
+// Output is recorded instead of displayed, and the clock is fixed.
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::cell::RefCell;
+
+    /// What the clock reads outside the browser: 2023-11-14 22:13:20 UTC
+    pub const FIXED_NOW: f64 = 1_700_000_000_000.0;
+
+    thread_local! {
+        static OUTPUT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub fn add_output(text: &str) {
+        OUTPUT.with_borrow_mut(|output| output.push(text.to_string()));
+    }
+
+    pub fn add_output_styled(segments: &[(&str, &str)]) {
+        add_output(&segments.iter().map(|(text, _)| *text).collect::<String>());
+    }
+
+    pub fn add_output_rich(segments: &[(&str, &str, Option<&str>)]) {
+        add_output(&segments.iter().map(|(text, _, _)| *text).collect::<String>());
+    }
+
+    pub fn clear_output() {
+        OUTPUT.with_borrow_mut(|output| output.clear());
+    }
+
+    pub fn remove_output_lines(count: usize) {
+        OUTPUT.with_borrow_mut(|output| output.truncate(output.len().saturating_sub(count)));
+    }
+
+    pub fn prompt_file_picker(_accept: &str) -> js_sys::Promise {
+        panic!("The file picker is only available in the browser")
+    }
+
+    pub fn trigger_download(_content: &[u8], _mime_type: &str, _filename: &str) {}
+
+    pub fn scroll_to_bottom() {}
+
+    pub fn schedule_autosave() {}
+
+    thread_local! {
+        static THEME: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    pub fn set_theme(name: &str) {
+        THEME.with_borrow_mut(|theme| *theme = Some(name.to_string()));
+    }
+
+    pub fn set_font_size(_pixels: u32) {}
+
+    pub fn set_prompt(_prompt: &str) {}
+
+    /// Pause once, as waiting for the browser would (a caller's future is
+    /// left pending, to be polled again)
+    pub async fn yield_to_browser() {
+        let mut yielded = false;
+        std::future::poll_fn(|context| {
+            if yielded {
+                return std::task::Poll::Ready(());
+            }
+            yielded = true;
+            context.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }).await
+    }
+
+    pub fn now() -> f64 {
+        FIXED_NOW
+    }
+
+    pub fn log(_message: &str) {}
+
+    pub fn timezone_offset_minutes(_ms: f64) -> i64 {
+        0
+    }
+
+    pub fn timezone_name() -> String {
+        "UTC".to_string()
+    }
+
+    pub fn browser_language() -> Option<String> {
+        None
+    }
+
+    pub fn page_url() -> String {
+        "http://localhost/".to_string()
+    }
+
+    pub fn user_agent() -> Option<String> {
+        None
+    }
+
+    pub fn page_host() -> String {
+        "localhost".to_string()
+    }
+
+    pub fn wasm_memory_bytes() -> Option<usize> {
+        None
+    }
+
+    thread_local! {
+        static PERSISTED: RefCell<std::collections::BTreeMap<String, String>> = const { RefCell::new(std::collections::BTreeMap::new()) };
+    }
+
+    pub fn persist_put(key: &str, value: &str) {
+        PERSISTED.with_borrow_mut(|records| records.insert(key.to_string(), value.to_string()));
+    }
+
+    pub fn persist_delete(key: &str) {
+        PERSISTED.with_borrow_mut(|records| records.retain(|stored, _| {
+            stored != key && !(key.ends_with('/') && stored.starts_with(key))
+        }));
+    }
+
+    pub fn persist_clear() {
+        PERSISTED.with_borrow_mut(|records| records.clear());
+    }
+
+    pub async fn persist_load() -> Option<String> {
+        PERSISTED.with_borrow(|records| serde_json::to_string(records).ok())
+    }
+
+    /// The theme the page was last asked to show
+    #[cfg(test)]
+    pub fn applied_theme() -> Option<String> {
+        THEME.with_borrow(|theme| theme.clone())
+    }
+
+    /// Take the lines output since the last call
+    #[cfg(test)]
+    pub fn take_output() -> Vec<String> {
+        OUTPUT.with_borrow_mut(std::mem::take)
+    }
 }
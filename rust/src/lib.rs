@@ -1,30 +1,88 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
 
 mod js_interop;
 mod filesystem;
 mod channels;
 mod commands;
 mod input_history;
-
-use js_interop::{add_output, clear_output, scroll_to_bottom};
-use filesystem::{Manifest, DirPath, FilePath, VIRTUAL_FS};
+mod textdiff;
+mod settings;
+mod hit;
+mod rng;
+#[cfg(any(test, feature = "fixtures"))]
+mod fixtures;
+mod reload;
+mod i18n;
+mod base64;
+mod recording;
+mod tips;
+mod features;
+mod digest;
+mod persist;
+mod autosave;
+mod markdown;
+mod pager;
+mod highlight;
+mod prompt;
+mod wizard;
+mod script;
+mod hsh;
+mod datetime;
+mod fortune;
+mod figlet;
+mod dice;
+mod theme;
+mod preferences;
+mod typetest;
+mod game;
+mod twenty48;
+
+use js_interop::{add_output, scroll_to_bottom, yield_to_browser};
+use filesystem::{Manifest, FilePath, VIRTUAL_FS};
 use filesystem::helpers::fetch_text;
-use channels::{handle_editor_message, handle_pretty_message, EDITOR_CHANNEL, PRETTY_CHANNEL};
+use filesystem::events::{emit, FsEvent};
+use channels::{handle_editor_message, handle_pretty_message, CHANNEL_HANDLERS, EDITOR_CHANNEL, PRETTY_CHANNEL};
 use commands::process_command;
-use commands::builtin::pretty::open_pretty_page;
+use commands::builtin::pick::start_pick;
 use input_history::INPUT_HISTORY;
 
 // Handler for next input - determines what function receives the next user input
 #[derive(Clone)]
+// Questions asked with `prompt::ask` are answered before any of these
 enum NextInputHandler {
     None,
-    PrettyConfirm { filepath: String, path_arg: String },
+    // `selected` tracks arrow key navigation; `then` is the command around a $(pick) substitution
+    Pick { entries: Vec<String>, selected: Option<usize>, then: Option<(String, String)> },
+    // The pager: `lines` of output, of which the first `shown` are on screen,
+    // and the handler to go back to once it's done
+    More { lines: Vec<String>, shown: usize, then: Box<NextInputHandler> },
+    // A game is being played (see game.rs, which holds it)
+    Game,
+    // Lines go to the RPG engine (commands/rpg), which holds the game
+    Rpg,
+}
+
+impl NextInputHandler {
+    // Handlers that take keys rather than commands: their input isn't echoed or kept in history
+    fn reads_keys(&self) -> bool {
+        matches!(self, NextInputHandler::More { .. } | NextInputHandler::Game)
+    }
 }
 
 thread_local! {
     static NEXT_INPUT_HANDLER: RefCell<NextInputHandler> = RefCell::new(NextInputHandler::None);
+    // Whether long output is still being shown, chunk by chunk
+    static SHOWING_OUTPUT: RefCell<bool> = const { RefCell::new(false) };
+    // Lines entered meanwhile, handled in order once it's all shown
+    static HELD_INPUT: RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Decide what receives the next input
+fn set_next_input_handler(handler: NextInputHandler) {
+    NEXT_INPUT_HANDLER.with(|h| *h.borrow_mut() = handler);
 }
 
 // Load manifest from server and initialize virtual filesystem
@@ -37,14 +95,86 @@ pub async fn load_manifest() -> Result<(), JsValue> {
     let manifest: Manifest = serde_json::from_str(&manifest_text)
         .map_err(|e| JsValue::from_str(&format!("Failed to parse manifest: {}", e)))?;
 
+    // Report anything wrong with the manifest, and load what can be loaded
+    let (manifest, problems) = filesystem::manifest::check(manifest);
+    for problem in &problems {
+        add_output(problem);
+    }
+
     // Initialize virtual filesystem from manifest (manifest is then dropped)
     VIRTUAL_FS.with(|vfs| {
         vfs.borrow_mut().initialize_from_manifest(&manifest);
     });
 
+    commands::builtin::system::mark_started();
+    register_subscribers();
+    features::load().await;
+    persist::restore().await;
+    theme::restore().await;
+    if let Some(problem) = preferences::load().await {
+        add_output(&problem);
+    }
+
     Ok(())
 }
 
+// Greeting shown once the terminal has loaded, in the visitor's language,
+// under the deployment's message of the day (setting motd) in large letters
+#[wasm_bindgen]
+pub async fn greeting_banner() -> String {
+    let mut banner = i18n::localized("banner").await
+        .unwrap_or_else(|| "Terminal ready. Type 'help' to get started, or try 'ls' to list files.".to_string());
+    if let Some(motd) = settings::get_setting("motd") {
+        banner = format!("{}\n\n{}", figlet::Font::block().render(&motd), banner);
+    }
+    if tips::enabled() && let Some(tip) = tips::next_tip().await {
+        return format!("{}\nTip: {}", banner, tip);
+    }
+    banner
+}
+
+// Serialize all in-memory state before the page swaps in a new WASM build
+#[wasm_bindgen]
+pub fn prepare_reload() -> String {
+    reload::prepare_reload()
+}
+
+// Restore state from prepare_reload (call after load_manifest on the new build)
+#[wasm_bindgen]
+pub fn restore_state(blob: &str) -> Result<(), JsValue> {
+    reload::restore_state(blob).map_err(|e| JsValue::from_str(&e))
+}
+
+// Steps of a cast link (the ?cast= parameter) as JSON: [{ delay, input }, ...]
+#[wasm_bindgen]
+pub fn decode_cast(encoded: &str) -> Result<String, JsValue> {
+    let steps = recording::decode_cast(encoded).map_err(|e| JsValue::from_str(&e))?;
+    serde_json::to_string(&steps).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// Switch to fixture mode: seeded randomness and canned fetches instead of the site
+// (only in builds with the fixtures feature)
+#[cfg(feature = "fixtures")]
+#[wasm_bindgen]
+pub fn enable_fixtures(seed: u32) {
+    fixtures::enable(seed as u64);
+}
+
+// Subsystems that react to filesystem events
+fn register_subscribers() {
+    hit::register();
+    // Before persist, which stores the abyss edits it tracks
+    filesystem::abyss_delta::register();
+    persist::register();
+    reload::register();
+    autosave::register();
+    filesystem::cave_of_dice::register();
+    filesystem::fetch_cache::register();
+    filesystem::prefetch::register();
+    preferences::register();
+    channels::register();
+}
+
 // Initialize BroadcastChannels for communication with editor and pretty viewer
 #[wasm_bindgen]
 pub fn initialize_broadcast_channels() -> Result<(), JsValue> {
@@ -57,7 +187,6 @@ pub fn initialize_broadcast_channels() -> Result<(), JsValue> {
         handle_editor_message(event);
     });
     editor_channel.set_onmessage(Some(editor_onmessage.as_ref().unchecked_ref()));
-    editor_onmessage.forget(); // Keep the closure alive
 
     // Create pretty channel
     let pretty_channel = BroadcastChannel::new("pretty_channel")?;
@@ -65,15 +194,16 @@ pub fn initialize_broadcast_channels() -> Result<(), JsValue> {
         handle_pretty_message(event);
     });
     pretty_channel.set_onmessage(Some(pretty_onmessage.as_ref().unchecked_ref()));
-    pretty_onmessage.forget(); // Keep the closure alive
 
-    // Store channels
+    // Store channels, and their handlers until they're closed
+    CHANNEL_HANDLERS.with_borrow_mut(|handlers| handlers.extend([editor_onmessage, pretty_onmessage]));
     EDITOR_CHANNEL.with(|ch| {
         *ch.borrow_mut() = Some(editor_channel);
     });
     PRETTY_CHANNEL.with(|ch| {
         *ch.borrow_mut() = Some(pretty_channel);
     });
+    channels::greet_tabs();
 
     Ok(())
 }
@@ -82,23 +212,31 @@ pub fn initialize_broadcast_channels() -> Result<(), JsValue> {
 #[wasm_bindgen]
 pub fn write_file(path: &str, content: String) -> Result<(), JsValue> {
     let filepath = filesystem::CURRENT_DIR.with(|cd| FilePath::parse(path, &cd.borrow()));
+    channels::may_save(&filepath).map_err(|e| JsValue::from_str(&format!("{}: {}", path, e)))?;
+    filesystem::quota::check_write(&filepath, content.len()).map_err(|e| JsValue::from_str(&e))?;
 
     VIRTUAL_FS.with(|vfs| {
         vfs.borrow_mut().write_file(&filepath, content);
     });
+    emit(FsEvent::FileWritten(filepath));
 
     Ok(())
 }
 
 // Read a file from the virtual filesystem (called from JavaScript)
-// Returns the content type: "InMemory:<content>", "ToFetch:<url>", or "NotFound"
+// Returns the content type: "InMemory:<content>", "Fetched:<content>" (a cached
+// site file), "ToFetch:<url>", or "NotFound"
 #[wasm_bindgen]
 pub fn read_file(path: &str) -> String {
     let filepath = filesystem::CURRENT_DIR.with(|cd| FilePath::parse(path, &cd.borrow()));
+    if filesystem::helpers::is_unpublished(&filepath) {
+        return "NotFound".to_string();
+    }
 
     VIRTUAL_FS.with(|vfs| {
         match vfs.borrow().get_content(&filepath) {
-            Some(filesystem::Content::InMemory(content)) => format!("InMemory:{}", content),
+            Some(filesystem::Content::InMemory(data)) => format!("InMemory:{}", data.text_lossy()),
+            Some(filesystem::Content::Fetched(text)) => format!("Fetched:{}", text),
             Some(filesystem::Content::ToFetch) => format!("ToFetch:{}", filepath.to_url()),
             None => "NotFound".to_string(),
         }
@@ -108,90 +246,52 @@ pub fn read_file(path: &str) -> String {
 // Export all in-memory files as JSON (called from JavaScript)
 #[wasm_bindgen]
 pub fn export_session() -> String {
-    use serde_json::json;
+    commands::export_session()
+}
 
-    VIRTUAL_FS.with(|vfs| {
-        let vfs_ref = vfs.borrow();
-        let mut files = serde_json::Map::new();
-
-        // Collect all InMemory files
-        for (dirpath, dir_contents) in &vfs_ref.content {
-            for (filename, content) in dir_contents {
-                if let filesystem::Content::InMemory(file_content) = content {
-                    let mut path_parts = Vec::new();
-                    for component in &dirpath.0 {
-                        match component {
-                            filesystem::NextDir::In(name) => path_parts.push(name.clone()),
-                            filesystem::NextDir::Out => path_parts.push("..".to_string()),
-                        }
-                    }
-
-                    let full_path = if path_parts.is_empty() {
-                        format!("/{}", filename)
-                    } else {
-                        format!("/{}/{}", path_parts.join("/"), filename)
-                    };
-
-                    files.insert(full_path, json!(file_content));
-                }
-            }
-        }
+// The autosave snapshot to keep in localStorage (called by the page, debounced)
+#[wasm_bindgen]
+pub fn autosave_snapshot() -> String {
+    autosave::snapshot()
+}
 
-        json!({
-            "version": "1.0",
-            "files": files
-        }).to_string()
-    })
+// Offer to restore an autosave found in localStorage when the page loads
+#[wasm_bindgen]
+pub fn offer_autosave(saved: &str) {
+    autosave::offer_restore(saved);
 }
 
 // Import session from JSON (called from JavaScript)
-// Returns number of files imported, or error message prefixed with "Error:"
+// Returns an import summary, or error message prefixed with "Error:"
 #[wasm_bindgen]
-pub fn import_session(session_json: String) -> String {
-    use serde_json::Value;
-
-    match serde_json::from_str::<Value>(&session_json) {
-        Ok(session) => {
-            // Check version
-            if let Some(version) = session.get("version").and_then(|v| v.as_str()) {
-                if version != "1.0" {
-                    return format!("Error: Unsupported session version: {}", version);
-                }
-            } else {
-                return "Error: Invalid session file: missing version".to_string();
-            }
-
-            // Get files object
-            let files = match session.get("files").and_then(|f| f.as_object()) {
-                Some(f) => f,
-                None => return "Error: Invalid session file: missing or invalid files".to_string(),
-            };
-
-            let mut count = 0;
-
-            // Import each file
-            VIRTUAL_FS.with(|vfs| {
-                for (path, content_value) in files {
-                    if let Some(content_str) = content_value.as_str() {
-                        // Parse the path
-                        let filepath = FilePath::parse(path, &DirPath::root());
-
-                        // Write to virtual filesystem
-                        vfs.borrow_mut().write_file(&filepath, content_str.to_string());
-                        count += 1;
-                    }
-                }
-            });
+pub async fn import_session(session_json: String) -> String {
+    commands::import_session(session_json, true, &commands::OnConflict::Overwrite).await
+}
 
-            format!("Imported {} file(s)", count)
+/// Move the file picker selection by `step`, wrapping around.
+/// Returns the newly selected path, or None if the picker isn't active.
+fn move_pick_selection(step: isize) -> Option<String> {
+    NEXT_INPUT_HANDLER.with_borrow_mut(|handler| match handler {
+        NextInputHandler::Pick { entries, selected, .. } => {
+            let len = entries.len() as isize;
+            let next = match *selected {
+                Some(idx) => (idx as isize + step).rem_euclid(len),
+                None if step < 0 => len - 1,
+                None => 0,
+            } as usize;
+            *selected = Some(next);
+            Some(entries[next].clone())
         }
-        Err(e) => format!("Error: Failed to parse session file: {}", e),
-    }
+        _ => None,
+    })
 }
 
 /// Handle arrow up key - returns previous input from history, or empty string if at beginning
 #[wasm_bindgen]
 pub fn handle_arrow_up() -> String {
+    if let Some(path) = move_pick_selection(-1) {
+        return path;
+    }
     INPUT_HISTORY.with(|history| {
         history.borrow_mut().arrow_up().unwrap_or_default()
     })
@@ -200,6 +300,9 @@ pub fn handle_arrow_up() -> String {
 /// Handle arrow down key - returns next input from history, or empty string if at end
 #[wasm_bindgen]
 pub fn handle_arrow_down() -> String {
+    if let Some(path) = move_pick_selection(1) {
+        return path;
+    }
     INPUT_HISTORY.with(|history| {
         history.borrow_mut().arrow_down().unwrap_or_default()
     })
@@ -208,43 +311,240 @@ pub fn handle_arrow_down() -> String {
 /// Main entry point from JavaScript - handles input and manages display
 #[wasm_bindgen]
 pub async fn handle_input(user_input: &str) {
+    // Long output is shown a chunk at a time, letting the browser run in
+    // between; what's typed meanwhile waits until the rest is shown
+    if SHOWING_OUTPUT.with_borrow(|showing| *showing) {
+        HELD_INPUT.with_borrow_mut(|held| held.push_back(user_input.to_string()));
+        return;
+    }
+    handle_line(user_input).await;
+    while let Some(line) = HELD_INPUT.with_borrow_mut(|held| held.pop_front()) {
+        handle_line(&line).await;
+    }
+}
+
+async fn handle_line(user_input: &str) {
+    let handler = NEXT_INPUT_HANDLER.with(|h| h.borrow().clone());
+    if handler.reads_keys() {
+        match handler {
+            // Untrimmed: the pager tells a space from a bare enter
+            NextInputHandler::More { lines, shown, then } => handle_more(user_input, lines, shown, *then),
+            NextInputHandler::Game => game::handle_key(user_input.trim()).await,
+            _ => {}
+        }
+        scroll_to_bottom();
+        return;
+    }
+
     let user_input = user_input.trim();
 
+    // A password is only ever passed to its prompt
+    if prompt::is_masked() {
+        add_output(&format!("{} ********", preferences::prompt()));
+        prompt::answer(user_input).await;
+        scroll_to_bottom();
+        return;
+    }
+
     // Add to history (skips empty inputs internally)
     INPUT_HISTORY.with(|history| {
         history.borrow_mut().add_input(user_input.to_string());
     });
 
-    // Display the input
-    add_output(&format!("> {}", user_input));
+    recording::record_input(user_input);
+
+    // Display the input, after the prompt it was typed at
+    let prompt = match handler {
+        NextInputHandler::Rpg => commands::rpg::prompt(),
+        _ => preferences::prompt(),
+    };
+    add_output(&format!("{} {}", prompt, user_input));
 
     // Dispatch based on current handler
-    let handler = NEXT_INPUT_HANDLER.with(|h| h.borrow().clone());
+    if prompt::is_waiting() {
+        prompt::answer(user_input).await;
+    } else {
+        match handler {
+            NextInputHandler::None => {
+                process_normal_command(user_input).await;
+            }
+            NextInputHandler::Pick { entries, then, .. } => {
+                handle_pick_selection(user_input, &entries, then).await;
+            }
+            NextInputHandler::Rpg => {
+                let result = commands::rpg::handle_line(user_input).await;
+                show_output(&result).await;
+            }
+            NextInputHandler::More { .. } | NextInputHandler::Game => unreachable!("handled above"),
+        }
+    }
+
+    autosave::after_command();
+    scroll_to_bottom();
+}
+
+/// Whether the input box should hide what's typed (a password is being asked for)
+#[wasm_bindgen]
+pub fn input_masked() -> bool {
+    prompt::is_masked()
+}
+
+/// Whether keys should go to a game rather than the command line
+#[wasm_bindgen]
+pub fn playing_game() -> bool {
+    game::is_playing()
+}
+
+/// A key pressed while a game is being played, by its browser name ("ArrowUp", "w")
+#[wasm_bindgen]
+pub async fn handle_game_key(key: &str) {
+    game::handle_key(key).await;
+    autosave::after_command();
+    scroll_to_bottom();
+}
+
+/// Escape or Ctrl+C: drop any questions waiting, and leave the pager, file picker or game
+#[wasm_bindgen]
+pub fn cancel_input() {
+    if game::is_playing() {
+        game::stop();
+        scroll_to_bottom();
+        return;
+    }
+    if commands::rpg::is_playing() && !prompt::is_waiting() {
+        commands::rpg::leave();
+        add_output("You stop playing.");
+        scroll_to_bottom();
+        return;
+    }
+    let was_asking = prompt::cancel();
+    // A question asked in the game (e.g. in a conversation): the game carries on
+    if commands::rpg::is_playing() {
+        add_output("Cancelled.");
+        scroll_to_bottom();
+        return;
+    }
+    let was_reading = NEXT_INPUT_HANDLER.with_borrow(|handler| !matches!(handler, NextInputHandler::None));
+    set_next_input_handler(NextInputHandler::None);
+    if was_asking || was_reading {
+        add_output("Cancelled.");
+        scroll_to_bottom();
+    }
+}
 
-    match handler {
-        NextInputHandler::None => {
-            process_normal_command(user_input).await;
+/// load-session asking whether the first of `conflicts` (the files it would
+/// replace that are still to decide) should be replaced by the session's
+/// copy; `skip` are the files to keep. Imports once every file is decided.
+fn ask_import_conflict(session_json: String, mut conflicts: Vec<String>, mut skip: BTreeSet<String>) {
+    let question = format!("{} already exists. Replace it? (y/n, a = replace all, s = skip all)", conflicts[0]);
+    let check = |input: &str| match input.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok("y".to_string()),
+        "n" | "no" => Ok("n".to_string()),
+        choice @ ("a" | "s") => Ok(choice.to_string()),
+        _ => Err("Please answer y, n, a or s.".to_string()),
+    };
+    prompt::ask(prompt::Prompt::new(&question, move |answer| async move {
+        match answer.as_str() {
+            "y" => {
+                conflicts.remove(0);
+            }
+            "n" => {
+                skip.insert(conflicts.remove(0));
+            }
+            "a" => conflicts.clear(),
+            _ => skip.extend(conflicts.drain(..)),
         }
-        NextInputHandler::PrettyConfirm { filepath, path_arg } => {
-            handle_pretty_confirm(user_input, &filepath, &path_arg);
+        if !conflicts.is_empty() {
+            ask_import_conflict(session_json, conflicts, skip);
+            return String::new();
+        }
+        commands::import_session(session_json, false, &commands::OnConflict::Skip(skip)).await
+    }).with_default("n").with_check(check));
+}
+
+// Lines output at a time before letting the browser catch up
+const OUTPUT_CHUNK_LINES: usize = 200;
+
+/// Show a command's output, a screenful at a time if it's long and paging is on.
+/// Otherwise long output (already complete, as commands return it whole) is
+/// added in chunks, so the page keeps responding while a big file is shown.
+async fn show_output(result: &str) {
+    let lines: Vec<String> = result.lines().map(str::to_string).collect();
+    match pager::page_size() {
+        Some(page) if lines.len() > page => {
+            for line in &lines[..page] {
+                add_output(line);
+            }
+            add_output(&pager::prompt(page, lines.len()));
+            let then = Box::new(NEXT_INPUT_HANDLER.with_borrow(|handler| handler.clone()));
+            set_next_input_handler(NextInputHandler::More { lines, shown: page, then });
+        }
+        _ => {
+            SHOWING_OUTPUT.with_borrow_mut(|showing| *showing = true);
+            for (i, chunk) in lines.chunks(OUTPUT_CHUNK_LINES).enumerate() {
+                if i > 0 {
+                    scroll_to_bottom();
+                    yield_to_browser().await;
+                }
+                for line in chunk {
+                    add_output(line);
+                }
+            }
+            SHOWING_OUTPUT.with_borrow_mut(|showing| *showing = false);
         }
     }
-    
-    scroll_to_bottom();
 }
 
-/// Handle confirmation for pretty command
-fn handle_pretty_confirm(user_input: &str, filepath: &str, path_arg: &str) {
-    let response = if user_input.to_lowercase() == "y" || user_input.to_lowercase() == "yes" {
-        open_pretty_page(filepath, path_arg)
-    } else {
-        "Cancelled.".to_string()
+/// Handle a key at the --More-- prompt: space shows the next page, enter the next line, q stops
+fn handle_more(user_input: &str, lines: Vec<String>, shown: usize, then: NextInputHandler) {
+    set_next_input_handler(then.clone());
+
+    let more = match user_input {
+        "" => 1,
+        _ if user_input.trim().is_empty() => pager::page_size().unwrap_or(lines.len()),
+        _ if user_input.trim().eq_ignore_ascii_case("q") => return,
+        _ => {
+            add_output("--More-- (space = next page, enter = next line, q = quit)");
+            set_next_input_handler(NextInputHandler::More { lines, shown, then: Box::new(then) });
+            return;
+        }
     };
 
-    add_output(&response);
+    let end = (shown + more).min(lines.len());
+    for line in &lines[shown..end] {
+        add_output(line);
+    }
+    if end < lines.len() {
+        add_output(&pager::prompt(end, lines.len()));
+        set_next_input_handler(NextInputHandler::More { lines, shown: end, then: Box::new(then) });
+    }
+}
+
+/// Handle a choice made in the file picker (a number, or a path chosen with the arrow keys)
+async fn handle_pick_selection(user_input: &str, entries: &[String], then: Option<(String, String)>) {
+    let choice = match user_input.parse::<usize>() {
+        Ok(n) if (1..=entries.len()).contains(&n) => Some(entries[n - 1].clone()),
+        _ => entries.iter().find(|entry| entry.as_str() == user_input).cloned(),
+    };
 
-    // Clear handler - return to normal mode
-    NEXT_INPUT_HANDLER.with(|h| *h.borrow_mut() = NextInputHandler::None);
+    let Some(path) = choice else {
+        if user_input.is_empty() || user_input.eq_ignore_ascii_case("q") {
+            add_output("Cancelled.");
+            set_next_input_handler(NextInputHandler::None);
+        } else {
+            // Stay in the picker so the user can try again
+            add_output(&format!("Not a valid choice: {} (enter 1-{}, or q to cancel)", user_input, entries.len()));
+        }
+        return;
+    };
+
+    // Return to normal mode before running anything that might prompt again
+    set_next_input_handler(NextInputHandler::None);
+
+    match then {
+        Some((before, after)) => process_normal_command(&format!("{}{}{}", before, path, after)).await,
+        None => add_output(&path),
+    }
 }
 
 /// Process a normal command (not a response to a prompt)
@@ -254,17 +554,50 @@ async fn process_normal_command(user_input: &str) {
         return;
     }
 
-    if user_input == "clear" {
-        clear_output();
+    // $(pick [dir]) asks for a file first, then runs the command with the chosen path
+    if let Some(start) = user_input.find("$(pick")
+        && let Some(len) = user_input[start..].find(')')
+    {
+        let inner = &user_input[start + 2..start + len];
+        let before = user_input[..start].to_string();
+        let after = user_input[start + len + 1..].to_string();
+
+        let result = start_pick(inner.split_whitespace().nth(1), Some((before, after))).await;
+        if !result.is_empty() {
+            add_output(&result);
+        }
         return;
     }
 
     let result = process_command(user_input).await;
+    show_output(&result).await;
+}
 
-    // Display output
-    if !result.is_empty() {
-        for line in result.lines() {
-            add_output(line);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::task::{Context, Waker};
+    use crate::fixtures::{block_on, enable};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_long_output_is_shown_in_chunks() {
+        enable(1);
+        let lines: Vec<String> = (1..=500).map(|n| format!("line {}", n)).collect();
+        write_file("big.txt", lines.join("\n")).unwrap();
+
+        let mut cat = std::pin::pin!(handle_input("cat big.txt"));
+        assert!(cat.as_mut().poll(&mut Context::from_waker(Waker::noop())).is_pending());
+        let shown = take_output();
+        assert_eq!(shown[1..], lines[..OUTPUT_CHUNK_LINES]);
+
+        // A line entered before the rest is shown waits its turn
+        block_on(handle_input("pwd"));
+        assert_eq!(take_output(), Vec::<String>::new());
+        block_on(cat);
+        let rest = take_output();
+        assert_eq!(rest[..300], lines[OUTPUT_CHUNK_LINES..]);
+        assert_eq!(rest[300..], [format!("{} pwd", preferences::prompt()), "/".to_string()]);
     }
 }
@@ -0,0 +1,322 @@
+//! Markdown for the terminal: turns a markdown file into lines of styled
+//! spans (see `render`), for showing a post without leaving the terminal.
+//! Parsed with pulldown-cmark (CommonMark, plus strikethrough and tables):
+//! headings, paragraphs, lists, quotes, code, rules, and inline code,
+//! emphasis, links and images are styled. Tables and raw HTML are shown as written.
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+
+/// A run of text in one style. `class` is a space separated list of CSS
+/// classes ("" for plain text) and `href` makes it a link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub class: String,
+    pub href: Option<String>,
+}
+
+/// One line of output
+pub type Line = Vec<Span>;
+
+fn span(text: &str, class: &str) -> Span {
+    Span { text: text.to_string(), class: class.to_string(), href: None }
+}
+
+struct Renderer<'a> {
+    source: &'a str,
+    lines: Vec<Line>,
+    // The line being filled, once something has been put on it
+    line: Option<Line>,
+    // Styles of what's open (quotes, the heading, emphasis, links), outermost first
+    classes: Vec<&'static str>,
+    // Targets of the links and images open (None where a target isn't safe)
+    hrefs: Vec<Option<String>>,
+    // Open lists: the number of the next item in an ordered one
+    lists: Vec<Option<u64>>,
+    // The bullet starting the next line (an item's first)
+    bullet: Option<String>,
+    quotes: usize,
+    in_code: bool,
+    in_table: bool,
+}
+
+impl<'a> Renderer<'a> {
+    // A fresh line: the bars of the quotes it's in, then any bullet waiting for it
+    fn new_line(&mut self) -> Line {
+        let mut line = Vec::new();
+        for _ in 0..self.quotes {
+            push_span(&mut line, "│ ", &["md-quote"], None);
+        }
+        if let Some(bullet) = self.bullet.take() {
+            line.push(span(&bullet, "md-bullet"));
+        }
+        line
+    }
+
+    fn end_line(&mut self) {
+        if let Some(line) = self.line.take() {
+            self.lines.push(line);
+        }
+    }
+
+    // Start a block at `offset`, leaving a blank line before it if the source did
+    fn block(&mut self, offset: usize) {
+        self.end_line();
+        if follows_blank_line(self.source, offset) && self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(Vec::new());
+        }
+    }
+
+    fn text(&mut self, text: &str, extra: &[&str]) {
+        let mut line = match self.line.take() {
+            Some(line) => line,
+            None => self.new_line(),
+        };
+        let href = self.hrefs.last().cloned().flatten();
+        push_span(&mut line, text, &[&self.classes[..], extra].concat(), href.as_deref());
+        self.line = Some(line);
+    }
+
+    // Lines shown on their own, each in one style
+    fn verbatim(&mut self, text: &str, prefix: &str, class: &str) {
+        self.end_line();
+        for source_line in text.lines() {
+            let mut line = self.new_line();
+            push_span(&mut line, &format!("{}{}", prefix, source_line), &[class], None);
+            self.lines.push(line);
+        }
+    }
+
+    fn start(&mut self, tag: Tag, offset: usize) {
+        match tag {
+            Tag::Paragraph | Tag::HtmlBlock => self.block(offset),
+            Tag::Heading { level, .. } => {
+                self.block(offset);
+                self.classes.push(["md-h1", "md-h2", "md-h3"][(level as usize).min(3) - 1]);
+            }
+            Tag::BlockQuote(_) => {
+                self.block(offset);
+                self.quotes += 1;
+                self.classes.push("md-quote");
+            }
+            Tag::CodeBlock(_) => {
+                self.block(offset);
+                self.in_code = true;
+            }
+            Tag::List(first) => {
+                self.end_line();
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.block(offset);
+                // Every level of nesting indents the bullet two more spaces
+                let indent = "  ".repeat(self.lists.len());
+                self.bullet = Some(match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}{}. ", indent, *number - 1)
+                    }
+                    _ => format!("{}• ", indent),
+                });
+            }
+            Tag::Emphasis => self.classes.push("md-em"),
+            Tag::Strong => self.classes.push("md-strong"),
+            Tag::Strikethrough => self.classes.push("md-strike"),
+            Tag::Link { link_type, dest_url, .. } => {
+                let target = match link_type {
+                    LinkType::Email => format!("mailto:{}", dest_url),
+                    _ => dest_url.to_string(),
+                };
+                self.classes.push("md-link");
+                self.hrefs.push(safe_href(&target));
+            }
+            // Images are shown as their description, linking to the picture
+            Tag::Image { dest_url, .. } => {
+                self.classes.push("md-link");
+                self.hrefs.push(safe_href(&dest_url));
+                self.text("[image: ", &[]);
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.end_line(),
+            TagEnd::Heading(_) => {
+                self.classes.pop();
+                self.end_line();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.end_line();
+                self.quotes -= 1;
+                self.classes.pop();
+            }
+            TagEnd::CodeBlock => self.in_code = false,
+            TagEnd::List(_) => {
+                self.end_line();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                // An empty item still shows its bullet
+                if self.line.is_none() && self.bullet.is_some() {
+                    let line = self.new_line();
+                    self.lines.push(line);
+                }
+                self.end_line();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.classes.pop();
+            }
+            TagEnd::Link => {
+                self.classes.pop();
+                self.hrefs.pop();
+            }
+            TagEnd::Image => {
+                self.text("]", &[]);
+                self.classes.pop();
+                self.hrefs.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Render markdown into styled lines
+pub fn render(markdown: &str) -> Vec<Line> {
+    let mut out = Renderer {
+        source: markdown,
+        lines: Vec::new(),
+        line: None,
+        classes: Vec::new(),
+        hrefs: Vec::new(),
+        lists: Vec::new(),
+        bullet: None,
+        quotes: 0,
+        in_code: false,
+        in_table: false,
+    };
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES;
+
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        if out.in_table {
+            out.in_table = event != Event::End(TagEnd::Table);
+            continue;
+        }
+        match event {
+            Event::Start(Tag::Table(_)) => {
+                out.block(range.start);
+                out.verbatim(&markdown[range], "", "");
+                out.in_table = true;
+            }
+            Event::Start(tag) => out.start(tag, range.start),
+            Event::End(tag) => out.end(tag),
+            Event::Text(text) if out.in_code => out.verbatim(&text, "    ", "md-code"),
+            Event::Text(text) | Event::InlineHtml(text) => out.text(&text, &[]),
+            Event::Code(code) => out.text(&code, &["md-code"]),
+            Event::Html(html) => out.verbatim(&html, "", ""),
+            Event::SoftBreak => out.text(" ", &[]),
+            Event::HardBreak => out.end_line(),
+            Event::Rule => {
+                out.block(range.start);
+                let mut line = out.new_line();
+                line.push(span(&"─".repeat(40), "md-rule"));
+                out.lines.push(line);
+            }
+            _ => {}
+        }
+    }
+    out.end_line();
+    out.lines
+}
+
+// Whether the line before the one `offset` is on is blank (or only a quote's '>')
+fn follows_blank_line(source: &str, offset: usize) -> bool {
+    let Some(line_start) = source[..offset].rfind('\n') else {
+        return false;
+    };
+    let before = &source[..line_start];
+    let previous = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+    previous.chars().all(|c| c == '>' || c.is_whitespace())
+}
+
+// Links only go somewhere harmless: the web, email, or a path on this site
+fn safe_href(href: &str) -> Option<String> {
+    let lower = href.trim().to_ascii_lowercase();
+    let has_scheme = lower.split(['/', '?', '#']).next().is_some_and(|first| first.contains(':'));
+    let allowed = lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("mailto:");
+    (allowed || !has_scheme).then(|| href.trim().to_string())
+}
+
+// Add a span, joining it onto the last one if they look the same
+fn push_span(line: &mut Line, text: &str, classes: &[&str], href: Option<&str>) {
+    if text.is_empty() {
+        return;
+    }
+    let class = classes.join(" ");
+    if let Some(last) = line.last_mut()
+        && last.class == class
+        && last.href.as_deref() == href
+    {
+        last.text.push_str(text);
+        return;
+    }
+    line.push(Span { text: text.to_string(), class, href: href.map(str::to_string) });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|line| line.iter().map(|span| span.text.as_str()).collect()).collect()
+    }
+
+    #[test]
+    fn test_blocks() {
+        let lines = render("# Title\n\nSome text\nrunning on.\n\n- one\n- two\n  - nested\n1. first\n\n> quoted\n\n```rust\nfn main() {}\n```\n---\nSub\n===");
+        assert_eq!(texts(&lines), [
+            "Title", "", "Some text running on.", "", "  • one", "  • two", "    • nested", "  1. first", "",
+            "│ quoted", "", "    fn main() {}", &"─".repeat(40), "Sub",
+        ]);
+        assert_eq!(lines[0][0].class, "md-h1");
+        assert_eq!(lines[11][0].class, "md-code");
+        assert_eq!(lines[13][0].class, "md-h1");
+    }
+
+    #[test]
+    fn test_inline() {
+        let lines = render("Use `ls -l`, **really** *now* or snake_case_name, see [the *post*](/blog/a.md)");
+        assert_eq!(lines[0], [
+            span("Use ", ""),
+            span("ls -l", "md-code"),
+            span(", ", ""),
+            span("really", "md-strong"),
+            span(" ", ""),
+            span("now", "md-em"),
+            span(" or snake_case_name, see ", ""),
+            Span { text: "the ".to_string(), class: "md-link".to_string(), href: Some("/blog/a.md".to_string()) },
+            Span { text: "post".to_string(), class: "md-link md-em".to_string(), href: Some("/blog/a.md".to_string()) },
+        ]);
+    }
+
+    #[test]
+    fn test_tables_images_and_breaks() {
+        let lines = render("| a | b |\n|---|---|\n| 1 | 2 |\n\n![a cat](/cat.png) <me@example.com>\\\nnext\n\n- loose\n\n- items\n\n> <b>raw</b>");
+        assert_eq!(texts(&lines), [
+            "| a | b |", "|---|---|", "| 1 | 2 |", "",
+            "[image: a cat] me@example.com", "next", "",
+            "  • loose", "", "  • items", "",
+            "│ <b>raw</b>",
+        ]);
+        assert_eq!(lines[4][0].href.as_deref(), Some("/cat.png"));
+        assert_eq!(lines[4][2].href.as_deref(), Some("mailto:me@example.com"));
+    }
+
+    #[test]
+    fn test_unsafe_links_are_not_links() {
+        let lines = render("[click](javascript:alert(1)) \\*literal\\* 2 * 3 * 4");
+        assert_eq!(lines[0][0].href, None);
+        assert_eq!(texts(&lines), ["click *literal* 2 * 3 * 4"]);
+    }
+}
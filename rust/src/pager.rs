@@ -0,0 +1,69 @@
+//! Paging long command output: past a screenful, the rest waits behind a
+//! `--More--` prompt (space for the next page, enter for the next line, q to
+//! stop). Off unless the site sets pager.lines to the lines per screenful,
+//! or the visitor sets pager_lines (see `crate::preferences`).
+
+use crate::preferences;
+
+pub const PAGER_KEY: &str = "pager.lines";
+
+/// Lines per screenful, if paging is on
+pub fn page_size() -> Option<usize> {
+    preferences::pager_lines()
+}
+
+/// The prompt after `shown` of `total` lines
+pub fn prompt(shown: usize, total: usize) -> String {
+    format!("--More-- ({}%)", shown * 100 / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{block_on, enable, run};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_long_output_is_paged() {
+        enable(1);
+        crate::write_file("lines.txt", "1\n2\n3\n4\n5\n6\n7".to_string()).unwrap();
+        crate::settings::set_setting("pager.lines", "3");
+        assert_eq!(run("cat lines.txt"), ["1", "2", "3", "--More-- (42%)"]);
+
+        // Keys at the prompt aren't commands: nothing is echoed or kept in history
+        block_on(crate::handle_input(""));
+        assert_eq!(take_output(), ["4", "--More-- (57%)"]);
+        block_on(crate::handle_input("x"));
+        assert_eq!(take_output(), ["--More-- (space = next page, enter = next line, q = quit)"]);
+        block_on(crate::handle_input(" "));
+        assert_eq!(take_output(), ["5", "6", "7"]);
+        assert_eq!(run("pwd"), ["/"]);
+
+        run("cat lines.txt");
+        block_on(crate::handle_input("q"));
+        assert!(take_output().is_empty());
+        assert_eq!(run("pwd"), ["/"]);
+        assert_eq!(crate::input_history::INPUT_HISTORY.with_borrow(|history| history.inputs().to_vec()), [
+            "cat lines.txt", "pwd", "cat lines.txt", "pwd",
+        ]);
+        crate::settings::set_setting("pager.lines", "");
+    }
+
+    #[test]
+    fn test_paging_in_the_rpg_goes_back_to_the_game() {
+        enable(1);
+        run("features rpg on");
+        run("rpg start");
+        crate::settings::set_setting("pager.lines", "3");
+        block_on(crate::handle_input("help"));
+        assert!(take_output().last().unwrap().starts_with("--More--"));
+        while crate::NEXT_INPUT_HANDLER.with_borrow(|handler| handler.reads_keys()) {
+            block_on(crate::handle_input(" "));
+        }
+        take_output();
+
+        // Lines still go to the game, not the shell
+        block_on(crate::handle_input("ls"));
+        assert_eq!(take_output(), ["[village] rpg> ls", "I don't know how to ls (type help for what you can do)"]);
+        crate::settings::set_setting("pager.lines", "");
+    }
+}
@@ -0,0 +1,351 @@
+//! Keeping the visitor's files in the browser's IndexedDB, so they survive a
+//! reload without save-session. Off until the visitor runs `persist on`; the
+//! choice is stored along with the files. Every file write and removal is
+//! mirrored to storage as it happens, and `load_manifest` restores them.
+//!
+//! Edits in the abyss are stored too, as what became of each path over what
+//! the server serves (the abyss delta), and replayed over it on restore.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use serde_json::{Map, Value};
+use serde_json::json;
+use crate::filesystem::{ABYSS_FS, Content, DirPath, FilePath, VIRTUAL_FS};
+use crate::filesystem::abyss_delta::{self, ABYSS_DELTA};
+use crate::filesystem::events::{subscribe, FsEvent};
+use crate::filesystem::helpers::path_in_abyss;
+use crate::js_interop::{log, persist_clear, persist_delete, persist_load, persist_put};
+use crate::reload::{content_from_json, content_to_json};
+
+// Records are keyed by path; directories end in '/', settings start with '!'
+const ENABLED_KEY: &str = "!enabled";
+
+// An abyss record holds a written file's content, "null" for a directory
+// made, or one of these (and a removed file or directory outside it is
+// REMOVED or REMADE too)
+const REMOVED: &str = "\"removed\"";
+// A directory removed (clearing out what the server has there) and made again
+const REMADE: &str = "\"remade\"";
+
+thread_local! {
+    static ENABLED: RefCell<bool> = const { RefCell::new(false) };
+    // Keys of the directories outside the abyss stored as removed, so one
+    // made again is stored as remade
+    static REMOVED_DIRS: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+pub fn enabled() -> bool {
+    ENABLED.with_borrow(|enabled| *enabled)
+}
+
+/// A directory's key in storage: its path, ending in '/'
+pub fn dir_key(dirpath: &DirPath) -> String {
+    let path = dirpath.to_string();
+    if path.ends_with('/') { path } else { path + "/" }
+}
+
+// Store a file's content, if it's one of the visitor's (in memory)
+fn store_file(filepath: &FilePath) {
+    let value = VIRTUAL_FS.with_borrow(|vfs| match vfs.get_content(filepath) {
+        Some(content @ Content::InMemory(_)) => Some(content_to_json(content).to_string()),
+        _ => None,
+    });
+    if let Some(value) = value {
+        persist_put(&filepath.to_string(), &value);
+    }
+}
+
+// Store what became of an abyss file, as the abyss delta has it
+fn store_abyss_file(path: &str) {
+    let filepath = FilePath::parse(path, &DirPath::root());
+    let (written, removed) = ABYSS_DELTA.with_borrow(|delta| (delta.written.contains(path), delta.removed_files.contains(path)));
+    let content = ABYSS_FS.with_borrow(|afs| match afs.files.get(&filepath.dir).and_then(|contents| contents.get(&filepath.file)) {
+        Some(content @ Content::InMemory(_)) if written => Some(content_to_json(content).to_string()),
+        _ => None,
+    });
+    match (content, removed) {
+        (Some(content), _) => persist_put(path, &content),
+        (None, true) => persist_put(path, REMOVED),
+        (None, false) => persist_delete(path),
+    }
+}
+
+// Store what became of an abyss directory, as the abyss delta has it
+// (nothing, once one the visitor made is removed)
+fn store_abyss_dir(path: &str) {
+    let (removed, created) = ABYSS_DELTA.with_borrow(|delta| (delta.removed_dirs.contains(path), delta.created_dirs.contains(path)));
+    let key = dir_key(&DirPath::parse(path, &DirPath::root()));
+    match (removed, created) {
+        (true, true) => persist_put(&key, REMADE),
+        (true, false) => persist_put(&key, REMOVED),
+        (false, true) => persist_put(&key, "null"),
+        (false, false) => {}
+    }
+}
+
+/// Mirror filesystem changes to storage while persistence is on.
+/// Abyss changes are read from the abyss delta, so this must be registered after it.
+pub fn register() {
+    subscribe(|event| {
+        if !enabled() {
+            return;
+        }
+        match event {
+            FsEvent::FileWritten(filepath) | FsEvent::FileRemoved(filepath) if path_in_abyss(&filepath.dir) => store_abyss_file(&filepath.to_string()),
+            FsEvent::DirCreated(dirpath) if path_in_abyss(dirpath) => store_abyss_dir(&dirpath.to_string()),
+            FsEvent::DirRemoved(dirpath) if path_in_abyss(dirpath) => {
+                // What was recorded below it goes with it
+                persist_delete(&dir_key(dirpath));
+                store_abyss_dir(&dirpath.to_string());
+            }
+            FsEvent::FileWritten(filepath) => store_file(filepath),
+            // Kept as a tombstone, or the site's file would come back with
+            // the manifest (restore drops it if the site has none there)
+            FsEvent::FileRemoved(filepath) => persist_put(&filepath.to_string(), REMOVED),
+            FsEvent::DirCreated(dirpath) => {
+                let key = dir_key(dirpath);
+                let remade = REMOVED_DIRS.with_borrow(|dirs| dirs.contains(&key));
+                persist_put(&key, if remade { REMADE } else { "null" });
+            }
+            // What was recorded below it goes with it, and a tombstone keeps
+            // the site's files there from coming back with the manifest
+            FsEvent::DirRemoved(dirpath) => {
+                let key = dir_key(dirpath);
+                persist_delete(&key);
+                persist_put(&key, REMOVED);
+                REMOVED_DIRS.with_borrow_mut(|dirs| dirs.insert(key));
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Turn persistence on, storing every directory and in-memory file there is now.
+/// Returns how many files were stored.
+pub fn enable() -> usize {
+    ENABLED.with_borrow_mut(|enabled| *enabled = true);
+    persist_put(ENABLED_KEY, "on");
+
+    let dirs: Vec<DirPath> = VIRTUAL_FS.with_borrow(|vfs| vfs.content.keys().cloned().collect());
+    let mut stored = 0;
+    for dirpath in dirs.iter().filter(|dirpath| !path_in_abyss(dirpath)) {
+        persist_put(&dir_key(dirpath), "null");
+        let files: Vec<String> = VIRTUAL_FS.with_borrow(|vfs| vfs.list_files_in_dir(dirpath));
+        for file in files {
+            let filepath = FilePath::new(dirpath.clone(), file);
+            if VIRTUAL_FS.with_borrow(|vfs| matches!(vfs.get_content(&filepath), Some(Content::InMemory(_)))) {
+                store_file(&filepath);
+                stored += 1;
+            }
+        }
+    }
+
+    let delta = ABYSS_DELTA.with_borrow(|delta| delta.clone());
+    for path in delta.removed_dirs.union(&delta.created_dirs) {
+        store_abyss_dir(path);
+    }
+    for path in delta.removed_files.union(&delta.written) {
+        store_abyss_file(path);
+    }
+    stored + delta.written.len()
+}
+
+/// Carry on storing changes (or not) as a previous build of the terminal
+/// was, without storing anything now
+pub fn resume(on: bool) {
+    ENABLED.with_borrow_mut(|enabled| *enabled = on);
+}
+
+/// Turn persistence off and forget everything stored (files in memory are untouched)
+pub fn disable() {
+    ENABLED.with_borrow_mut(|enabled| *enabled = false);
+    REMOVED_DIRS.with_borrow_mut(|dirs| dirs.clear());
+    persist_clear();
+}
+
+/// Bring back stored directories and files (called once the manifest has loaded)
+pub async fn restore() {
+    let Some(text) = persist_load().await else { return };
+    let Ok(records) = serde_json::from_str::<Map<String, Value>>(&text) else { return };
+    if records.get(ENABLED_KEY).and_then(|value| value.as_str()) != Some("on") {
+        return;
+    }
+    ENABLED.with_borrow_mut(|enabled| *enabled = true);
+
+    let root = DirPath::root();
+    let in_abyss = |key: &str| key.starts_with('/') && path_in_abyss(&DirPath::parse(key, &root));
+    VIRTUAL_FS.with_borrow_mut(|vfs| {
+        for (key, value) in &records {
+            if key.starts_with('!') || in_abyss(key) {
+                continue;
+            }
+            // Keys are in order, so a removed directory is cleared before
+            // anything stored below it (once it was made again) comes back
+            let tombstone = matches!(value.as_str(), Some(REMOVED | REMADE));
+            if tombstone && key.ends_with('/') {
+                let removed = vfs.remove_dir_all(&DirPath::parse(key, &root)).is_ok();
+                if value.as_str() == Some(REMOVED) && !removed {
+                    persist_delete(key);
+                    continue;
+                }
+                REMOVED_DIRS.with_borrow_mut(|dirs| dirs.insert(key.clone()));
+                if value.as_str() == Some(REMOVED) {
+                    continue;
+                }
+            } else if tombstone {
+                if !vfs.remove_file(&FilePath::parse(key, &root)) {
+                    persist_delete(key);
+                }
+                continue;
+            }
+            let dirpath = if key.ends_with('/') { DirPath::parse(key, &root) } else { FilePath::parse(key, &root).dir };
+            // Parents first, so every level can be listed
+            for depth in 1..=dirpath.0.len() {
+                vfs.create_dir(DirPath(dirpath.0[..depth].to_vec()));
+            }
+            if key.ends_with('/') {
+                continue;
+            }
+            let content = value.as_str()
+                .and_then(|value| serde_json::from_str(value).ok())
+                .and_then(|value| content_from_json(&value));
+            if let Some(Content::InMemory(data)) = content {
+                vfs.write_file_data(&FilePath::parse(key, &root), data);
+            }
+        }
+    });
+
+    // The abyss edits, as a delta to replay over what the server serves now
+    let (mut removed_dirs, mut removed_files, mut created_dirs, mut files) = (Vec::new(), Vec::new(), Vec::new(), Map::new());
+    for (key, value) in records.iter().filter(|(key, _)| in_abyss(key)) {
+        let path = key.trim_end_matches('/').to_string();
+        match (key.ends_with('/'), value.as_str().unwrap_or_default()) {
+            (true, REMADE) => {
+                removed_dirs.push(path.clone());
+                created_dirs.push(path);
+            }
+            (true, REMOVED) => removed_dirs.push(path),
+            (true, _) => created_dirs.push(path),
+            (false, REMOVED) => removed_files.push(path),
+            (false, value) => {
+                if let Ok(value) = serde_json::from_str(value) {
+                    files.insert(path, value);
+                }
+            }
+        }
+    }
+    let delta = json!({
+        "removed_dirs": removed_dirs,
+        "removed_files": removed_files,
+        "created_dirs": created_dirs,
+        "files": files,
+    });
+    let file_bytes = |entry: &Value| match content_from_json(entry) {
+        Some(Content::InMemory(data)) => Some(data.bytes),
+        _ => None,
+    };
+    for (path, result) in abyss_delta::import(&delta, file_bytes).await {
+        if let Err(e) = result {
+            log(&format!("persist: {}: {}", path, e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::fixtures::{block_on, run};
+    use crate::filesystem::{DirPath, FilePath};
+
+    #[test]
+    fn test_persist_across_reloads() {
+        fixtures::enable(1);
+        crate::persist::register();
+        crate::write_file("early.txt", "before".to_string()).unwrap();
+        assert_eq!(run("persist"), ["Persistence is off. Type 'persist on' to keep your files across reloads."]);
+        assert_eq!(run("persist on"), ["Persistence is on: your files will be kept in this browser (1 file(s) stored now)."]);
+
+        // Changes from now on are mirrored as they happen
+        run("mkdir -p docs/drafts");
+        crate::write_file("docs/drafts/post.md", "# Draft".to_string()).unwrap();
+        crate::write_file("gone.txt", "bye".to_string()).unwrap();
+        run("rm gone.txt");
+        run("rm -f about.txt");
+        assert_eq!(run("persist status"), ["Persistence is on: 2 file(s) (13 bytes) are kept in this browser."]);
+
+        // A reload: a fresh filesystem, then whatever was stored
+        fixtures::enable(1);
+        block_on(crate::persist::restore());
+        assert_eq!(run("cat early.txt"), ["before"]);
+        assert_eq!(run("ls docs/drafts"), ["post.md"]);
+        assert_eq!(run("cat gone.txt"), ["cat: gone.txt: No such file"]);
+        // A removed site file stays removed, while a tombstone with nothing
+        // under it is dropped
+        assert_eq!(run("cat about.txt"), ["cat: about.txt: No such file"]);
+        let records = block_on(crate::js_interop::persist_load()).unwrap();
+        assert!(records.contains("/about.txt") && !records.contains("/gone.txt"), "{}", records);
+
+        assert_eq!(run("persist off"), ["Persistence is off: stored files were removed from this browser (your open files are unchanged)."]);
+        fixtures::enable(1);
+        block_on(crate::persist::restore());
+        assert_eq!(run("cat early.txt"), ["cat: early.txt: No such file"]);
+    }
+
+    #[test]
+    fn test_persist_removed_directories() {
+        fixtures::enable(1);
+        crate::persist::register();
+        run("persist on");
+        run("rm -rf rpg");
+        // Cleared out and made again, so none of the site's files come back in it
+        run("rm -rf config");
+        run("mkdir config");
+        crate::write_file("config/mine.toml", "theme = \"dark\"".to_string()).unwrap();
+        run("mkdir scratch");
+        run("rm -r scratch");
+
+        fixtures::enable(1);
+        block_on(crate::persist::restore());
+        assert_eq!(run("ls /"), ["about.txt", "abyss/", "config/", "fortunes.txt"]);
+        assert_eq!(run("ls /config"), ["mine.toml"]);
+        // A tombstone with nothing of the site's under it is dropped
+        let records = block_on(crate::js_interop::persist_load()).unwrap();
+        assert!(records.contains("/rpg/") && !records.contains("/scratch/"), "{}", records);
+
+        // And the directories stay as they were through another reload
+        fixtures::enable(1);
+        block_on(crate::persist::restore());
+        assert_eq!(run("ls /"), ["about.txt", "abyss/", "config/", "fortunes.txt"]);
+        assert_eq!(run("ls /config"), ["mine.toml"]);
+    }
+
+    #[test]
+    fn test_persist_abyss_edits() {
+        fixtures::enable(1);
+        crate::filesystem::abyss_delta::register();
+        crate::persist::register();
+        run("cd /abyss");
+        run("mkdir camp");
+        assert_eq!(run("persist on"), ["Persistence is on: your files will be kept in this browser (0 file(s) stored now)."]);
+        run("rm warning.txt");
+        block_on(crate::filesystem::helpers::write_file(&FilePath::parse("/abyss/camp/log.txt", &DirPath::root()), "day 1")).unwrap();
+        // Cleared out and made again, so nothing of the server's is left in it
+        run("rm -r level1/level2");
+        run("mkdir level1/level2");
+
+        // A reload replays the edits over the abyss as the server serves it
+        fixtures::enable(1);
+        assert_eq!(run("ls /abyss"), ["level1/", "mirror_halls/", "warning.txt"]);
+        block_on(crate::persist::restore());
+        assert_eq!(run("ls /abyss"), ["camp/", "level1/", "mirror_halls/"]);
+        assert_eq!(run("cat /abyss/camp/log.txt"), ["day 1"]);
+        assert_eq!(run("ls /abyss/level1/level2"), ["(empty directory)"]);
+        assert_eq!(run("ls /"), ["about.txt", "abyss/", "config/", "fortunes.txt", "rpg/"]);
+
+        // What the visitor made and removed again leaves nothing to replay
+        run("rm -r /abyss/camp");
+        fixtures::enable(1);
+        block_on(crate::persist::restore());
+        assert_eq!(run("ls /abyss"), ["level1/", "mirror_halls/"]);
+    }
+}
@@ -0,0 +1,247 @@
+//! The visitor's preferences, kept in /.config/terminal.toml and changed
+//! with the `settings` command (or by editing the file). The file is read
+//! when the terminal starts and again whenever it's written.
+//!
+//! Where a preference overlaps a deployment setting (pager.lines,
+//! prefetch), the visitor's choice wins; left unset, the site's applies.
+
+use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use crate::filesystem::{Content, VIRTUAL_FS};
+use crate::filesystem::events::{subscribe, FsEvent};
+use crate::filesystem::file_paths::PREFERENCES_FILE_PATH;
+use crate::filesystem::helpers::{get_file_content, write_file};
+use crate::js_interop::{set_font_size, set_prompt};
+use crate::pager::PAGER_KEY;
+use crate::settings::get_setting;
+
+pub const DEFAULT_FONT_SIZE: u32 = 16;
+pub const DEFAULT_PROMPT: &str = ">";
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Every preference, with what it's for
+pub const KEYS: &[(&str, &str)] = &[
+    ("font_size", "Text size in pixels (8 to 40)"),
+    ("prompt", "The text before the command line"),
+    ("pager_lines", "Lines of output before --More-- (0 for no paging)"),
+    ("prefetch", "Fetch abyss directories ahead of time (on or off)"),
+    ("history_size", "Commands kept for the up arrow and history (1 to 10000)"),
+    ("cave_seed", "The number the cave of dice is built from (its layout changes with it)"),
+];
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preferences {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pager_lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefetch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cave_seed: Option<u64>,
+}
+
+thread_local! {
+    static PREFERENCES: RefCell<Preferences> = RefCell::new(Preferences::default());
+}
+
+impl Preferences {
+    pub fn parse(text: &str) -> Result<Preferences, String> {
+        let preferences: Preferences = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        for (key, _) in KEYS {
+            if let Some(value) = preferences.get(key) {
+                check(key, &value)?;
+            }
+        }
+        Ok(preferences)
+    }
+
+    /// A preference's value as text, if set
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "font_size" => self.font_size.map(|size| size.to_string()),
+            "prompt" => self.prompt.clone(),
+            "pager_lines" => self.pager_lines.map(|lines| lines.to_string()),
+            "prefetch" => self.prefetch.map(|on| if on { "on" } else { "off" }.to_string()),
+            "history_size" => self.history_size.map(|size| size.to_string()),
+            "cave_seed" => self.cave_seed.map(|seed| seed.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Set a preference from text (None unsets it)
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<(), String> {
+        let Some(value) = value else {
+            match key {
+                "font_size" => self.font_size = None,
+                "prompt" => self.prompt = None,
+                "pager_lines" => self.pager_lines = None,
+                "prefetch" => self.prefetch = None,
+                "history_size" => self.history_size = None,
+                "cave_seed" => self.cave_seed = None,
+                _ => return Err(format!("{}: No such setting", key)),
+            }
+            return Ok(());
+        };
+        check(key, value)?;
+        match key {
+            "font_size" => self.font_size = value.parse().ok(),
+            "prompt" => self.prompt = Some(value.to_string()),
+            "pager_lines" => self.pager_lines = value.parse().ok(),
+            "prefetch" => self.prefetch = Some(value == "on" || value == "true"),
+            "history_size" => self.history_size = value.parse().ok(),
+            "cave_seed" => self.cave_seed = value.parse().ok(),
+            _ => unreachable!("checked above"),
+        }
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap_or_default()
+    }
+}
+
+// Whether `value` is allowed for `key`
+fn check(key: &str, value: &str) -> Result<(), String> {
+    let in_range = |low: usize, high: usize| match value.parse::<usize>() {
+        Ok(n) if (low..=high).contains(&n) => Ok(()),
+        _ => Err(format!("{}: {} must be a whole number from {} to {}", value, key, low, high)),
+    };
+    match key {
+        "font_size" => in_range(8, 40),
+        "prompt" if value.chars().count() > 40 => Err("prompt is at most 40 characters".to_string()),
+        "prompt" => Ok(()),
+        "pager_lines" => in_range(0, 1000),
+        "prefetch" => match value {
+            "on" | "off" | "true" | "false" => Ok(()),
+            _ => Err(format!("{}: prefetch must be on or off", value)),
+        },
+        "history_size" => in_range(1, 10000),
+        "cave_seed" => match value.parse::<u64>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!("{}: cave_seed must be a whole number", value)),
+        },
+        _ => Err(format!("{}: No such setting", key)),
+    }
+}
+
+/// The visitor's preferences as they stand
+pub fn current() -> Preferences {
+    PREFERENCES.with_borrow(|preferences| preferences.clone())
+}
+
+pub fn font_size() -> u32 {
+    current().font_size.unwrap_or(DEFAULT_FONT_SIZE)
+}
+
+pub fn prompt() -> String {
+    current().prompt.unwrap_or_else(|| DEFAULT_PROMPT.to_string())
+}
+
+/// Lines per screenful if paging is on: the visitor's choice, else the site's
+pub fn pager_lines() -> Option<usize> {
+    current().pager_lines
+        .or_else(|| get_setting(PAGER_KEY).and_then(|value| value.parse().ok()))
+        .filter(|lines| *lines > 0)
+}
+
+/// Whether to prefetch: the visitor's choice, else on unless the site turns it off
+pub fn prefetch() -> bool {
+    current().prefetch.unwrap_or_else(|| get_setting("prefetch").as_deref() != Some("off"))
+}
+
+pub fn history_size() -> usize {
+    current().history_size.unwrap_or(DEFAULT_HISTORY_SIZE)
+}
+
+/// Save new preferences to the file (which puts them into effect)
+pub async fn save(preferences: &Preferences) -> Result<(), String> {
+    let reseeded = current().cave_seed != preferences.cave_seed;
+    crate::commands::builtin::filesystem::create_dir_all(&PREFERENCES_FILE_PATH.dir).await?;
+    write_file(&PREFERENCES_FILE_PATH, preferences.to_toml()).await?;
+    // A new seed is a new cave, built on the next visit
+    if reseeded {
+        crate::filesystem::cave_of_dice::forget();
+    }
+    // The listener in `register` would pick this up too, but may not be listening yet
+    apply(preferences.clone());
+    Ok(())
+}
+
+/// Put `preferences` into effect (without writing them to the file)
+pub fn apply(preferences: Preferences) {
+    PREFERENCES.with_borrow_mut(|current| *current = preferences);
+    set_font_size(font_size());
+    set_prompt(&prompt());
+}
+
+// Read the file as it is now: defaults if it's missing, an error if it's invalid
+fn reread(text: Option<String>) -> Result<(), String> {
+    match text {
+        None => {
+            apply(Preferences::default());
+            Ok(())
+        }
+        Some(text) => Preferences::parse(&text).map(apply),
+    }
+}
+
+/// Read the preferences file (called once the visitor's files are restored).
+/// A file that can't be read is reported and otherwise ignored.
+pub async fn load() -> Option<String> {
+    let text = get_file_content(&PREFERENCES_FILE_PATH).await.ok();
+    reread(text).err().map(|e| format!("settings: {}: {}", PREFERENCES_FILE_PATH.to_string(), e))
+}
+
+/// Pick up changes to the file however it's written (settings, edit, load)
+pub fn register() {
+    subscribe(|event| match event {
+        FsEvent::FileWritten(filepath) if *filepath == *PREFERENCES_FILE_PATH => {
+            let text = VIRTUAL_FS.with_borrow(|vfs| match vfs.get_content(filepath) {
+                Some(Content::InMemory(data)) => Some(data.text_lossy().into_owned()),
+                _ => None,
+            });
+            // An invalid file keeps the last good preferences
+            let _ = reread(Some(text.unwrap_or_default()));
+        }
+        FsEvent::FileRemoved(filepath) if *filepath == *PREFERENCES_FILE_PATH => {
+            let _ = reread(None);
+        }
+        _ => {}
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let preferences = Preferences::parse("font_size = 18\nprompt = \"$\"\nprefetch = false\n").unwrap();
+        assert_eq!(preferences.font_size, Some(18));
+        assert_eq!(preferences.get("prompt").as_deref(), Some("$"));
+        assert_eq!(preferences.get("prefetch").as_deref(), Some("off"));
+        assert_eq!(preferences.get("pager_lines"), None);
+        assert_eq!(Preferences::parse(&preferences.to_toml()), Ok(preferences));
+
+        assert!(Preferences::parse("colour = \"red\"").unwrap_err().contains("unknown field `colour`"));
+        assert_eq!(Preferences::parse("font_size = 4"), Err("4: font_size must be a whole number from 8 to 40".to_string()));
+    }
+
+    #[test]
+    fn test_set() {
+        let mut preferences = Preferences::default();
+        preferences.set("history_size", Some("50")).unwrap();
+        preferences.set("prefetch", Some("off")).unwrap();
+        assert_eq!(preferences.to_toml(), "prefetch = false\nhistory_size = 50\n");
+        preferences.set("prefetch", None).unwrap();
+        assert_eq!(preferences.to_toml(), "history_size = 50\n");
+        assert_eq!(preferences.set("prefetch", Some("maybe")), Err("maybe: prefetch must be on or off".to_string()));
+        assert_eq!(preferences.set("colour", Some("red")), Err("colour: No such setting".to_string()));
+    }
+}
@@ -0,0 +1,192 @@
+//! Follow-up questions. A command that needs more input asks a `Prompt`
+//! and returns; the next line typed goes to the prompt instead of being run
+//! as a command. Prompts can give a default (used when the answer is left
+//! empty) and check answers, asking again until one is acceptable.
+//!
+//! A masked prompt (for passwords) has its answer shown as dots in the
+//! input box, and kept out of the history, recordings and echoed output.
+//!
+//! Prompts stack: a prompt's answer can ask further questions, which are
+//! answered before anything asked earlier. Escape or Ctrl+C cancels every
+//! prompt waiting.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use crate::js_interop::add_output;
+
+type Answered = Pin<Box<dyn Future<Output = String>>>;
+type OnAnswer = Box<dyn FnOnce(String) -> Answered>;
+type Check = Box<dyn Fn(&str) -> Result<String, String>>;
+
+pub struct Prompt {
+    question: String,
+    default: Option<String>,
+    check: Option<Check>,
+    masked: bool,
+    on_answer: OnAnswer,
+}
+
+impl Prompt {
+    /// Ask `question`; the answer is passed to `on_answer`, whose result is shown
+    pub fn new<F, Fut>(question: &str, on_answer: F) -> Prompt
+    where
+        F: FnOnce(String) -> Fut + 'static,
+        Fut: Future<Output = String> + 'static,
+    {
+        Prompt {
+            question: question.to_string(),
+            default: None,
+            check: None,
+            masked: false,
+            on_answer: Box::new(move |answer| Box::pin(on_answer(answer))),
+        }
+    }
+
+    /// The answer an empty line stands for, shown after the question
+    pub fn with_default(mut self, answer: &str) -> Prompt {
+        self.default = Some(answer.to_string());
+        self
+    }
+
+    /// Check each answer: Ok gives the answer to use (e.g. tidied up), Err
+    /// a message to show before asking again
+    pub fn with_check(mut self, check: impl Fn(&str) -> Result<String, String> + 'static) -> Prompt {
+        self.check = Some(Box::new(check));
+        self
+    }
+
+    /// Hide the answer: dots as it's typed, and never kept
+    pub fn masked(mut self) -> Prompt {
+        self.masked = true;
+        self
+    }
+
+    fn show(&self) {
+        match &self.default {
+            Some(default) => add_output(&format!("{} [{}]", self.question, default)),
+            None => add_output(&self.question),
+        }
+    }
+}
+
+thread_local! {
+    // Waiting prompts, the next to answer last
+    static PROMPTS: RefCell<Vec<Prompt>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Ask a (y/n) question; if the answer is y/yes, `on_yes` runs and its
+/// result is shown, otherwise the question is cancelled
+pub fn confirm<F, Fut>(question: &str, on_yes: F)
+where
+    F: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = String> + 'static,
+{
+    ask(Prompt::new(&format!("{} (y/n)", question), |answer| async move {
+        if !(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")) {
+            return "Cancelled.".to_string();
+        }
+        on_yes().await
+    }));
+}
+
+/// Ask `prompt` now, ahead of any questions already waiting.
+/// Commands that ask should return an empty string, as the question is already shown.
+pub fn ask(prompt: Prompt) {
+    prompt.show();
+    PROMPTS.with_borrow_mut(|prompts| prompts.push(prompt));
+}
+
+/// Whether the next input answers a prompt
+pub fn is_waiting() -> bool {
+    PROMPTS.with_borrow(|prompts| !prompts.is_empty())
+}
+
+/// Whether the next input answers a masked prompt
+pub fn is_masked() -> bool {
+    PROMPTS.with_borrow(|prompts| prompts.last().is_some_and(|prompt| prompt.masked))
+}
+
+/// Answer the waiting prompt with `input`
+pub async fn answer(input: &str) {
+    let Some((prompt, still_waiting)) = PROMPTS.with_borrow_mut(|prompts| prompts.pop().map(|prompt| (prompt, prompts.len()))) else {
+        return;
+    };
+    let input = match &prompt.default {
+        Some(default) if input.is_empty() => default.clone(),
+        _ => input.to_string(),
+    };
+    let answer = match &prompt.check {
+        Some(check) => match check(&input) {
+            Ok(answer) => answer,
+            Err(message) => {
+                add_output(&message);
+                ask(prompt);
+                return;
+            }
+        },
+        None => input,
+    };
+
+    let result = (prompt.on_answer)(answer).await;
+    if !result.is_empty() {
+        add_output(&result);
+    }
+    // An earlier question is next, unless the answer asked another: repeat it
+    PROMPTS.with_borrow(|prompts| {
+        if prompts.len() == still_waiting && let Some(earlier) = prompts.last() {
+            earlier.show();
+        }
+    });
+}
+
+/// Drop every waiting prompt. Returns whether there were any.
+pub fn cancel() -> bool {
+    let cancelled = PROMPTS.with_borrow_mut(std::mem::take);
+    !cancelled.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{block_on, enable, run};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_prompts_chain_check_and_cancel() {
+        use crate::prompt::{ask, Prompt};
+        enable(1);
+        // A name with a default, then an age that has to be a number
+        ask(Prompt::new("Name?", |name| async move {
+            ask(Prompt::new("Age?", move |age| async move { format!("{} is {}", name, age) })
+                .with_check(|age| age.parse::<u32>().map(|age| age.to_string()).map_err(|_| "Not a number".to_string())));
+            String::new()
+        }).with_default("Ada"));
+        assert_eq!(take_output(), ["Name? [Ada]"]);
+        assert_eq!(run(""), ["Age?"]);
+        assert_eq!(run("old"), ["Not a number", "Age?"]);
+        assert_eq!(run("36"), ["Ada is 36"]);
+        assert_eq!(run("pwd"), ["/"]);
+
+        // Questions asked while another waits are answered first, then the first is asked again
+        crate::prompt::confirm("Remove it?", || async { "Removed.".to_string() });
+        ask(Prompt::new("Colour?", |colour| async move { colour }));
+        take_output();
+        assert_eq!(run("blue"), ["blue", "Remove it? (y/n)"]);
+        crate::cancel_input();
+        assert_eq!(take_output(), ["Cancelled."]);
+        assert_eq!(run("pwd"), ["/"]);
+    }
+
+    #[test]
+    fn test_masked_prompts_keep_answers_private() {
+        use crate::prompt::{ask, Prompt};
+        enable(1);
+        ask(Prompt::new("Password:", |password| async move { format!("{} letters", password.len()) }).masked());
+        take_output();
+        assert!(crate::input_masked());
+        block_on(crate::handle_input("hunter2"));
+        assert_eq!(take_output(), ["> ********", "7 letters"]);
+        assert!(!crate::input_masked());
+        assert_eq!(run("history"), ["1  history"]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Recording terminal sessions (`record`), and casts (`cast`): recordings
+//! packed small enough to go in a link that replays them when the page loads.
+
+use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use crate::js_interop::now;
+
+const RECORDING_VERSION: u64 = 1;
+const CAST_VERSION: &str = "1";
+
+/// One input typed during a recording
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    /// Milliseconds since the previous input (or since recording started)
+    pub delay: u64,
+    pub input: String,
+}
+
+struct Recording {
+    last: f64,
+    steps: Vec<Step>,
+}
+
+thread_local! {
+    static RECORDING: RefCell<Option<Recording>> = const { RefCell::new(None) };
+}
+
+/// Start recording inputs. Returns false if a recording is already running.
+pub fn start() -> bool {
+    RECORDING.with_borrow_mut(|recording| {
+        if recording.is_some() {
+            return false;
+        }
+        *recording = Some(Recording { last: now(), steps: Vec::new() });
+        true
+    })
+}
+
+/// Stop recording, returning what was recorded (None if not recording)
+pub fn stop() -> Option<Vec<Step>> {
+    RECORDING.with_borrow_mut(|recording| recording.take().map(|recording| recording.steps))
+}
+
+/// Add an input to the running recording, if any.
+/// `record` commands themselves aren't part of the recording.
+pub fn record_input(input: &str) {
+    if input.is_empty() || input.split_whitespace().next() == Some("record") {
+        return;
+    }
+    RECORDING.with_borrow_mut(|recording| {
+        if let Some(recording) = recording {
+            let time = now();
+            recording.steps.push(Step { delay: (time - recording.last).max(0.0) as u64, input: input.to_string() });
+            recording.last = time;
+        }
+    });
+}
+
+/// A recording as saved in a file (JSON)
+pub fn to_file(steps: &[Step]) -> String {
+    serde_json::to_string_pretty(&serde_json::json!({
+        "version": RECORDING_VERSION,
+        "steps": steps,
+    })).unwrap_or_default()
+}
+
+pub fn from_file(text: &str) -> Result<Vec<Step>, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|_| "Not a recording".to_string())?;
+    match value.get("version").and_then(|v| v.as_u64()) {
+        Some(RECORDING_VERSION) => {}
+        Some(version) => return Err(format!("Unsupported recording version: {}", version)),
+        None => return Err("Not a recording".to_string()),
+    }
+    serde_json::from_value(value["steps"].clone()).map_err(|_| "Not a recording".to_string())
+}
+
+/// Pack a recording for a URL: a version line, then "DELAY INPUT" per step,
+/// in URL-safe base64
+pub fn encode_cast(steps: &[Step]) -> String {
+    let lines: Vec<String> = std::iter::once(CAST_VERSION.to_string())
+        .chain(steps.iter().map(|step| format!("{} {}", step.delay, step.input)))
+        .collect();
+    crate::base64::encode(lines.join("\n").as_bytes())
+        .replace('+', "-")
+        .replace('/', "_")
+        .trim_end_matches('=')
+        .to_string()
+}
+
+pub fn decode_cast(encoded: &str) -> Result<Vec<Step>, String> {
+    let standard = encoded.replace('-', "+").replace('_', "/");
+    let bytes = crate::base64::decode(&standard).map_err(|_| "Invalid cast".to_string())?;
+    let text = String::from_utf8(bytes).map_err(|_| "Invalid cast".to_string())?;
+
+    let mut lines = text.lines();
+    match lines.next() {
+        Some(CAST_VERSION) => {}
+        Some(version) => return Err(format!("Unsupported cast version: {}", version)),
+        None => return Err("Invalid cast".to_string()),
+    }
+    lines
+        .map(|line| {
+            let (delay, input) = line.split_once(' ').ok_or("Invalid cast")?;
+            let delay = delay.parse().map_err(|_| "Invalid cast")?;
+            Ok(Step { delay, input: input.to_string() })
+        })
+        .collect::<Result<_, &str>>()
+        .map_err(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, run};
+
+    fn steps() -> Vec<Step> {
+        vec![
+            Step { delay: 0, input: "ls".to_string() },
+            Step { delay: 1500, input: "cat /abyss/warning.txt".to_string() },
+            Step { delay: 20, input: "echo ünïcode & more?".to_string() },
+        ]
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        assert_eq!(from_file(&to_file(&steps())).unwrap(), steps());
+        assert_eq!(from_file(r#"{"version": 2, "steps": []}"#), Err("Unsupported recording version: 2".to_string()));
+        assert_eq!(from_file("hello"), Err("Not a recording".to_string()));
+    }
+
+    #[test]
+    fn test_cast_round_trip() {
+        let cast = encode_cast(&steps());
+        assert!(cast.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(decode_cast(&cast).unwrap(), steps());
+        assert_eq!(decode_cast("!!"), Err("Invalid cast".to_string()));
+    }
+
+    #[test]
+    fn test_record_and_cast() {
+        enable(1);
+        assert_eq!(run("record stop"), ["record: Not recording (start with 'record start')"]);
+        run("record start");
+        assert_eq!(run("record start"), ["record: Already recording"]);
+        run("cd abyss");
+        run("ls");
+        assert_eq!(run("record stop demo.rec"), ["Recorded 2 command(s) to demo.rec. Share it with 'cast demo.rec'."]);
+
+        let output = run("cast demo.rec");
+        assert_eq!(output[0], "Cast written to /abyss/demo.cast");
+        let link = output[1].strip_prefix("Replay link: http://localhost/?cast=").unwrap();
+        let steps = crate::recording::decode_cast(link).unwrap();
+        let inputs: Vec<&str> = steps.iter().map(|step| step.input.as_str()).collect();
+        assert_eq!(inputs, ["cd abyss", "ls"]);
+        assert_eq!(run("cat demo.cast"), [link]);
+
+        assert_eq!(run("cast warning.txt"), ["cast: warning.txt: Not a recording"]);
+    }
+}
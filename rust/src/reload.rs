@@ -0,0 +1,386 @@
+//! Carrying a visitor's session across a deploy.
+//! The page calls `prepare_reload` before swapping in a new WASM build, then
+//! `restore_state` on the new build once its manifest has loaded.
+//! Preparing closes this build's broadcast channels; the new build opens its own.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use serde_json::{json, Map, Value};
+use crate::filesystem::{ABYSS_FS, CAVE_OF_DICE_INITIALISED, CURRENT_DIR, OWNER_MODE, VIRTUAL_FS};
+use crate::filesystem::{Content, Contents, DirPath, Directories, FileData, FilePath};
+use crate::filesystem::abyss_delta::{AbyssDelta, ABYSS_DELTA};
+use crate::filesystem::cave_of_dice;
+use crate::filesystem::events::{subscribe, FsEvent};
+use crate::filesystem::helpers::path_in_abyss;
+use crate::persist::dir_key;
+use crate::{autosave, persist, preferences};
+use crate::input_history::INPUT_HISTORY;
+use crate::settings::SETTINGS;
+
+const STATE_VERSION: u64 = 1;
+
+// What became of a path outside the abyss (keyed as persist keys them), so
+// the new build's site content doesn't bring back what the visitor removed.
+// The abyss delta carries the abyss's.
+const MADE: &str = "made";
+const REMOVED: &str = "removed";
+// A directory removed (clearing out what the site has there) and made again
+const REMADE: &str = "remade";
+
+thread_local! {
+    static CHANGES: RefCell<BTreeMap<String, &'static str>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Track the directories the visitor makes and what they remove, to carry over a reload
+pub fn register() {
+    subscribe(|event| match event {
+        FsEvent::FileRemoved(filepath) if !path_in_abyss(&filepath.dir) => {
+            CHANGES.with_borrow_mut(|changes| changes.insert(filepath.to_string(), REMOVED));
+        }
+        FsEvent::DirCreated(dirpath) if !path_in_abyss(dirpath) => CHANGES.with_borrow_mut(|changes| {
+            let key = dir_key(dirpath);
+            let remade = matches!(changes.get(&key).copied(), Some(REMOVED | REMADE));
+            changes.insert(key, if remade { REMADE } else { MADE });
+        }),
+        // What was recorded below it goes with it
+        FsEvent::DirRemoved(dirpath) if !path_in_abyss(dirpath) => CHANGES.with_borrow_mut(|changes| {
+            let key = dir_key(dirpath);
+            changes.retain(|path, _| !path.starts_with(&key));
+            changes.insert(key, REMOVED);
+        }),
+        _ => {}
+    });
+}
+
+// In-memory files keep their content and times; static files are just marked
+pub fn content_to_json(content: &Content) -> Value {
+    match content {
+        Content::InMemory(data) => {
+            let mut value = json!({
+                "created": data.meta.created,
+                "modified": data.meta.modified,
+            });
+            match data.text() {
+                Some(text) => value["content"] = json!(text),
+                None => value["base64"] = json!(crate::base64::encode(&data.bytes)),
+            }
+            value
+        }
+        // The cache starts afresh
+        Content::ToFetch | Content::Fetched(_) => Value::Null,
+    }
+}
+
+pub fn content_from_json(value: &Value) -> Option<Content> {
+    if value.is_null() {
+        return Some(Content::ToFetch);
+    }
+    let bytes = match value.get("content") {
+        Some(text) => text.as_str()?.as_bytes().to_vec(),
+        None => crate::base64::decode(value.get("base64")?.as_str()?).ok()?,
+    };
+    let created = value.get("created")?.as_f64()?;
+    let modified = value.get("modified")?.as_f64()?;
+    Some(Content::InMemory(FileData::with_times(bytes, created, modified)))
+}
+
+fn contents_to_json(files: &std::collections::HashMap<String, Content>) -> Value {
+    files.iter()
+        .map(|(name, content)| (name.clone(), content_to_json(content)))
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+/// Serialize all in-memory state: files, the abyss, history, settings (with
+/// feature overrides), preferences, persist and autosave, and where the
+/// visitor is. The broadcast channels are closed, as the page is about to
+/// hand over to a new build.
+pub fn prepare_reload() -> String {
+    crate::channels::close_channels();
+
+    let vfs = VIRTUAL_FS.with_borrow(|vfs|
+        vfs.content.iter()
+            .map(|(dir, files)| (dir.to_string(), contents_to_json(files)))
+            .collect::<Map<_, _>>()
+    );
+
+    let (abyss_files, abyss_dirs) = ABYSS_FS.with_borrow(|afs| (
+        afs.files.iter()
+            .map(|(dir, contents)| (dir.to_string(), contents_to_json(&contents.0)))
+            .collect::<Map<_, _>>(),
+        afs.dirs.iter()
+            .map(|(dir, dirs)| (dir.to_string(), json!(dirs.0)))
+            .collect::<Map<_, _>>(),
+    ));
+
+    json!({
+        "version": STATE_VERSION,
+        "vfs": vfs,
+        "abyss": {
+            "files": abyss_files,
+            "dirs": abyss_dirs,
+            "cave_of_dice": CAVE_OF_DICE_INITIALISED.with_borrow(|x| *x),
+            "delta": ABYSS_DELTA.with_borrow(|delta| delta.paths_to_json()),
+            "cave": cave_of_dice::reload_to_json(),
+        },
+        "history": INPUT_HISTORY.with_borrow(|history| history.inputs().to_vec()),
+        "settings": SETTINGS.with_borrow(|settings|
+            settings.entries().map(|(key, value)| (key.to_string(), json!(value))).collect::<Map<_, _>>()
+        ),
+        "changes": CHANGES.with_borrow(|changes| changes.clone()),
+        "cwd": CURRENT_DIR.with_borrow(|cd| cd.to_string()),
+        "owner_mode": OWNER_MODE.with_borrow(|x| *x),
+        "preferences": preferences::current(),
+        "persist": persist::enabled(),
+        "autosave_dirty": autosave::dirty(),
+    }).to_string()
+}
+
+/// Restore state saved by `prepare_reload`, after the new manifest has loaded.
+/// What the visitor removed is removed from the new site content again, then
+/// their directories and in-memory files are layered over it, so static files
+/// added by the deploy still appear. The abyss is restored as it was.
+pub fn restore_state(blob: &str) -> Result<(), String> {
+    let state: Value = serde_json::from_str(blob).map_err(|e| format!("Invalid state: {}", e))?;
+    match state.get("version").and_then(|v| v.as_u64()) {
+        Some(STATE_VERSION) => {}
+        Some(version) => return Err(format!("Unsupported state version: {}", version)),
+        None => return Err("Invalid state: missing version".to_string()),
+    }
+
+    let root = DirPath::root();
+    let object = |key: &str| state.get(key).and_then(|v| v.as_object()).cloned().unwrap_or_default();
+
+    // Keys are in order, so a removed directory is cleared before anything
+    // removed below it once it was made again
+    let mut changes = BTreeMap::new();
+    VIRTUAL_FS.with_borrow_mut(|vfs| {
+        for (key, value) in object("changes") {
+            let Some(change) = [MADE, REMOVED, REMADE].into_iter().find(|change| value.as_str() == Some(change)) else {
+                continue;
+            };
+            let removed = match (key.ends_with('/'), change) {
+                (_, MADE) => false,
+                (true, _) => vfs.remove_dir_all(&DirPath::parse(&key, &root)).is_ok(),
+                (false, _) => vfs.remove_file(&FilePath::parse(&key, &root)),
+            };
+            // A tombstone with nothing of the site's under it is dropped
+            match (change, removed) {
+                (REMOVED, false) => {}
+                (REMADE, false) => { changes.insert(key, MADE); }
+                _ => { changes.insert(key, change); }
+            }
+        }
+    });
+
+    let files: Vec<(FilePath, Content)> = object("vfs").iter()
+        .flat_map(|(dir, files)| {
+            let dirpath = DirPath::parse(dir, &root);
+            files.as_object().into_iter().flatten().filter_map(move |(name, value)| match content_from_json(value) {
+                Some(content @ Content::InMemory(_)) => Some((FilePath::new(dirpath.clone(), name.clone()), content)),
+                _ => None,
+            })
+        })
+        .collect();
+    // Only the visitor's directories come back: ones they made, and ones holding their files
+    let dirs: Vec<DirPath> = changes.iter()
+        .filter(|(key, change)| key.ends_with('/') && **change != REMOVED)
+        .map(|(key, _)| DirPath::parse(key, &root))
+        .chain(files.iter().map(|(filepath, _)| filepath.dir.clone()))
+        .collect();
+
+    if let Some(cwd) = state.get("cwd").and_then(|v| v.as_str()) {
+        let dirpath = DirPath::parse(cwd, &root);
+        // A directory that only existed in the old build's content can't be returned to
+        let exists = path_in_abyss(&dirpath)
+            || VIRTUAL_FS.with_borrow(|vfs| vfs.dir_exists(&dirpath))
+            || dirs.contains(&dirpath);
+        if exists {
+            CURRENT_DIR.with_borrow_mut(|cd| *cd = dirpath);
+        }
+    }
+
+    VIRTUAL_FS.with_borrow_mut(|vfs| {
+        for dirpath in &dirs {
+            // Parents first, so every level can be listed
+            for depth in 1..=dirpath.0.len() {
+                vfs.create_dir(DirPath(dirpath.0[..depth].to_vec()));
+            }
+        }
+        for (filepath, content) in files {
+            vfs.content.entry(filepath.dir).or_default().insert(filepath.file, content);
+        }
+    });
+    CHANGES.with_borrow_mut(|current| *current = changes);
+
+    if let Some(abyss) = state.get("abyss") {
+        let section = |key: &str| abyss.get(key).and_then(|v| v.as_object()).cloned().unwrap_or_default();
+        ABYSS_FS.with_borrow_mut(|afs| {
+            afs.files = section("files").iter()
+                .map(|(dir, files)| {
+                    let contents = files.as_object().into_iter().flatten()
+                        .filter_map(|(name, value)| Some((name.clone(), content_from_json(value)?)))
+                        .collect();
+                    (DirPath::parse(dir, &root), Contents(contents))
+                })
+                .collect();
+            afs.dirs = section("dirs").iter()
+                .map(|(dir, names)| {
+                    let names = names.as_array().into_iter().flatten()
+                        .filter_map(|name| name.as_str().map(str::to_string))
+                        .collect();
+                    (DirPath::parse(dir, &root), Directories(names))
+                })
+                .collect();
+        });
+        let cave = abyss.get("cave_of_dice").and_then(|v| v.as_bool()).unwrap_or(false);
+        CAVE_OF_DICE_INITIALISED.with_borrow_mut(|x| *x = cave);
+        if let Some(delta) = abyss.get("delta") {
+            ABYSS_DELTA.with_borrow_mut(|current| *current = AbyssDelta::paths_from_json(delta));
+        }
+        if let Some(cave) = abyss.get("cave") {
+            cave_of_dice::reload_from_json(cave);
+        }
+    }
+
+    INPUT_HISTORY.with_borrow_mut(|history| {
+        for input in state.get("history").and_then(|v| v.as_array()).into_iter().flatten() {
+            if let Some(input) = input.as_str() {
+                history.add_input(input.to_string());
+            }
+        }
+    });
+
+    SETTINGS.with_borrow_mut(|settings| {
+        for (key, value) in object("settings") {
+            if let Some(value) = value.as_str() {
+                settings.set(&key, value);
+            }
+        }
+    });
+
+    let owner_mode = state.get("owner_mode").and_then(|v| v.as_bool()).unwrap_or(false);
+    OWNER_MODE.with_borrow_mut(|x| *x = owner_mode);
+
+    // The new build read the preferences file before it was restored above
+    if let Some(carried) = state.get("preferences").and_then(|v| serde_json::from_value(v.clone()).ok()) {
+        preferences::apply(carried);
+    }
+    if let Some(on) = state.get("persist").and_then(|v| v.as_bool()) {
+        persist::resume(on);
+    }
+    autosave::set_dirty(state.get("autosave_dirty").and_then(|v| v.as_bool()).unwrap_or(false));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FilePath;
+    use crate::fixtures::{enable, run};
+
+    #[test]
+    fn test_round_trip() {
+        let notes = FilePath::parse("/notes/todo.txt", &DirPath::root());
+        VIRTUAL_FS.with_borrow_mut(|vfs| vfs.write_file_data(&notes, FileData::with_times("milk".to_string(), 1.0, 2.0)));
+        CURRENT_DIR.with_borrow_mut(|cd| *cd = notes.dir.clone());
+        INPUT_HISTORY.with_borrow_mut(|history| history.add_input("edit todo.txt".to_string()));
+
+        let blob = prepare_reload();
+
+        // A fresh build starts empty
+        VIRTUAL_FS.with_borrow_mut(|vfs| vfs.content.clear());
+        CURRENT_DIR.with_borrow_mut(|cd| *cd = DirPath::root());
+        INPUT_HISTORY.with_borrow_mut(|history| *history = crate::input_history::InputHistory::new());
+
+        restore_state(&blob).unwrap();
+
+        let restored = VIRTUAL_FS.with_borrow(|vfs| match vfs.get_content(&notes) {
+            Some(Content::InMemory(data)) => Some((data.bytes.clone(), data.meta.created, data.meta.modified)),
+            _ => None,
+        });
+        assert_eq!(restored, Some((b"milk".to_vec(), 1.0, 2.0)));
+        assert_eq!(CURRENT_DIR.with_borrow(|cd| cd.to_string()), "/notes");
+        assert_eq!(INPUT_HISTORY.with_borrow(|history| history.inputs().to_vec()), ["edit todo.txt"]);
+    }
+
+    #[test]
+    fn test_carries_preferences_cave_and_flags() {
+        enable(1);
+        crate::filesystem::cave_of_dice::register();
+        run("settings set cave_seed 12345");
+        run("features rpg on");
+        run("persist on");
+        run("cd /abyss/level1/level2/cave_of_dice/d20");
+        let map = run("map");
+        // A change the page hasn't been asked to autosave yet
+        autosave::set_dirty(true);
+
+        let blob = prepare_reload();
+
+        // A fresh build, its manifest loaded
+        enable(1);
+        cave_of_dice::forget();
+        SETTINGS.with_borrow_mut(|settings| *settings = crate::settings::Settings::new());
+        preferences::apply(Default::default());
+        persist::resume(false);
+        autosave::set_dirty(false);
+
+        restore_state(&blob).unwrap();
+        assert_eq!(preferences::current().cave_seed, Some(12345));
+        assert!(crate::features::is_enabled("rpg"));
+        assert!(persist::enabled() && autosave::dirty());
+        assert_eq!(run("map"), map);
+    }
+
+    #[test]
+    fn test_directories_the_new_build_dropped() {
+        enable(1);
+        register();
+        crate::write_file("/config/mine.toml", "theme = \"dark\"".to_string()).unwrap();
+        run("cd /rpg/lantern_road");
+
+        let blob = prepare_reload();
+
+        // A deploy without /rpg or /config
+        enable(1);
+        VIRTUAL_FS.with_borrow_mut(|vfs| {
+            vfs.remove_dir_all(&DirPath::parse("/rpg", &DirPath::root())).unwrap();
+            vfs.remove_dir_all(&DirPath::parse("/config", &DirPath::root())).unwrap();
+        });
+
+        restore_state(&blob).unwrap();
+        assert_eq!(run("pwd"), ["/"]);
+        assert_eq!(run("ls /"), ["about.txt", "abyss/", "config/", "fortunes.txt"]);
+        assert_eq!(run("ls /config"), ["mine.toml"]);
+    }
+
+    #[test]
+    fn test_removed_site_files_stay_removed() {
+        enable(1);
+        register();
+        run("rm -f about.txt");
+        run("rm -rf rpg");
+        // Cleared out and made again, so none of the site's files come back in it
+        run("rm -rf config");
+        run("mkdir config");
+        run("mkdir scratch");
+        run("cd config");
+
+        // Through one reload, and the next
+        for _ in 0..2 {
+            let blob = prepare_reload();
+            enable(1);
+            restore_state(&blob).unwrap();
+            assert_eq!(run("pwd"), ["/config"]);
+            assert_eq!(run("ls /"), ["abyss/", "config/", "fortunes.txt", "scratch/"]);
+            assert_eq!(run("ls /config"), ["(empty directory)"]);
+        }
+    }
+
+    #[test]
+    fn test_rejects_unknown_version() {
+        assert_eq!(restore_state(r#"{"version": 99}"#), Err("Unsupported state version: 99".to_string()));
+    }
+}
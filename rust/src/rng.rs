@@ -0,0 +1,87 @@
+//! Random numbers for the terminal (dice, `random`).
+//! Normally these come from the thread RNG; fixture mode swaps in a seeded one.
+
+use std::cell::RefCell;
+use rand::Rng;
+use rand::distr::uniform::{SampleRange, SampleUniform};
+use rand::rngs::StdRng;
+
+thread_local! {
+    static SEEDED: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Make all following random numbers on this thread come from `seed`
+#[cfg(any(test, feature = "fixtures"))]
+pub fn seed(seed: u64) {
+    use rand::SeedableRng;
+    SEEDED.with_borrow_mut(|rng| *rng = Some(StdRng::seed_from_u64(seed)));
+}
+
+/// A random value in `range`
+pub fn random_range<T, R>(range: R) -> T
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    SEEDED.with_borrow_mut(|rng| match rng {
+        Some(rng) => rng.random_range(range),
+        None => rand::random_range(range),
+    })
+}
+
+/// One of `items` at random, or None if there are none
+pub fn choose<T>(items: &[T]) -> Option<&T> {
+    match items.is_empty() {
+        true => None,
+        false => items.get(random_range(0..items.len())),
+    }
+}
+
+/// Put `items` in a random order (Fisher-Yates)
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random_range(0..=i));
+    }
+}
+
+/// A random (version 4) UUID, e.g. "0b9a4f3e-5c2d-4e1f-8a7b-6c5d4e3f2a1b"
+pub fn uuid() -> String {
+    let mut bytes: [u8; 16] = std::array::from_fn(|_| random_range(0..=255));
+    bytes[6] = bytes[6] & 0x0f | 0x40;  // version 4
+    bytes[8] = bytes[8] & 0x3f | 0x80;  // RFC 4122 variant
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_sequence_repeats() {
+        seed(7);
+        let first: Vec<u8> = (0..8).map(|_| random_range(0..100)).collect();
+        seed(7);
+        let second: Vec<u8> = (0..8).map(|_| random_range(0..100)).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_helpers() {
+        seed(7);
+        let mut items: Vec<u32> = (0..20).collect();
+        shuffle(&mut items);
+        let mut sorted = items.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+        assert_ne!(items, sorted);
+
+        assert!(choose(&items).is_some_and(|item| *item < 20));
+        assert_eq!(choose::<u32>(&[]), None);
+
+        let id = uuid();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.chars().nth(14), Some('4'));
+        assert!("89ab".contains(id.chars().nth(19).unwrap()));
+    }
+}
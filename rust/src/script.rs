@@ -0,0 +1,156 @@
+//! Scripts: text files of commands, run one line at a time as if typed
+//! (or, for .hsh files, as hsh). Files in /bin are commands of their own
+//! (see `find_command`), so visitors can add to the terminal from inside it.
+//!
+//! Before a line runs, `$1`..`$9` become the script's arguments (empty if
+//! not given), `$@` all of them, `$#` how many there are and `$0` the
+//! script's name. Blank lines and lines starting with `#` are skipped.
+
+use std::cell::Cell;
+use crate::commands::process_command;
+use crate::filesystem::{DirPath, FilePath, NextDir};
+use crate::filesystem::events::{emit, FsEvent};
+use crate::filesystem::helpers::{file_exists, get_file_bytes};
+use crate::hsh;
+
+/// Where scripts that work as commands live
+pub const BIN_DIR: &str = "bin";
+
+// Scripts may run scripts, but not forever
+const MAX_DEPTH: usize = 16;
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The script in /bin run by typing `name`: /bin/name, or /bin/name.sh or .hsh
+pub async fn find_command(name: &str) -> Option<FilePath> {
+    if name.contains('/') || name.starts_with('.') {
+        return None;
+    }
+    let bin = DirPath(vec![NextDir::In(BIN_DIR.into())]);
+    for file in [name.to_string(), format!("{}.sh", name), format!("{}.hsh", name)] {
+        let filepath = FilePath::new(bin.clone(), file);
+        if file_exists(&filepath).await {
+            return Some(filepath);
+        }
+    }
+    None
+}
+
+/// How a script's lines are read
+#[derive(Clone, Copy, PartialEq)]
+pub enum Language {
+    /// One command a line
+    Lines,
+    /// hsh, with variables and loops (see `crate::hsh`)
+    Hsh,
+}
+
+impl Language {
+    pub fn of(filepath: &FilePath) -> Language {
+        match filepath.file.ends_with(".hsh") {
+            true => Language::Hsh,
+            false => Language::Lines,
+        }
+    }
+}
+
+/// Run the script at `filepath` with `args`, returning everything it printed
+pub async fn run(filepath: &FilePath, language: Language, name: &str, args: &[&str]) -> String {
+    let Ok(bytes) = get_file_bytes(filepath).await else {
+        return format!("{}: {}: No such file", name, filepath.to_string());
+    };
+    let Ok(script) = String::from_utf8(bytes) else {
+        return format!("{}: {}: Not a script (binary file)", name, filepath.to_string());
+    };
+    if DEPTH.get() >= MAX_DEPTH {
+        return format!("{}: Scripts nested too deeply", name);
+    }
+    emit(FsEvent::FileRead(filepath.clone()));
+
+    DEPTH.set(DEPTH.get() + 1);
+    let output = match language {
+        Language::Lines => run_lines(&script, name, args).await,
+        Language::Hsh => hsh::run(&script, name, args).await,
+    };
+    DEPTH.set(DEPTH.get() - 1);
+    output
+}
+
+async fn run_lines(script: &str, name: &str, args: &[&str]) -> String {
+    let mut output = Vec::new();
+    for line in script.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Boxed, as commands can run scripts in turn
+        let result = Box::pin(process_command(&substitute(line, name, args))).await;
+        if !result.is_empty() {
+            output.push(result);
+        }
+    }
+    output.join("\n")
+}
+
+/// `line` with the script's arguments put in for `$1`, `$@` and the rest
+pub fn substitute(line: &str, name: &str, args: &[&str]) -> String {
+    let mut out = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('@') => out.push_str(&args.join(" ")),
+            Some('#') => out.push_str(&args.len().to_string()),
+            Some('0') => out.push_str(name),
+            Some(digit @ '1'..='9') => {
+                let n = digit.to_digit(10).unwrap_or_default() as usize;
+                out.push_str(args.get(n - 1).copied().unwrap_or_default());
+            }
+            _ => {
+                out.push('$');
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::fixtures::enable;
+
+    #[test]
+    fn test_substitute() {
+        let args = ["a.txt", "b.txt"];
+        assert_eq!(substitute("diff $1 $2", "cmp", &args), "diff a.txt b.txt");
+        assert_eq!(substitute("echo $0 got $# ($@)", "cmp", &args), "echo cmp got 2 (a.txt b.txt)");
+        assert_eq!(substitute("echo [$3] costs $5", "cmp", &args), "echo [] costs ");
+        assert_eq!(substitute("echo $ and $x", "cmp", &args), "echo $ and $x");
+    }
+
+    #[test]
+    fn test_scripts_in_bin_are_commands() {
+        enable(1);
+        fixtures::run("mkdir /bin");
+        crate::write_file("/bin/greet", "# Say hello\necho Hello, $1!\n\necho ($# arguments to $0)".to_string()).unwrap();
+        crate::write_file("/bin/both.sh", "greet $1\nwc -c $2".to_string()).unwrap();
+        crate::write_file("/bin/loop", "loop".to_string()).unwrap();
+        crate::write_file("notes.txt", "twelve bytes".to_string()).unwrap();
+
+        assert_eq!(fixtures::run("greet world"), ["Hello, world!", "(1 arguments to greet)"]);
+        assert_eq!(fixtures::run("both there notes.txt"), ["Hello, there!", "(1 arguments to greet)", "12 notes.txt"]);
+        assert_eq!(fixtures::run("loop"), ["loop: Scripts nested too deeply"]);
+        assert_eq!(fixtures::run("help both"), ["both runs the script /bin/both.sh"]);
+        // Builtins come first, and only /bin is looked in
+        crate::write_file("/bin/ls", "echo not ls".to_string()).unwrap();
+        assert_ne!(fixtures::run("ls /bin"), ["not ls"]);
+        assert_eq!(fixtures::run("notes.txt"), ["Command not found: notes.txt", "Type 'help' for available commands."]);
+    }
+}
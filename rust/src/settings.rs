@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Key/value settings for optional subsystems.
+/// Unset keys mean the subsystem keeps its default (usually disabled).
+pub struct Settings {
+    values: HashMap<String, String>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        if value.is_empty() {
+            self.values.remove(key);
+        } else {
+            self.values.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+thread_local! {
+    pub static SETTINGS: RefCell<Settings> = RefCell::new(Settings::new());
+}
+
+/// Get a setting's value, if set
+pub fn get_setting(key: &str) -> Option<String> {
+    SETTINGS.with_borrow(|settings| settings.get(key).map(|value| value.to_string()))
+}
+
+/// Set a setting (called from JavaScript so deployments can configure the terminal).
+/// An empty value unsets the key.
+#[wasm_bindgen]
+pub fn set_setting(key: &str, value: &str) {
+    SETTINGS.with_borrow_mut(|settings| settings.set(key, value));
+}
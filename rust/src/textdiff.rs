@@ -0,0 +1,141 @@
+//! Line-based diffing using a longest-common-subsequence table
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+impl Edit<'_> {
+    fn is_change(&self) -> bool {
+        !matches!(self, Edit::Equal(_))
+    }
+}
+
+/// Compute the line edits turning `old` into `new`
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Edit<'a>> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            edits.push(Edit::Equal(a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            edits.push(Edit::Delete(a[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(b[j]));
+            j += 1;
+        }
+    }
+    edits.extend(a[i..].iter().map(|line| Edit::Delete(line)));
+    edits.extend(b[j..].iter().map(|line| Edit::Insert(line)));
+    edits
+}
+
+/// Render a unified diff with `context` lines around each change.
+/// Returns an empty string if the inputs have identical lines.
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str, context: usize) -> String {
+    let edits = diff_lines(old, new);
+    let changes: Vec<usize> = edits.iter()
+        .enumerate()
+        .filter(|(_, edit)| edit.is_change())
+        .map(|(idx, _)| idx)
+        .collect();
+
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes whose context windows touch into hunks of edit indices
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changes {
+        let start = idx.saturating_sub(context);
+        let end = (idx + context + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}", old_name, new_name);
+    for (start, end) in hunks {
+        // Line numbers (1-based) at the start of the hunk
+        let old_start = 1 + edits[..start].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_start = 1 + edits[..start].iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+        let old_len = edits[start..end].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_len = edits[start..end].iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+
+        // Empty ranges are reported as starting at the line before, like GNU diff
+        let old_start = if old_len == 0 { old_start - 1 } else { old_start };
+        let new_start = if new_len == 0 { new_start - 1 } else { new_start };
+
+        out.push_str(&format!("\n@@ -{},{} +{},{} @@", old_start, old_len, new_start, new_len));
+        for edit in &edits[start..end] {
+            match edit {
+                Edit::Equal(line) => out.push_str(&format!("\n {}", line)),
+                Edit::Delete(line) => out.push_str(&format!("\n-{}", line)),
+                Edit::Insert(line) => out.push_str(&format!("\n+{}", line)),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical() {
+        assert_eq!(unified("a", "b", "x\ny\n", "x\ny\n", 3), "");
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let edits = diff_lines("a\nb\nc", "a\nc\nd");
+        assert_eq!(edits, vec![
+            Edit::Equal("a"),
+            Edit::Delete("b"),
+            Edit::Equal("c"),
+            Edit::Insert("d"),
+        ]);
+    }
+
+    #[test]
+    fn test_unified_single_hunk() {
+        let out = unified("old.txt", "new.txt", "one\ntwo\nthree", "one\n2\nthree", 1);
+        assert_eq!(out, "--- old.txt\n+++ new.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three");
+    }
+
+    #[test]
+    fn test_unified_separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9";
+        let new = "1\nX\n3\n4\n5\n6\n7\nY\n9";
+        let out = unified("a", "b", old, new, 1);
+        assert_eq!(out, "--- a\n+++ b\n@@ -1,3 +1,3 @@\n 1\n-2\n+X\n 3\n@@ -7,3 +7,3 @@\n 7\n-8\n+Y\n 9");
+    }
+
+    #[test]
+    fn test_unified_insert_into_empty() {
+        let out = unified("a", "b", "", "new line", 3);
+        assert_eq!(out, "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+new line");
+    }
+}
@@ -0,0 +1,50 @@
+//! Colour themes for the terminal. The chosen theme's name is kept in
+//! /.config/theme, so it's saved and restored along with the visitor's
+//! other files, and the page applies it (see setTheme in index.html), which
+//! also remembers it in the browser for the next visit.
+
+use crate::commands::builtin::filesystem::create_dir_all;
+use crate::filesystem::file_paths::THEME_FILE_PATH;
+use crate::filesystem::helpers::{get_file_content, write_file};
+use crate::js_interop::set_theme;
+
+pub const DEFAULT: &str = "default";
+
+/// Every theme the page has styles for, with a description
+pub const THEMES: &[(&str, &str)] = &[
+    ("default", "Green on black, like a classic terminal"),
+    ("amber", "Amber on black, like an old monochrome monitor"),
+    ("light", "Dark text on a light background"),
+    ("solarized", "Solarized dark, with softer colours"),
+];
+
+pub fn is_known(name: &str) -> bool {
+    THEMES.iter().any(|(theme, _)| *theme == name)
+}
+
+/// The chosen theme (the default if none has been chosen)
+pub async fn current() -> String {
+    match get_file_content(&THEME_FILE_PATH).await {
+        Ok(name) if is_known(name.trim()) => name.trim().to_string(),
+        _ => DEFAULT.to_string(),
+    }
+}
+
+/// Switch to the theme `name`, and remember it
+pub async fn choose(name: &str) -> Result<(), String> {
+    if !is_known(name) {
+        return Err(format!("{}: No such theme", name));
+    }
+    create_dir_all(&THEME_FILE_PATH.dir).await?;
+    write_file(&THEME_FILE_PATH, format!("{}\n", name)).await?;
+    set_theme(name);
+    Ok(())
+}
+
+/// Apply the chosen theme, once the visitor's files have been restored
+pub async fn restore() {
+    let theme = current().await;
+    if theme != DEFAULT {
+        set_theme(&theme);
+    }
+}
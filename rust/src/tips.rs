@@ -0,0 +1,85 @@
+//! Tips of the day: one-line hints from content/.tips.txt. They're shown in
+//! the file's order, so new visitors discover features a step at a time:
+//! one when the terminal loads, by the `tip` command, and now and then after `help`.
+
+use std::cell::RefCell;
+use crate::filesystem::file_paths::TIPS_FILE_PATH;
+use crate::filesystem::helpers::get_file_content;
+use crate::rng::random_range;
+use crate::settings::get_setting;
+
+// On average, one `help` in this many ends with a tip
+const HELP_TIP_ODDS: u32 = 3;
+
+thread_local! {
+    static NEXT_TIP: RefCell<usize> = const { RefCell::new(0) };
+}
+
+/// Tips from the tips file: one per line, skipping blank lines and # comments
+pub fn parse_tips(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+pub async fn tips() -> Vec<String> {
+    get_file_content(&TIPS_FILE_PATH).await
+        .map(|text| parse_tips(&text))
+        .unwrap_or_default()
+}
+
+/// The next tip in the rotation (starting over after the last one)
+pub async fn next_tip() -> Option<String> {
+    let tips = tips().await;
+    if tips.is_empty() {
+        return None;
+    }
+    let index = NEXT_TIP.with_borrow_mut(|next| {
+        let index = *next % tips.len();
+        *next = index + 1;
+        index
+    });
+    Some(tips[index].clone())
+}
+
+/// Tips can be turned off per deployment with the setting tips=off
+pub fn enabled() -> bool {
+    get_setting("tips").as_deref() != Some("off")
+}
+
+/// Sometimes the next tip, for the end of `help` output
+pub async fn occasional_tip() -> Option<String> {
+    if !enabled() || random_range(0..HELP_TIP_ODDS) != 0 {
+        return None;
+    }
+    next_tip().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+
+    #[test]
+    fn test_parse_tips() {
+        let text = "# Tips, in order\n\nTry 'ls'.\n  Try 'cd blog'.  \n# end\n";
+        assert_eq!(parse_tips(text), ["Try 'ls'.", "Try 'cd blog'."]);
+    }
+
+    #[test]
+    fn test_tips_rotate() {
+        enable(1);
+        let banner = block_on(crate::greeting_banner());
+        assert!(banner.ends_with("\nTip: Type 'ls' to see what's here, and 'cd blog' to look around."));
+        assert_eq!(run("tip"), ["Tip: 'pretty blog/making_this.md' renders a post with its formatting and maths."]);
+
+        let all = run("tip all");
+        assert!(all[0].starts_with(" 1. Type 'ls'"));
+        assert_eq!(run("ls"), ["about.txt", "abyss/", "config/", "fortunes.txt", "rpg/"]);
+
+        crate::settings::set_setting("tips", "off");
+        assert!(!block_on(crate::greeting_banner()).contains("Tip:"));
+    }
+}
@@ -0,0 +1,271 @@
+//! 2048: slide the tiles on a 4x4 board; two tiles with the same number
+//! that meet add up into one. After every move that changes the board a new
+//! tile (a 2, or now and then a 4) appears on an empty square. The game is
+//! over when no move changes the board.
+//!
+//! The game is saved (as JSON) to /.config/2048.json after every move.
+
+use serde::{Deserialize, Serialize};
+use crate::filesystem::FilePath;
+use crate::filesystem::file_paths::TWENTY48_FILE_PATH;
+use crate::game::{Game, Key};
+use crate::rng::{choose, random_range};
+
+pub const SIZE: usize = 4;
+pub const GOAL: u32 = 2048;
+
+// The width of a square on the board, between the bars
+const CELL_WIDTH: usize = 6;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Board {
+    /// Rows from the top; 0 is an empty square
+    pub tiles: [[u32; SIZE]; SIZE],
+    pub score: u32,
+    /// The best score of any game, this one included
+    pub best: u32,
+}
+
+impl Board {
+    /// A new game with two tiles on it
+    pub fn new(best: u32) -> Board {
+        let mut board = Board { tiles: [[0; SIZE]; SIZE], score: 0, best };
+        board.spawn();
+        board.spawn();
+        board
+    }
+
+    pub fn parse(json: &str) -> Result<Board, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Put a 2 (or, one time in ten, a 4) on a random empty square
+    fn spawn(&mut self) {
+        let empty: Vec<(usize, usize)> = (0..SIZE)
+            .flat_map(|row| (0..SIZE).map(move |col| (row, col)))
+            .filter(|&(row, col)| self.tiles[row][col] == 0)
+            .collect();
+        if let Some(&(row, col)) = choose(&empty) {
+            self.tiles[row][col] = if random_range(0..10) == 0 { 4 } else { 2 };
+        }
+    }
+
+    /// Slide every tile towards `direction`, merging pairs. Returns whether anything moved.
+    pub fn slide(&mut self, direction: Direction) -> bool {
+        let before = self.tiles;
+        for line in 0..SIZE {
+            // The squares of this row or column, starting from the side slid towards
+            let squares: Vec<(usize, usize)> = (0..SIZE)
+                .map(|i| match direction {
+                    Direction::Left => (line, i),
+                    Direction::Right => (line, SIZE - 1 - i),
+                    Direction::Up => (i, line),
+                    Direction::Down => (SIZE - 1 - i, line),
+                })
+                .collect();
+            let values: Vec<u32> = squares.iter().map(|&(row, col)| self.tiles[row][col]).collect();
+            let (slid, points) = slide_line(&values);
+            self.score += points;
+            for (&(row, col), value) in squares.iter().zip(slid) {
+                self.tiles[row][col] = value;
+            }
+        }
+        self.best = self.best.max(self.score);
+        self.tiles != before
+    }
+
+    /// Play a move: slide, and add a tile if anything moved
+    pub fn play(&mut self, direction: Direction) {
+        if self.slide(direction) {
+            self.spawn();
+        }
+    }
+
+    pub fn can_move(&self) -> bool {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right].iter()
+            .any(|&direction| self.clone().slide(direction))
+    }
+
+    pub fn highest(&self) -> u32 {
+        self.tiles.iter().flatten().copied().max().unwrap_or(0)
+    }
+}
+
+/// Slide one line of tiles towards its start: the line afterwards, and the points scored
+fn slide_line(values: &[u32]) -> (Vec<u32>, u32) {
+    let mut slid = Vec::with_capacity(values.len());
+    let mut points = 0;
+    // A tile can only merge once per move
+    let mut mergeable = false;
+    for &value in values.iter().filter(|value| **value > 0) {
+        match slid.last_mut() {
+            Some(last) if mergeable && *last == value => {
+                *last += value;
+                points += *last;
+                mergeable = false;
+            }
+            _ => {
+                slid.push(value);
+                mergeable = true;
+            }
+        }
+    }
+    slid.resize(values.len(), 0);
+    (slid, points)
+}
+
+impl Game for Board {
+    fn draw(&self) -> Vec<String> {
+        let border = format!("+{}", format!("{}+", "-".repeat(CELL_WIDTH)).repeat(SIZE));
+        let mut lines = vec![format!("Score: {}   Best: {}", self.score, self.best), border.clone()];
+        for row in &self.tiles {
+            let squares: String = row.iter()
+                .map(|&tile| match tile {
+                    0 => format!("{:width$}|", "", width = CELL_WIDTH),
+                    _ => format!("{:>width$} |", tile, width = CELL_WIDTH - 1),
+                })
+                .collect();
+            lines.push(format!("|{}", squares));
+            lines.push(border.clone());
+        }
+        if self.highest() >= GOAL {
+            lines.push(format!("You made {}! Keep going for a higher score.", GOAL));
+        }
+        lines.push("Arrow keys or WASD to move, q to stop".to_string());
+        lines
+    }
+
+    fn play(&mut self, key: Key) {
+        let direction = match key {
+            Key::Up | Key::Char('w') | Key::Char('k') => Direction::Up,
+            Key::Down | Key::Char('s') | Key::Char('j') => Direction::Down,
+            Key::Left | Key::Char('a') | Key::Char('h') => Direction::Left,
+            Key::Right | Key::Char('d') | Key::Char('l') => Direction::Right,
+            Key::Char(_) => return,
+        };
+        Board::play(self, direction);
+    }
+
+    fn over(&self) -> Option<String> {
+        (!self.can_move()).then(|| format!("No moves left. Final score: {} (highest tile {})", self.score, self.highest()))
+    }
+
+    fn save(&self) -> Option<(FilePath, String)> {
+        Some((TWENTY48_FILE_PATH.clone(), self.to_json()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{block_on, enable, run};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_slide_line() {
+        assert_eq!(slide_line(&[2, 0, 2, 4]), (vec![4, 4, 0, 0], 4));
+        // Each tile merges at most once, nearest the wall first
+        assert_eq!(slide_line(&[2, 2, 2, 2]), (vec![4, 4, 0, 0], 8));
+        assert_eq!(slide_line(&[4, 4, 8, 0]), (vec![8, 8, 0, 0], 8));
+        assert_eq!(slide_line(&[2, 2, 2, 0]), (vec![4, 2, 0, 0], 4));
+        assert_eq!(slide_line(&[0, 0, 0, 0]), (vec![0, 0, 0, 0], 0));
+    }
+
+    #[test]
+    fn test_slide() {
+        let mut board = Board {
+            tiles: [[2, 0, 0, 2], [0, 4, 0, 4], [0, 0, 0, 0], [8, 0, 8, 16]],
+            score: 10,
+            best: 12,
+        };
+        assert!(board.slide(Direction::Right));
+        assert_eq!(board.tiles, [[0, 0, 0, 4], [0, 0, 0, 8], [0, 0, 0, 0], [0, 0, 16, 16]]);
+        assert_eq!((board.score, board.best), (38, 38));
+        assert!(board.slide(Direction::Up));
+        assert_eq!(board.tiles, [[0, 0, 16, 4], [0, 0, 0, 8], [0, 0, 0, 16], [0, 0, 0, 0]]);
+        assert!(!board.slide(Direction::Up));
+    }
+
+    #[test]
+    fn test_game_over() {
+        let mut board = Board { tiles: [[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, 8]], score: 0, best: 0 };
+        assert!(!board.can_move());
+        board.tiles[3][3] = 4;
+        assert!(board.can_move());
+    }
+
+    #[test]
+    fn test_new_and_saved_games() {
+        crate::rng::seed(4);
+        let board = Board::new(100);
+        assert_eq!(board.tiles.iter().flatten().filter(|tile| **tile > 0).count(), 2);
+        assert_eq!(Board::parse(&board.to_json()), Ok(board));
+        assert!(Board::parse("{\"tiles\": []}").is_err());
+    }
+
+    #[test]
+    fn test_2048() {
+        enable(1);
+        assert_eq!(run("2048"), ["2048: This feature is turned off (try 'features games on')"]);
+        run("features games on");
+        let border = "+------+------+------+------+";
+        let empty = "|      |      |      |      |";
+        assert_eq!(run("2048"), [
+            "Score: 0   Best: 0", border, empty, border, empty, border,
+            "|      |      |      |    2 |", border, "|      |    2 |      |      |", border,
+            "Arrow keys or WASD to move, q to stop",
+        ]);
+        // Each key draws the board again in place of the last
+        block_on(crate::handle_game_key("ArrowLeft"));
+        assert_eq!(take_output()[2..9], [
+            "|      |      |    2 |      |", border, empty, border,
+            "|    2 |      |      |      |", border, "|    2 |      |      |      |",
+        ]);
+        // Typed moves work too, and aren't kept as commands
+        block_on(crate::handle_input("w"));
+        assert_eq!(take_output()[..5], ["Score: 4   Best: 4", border, "|    4 |      |    2 |      |", border, "|      |    2 |      |      |"]);
+        block_on(crate::handle_game_key("Shift"));
+        assert_eq!(take_output().len(), 0);
+
+        crate::cancel_input();
+        assert_eq!(take_output(), ["Game saved: run it again to carry on."]);
+        assert_eq!(run("cat /.config/2048.json"), ["{\"tiles\":[[4,0,2,0],[0,2,0,0],[0,0,0,0],[0,0,0,0]],\"score\":4,\"best\":4}"]);
+        assert!(crate::commands::export_session().contains("2048.json"));
+        assert_eq!(run("history"), ["1  2048", "2  features games on", "3  2048", "4  cat /.config/2048.json", "5  history"]);
+
+        // The saved game carries on where it stopped
+        assert_eq!(run("2048")[2], "|    4 |      |    2 |      |");
+        block_on(crate::handle_game_key("q"));
+        assert_eq!(take_output(), ["Game saved: run it again to carry on."]);
+
+        // One move from the end: the game stops, and the next starts afresh but keeps the best score
+        let stuck = "{\"tiles\":[[2,4,2,4],[4,2,4,2],[8,4,2,4],[16,32,64,0]],\"score\":900,\"best\":1200}";
+        crate::write_file("/.config/2048.json", stuck.to_string()).unwrap();
+        run("2048");
+        block_on(crate::handle_game_key("ArrowRight"));
+        assert_eq!(take_output().last().unwrap(), "No moves left. Final score: 900 (highest tile 64)");
+        assert!(!crate::playing_game());
+        assert_eq!(run("2048")[0], "Score: 0   Best: 1200");
+        crate::cancel_input();
+        take_output();
+        assert_eq!(run("2048 new")[0], "Score: 0   Best: 1200");
+        crate::cancel_input();
+        take_output();
+
+        crate::write_file("/.config/2048.json", "{}".to_string()).unwrap();
+        assert_eq!(run("2048"), ["2048: /.config/2048.json: Not a saved game (2048 new starts again)"]);
+        assert_eq!(run("2048 old"), ["Usage: 2048 [new]"]);
+    }
+}
@@ -0,0 +1,177 @@
+//! Typing tests: passages picked at random from content/.typetest.txt, typed
+//! a line at a time and scored on speed and accuracy. Passages in the file
+//! are separated by blank lines, and lines starting with `#` are comments.
+//!
+//! The best scores are kept in /.config/typetest-scores, one per line as
+//! `wpm accuracy date`, best first.
+
+use crate::commands::edit_distance;
+use crate::datetime::DateTime;
+use crate::filesystem::file_paths::{TYPETEST_FILE_PATH, TYPETEST_SCORES_FILE_PATH};
+use crate::filesystem::helpers::{get_file_content, write_file};
+use crate::rng::choose;
+
+/// How many scores the table keeps
+pub const MAX_SCORES: usize = 5;
+
+// A test typed faster than this is timed as taking this long
+const MIN_ELAPSED_MS: f64 = 1000.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    /// Net words per minute: five characters to a word, scaled by accuracy
+    pub wpm: u32,
+    /// Percentage of the passage's characters typed correctly
+    pub accuracy: u32,
+    /// When the test was taken, as YYYY-MM-DD
+    pub date: String,
+}
+
+pub fn parse_passages(text: &str) -> Vec<Vec<String>> {
+    let mut passages = Vec::new();
+    let mut passage: Vec<String> = Vec::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let line = line.trim();
+        if line.is_empty() {
+            if !passage.is_empty() {
+                passages.push(std::mem::take(&mut passage));
+            }
+        } else {
+            passage.push(line.to_string());
+        }
+    }
+    if !passage.is_empty() {
+        passages.push(passage);
+    }
+    passages
+}
+
+/// A random passage, if there are any
+pub async fn pick() -> Option<Vec<String>> {
+    let passages = get_file_content(&TYPETEST_FILE_PATH).await
+        .map(|text| parse_passages(&text))
+        .unwrap_or_default();
+    choose(&passages).cloned()
+}
+
+/// Score the lines typed against the passage, over `elapsed_ms`
+pub fn score(passage: &[String], typed: &[String], elapsed_ms: f64) -> Score {
+    let length: usize = passage.iter().map(|line| line.chars().count()).sum();
+    let mistakes: usize = passage.iter().zip(typed)
+        .map(|(expected, typed)| edit_distance(expected, typed.trim_end()))
+        .sum();
+    let accuracy = match length {
+        0 => 100,
+        _ => (length.saturating_sub(mistakes) * 100 / length) as u32,
+    };
+    let typed_length: usize = typed.iter().map(|line| line.trim_end().chars().count()).sum();
+    let minutes = elapsed_ms.max(MIN_ELAPSED_MS) / 60_000.0;
+    let gross = typed_length as f64 / 5.0 / minutes;
+    Score {
+        wpm: (gross * accuracy as f64 / 100.0).round() as u32,
+        accuracy,
+        date: DateTime::local().format("%Y-%m-%d"),
+    }
+}
+
+pub fn parse_scores(text: &str) -> Vec<Score> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let wpm = fields.next()?.parse().ok()?;
+            let accuracy = fields.next()?.parse().ok()?;
+            let date = fields.next().unwrap_or_default().to_string();
+            Some(Score { wpm, accuracy, date })
+        })
+        .collect()
+}
+
+pub async fn scores() -> Vec<Score> {
+    get_file_content(&TYPETEST_SCORES_FILE_PATH).await
+        .map(|text| parse_scores(&text))
+        .unwrap_or_default()
+}
+
+/// Add `score` to the table if it's good enough, returning its place (from 1)
+pub async fn record(score: Score) -> Result<Option<usize>, String> {
+    let mut table = scores().await;
+    // Ties go below the scores already there
+    let place = table.iter()
+        .position(|kept| (score.wpm, score.accuracy) > (kept.wpm, kept.accuracy))
+        .unwrap_or(table.len());
+    if place >= MAX_SCORES {
+        return Ok(None);
+    }
+    table.insert(place, score);
+    table.truncate(MAX_SCORES);
+    let text: String = table.iter()
+        .map(|score| format!("{} {} {}\n", score.wpm, score.accuracy, score.date))
+        .collect();
+    crate::commands::builtin::filesystem::create_dir_all(&TYPETEST_SCORES_FILE_PATH.dir).await?;
+    write_file(&TYPETEST_SCORES_FILE_PATH, text).await?;
+    Ok(Some(place + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{enable, overwrite, run};
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_passages() {
+        let passages = parse_passages("# Passages\nOne line.\nTwo lines.\n\n\n  Another  \n# done\n");
+        assert_eq!(passages, [lines(&["One line.", "Two lines."]), lines(&["Another"])]);
+        assert!(parse_passages("# nothing\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_score() {
+        let passage = lines(&["hello world", "typing test"]);
+        // 22 characters in a minute, all correct
+        let perfect = score(&passage, &passage, 60_000.0);
+        assert_eq!((perfect.wpm, perfect.accuracy), (4, 100));
+        // Two mistakes (a swap counts once) over half a minute
+        let sloppy = score(&passage, &lines(&["hello wrold", "typing tst"]), 30_000.0);
+        assert_eq!((sloppy.wpm, sloppy.accuracy), (8, 90));
+        // Lines left out count against accuracy
+        assert_eq!(score(&passage, &lines(&["hello world", ""]), 60_000.0).accuracy, 50);
+    }
+
+    #[test]
+    fn test_parse_scores() {
+        let scores = parse_scores("62 98 2023-11-14\nnonsense\n40 100\n");
+        assert_eq!(scores, [
+            Score { wpm: 62, accuracy: 98, date: "2023-11-14".to_string() },
+            Score { wpm: 40, accuracy: 100, date: String::new() },
+        ]);
+    }
+
+    #[test]
+    fn test_typetest() {
+        enable(1);
+        assert_eq!(run("typetest"), ["typetest: This feature is turned off (try 'features games on')"]);
+        run("features games on");
+        assert_eq!(run("typetest scores"), ["No scores yet: run typetest to set one"]);
+
+        overwrite("/.typetest.txt", "# One passage\nhello world\ntyping test\n");
+        assert_eq!(run("typetest"), [
+            "Type each line as it appears and press Enter (Escape to give up).",
+            "The clock starts now.",
+            "hello world",
+        ]);
+        assert_eq!(run("hello world"), ["typing test"]);
+        // The fixture clock stands still, so this took the shortest time counted: a second
+        assert_eq!(run("typing test"), ["264 WPM, 100% accuracy\nNew high score: #1 (typetest scores to see them all)"]);
+
+        run("typetest");
+        run("hello wrold");
+        assert_eq!(run("typing tst"), ["227 WPM, 90% accuracy\nNew high score: #2 (typetest scores to see them all)"]);
+        assert_eq!(run("typetest scores"), ["1. 264 WPM  100%  2023-11-14", "2. 227 WPM   90%  2023-11-14"]);
+        assert_eq!(run("cat /.config/typetest-scores"), ["264 100 2023-11-14", "227 90 2023-11-14"]);
+        assert_eq!(run("typetest fast"), ["Usage: typetest", "       typetest scores"]);
+    }
+}
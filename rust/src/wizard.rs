@@ -0,0 +1,146 @@
+//! Wizards: commands that ask a series of questions (through the prompt
+//! subsystem) and write a file from the answers at the end. A command
+//! implements `Wizard` and returns `start(wizard)`. Escape cancels a wizard
+//! part way through, and nothing is written.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+use crate::commands::builtin::filesystem::create_dir_all;
+use crate::filesystem::FilePath;
+use crate::filesystem::helpers::{file_exists, write_file};
+use crate::prompt::{self, Prompt};
+
+/// The answers so far, by question key
+pub type Answers = BTreeMap<&'static str, String>;
+
+/// Checks (and may tidy) an answer; an error is shown and the question asked again
+pub type Check = fn(&str) -> Result<String, String>;
+
+/// One question a wizard asks
+pub struct Question {
+    pub key: &'static str,
+    pub text: String,
+    /// The answer if Enter is pressed on its own
+    pub default: Option<String>,
+    pub check: Option<Check>,
+}
+
+impl Question {
+    pub fn new(key: &'static str, text: &str) -> Question {
+        Question { key, text: text.to_string(), default: None, check: None }
+    }
+
+    pub fn with_default(mut self, answer: &str) -> Question {
+        self.default = Some(answer.to_string());
+        self
+    }
+
+    pub fn with_check(mut self, check: Check) -> Question {
+        self.check = Some(check);
+        self
+    }
+}
+
+pub trait Wizard {
+    /// The command, for messages
+    fn name(&self) -> &str;
+
+    /// What to ask, in order
+    fn questions(&self) -> Vec<Question>;
+
+    /// The file to write and what to write in it, once everything is answered
+    fn assemble(&self, answers: &Answers) -> Result<(FilePath, String), String>;
+}
+
+/// Ask the wizard's questions in turn and then write its file.
+/// Commands return this: the first question is already shown.
+pub fn start(wizard: impl Wizard + 'static) -> String {
+    let questions = wizard.questions().into();
+    ask_next(Rc::new(wizard), questions, Answers::new());
+    String::new()
+}
+
+fn ask_next(wizard: Rc<dyn Wizard>, mut questions: VecDeque<Question>, mut answers: Answers) {
+    let Some(question) = questions.pop_front() else {
+        return;
+    };
+    let key = question.key;
+    let mut prompt = Prompt::new(&question.text, move |answer| async move {
+        answers.insert(key, answer);
+        if questions.is_empty() {
+            return finish(wizard.as_ref(), &answers).await;
+        }
+        ask_next(wizard, questions, answers);
+        String::new()
+    });
+    if let Some(default) = &question.default {
+        prompt = prompt.with_default(default);
+    }
+    if let Some(check) = question.check {
+        prompt = prompt.with_check(check);
+    }
+    prompt::ask(prompt);
+}
+
+async fn finish(wizard: &dyn Wizard, answers: &Answers) -> String {
+    let (filepath, content) = match wizard.assemble(answers) {
+        Ok(file) => file,
+        Err(e) => return format!("{}: {}", wizard.name(), e),
+    };
+    let path = filepath.to_string();
+    if file_exists(&filepath).await {
+        return format!("{}: {}: File already exists", wizard.name(), path);
+    }
+    if let Err(e) = create_dir_all(&filepath.dir).await {
+        return format!("{}: {}: {}", wizard.name(), filepath.dir.to_string(), e);
+    }
+    match write_file(&filepath, content).await {
+        Ok(_) => format!("Created {}", path),
+        Err(e) => format!("{}: {}: {}", wizard.name(), path, e),
+    }
+}
+
+/// A check for questions that need an answer
+pub fn required(answer: &str) -> Result<String, String> {
+    match answer.trim() {
+        "" => Err("Please give an answer (or press Escape to cancel).".to_string()),
+        answer => Ok(answer.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{enable, run};
+    use crate::js_interop::take_output;
+
+    #[test]
+    fn test_new_post_wizard() {
+        enable(1);
+        assert_eq!(run("new-post"), ["Title:"]);
+        assert_eq!(run(""), ["Please give an answer (or press Escape to cancel).", "Title:"]);
+        assert_eq!(run("Hello, \"World\"!"), ["Tags (comma separated, or none):"]);
+        assert_eq!(run("rust, wasm"), ["Template (post, note, math): [post]"]);
+        assert_eq!(run("poem"), ["No template called 'poem'.", "Template (post, note, math): [post]"]);
+        assert_eq!(run(""), ["Created /blog/hello-world.md"]);
+        assert_eq!(run("cat /blog/hello-world.md"), [
+            "---",
+            "title: \"Hello, 'World'!\"",
+            &format!("date: {}", crate::filesystem::helpers::today_iso()),
+            "tags: [rust, wasm]",
+            "---",
+            "",
+            "Write your post here.",
+        ]);
+
+        // The same title again doesn't replace it; Escape part way leaves nothing behind
+        run("new-post");
+        run("hello world");
+        run("");
+        assert_eq!(run("note"), ["new-post: /blog/hello-world.md: File already exists"]);
+        run("new-post /drafts");
+        run("Unfinished");
+        crate::cancel_input();
+        assert_eq!(take_output(), ["Cancelled."]);
+        assert_eq!(run("ls /drafts"), ["ls: /drafts: No such directory"]);
+    }
+}
@@ -2,8 +2,53 @@
 
 import os
 import json
+import tomllib
 from pathlib import Path
 
+def read_front_matter(path):
+    """Read draft/publish_date and title/date/tags from a markdown file's front matter, if any.
+    YAML front matter sits between --- lines, TOML between +++ lines."""
+    with open(path, encoding='utf-8-sig') as f:
+        fence = f.readline().strip()
+        if fence not in ('---', '+++'):
+            return {}
+        lines = []
+        for line in f:
+            if line.strip() == fence:
+                break
+            lines.append(line)
+        else:
+            return {}  # never closed: not front matter
+
+    if fence == '+++':
+        try:
+            fields = tomllib.loads(''.join(lines))
+        except tomllib.TOMLDecodeError:
+            return {}
+    else:
+        fields = {}
+        for line in lines:
+            if line.lstrip().startswith('- ') and isinstance(fields.get('tags'), list):
+                fields['tags'].append(line.strip()[2:].strip().strip('"\''))
+                continue
+            key, _, value = line.partition(':')
+            key, value = key.strip(), value.strip().strip('"\'')
+            if key == 'tags':
+                value = value.strip('[]')
+                fields['tags'] = [tag.strip().strip('"\'') for tag in value.split(',') if tag.strip()]
+            elif value:
+                fields[key] = value
+
+    entry = {}
+    if str(fields.get('draft', '')).lower() == 'true':
+        entry['draft'] = True
+    for key in ('publish_date', 'title', 'date'):
+        if fields.get(key):
+            entry[key] = str(fields[key])
+    if fields.get('tags'):
+        entry['tags'] = [str(tag) for tag in fields['tags']]
+    return entry
+
 def generate_manifest(content_dir, output_file):
     """Generate a manifest.json from the content directory structure"""
 
@@ -32,16 +77,20 @@ def generate_manifest(content_dir, output_file):
                 for i in range(len(parts)):
                     directories.add('/'.join(parts[:i+1]))
 
-            files.append({
+            entry = {
                 "name": item.name,
                 "path": dir_path
-            })
+            }
+            if item.suffix == '.md':
+                entry.update(read_front_matter(item))
+            files.append(entry)
         elif item.is_dir() and relative.parts[0] == 'abyss' and len(relative.parts) == 1:
             # Add the abyss directory itself to the directory list
             directories.add('abyss')
 
     # Create manifest structure
     manifest = {
+        "version": 1,
         "files": sorted(files, key=lambda x: (x['path'], x['name'])),
         "directories": sorted(list(directories))
     }